@@ -1,12 +1,10 @@
-use cinnamon::client::NightscoutClient;
-use cinnamon::models::properties::PropertyType;
-use std::env;
+use cinnamon::prelude::*;
 use std::error::Error;
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn Error>> {
-    let url = env::var("NS_URL").expect("NS_URL not set");
-    let client = NightscoutClient::new(&url)?;
+    // Reads NIGHTSCOUT_URL, and NIGHTSCOUT_TOKEN for protected sites
+    let client = NightscoutClient::from_env()?;
 
     // Request specific properties (faster than fetching everything)
     let stats = client
@@ -1,28 +1,15 @@
-use chrono::Utc;
-use cinnamon::client::NightscoutClient;
-use cinnamon::models::treatments::Treatment;
-use std::env;
+use cinnamon::prelude::*;
 use std::error::Error;
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn Error>> {
-    let url = env::var("NS_URL").expect("NS_URL not set");
-    let token = env::var("NS_TOKEN").expect("NS_TOKEN not set"); // Token IS required for writing
-    let client = NightscoutClient::new(&url)?.with_secret(token);
+    // Reads NIGHTSCOUT_URL and NIGHTSCOUT_API_SECRET, which IS required for writing
+    let client = NightscoutClient::from_env()?;
 
-    let snack = Treatment {
-        id: None,
-        event_type: "Carb Correction".to_string(),
-        created_at: Utc::now().to_rfc3339(),
-        carbs: Some(15.0),
-        notes: Some("Mid-afternoon snack via Cinnamon".to_string()),
-        entered_by: Some("Cinnamon-Rust".to_string()),
-        // Fill other fields with None
-        glucose: None,
-        glucose_type: None,
-        insulin: None,
-        units: None,
-    };
+    let snack = Treatment::carbs(15.0)
+        .notes("Mid-afternoon snack via Cinnamon")
+        .entered_by("Cinnamon-Rust")
+        .build()?;
 
     println!("Uploading treatment.");
 
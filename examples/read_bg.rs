@@ -1,13 +1,10 @@
-use cinnamon::client::NightscoutClient;
-use std::env;
+use cinnamon::prelude::*;
 use std::error::Error;
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn Error>> {
-    // Initialize (Read-only doesn't strictly need a token, but good practice)
-    let url = env::var("NS_URL").expect("NS_URL not set");
-    let token = env::var("NS_TOKEN").expect("NS_TOKEN not set");
-    let client = NightscoutClient::new(&url)?.with_secret(token);
+    // Reads NIGHTSCOUT_URL, and NIGHTSCOUT_TOKEN or NIGHTSCOUT_API_SECRET for protected sites
+    let client = NightscoutClient::from_env()?;
 
     println!("Fetching latest glucose data.");
 
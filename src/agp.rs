@@ -0,0 +1,187 @@
+//! Ambulatory Glucose Profile (AGP) computed from SGV history.
+//!
+//! Readings from every day are folded onto a single 24h axis and summarized per time-of-day
+//! bucket as the median and the 5/25/75/95 percentile bands.
+
+use crate::client::NightscoutClient;
+use crate::error::NightscoutError;
+use crate::models::entries::SgvEntry;
+use crate::query_builder::{days_ago, HasDate};
+
+use chrono::{DateTime, Timelike, Utc};
+use chrono_tz::Tz;
+use futures::TryStreamExt;
+use serde::{Deserialize, Serialize};
+
+const MINUTES_PER_DAY: u32 = 24 * 60;
+
+/// Percentile bands of one time-of-day bucket, in mg/dL.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub struct AgpPercentiles {
+    pub p5: f64,
+    pub p25: f64,
+    pub median: f64,
+    pub p75: f64,
+    pub p95: f64,
+}
+
+/// One time-of-day bucket of the profile.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct AgpBucket {
+    /// Start of the bucket, in minutes after local midnight.
+    pub start_minute: u32,
+    /// Number of readings in the bucket.
+    pub count: usize,
+    /// `None` when the bucket has no readings.
+    pub percentiles: Option<AgpPercentiles>,
+}
+
+/// An Ambulatory Glucose Profile.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct Agp {
+    /// Width of each bucket, in minutes.
+    pub bucket_minutes: u32,
+    /// Buckets covering the whole day, starting at midnight.
+    pub buckets: Vec<AgpBucket>,
+}
+
+impl Agp {
+    /// Builds the profile from SGV entries, bucketing by local time of day in `tz`.
+    ///
+    /// Returns `None` if there are no entries or `bucket_minutes` doesn't evenly divide a day.
+    pub fn from_entries(entries: &[SgvEntry], bucket_minutes: u32, tz: Tz) -> Option<Self> {
+        if entries.is_empty()
+            || bucket_minutes == 0
+            || !MINUTES_PER_DAY.is_multiple_of(bucket_minutes)
+        {
+            return None;
+        }
+
+        let bucket_count = (MINUTES_PER_DAY / bucket_minutes) as usize;
+        let mut values: Vec<Vec<f64>> = vec![Vec::new(); bucket_count];
+        for entry in entries {
+            let Some(time) = entry.timestamp() else {
+                continue;
+            };
+            let local = time.with_timezone(&tz);
+            let minute = local.hour() * 60 + local.minute();
+            values[(minute / bucket_minutes) as usize].push(f64::from(entry.sgv));
+        }
+
+        let buckets = values
+            .into_iter()
+            .enumerate()
+            .map(|(index, mut bucket)| {
+                bucket.sort_by(|a, b| a.total_cmp(b));
+                AgpBucket {
+                    start_minute: index as u32 * bucket_minutes,
+                    count: bucket.len(),
+                    percentiles: (!bucket.is_empty()).then(|| AgpPercentiles {
+                        p5: percentile(&bucket, 5.0),
+                        p25: percentile(&bucket, 25.0),
+                        median: percentile(&bucket, 50.0),
+                        p75: percentile(&bucket, 75.0),
+                        p95: percentile(&bucket, 95.0),
+                    }),
+                }
+            })
+            .collect();
+
+        Some(Self {
+            bucket_minutes,
+            buckets,
+        })
+    }
+}
+
+/// Linearly interpolated percentile `p` (0-100) of non-empty, sorted `values`.
+fn percentile(sorted: &[f64], p: f64) -> f64 {
+    let rank = p / 100.0 * (sorted.len() - 1) as f64;
+    let lower = rank.floor() as usize;
+    let upper = rank.ceil() as usize;
+    sorted[lower] + (sorted[upper] - sorted[lower]) * (rank - lower as f64)
+}
+
+/// A builder computing an AGP over SGV history fetched from Nightscout.
+///
+/// Created by [`SgvService::agp`](crate::models::entries::SgvService::agp).
+pub struct AgpRequest {
+    client: NightscoutClient,
+    from: DateTime<Utc>,
+    to: Option<DateTime<Utc>>,
+    bucket_minutes: u32,
+    tz: Option<Tz>,
+}
+
+/// Number of entries fetched per request while collecting history.
+const AGP_PAGE_SIZE: usize = 1000;
+
+impl AgpRequest {
+    pub fn new(client: NightscoutClient) -> Self {
+        Self {
+            from: days_ago(client.server_now(), 14),
+            tz: client.timezone,
+            client,
+            to: None,
+            bucket_minutes: 15,
+        }
+    }
+
+    /// Uses the readings of the last `days` days. Default is 14.
+    pub fn last_days(mut self, days: i64) -> Self {
+        self.from = days_ago(self.client.server_now(), days);
+        self.to = None;
+        self
+    }
+
+    /// Uses readings on or after this date.
+    pub fn from(mut self, date: DateTime<Utc>) -> Self {
+        self.from = date;
+        self
+    }
+
+    /// Uses readings on or before this date.
+    pub fn to(mut self, date: DateTime<Utc>) -> Self {
+        self.to = Some(date);
+        self
+    }
+
+    /// Sets the bucket width in minutes. Must evenly divide a day. Default is 15.
+    pub fn bucket_minutes(mut self, minutes: u32) -> Self {
+        self.bucket_minutes = minutes;
+        self
+    }
+
+    /// Sets the timezone used for the time of day. Defaults to the client's timezone, then
+    /// to the timezone of the active profile, and to UTC when neither is known.
+    pub fn timezone(mut self, tz: Tz) -> Self {
+        self.tz = Some(tz);
+        self
+    }
+
+    /// Fetches the readings and computes the profile.
+    ///
+    /// Returns `NightscoutError::NotFound` if there is no data in the requested range.
+    pub async fn send(self) -> Result<Agp, NightscoutError> {
+        let mut query = self.client.sgv().get().from(self.from);
+        if let Some(to) = self.to {
+            query = query.to(to);
+        }
+
+        let entries: Vec<SgvEntry> = query.paginate(AGP_PAGE_SIZE).try_collect().await?;
+
+        let tz = match self.tz {
+            Some(tz) => tz,
+            None => self
+                .client
+                .analysis()
+                .active_profile(self.to.unwrap_or_else(|| self.client.server_now()))
+                .await
+                .ok()
+                .and_then(|profile| profile.tz())
+                .unwrap_or(Tz::UTC),
+        };
+
+        Agp::from_entries(&entries, self.bucket_minutes, tz).ok_or(NightscoutError::NotFound)
+    }
+}
@@ -0,0 +1,282 @@
+//! Local evaluation of the Nightscout glucose, stale data and pump battery alarms.
+//!
+//! The rules follow the server's `simplealarms`, `timeago` and `pump` plugins, using the
+//! thresholds and alarm switches from the status settings, so a bot raises the same alarms
+//! as the web UI without polling it.
+
+use crate::client::NightscoutClient;
+use crate::error::NightscoutError;
+use crate::models::devicestatus::DeviceStatus;
+use crate::models::entries::SgvEntry;
+use crate::models::notifications::AlarmLevel;
+use crate::models::status::StatusSettings;
+use crate::query_builder::HasDate;
+
+use chrono::{DateTime, Duration, Utc};
+
+/// Number of recent device statuses searched for the pump battery.
+const DEVICE_STATUS_COUNT: usize = 10;
+
+/// The condition behind an [`ActiveAlarm`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum AlarmKind {
+    UrgentHigh,
+    High,
+    Low,
+    UrgentLow,
+    /// No reading for longer than the stale data thresholds.
+    StaleData,
+    PumpBatteryLow,
+}
+
+/// An alarm raised by [`evaluate`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct ActiveAlarm {
+    pub kind: AlarmKind,
+    pub level: AlarmLevel,
+    pub title: String,
+    pub message: String,
+}
+
+/// Thresholds and switches of the alarms, with the Nightscout defaults.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AlarmSettings {
+    /// Urgent high threshold (mg/dL).
+    pub bg_high: f64,
+    /// High threshold (mg/dL).
+    pub bg_target_top: f64,
+    /// Low threshold (mg/dL).
+    pub bg_target_bottom: f64,
+    /// Urgent low threshold (mg/dL).
+    pub bg_low: f64,
+    pub urgent_high: bool,
+    pub high: bool,
+    pub low: bool,
+    pub urgent_low: bool,
+    /// Minutes without a reading before a warning, `None` to disable it.
+    pub stale_warn_mins: Option<i64>,
+    /// Minutes without a reading before an urgent alarm, `None` to disable it.
+    pub stale_urgent_mins: Option<i64>,
+    pub pump_battery_low: bool,
+    /// Pump battery warning threshold (%), an urgent alarm is raised 10 points lower.
+    pub pump_battery_warn_percent: f64,
+    /// Pump battery warning threshold (V), an urgent alarm is raised 0.05 V lower.
+    pub pump_battery_warn_voltage: f64,
+}
+
+impl Default for AlarmSettings {
+    fn default() -> Self {
+        Self {
+            bg_high: 260.0,
+            bg_target_top: 180.0,
+            bg_target_bottom: 80.0,
+            bg_low: 55.0,
+            urgent_high: true,
+            high: true,
+            low: true,
+            urgent_low: true,
+            stale_warn_mins: Some(15),
+            stale_urgent_mins: Some(30),
+            pump_battery_low: false,
+            pump_battery_warn_percent: 30.0,
+            pump_battery_warn_voltage: 1.35,
+        }
+    }
+}
+
+impl AlarmSettings {
+    /// Reads the thresholds and alarm switches of the server, keeping the defaults for
+    /// anything missing.
+    pub fn from_status(settings: &StatusSettings) -> Self {
+        let mut alarms = Self::default();
+        if let Some(thresholds) = &settings.thresholds {
+            let set = |value: &mut f64, threshold: Option<i64>| {
+                if let Some(threshold) = threshold {
+                    *value = threshold as f64;
+                }
+            };
+            set(&mut alarms.bg_high, thresholds.bg_high);
+            set(&mut alarms.bg_target_top, thresholds.bg_target_top);
+            set(&mut alarms.bg_target_bottom, thresholds.bg_target_bottom);
+            set(&mut alarms.bg_low, thresholds.bg_low);
+        }
+
+        alarms.urgent_high = settings.alarm_urgent_high.unwrap_or(alarms.urgent_high);
+        alarms.high = settings.alarm_high.unwrap_or(alarms.high);
+        alarms.low = settings.alarm_low.unwrap_or(alarms.low);
+        alarms.urgent_low = settings.alarm_urgent_low.unwrap_or(alarms.urgent_low);
+        alarms.pump_battery_low = settings
+            .alarm_pump_battery_low
+            .unwrap_or(alarms.pump_battery_low);
+
+        if settings.alarm_timeago_warn == Some(false) {
+            alarms.stale_warn_mins = None;
+        } else if let Some(mins) = settings.alarm_timeago_warn_mins {
+            alarms.stale_warn_mins = Some(mins);
+        }
+        if settings.alarm_timeago_urgent == Some(false) {
+            alarms.stale_urgent_mins = None;
+        } else if let Some(mins) = settings.alarm_timeago_urgent_mins {
+            alarms.stale_urgent_mins = Some(mins);
+        }
+
+        alarms
+    }
+}
+
+/// Evaluates the alarms active at `at`.
+///
+/// Glucose alarms use the most recent of `entries`, the pump battery alarm the most recent
+/// device status reporting a battery. Alarms are returned most severe first.
+pub fn evaluate(
+    settings: &AlarmSettings,
+    entries: &[SgvEntry],
+    statuses: &[DeviceStatus],
+    at: DateTime<Utc>,
+) -> Vec<ActiveAlarm> {
+    let mut alarms = Vec::new();
+
+    let latest = entries.iter().max_by_key(|entry| entry.date);
+    let stale = match latest.and_then(|entry| entry.timestamp()) {
+        Some(time) => stale_alarm(settings, at - time),
+        None => None,
+    };
+
+    match (latest, stale) {
+        (_, Some(stale)) => alarms.push(stale),
+        (Some(entry), None) => alarms.extend(glucose_alarm(settings, f64::from(entry.sgv))),
+        (None, None) => {}
+    }
+
+    if settings.pump_battery_low {
+        let battery = statuses
+            .iter()
+            .filter_map(|status| Some((status.created_at, status.pump.as_ref()?.battery.as_ref()?)))
+            .max_by_key(|(created_at, _)| *created_at)
+            .map(|(_, battery)| battery);
+        alarms
+            .extend(battery.and_then(|battery| {
+                pump_battery_alarm(settings, battery.percent, battery.voltage)
+            }));
+    }
+
+    alarms.sort_by_key(|alarm| std::cmp::Reverse(alarm.level));
+    alarms
+}
+
+fn glucose_alarm(settings: &AlarmSettings, sgv: f64) -> Option<ActiveAlarm> {
+    let (kind, level, title) = if settings.urgent_high && sgv > settings.bg_high {
+        (AlarmKind::UrgentHigh, AlarmLevel::Urgent, "Urgent HIGH")
+    } else if settings.high && sgv > settings.bg_target_top {
+        (AlarmKind::High, AlarmLevel::Warn, "High warning")
+    } else if settings.urgent_low && sgv < settings.bg_low {
+        (AlarmKind::UrgentLow, AlarmLevel::Urgent, "Urgent LOW")
+    } else if settings.low && sgv < settings.bg_target_bottom {
+        (AlarmKind::Low, AlarmLevel::Warn, "Low warning")
+    } else {
+        return None;
+    };
+
+    Some(ActiveAlarm {
+        kind,
+        level,
+        title: title.to_string(),
+        message: format!("BG now: {sgv}"),
+    })
+}
+
+fn stale_alarm(settings: &AlarmSettings, age: Duration) -> Option<ActiveAlarm> {
+    let minutes = age.num_minutes();
+    let level = if settings
+        .stale_urgent_mins
+        .is_some_and(|mins| minutes >= mins)
+    {
+        AlarmLevel::Urgent
+    } else if settings.stale_warn_mins.is_some_and(|mins| minutes >= mins) {
+        AlarmLevel::Warn
+    } else {
+        return None;
+    };
+
+    Some(ActiveAlarm {
+        kind: AlarmKind::StaleData,
+        level,
+        title: "Stale data, check rig?".to_string(),
+        message: format!("Last received: {minutes} mins ago"),
+    })
+}
+
+fn pump_battery_alarm(
+    settings: &AlarmSettings,
+    percent: Option<f64>,
+    voltage: Option<f64>,
+) -> Option<ActiveAlarm> {
+    let (level, reading) = match (percent, voltage) {
+        (Some(percent), _) if percent <= settings.pump_battery_warn_percent - 10.0 => {
+            (AlarmLevel::Urgent, format!("{percent}%"))
+        }
+        (Some(percent), _) if percent <= settings.pump_battery_warn_percent => {
+            (AlarmLevel::Warn, format!("{percent}%"))
+        }
+        (None, Some(voltage)) if voltage <= settings.pump_battery_warn_voltage - 0.05 => {
+            (AlarmLevel::Urgent, format!("{voltage}v"))
+        }
+        (None, Some(voltage)) if voltage <= settings.pump_battery_warn_voltage => {
+            (AlarmLevel::Warn, format!("{voltage}v"))
+        }
+        _ => return None,
+    };
+
+    Some(ActiveAlarm {
+        kind: AlarmKind::PumpBatteryLow,
+        level,
+        title: "Pump Battery Low".to_string(),
+        message: format!("Pump battery: {reading}"),
+    })
+}
+
+pub struct AlarmsService {
+    pub client: NightscoutClient,
+}
+
+impl AlarmsService {
+    /// Evaluates the alarms active now, with the server's settings and latest data.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// # use cinnamon::client::NightscoutClient;
+    /// # use cinnamon::models::notifications::AlarmLevel;
+    /// # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+    /// let client = NightscoutClient::new("https://ns.example.com")?;
+    ///
+    /// for alarm in client.alarms().active().await? {
+    ///     if alarm.level == AlarmLevel::Urgent {
+    ///         println!("{}: {}", alarm.title, alarm.message);
+    ///     }
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn active(&self) -> Result<Vec<ActiveAlarm>, NightscoutError> {
+        let status = self.client.status();
+        let (status, entries, statuses) = futures::join!(
+            status.get(),
+            self.client.sgv().get().limit(1).send(),
+            self.client
+                .devicestatus()
+                .get()
+                .limit(DEVICE_STATUS_COUNT)
+                .send(),
+        );
+
+        let settings = status?
+            .settings
+            .as_ref()
+            .map(AlarmSettings::from_status)
+            .unwrap_or_default();
+
+        Ok(evaluate(&settings, &entries?, &statuses?, Utc::now()))
+    }
+}
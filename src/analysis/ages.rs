@@ -0,0 +1,85 @@
+//! Device ages (CAGE, SAGE, IAGE, BAGE) derived from change treatments.
+
+use crate::models::treatments::Treatment;
+
+use chrono::{DateTime, Duration, Utc};
+
+/// Event type logged when the infusion site (cannula) is changed.
+pub const SITE_CHANGE_EVENT: &str = "Site Change";
+
+/// Event type logged when a new sensor is started.
+pub const SENSOR_START_EVENT: &str = "Sensor Start";
+
+/// Event type logged when the sensor is replaced.
+pub const SENSOR_CHANGE_EVENT: &str = "Sensor Change";
+
+/// Event type logged when the insulin reservoir or cartridge is changed.
+pub const INSULIN_CHANGE_EVENT: &str = "Insulin Change";
+
+/// Event type logged when the pump battery is changed.
+pub const PUMP_BATTERY_CHANGE_EVENT: &str = "Pump Battery Change";
+
+/// Event types considered by [`ages`].
+pub const AGE_EVENTS: [&str; 5] = [
+    SITE_CHANGE_EVENT,
+    SENSOR_START_EVENT,
+    SENSOR_CHANGE_EVENT,
+    INSULIN_CHANGE_EVENT,
+    PUMP_BATTERY_CHANGE_EVENT,
+];
+
+/// The last change of a consumable.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Age {
+    pub changed_at: DateTime<Utc>,
+    /// Event type of the treatment that logged the change.
+    pub event_type: String,
+    pub notes: Option<String>,
+}
+
+impl Age {
+    /// Time elapsed between the change and `at`.
+    pub fn age_at(&self, at: DateTime<Utc>) -> Duration {
+        at - self.changed_at
+    }
+
+    /// Whole hours elapsed since the change, as shown by the Nightscout pills.
+    pub fn hours(&self) -> i64 {
+        self.age_at(Utc::now()).num_hours()
+    }
+}
+
+/// Ages of the consumables, `None` where no change was found.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Ages {
+    /// Cannula age, from Site Change treatments.
+    pub cannula: Option<Age>,
+    /// Sensor age, from Sensor Start and Sensor Change treatments.
+    pub sensor: Option<Age>,
+    /// Insulin age, from Insulin Change treatments.
+    pub insulin: Option<Age>,
+    /// Pump battery age, from Pump Battery Change treatments.
+    pub battery: Option<Age>,
+}
+
+/// Finds the latest change of each consumable among `treatments`.
+pub fn ages(treatments: &[Treatment]) -> Ages {
+    let latest = |events: &[&str]| {
+        treatments
+            .iter()
+            .filter(|t| events.contains(&t.event_type.as_str()))
+            .max_by_key(|t| t.created_at)
+            .map(|t| Age {
+                changed_at: t.created_at,
+                event_type: t.event_type.clone(),
+                notes: t.notes.clone(),
+            })
+    };
+
+    Ages {
+        cannula: latest(&[SITE_CHANGE_EVENT]),
+        sensor: latest(&[SENSOR_START_EVENT, SENSOR_CHANGE_EVENT]),
+        insulin: latest(&[INSULIN_CHANGE_EVENT]),
+        battery: latest(&[PUMP_BATTERY_CHANGE_EVENT]),
+    }
+}
@@ -0,0 +1,116 @@
+//! The AR2 forecast of Nightscout's `ar2` plugin.
+//!
+//! A second order autoregressive model of the logarithm of glucose, fitted by Nightscout
+//! on CGM data, projects the last two readings forward in 5-minute steps. The cone around
+//! the forecast widens with each step by the plugin's fixed coefficients.
+
+use crate::analysis::series::SgvSeries;
+
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Glucose (mg/dL) the logarithm is taken relative to.
+const BG_REF: f64 = 140.0;
+/// Lowest forecast value (mg/dL).
+const BG_MIN: f64 = 36.0;
+/// Highest forecast value (mg/dL).
+const BG_MAX: f64 = 400.0;
+/// Weights of the previous and current log-glucose.
+const AR: [f64; 2] = [-0.723, 1.716];
+/// Half-width of the cone in log-glucose, per step. Later steps reuse the last one.
+const CONE: [f64; 13] = [
+    0.020, 0.041, 0.061, 0.081, 0.099, 0.116, 0.132, 0.146, 0.159, 0.171, 0.182, 0.192, 0.201,
+];
+/// Interval between forecast points, and expected between the two readings used.
+const STEP_MINUTES: i64 = 5;
+/// The two latest readings must be at most this far apart.
+const MAX_READING_INTERVAL_MINUTES: i64 = 10;
+
+/// A point of an [`Ar2Forecast`].
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub struct ForecastPoint {
+    pub time: DateTime<Utc>,
+    /// Forecast glucose (mg/dL).
+    pub mgdl: f64,
+    /// Bottom of the cone (mg/dL).
+    pub low: f64,
+    /// Top of the cone (mg/dL).
+    pub high: f64,
+}
+
+/// The AR2 forecast following the latest reading of a series, see [`ar2_forecast`].
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct Ar2Forecast {
+    /// Points 5 minutes apart, the first one 5 minutes after the latest reading.
+    pub points: Vec<ForecastPoint>,
+}
+
+impl Ar2Forecast {
+    /// The forecast at `minutes` after the latest reading, rounded down to a step.
+    pub fn at_minutes(&self, minutes: i64) -> Option<&ForecastPoint> {
+        let step = usize::try_from(minutes / STEP_MINUTES).ok()?;
+        self.points.get(step.checked_sub(1)?)
+    }
+
+    /// The lowest forecast value, to anticipate a low.
+    pub fn min(&self) -> Option<f64> {
+        self.points.iter().map(|point| point.mgdl).reduce(f64::min)
+    }
+
+    /// The highest forecast value.
+    pub fn max(&self) -> Option<f64> {
+        self.points.iter().map(|point| point.mgdl).reduce(f64::max)
+    }
+}
+
+/// Forecasts glucose up to `horizon` after the latest reading of `series`.
+///
+/// Returns `None` when the series does not end with two readings at most 10 minutes
+/// apart, as the model needs the current trend.
+///
+/// # Example
+///
+/// ```rust
+/// # use cinnamon::analysis::ar2::ar2_forecast;
+/// # use cinnamon::analysis::series::{GlucosePoint, SgvSeries};
+/// # use chrono::{Duration, Utc};
+/// let now = Utc::now();
+/// let series = SgvSeries::new(vec![
+///     GlucosePoint { time: now - Duration::minutes(5), mgdl: 150.0 },
+///     GlucosePoint { time: now, mgdl: 140.0 },
+/// ]);
+///
+/// let forecast = ar2_forecast(&series, Duration::minutes(30)).unwrap();
+/// assert_eq!(forecast.points.len(), 6);
+/// assert!(forecast.points[5].mgdl < 140.0);
+/// ```
+pub fn ar2_forecast(series: &SgvSeries, horizon: Duration) -> Option<Ar2Forecast> {
+    let [previous, current] = series.points().last_chunk::<2>()?;
+    let interval = current.time - previous.time;
+    if interval > Duration::minutes(MAX_READING_INTERVAL_MINUTES)
+        || previous.mgdl < BG_MIN
+        || current.mgdl < BG_MIN
+    {
+        return None;
+    }
+
+    let steps = horizon.num_minutes().max(0) / STEP_MINUTES;
+    let mut y = [(previous.mgdl / BG_REF).ln(), (current.mgdl / BG_REF).ln()];
+    let to_mgdl = |y: f64| (BG_REF * y.exp()).round().clamp(BG_MIN, BG_MAX);
+
+    let points = (1..=steps)
+        .map(|step| {
+            y = [y[1], AR[0] * y[0] + AR[1] * y[1]];
+            let cone = CONE[(step as usize - 1).min(CONE.len() - 1)];
+
+            ForecastPoint {
+                time: current.time + Duration::minutes(step * STEP_MINUTES),
+                mgdl: to_mgdl(y[1]),
+                low: to_mgdl(y[1] - cone),
+                high: to_mgdl(y[1] + cone),
+            }
+        })
+        .collect();
+
+    Some(Ar2Forecast { points })
+}
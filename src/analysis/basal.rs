@@ -0,0 +1,217 @@
+//! Effective basal rate reconstruction from the profile schedule and temp basals.
+
+use crate::models::profile::ProfileConfig;
+use crate::models::treatments::Treatment;
+use crate::query_builder::HasDate;
+
+use chrono::{DateTime, Days, Duration, TimeZone, Utc};
+use chrono_tz::Tz;
+
+/// Event type Nightscout uses for temp basal treatments.
+pub const TEMP_BASAL_EVENT: &str = "Temp Basal";
+
+/// A span of time delivered at a constant basal rate.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BasalSegment {
+    pub start: DateTime<Utc>,
+    pub end: DateTime<Utc>,
+    /// Effective rate (U/h).
+    pub rate: f64,
+    /// Rate from the profile schedule (U/h).
+    pub scheduled_rate: f64,
+    /// Whether a temp basal was running.
+    pub temp: bool,
+}
+
+impl BasalSegment {
+    /// Insulin delivered during the segment (U).
+    pub fn insulin(&self) -> f64 {
+        self.rate * (self.end - self.start).num_milliseconds() as f64 / 3_600_000.0
+    }
+}
+
+/// The effective basal rate over a date range.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct BasalTimeline {
+    pub segments: Vec<BasalSegment>,
+}
+
+impl BasalTimeline {
+    /// Total basal insulin delivered over the timeline (U).
+    pub fn total_insulin(&self) -> f64 {
+        self.segments.iter().map(BasalSegment::insulin).sum()
+    }
+
+    /// Basal insulin delivered within `[from, to)` (U).
+    pub fn insulin_between(&self, from: DateTime<Utc>, to: DateTime<Utc>) -> f64 {
+        self.segments
+            .iter()
+            .map(|segment| {
+                let start = segment.start.max(from);
+                let end = segment.end.min(to);
+                if end <= start {
+                    return 0.0;
+                }
+                segment.rate * (end - start).num_milliseconds() as f64 / 3_600_000.0
+            })
+            .sum()
+    }
+
+    /// Effective rate at `at`, if it falls within the timeline.
+    pub fn rate_at(&self, at: DateTime<Utc>) -> Option<f64> {
+        self.segments
+            .iter()
+            .find(|segment| segment.start <= at && at < segment.end)
+            .map(|segment| segment.rate)
+    }
+}
+
+/// A temp basal with its effective end, after later temps cancelled it.
+struct TempBasal<'a> {
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+    treatment: &'a Treatment,
+}
+
+impl TempBasal<'_> {
+    fn rate(&self, scheduled: f64) -> f64 {
+        let treatment = self.treatment;
+        treatment
+            .absolute
+            .or(treatment.rate)
+            .or(treatment
+                .percent
+                .map(|percent| scheduled * (1.0 + percent / 100.0)))
+            .unwrap_or(scheduled)
+            .max(0.0)
+    }
+}
+
+/// Orders the temp basals and truncates each one at the start of the next.
+///
+/// A temp basal with a zero duration only cancels the one before it.
+fn temp_basals(treatments: &[Treatment]) -> Vec<TempBasal<'_>> {
+    let mut temps: Vec<(DateTime<Utc>, &Treatment)> = treatments
+        .iter()
+        .filter(|treatment| treatment.event_type == TEMP_BASAL_EVENT)
+        .filter_map(|treatment| Some((treatment.timestamp()?, treatment)))
+        .collect();
+    temps.sort_by_key(|(start, _)| *start);
+
+    let mut result = Vec::with_capacity(temps.len());
+    for (index, (start, treatment)) in temps.iter().enumerate() {
+        let minutes = treatment.duration.unwrap_or(0.0);
+        let mut end = start
+            .checked_add_signed(Duration::milliseconds((minutes * 60_000.0) as i64))
+            .unwrap_or(DateTime::<Utc>::MAX_UTC);
+        if let Some((next, _)) = temps.get(index + 1) {
+            end = end.min(*next);
+        }
+
+        if end > *start {
+            result.push(TempBasal {
+                start: *start,
+                end,
+                treatment,
+            });
+        }
+    }
+
+    result
+}
+
+/// Instants within `(from, to)` at which the profile's basal schedule changes.
+fn schedule_changes(
+    profile: &ProfileConfig,
+    from: DateTime<Utc>,
+    to: DateTime<Utc>,
+) -> Vec<DateTime<Utc>> {
+    let tz = profile.tz().unwrap_or(Tz::UTC);
+    let offsets: Vec<u32> = profile
+        .basal
+        .iter()
+        .filter_map(|entry| entry.seconds())
+        .collect();
+
+    let mut changes = Vec::new();
+    let first_day = from.with_timezone(&tz).date_naive() - Days::new(1);
+    let last_day = to.with_timezone(&tz).date_naive();
+
+    for day in first_day.iter_days().take_while(|day| *day <= last_day) {
+        let Some(midnight) = day.and_hms_opt(0, 0, 0) else {
+            continue;
+        };
+        for seconds in &offsets {
+            let Some(local) = midnight.checked_add_signed(Duration::seconds(i64::from(*seconds)))
+            else {
+                continue;
+            };
+            if let Some(change) = tz.from_local_datetime(&local).earliest() {
+                let change = change.with_timezone(&Utc);
+                if from < change && change < to {
+                    changes.push(change);
+                }
+            }
+        }
+    }
+
+    changes
+}
+
+/// Merges the profile's basal schedule with temp basal treatments over `[from, to)`.
+///
+/// Treatments other than temp basals are ignored. Temp basals give their rate as `absolute`,
+/// `rate` or a `percent` change of the scheduled rate, and end early when another one starts.
+pub fn basal_timeline(
+    profile: &ProfileConfig,
+    treatments: &[Treatment],
+    from: DateTime<Utc>,
+    to: DateTime<Utc>,
+) -> BasalTimeline {
+    if to <= from {
+        return BasalTimeline::default();
+    }
+
+    let temps = temp_basals(treatments);
+
+    let mut boundaries = vec![from, to];
+    boundaries.extend(schedule_changes(profile, from, to));
+    for temp in &temps {
+        boundaries.extend(
+            [temp.start, temp.end]
+                .into_iter()
+                .filter(|t| from < *t && *t < to),
+        );
+    }
+    boundaries.sort();
+    boundaries.dedup();
+
+    let mut timeline = BasalTimeline::default();
+    for window in boundaries.windows(2) {
+        let (start, end) = (window[0], window[1]);
+        let scheduled_rate = profile.basal_at(start).unwrap_or(0.0);
+        let temp = temps
+            .iter()
+            .find(|temp| temp.start <= start && start < temp.end);
+        let rate = temp.map_or(scheduled_rate, |temp| temp.rate(scheduled_rate));
+
+        match timeline.segments.last_mut() {
+            Some(last)
+                if last.rate == rate
+                    && last.scheduled_rate == scheduled_rate
+                    && last.temp == temp.is_some() =>
+            {
+                last.end = end;
+            }
+            _ => timeline.segments.push(BasalSegment {
+                start,
+                end,
+                rate,
+                scheduled_rate,
+                temp: temp.is_some(),
+            }),
+        }
+    }
+
+    timeline
+}
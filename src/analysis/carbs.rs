@@ -0,0 +1,85 @@
+//! Local carbs on board (COB) calculation from carb treatments.
+//!
+//! Carbs are absorbed linearly at a fixed rate after an initial delay, like the Nightscout
+//! COB plugin does when no insulin-adjusted absorption is available.
+
+use crate::models::treatments::Treatment;
+use crate::query_builder::HasDate;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// A linear carb absorption model.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct CarbModel {
+    /// Absorption rate, in grams per hour.
+    pub absorption_rate: f64,
+    /// Minutes before absorption starts.
+    pub delay_minutes: f64,
+}
+
+impl Default for CarbModel {
+    /// 30 g/h after a 20 minute delay, the Nightscout defaults.
+    fn default() -> Self {
+        Self {
+            absorption_rate: 30.0,
+            delay_minutes: 20.0,
+        }
+    }
+}
+
+impl CarbModel {
+    pub fn new(absorption_rate: f64, delay_minutes: f64) -> Self {
+        Self {
+            absorption_rate,
+            delay_minutes,
+        }
+    }
+
+    /// Grams of `carbs` still unabsorbed `minutes` after they were eaten.
+    pub fn remaining(&self, carbs: f64, minutes: f64) -> f64 {
+        if minutes < 0.0 {
+            return 0.0;
+        }
+
+        let absorbing = (minutes - self.delay_minutes).max(0.0);
+        (carbs - absorbing * self.absorption_rate / 60.0).max(0.0)
+    }
+}
+
+/// Result of a local COB calculation.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CobResult {
+    /// Carbs on board (g).
+    pub cob: f64,
+    /// Time of the most recent carb entry taken into account.
+    pub last_carbs: Option<DateTime<Utc>>,
+}
+
+/// Computes the carbs on board at `at` from the carbs of the given treatments.
+///
+/// Treatments without carbs, without a parseable timestamp or entered after `at` are ignored.
+pub fn cob_at(treatments: &[Treatment], model: &CarbModel, at: DateTime<Utc>) -> CobResult {
+    let mut result = CobResult {
+        cob: 0.0,
+        last_carbs: None,
+    };
+
+    for treatment in treatments {
+        let (Some(carbs), Some(time)) = (treatment.carbs, treatment.timestamp()) else {
+            continue;
+        };
+        if carbs <= 0.0 || time > at {
+            continue;
+        }
+
+        let minutes = (at - time).num_milliseconds() as f64 / 60_000.0;
+        result.cob += model.remaining(carbs, minutes);
+
+        if result.last_carbs.is_none_or(|last| time > last) {
+            result.last_carbs = Some(time);
+        }
+    }
+
+    result
+}
@@ -0,0 +1,154 @@
+//! Detection of hypoglycemic and hyperglycemic excursions in SGV history.
+
+use crate::models::entries::SgvEntry;
+use crate::query_builder::HasDate;
+use crate::stats::TargetRanges;
+
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Direction of an excursion.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum EventKind {
+    Hypo,
+    Hyper,
+}
+
+/// Consensus severity of an excursion.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Severity {
+    /// Crossed the low or high threshold only.
+    Level1,
+    /// Crossed the very low or very high threshold.
+    Level2,
+}
+
+/// A continuous excursion outside the target range.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct GlycemicEvent {
+    pub kind: EventKind,
+    pub severity: Severity,
+    /// Time of the first reading out of range.
+    pub start: DateTime<Utc>,
+    /// One sample interval after the last reading out of range, when the excursion was
+    /// last known to go on.
+    pub end: DateTime<Utc>,
+    /// Nadir for hypos, peak for hypers (mg/dL).
+    pub extreme: f64,
+    pub extreme_at: DateTime<Utc>,
+    /// Number of readings in the excursion.
+    pub readings: usize,
+}
+
+impl GlycemicEvent {
+    /// Time spent out of range, counting each reading for one sample interval.
+    pub fn duration(&self) -> Duration {
+        self.end - self.start
+    }
+}
+
+/// Thresholds and rules used by [`detect_events`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct EventOptions {
+    pub ranges: TargetRanges,
+    /// Excursions shorter than this are ignored. Default is 15 minutes.
+    pub min_duration: Duration,
+    /// A gap between readings longer than this splits an excursion. Default is 15 minutes.
+    pub max_gap: Duration,
+    /// Time covered by each reading. Default is 5 minutes, the interval of most CGMs.
+    pub sample_interval: Duration,
+}
+
+impl Default for EventOptions {
+    fn default() -> Self {
+        Self {
+            ranges: TargetRanges::default(),
+            min_duration: Duration::minutes(15),
+            max_gap: Duration::minutes(15),
+            sample_interval: Duration::minutes(5),
+        }
+    }
+}
+
+impl EventOptions {
+    fn kind_of(&self, value: f64) -> Option<EventKind> {
+        if value < self.ranges.low {
+            Some(EventKind::Hypo)
+        } else if value > self.ranges.high {
+            Some(EventKind::Hyper)
+        } else {
+            None
+        }
+    }
+
+    fn severity_of(&self, kind: EventKind, extreme: f64) -> Severity {
+        let level2 = match kind {
+            EventKind::Hypo => extreme < self.ranges.very_low,
+            EventKind::Hyper => extreme > self.ranges.very_high,
+        };
+        if level2 {
+            Severity::Level2
+        } else {
+            Severity::Level1
+        }
+    }
+}
+
+/// Scans SGV entries (in any order) for excursions below the low or above the high threshold.
+pub fn detect_events(entries: &[SgvEntry], options: &EventOptions) -> Vec<GlycemicEvent> {
+    let mut readings: Vec<(DateTime<Utc>, f64)> = entries
+        .iter()
+        .filter_map(|entry| Some((entry.timestamp()?, f64::from(entry.sgv))))
+        .collect();
+    readings.sort_by_key(|(time, _)| *time);
+
+    let mut events = Vec::new();
+    let mut current: Option<GlycemicEvent> = None;
+    let mut close = |event: GlycemicEvent| {
+        if event.duration() >= options.min_duration {
+            events.push(event);
+        }
+    };
+
+    for (time, value) in readings {
+        let kind = options.kind_of(value);
+
+        if let Some(event) = current.as_mut() {
+            let last = event.end - options.sample_interval;
+            if Some(event.kind) == kind && time - last <= options.max_gap {
+                event.end = time + options.sample_interval;
+                event.readings += 1;
+                let more_extreme = match event.kind {
+                    EventKind::Hypo => value < event.extreme,
+                    EventKind::Hyper => value > event.extreme,
+                };
+                if more_extreme {
+                    event.extreme = value;
+                    event.extreme_at = time;
+                }
+                event.severity = options.severity_of(event.kind, event.extreme);
+                continue;
+            }
+        }
+
+        if let Some(event) = current.take() {
+            close(event);
+        }
+        current = kind.map(|kind| GlycemicEvent {
+            kind,
+            severity: options.severity_of(kind, value),
+            start: time,
+            end: time + options.sample_interval,
+            extreme: value,
+            extreme_at: time,
+            readings: 1,
+        });
+    }
+
+    if let Some(event) = current {
+        close(event);
+    }
+
+    events
+}
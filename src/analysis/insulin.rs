@@ -0,0 +1,157 @@
+//! Local insulin on board (IOB) calculation from bolus treatments.
+//!
+//! This mirrors the models used by Nightscout and oref0, for servers where the IOB plugin
+//! is disabled or the devicestatus uploads are stale.
+
+use crate::models::treatments::Treatment;
+use crate::query_builder::HasDate;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// The shape of the insulin action curve.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub enum InsulinCurve {
+    /// The legacy Nightscout model: a bilinear activity curve peaking at 75 minutes,
+    /// stretched to the DIA.
+    Bilinear,
+    /// The oref0 exponential model with the given activity peak, in minutes.
+    Exponential { peak_minutes: f64 },
+}
+
+impl InsulinCurve {
+    /// Exponential curve for rapid-acting insulin (Humalog, Novolog), peaking at 75 minutes.
+    pub const RAPID_ACTING: InsulinCurve = InsulinCurve::Exponential { peak_minutes: 75.0 };
+
+    /// Exponential curve for ultra-rapid insulin (Fiasp, Lyumjev), peaking at 55 minutes.
+    pub const ULTRA_RAPID: InsulinCurve = InsulinCurve::Exponential { peak_minutes: 55.0 };
+}
+
+/// An insulin action model: a curve and a duration of insulin action.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct InsulinModel {
+    /// Duration of insulin action, in hours.
+    pub dia_hours: f64,
+    pub curve: InsulinCurve,
+}
+
+impl Default for InsulinModel {
+    /// Rapid-acting insulin with a 5 hour DIA, the oref0 defaults.
+    fn default() -> Self {
+        Self {
+            dia_hours: 5.0,
+            curve: InsulinCurve::RAPID_ACTING,
+        }
+    }
+}
+
+impl InsulinModel {
+    pub fn new(dia_hours: f64, curve: InsulinCurve) -> Self {
+        Self { dia_hours, curve }
+    }
+
+    /// Fraction of a dose still on board `minutes` after it was given.
+    pub fn iob_fraction(&self, minutes: f64) -> f64 {
+        self.contribution(minutes).0
+    }
+
+    /// Fraction of a dose absorbed per minute, `minutes` after it was given.
+    pub fn activity_fraction(&self, minutes: f64) -> f64 {
+        self.contribution(minutes).1
+    }
+
+    /// Returns `(iob_fraction, activity_fraction_per_minute)`.
+    fn contribution(&self, minutes: f64) -> (f64, f64) {
+        if minutes < 0.0 {
+            return (0.0, 0.0);
+        }
+
+        match self.curve {
+            InsulinCurve::Bilinear => {
+                let dia = self.dia_hours.max(3.0);
+                let scale = 3.0 / dia;
+                let peak = 75.0;
+                let min_ago = scale * minutes;
+
+                if min_ago < peak {
+                    let x1 = min_ago / 5.0 + 1.0;
+                    let iob = 1.0 - 0.001852 * x1 * x1 + 0.001852 * x1;
+                    let activity = (2.0 / dia / 60.0 / peak) * min_ago;
+                    (iob, activity)
+                } else if min_ago < 180.0 {
+                    let x2 = (min_ago - 75.0) / 5.0;
+                    let iob = 0.001323 * x2 * x2 - 0.054233 * x2 + 0.55556;
+                    let activity =
+                        2.0 / dia / 60.0 - (min_ago - peak) * 2.0 / dia / 60.0 / (180.0 - peak);
+                    (iob.max(0.0), activity.max(0.0))
+                } else {
+                    (0.0, 0.0)
+                }
+            }
+            InsulinCurve::Exponential { peak_minutes } => {
+                let end = self.dia_hours * 60.0;
+                if minutes >= end {
+                    return (0.0, 0.0);
+                }
+
+                let t = minutes;
+                let tau =
+                    peak_minutes * (1.0 - peak_minutes / end) / (1.0 - 2.0 * peak_minutes / end);
+                let a = 2.0 * tau / end;
+                let s = 1.0 / (1.0 - a + (1.0 + a) * (-end / tau).exp());
+
+                let activity = (s / tau.powi(2)) * t * (1.0 - t / end) * (-t / tau).exp();
+                let iob = 1.0
+                    - s * (1.0 - a)
+                        * ((t.powi(2) / (tau * end * (1.0 - a)) - t / tau - 1.0)
+                            * (-t / tau).exp()
+                            + 1.0);
+
+                (iob.clamp(0.0, 1.0), activity.max(0.0))
+            }
+        }
+    }
+}
+
+/// Result of a local IOB calculation.
+#[derive(Debug, Clone, PartialEq)]
+pub struct IobResult {
+    /// Insulin on board (U).
+    pub iob: f64,
+    /// Insulin activity (U/min).
+    pub activity: f64,
+    /// Time of the most recent bolus taken into account.
+    pub last_bolus: Option<DateTime<Utc>>,
+}
+
+/// Computes the insulin on board at `at` from the insulin of the given treatments.
+///
+/// Treatments without insulin, without a parseable timestamp or given after `at` are ignored.
+pub fn iob_at(treatments: &[Treatment], model: &InsulinModel, at: DateTime<Utc>) -> IobResult {
+    let mut result = IobResult {
+        iob: 0.0,
+        activity: 0.0,
+        last_bolus: None,
+    };
+
+    for treatment in treatments {
+        let (Some(insulin), Some(time)) = (treatment.insulin, treatment.timestamp()) else {
+            continue;
+        };
+        if insulin <= 0.0 || time > at {
+            continue;
+        }
+
+        let minutes = (at - time).num_milliseconds() as f64 / 60_000.0;
+        let (iob, activity) = model.contribution(minutes);
+        result.iob += insulin * iob;
+        result.activity += insulin * activity;
+
+        if result.last_bolus.is_none_or(|last| time > last) {
+            result.last_bolus = Some(time);
+        }
+    }
+
+    result
+}
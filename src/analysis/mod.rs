@@ -0,0 +1,245 @@
+//! Local analysis of Nightscout data.
+//!
+//! These computations run on the client, as a fallback for servers where the equivalent
+//! plugins are disabled or their data is stale.
+
+pub mod ages;
+pub mod ar2;
+pub mod basal;
+pub mod carbs;
+pub mod events;
+pub mod insulin;
+pub mod series;
+
+use crate::client::NightscoutClient;
+use crate::error::NightscoutError;
+use crate::models::entries::SgvEntry;
+use crate::models::profile::{self, ProfileConfig, PROFILE_SWITCH_EVENT};
+use crate::models::treatments::Treatment;
+use crate::query_builder::{saturating_sub, FilterOp};
+use ages::{Ages, AGE_EVENTS};
+use ar2::Ar2Forecast;
+use basal::{BasalTimeline, TEMP_BASAL_EVENT};
+use carbs::{CarbModel, CobResult};
+use events::{EventOptions, GlycemicEvent};
+use insulin::{InsulinModel, IobResult};
+use series::SgvSeries;
+
+use chrono::{DateTime, Duration, Utc};
+use futures::TryStreamExt;
+
+/// How far back carb treatments are considered for COB.
+const CARBS_LOOKBACK_HOURS: i64 = 8;
+
+/// How far back temp basals are fetched, to catch one already running at the range start.
+const TEMP_BASAL_LOOKBACK_HOURS: i64 = 24;
+
+/// How far back change treatments are searched for the device ages.
+const AGES_LOOKBACK_DAYS: i64 = 90;
+
+/// Number of recent Profile Switch treatments searched for the one in effect.
+const PROFILE_SWITCH_LIMIT: usize = 50;
+
+/// Number of recent readings fetched for the AR2 forecast.
+const AR2_READINGS: usize = 3;
+
+/// Number of entries fetched per request while collecting history.
+const ENTRIES_PAGE_SIZE: usize = 1000;
+
+/// Number of treatments fetched per request while collecting history.
+const TREATMENTS_PAGE_SIZE: usize = 500;
+
+pub struct AnalysisService {
+    pub client: NightscoutClient,
+    insulin_model: InsulinModel,
+    carb_model: CarbModel,
+}
+
+impl AnalysisService {
+    pub fn new(client: NightscoutClient) -> Self {
+        Self {
+            client,
+            insulin_model: InsulinModel::default(),
+            carb_model: CarbModel::default(),
+        }
+    }
+
+    /// Overrides the insulin model used for IOB. Defaults to rapid-acting with a 5h DIA.
+    pub fn insulin_model(mut self, model: InsulinModel) -> Self {
+        self.insulin_model = model;
+        self
+    }
+
+    /// Overrides the carb absorption model used for COB. Defaults to 30 g/h after 20 minutes.
+    pub fn carb_model(mut self, model: CarbModel) -> Self {
+        self.carb_model = model;
+        self
+    }
+
+    /// Fetches treatments created in `[from, to]`.
+    async fn treatments_between(
+        &self,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> Result<Vec<Treatment>, NightscoutError> {
+        self.client
+            .treatments()
+            .get()
+            .from(from)
+            .to(to)
+            .paginate(TREATMENTS_PAGE_SIZE)
+            .try_collect()
+            .await
+    }
+
+    /// Computes the insulin on board at `at` from the bolus treatments of the previous DIA.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// # use cinnamon::client::NightscoutClient;
+    /// # use chrono::Utc;
+    /// # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+    /// let client = NightscoutClient::new("https://ns.example.com")?;
+    /// let iob = client.analysis().iob_at(Utc::now()).await?;
+    /// println!("IOB: {:.2} U", iob.iob);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn iob_at(&self, at: DateTime<Utc>) -> Result<IobResult, NightscoutError> {
+        let minutes = (self.insulin_model.dia_hours * 60.0).ceil() as i64;
+        let window = Duration::try_minutes(minutes).unwrap_or(Duration::MAX);
+        let treatments = self
+            .treatments_between(saturating_sub(at, window), at)
+            .await?;
+
+        Ok(insulin::iob_at(&treatments, &self.insulin_model, at))
+    }
+
+    /// Computes the carbs on board at `at` from the carb treatments of the previous hours.
+    pub async fn cob_at(&self, at: DateTime<Utc>) -> Result<CobResult, NightscoutError> {
+        let window = Duration::hours(CARBS_LOOKBACK_HOURS);
+        let treatments = self
+            .treatments_between(saturating_sub(at, window), at)
+            .await?;
+
+        Ok(carbs::cob_at(&treatments, &self.carb_model, at))
+    }
+
+    /// Returns the default profile of the profile set active at `at`.
+    pub async fn active_profile(
+        &self,
+        at: DateTime<Utc>,
+    ) -> Result<ProfileConfig, NightscoutError> {
+        let sets = self.client.profiles().get().await?;
+
+        sets.iter()
+            .filter(|set| set.start().is_none_or(|start| start <= at))
+            .max_by_key(|set| set.start())
+            .or(sets.first())
+            .and_then(|set| set.default_profile())
+            .cloned()
+            .ok_or(NightscoutError::NotFound)
+    }
+
+    /// Returns the profile in effect at `at`, taking Profile Switch treatments into account.
+    ///
+    /// See [`effective_profile`](crate::models::profile::effective_profile).
+    pub async fn effective_profile(
+        &self,
+        at: DateTime<Utc>,
+    ) -> Result<ProfileConfig, NightscoutError> {
+        let sets = self.client.profiles().get().await?;
+        let switches = self
+            .client
+            .treatments()
+            .get()
+            .filter("eventType", FilterOp::Eq, PROFILE_SWITCH_EVENT)
+            .to(at)
+            .limit(PROFILE_SWITCH_LIMIT)
+            .send()
+            .await?;
+
+        profile::effective_profile(&sets, &switches, at).ok_or(NightscoutError::NotFound)
+    }
+
+    /// Computes the cannula, sensor, insulin and pump battery ages from the change
+    /// treatments of the last 90 days.
+    ///
+    /// A fallback for servers where the cage, sage, iage and bage plugins are disabled.
+    pub async fn ages(&self) -> Result<Ages, NightscoutError> {
+        let now = Utc::now();
+        let treatments: Vec<Treatment> = self
+            .client
+            .treatments()
+            .get()
+            .filter_in("eventType", &AGE_EVENTS)
+            .from(saturating_sub(now, Duration::days(AGES_LOOKBACK_DAYS)))
+            .to(now)
+            .paginate(TREATMENTS_PAGE_SIZE)
+            .try_collect()
+            .await?;
+
+        Ok(ages::ages(&treatments))
+    }
+
+    /// Reconstructs the effective basal rate over `[from, to)`.
+    ///
+    /// Uses the profile active at `from` and the temp basal treatments of the range.
+    pub async fn basal_timeline(
+        &self,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> Result<BasalTimeline, NightscoutError> {
+        let profile = self.active_profile(from).await?;
+        let temps: Vec<Treatment> = self
+            .client
+            .treatments()
+            .get()
+            .filter("eventType", FilterOp::Eq, TEMP_BASAL_EVENT)
+            .from(saturating_sub(
+                from,
+                Duration::hours(TEMP_BASAL_LOOKBACK_HOURS),
+            ))
+            .to(to)
+            .paginate(TREATMENTS_PAGE_SIZE)
+            .try_collect()
+            .await?;
+
+        Ok(basal::basal_timeline(&profile, &temps, from, to))
+    }
+
+    /// Detects hypo and hyper excursions in the SGV history of `[from, to]`.
+    pub async fn glycemic_events(
+        &self,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+        options: &EventOptions,
+    ) -> Result<Vec<GlycemicEvent>, NightscoutError> {
+        let entries: Vec<SgvEntry> = self
+            .client
+            .sgv()
+            .get()
+            .from(from)
+            .to(to)
+            .paginate(ENTRIES_PAGE_SIZE)
+            .try_collect()
+            .await?;
+
+        Ok(events::detect_events(&entries, options))
+    }
+
+    /// Forecasts glucose up to `horizon` from the latest readings with the AR2 model,
+    /// `None` without two recent consecutive readings. See [`ar2::ar2_forecast`].
+    pub async fn ar2_forecast(
+        &self,
+        horizon: Duration,
+    ) -> Result<Option<Ar2Forecast>, NightscoutError> {
+        let entries = self.client.sgv().get().limit(AR2_READINGS).send().await?;
+
+        Ok(ar2::ar2_forecast(
+            &SgvSeries::from_entries(&entries),
+            horizon,
+        ))
+    }
+}
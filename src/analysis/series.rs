@@ -0,0 +1,200 @@
+//! Time-ordered glucose series, with gap detection and resampling to a fixed grid.
+
+use crate::models::entries::SgvEntry;
+use crate::models::trends::Trend;
+use crate::query_builder::HasDate;
+
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Readings further apart than this have no delta.
+const MAX_DELTA_INTERVAL_MINUTES: i64 = 15;
+
+/// Readings within this many minutes of the latest one are used for the slope.
+const SLOPE_WINDOW_MINUTES: i64 = 15;
+
+/// One glucose reading of a series.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub struct GlucosePoint {
+    pub time: DateTime<Utc>,
+    /// Glucose value (mg/dL).
+    pub mgdl: f64,
+}
+
+/// A stretch of time without readings.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub struct Gap {
+    /// Time of the last reading before the gap.
+    pub start: DateTime<Utc>,
+    /// Time of the first reading after the gap.
+    pub end: DateTime<Utc>,
+}
+
+impl Gap {
+    pub fn duration(&self) -> Duration {
+        self.end - self.start
+    }
+}
+
+/// Glucose readings sorted by time, oldest first, with at most one reading per instant.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Default)]
+pub struct SgvSeries {
+    points: Vec<GlucosePoint>,
+}
+
+impl SgvSeries {
+    /// Builds a series from points in any order. Duplicate timestamps keep the first point.
+    pub fn new(mut points: Vec<GlucosePoint>) -> Self {
+        points.sort_by_key(|point| point.time);
+        points.dedup_by_key(|point| point.time);
+        Self { points }
+    }
+
+    /// Builds a series from SGV entries, skipping entries without a valid date.
+    pub fn from_entries(entries: &[SgvEntry]) -> Self {
+        Self::new(
+            entries
+                .iter()
+                .filter_map(|entry| {
+                    Some(GlucosePoint {
+                        time: entry.timestamp()?,
+                        mgdl: f64::from(entry.sgv),
+                    })
+                })
+                .collect(),
+        )
+    }
+
+    pub fn points(&self) -> &[GlucosePoint] {
+        &self.points
+    }
+
+    pub fn len(&self) -> usize {
+        self.points.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.points.is_empty()
+    }
+
+    /// The most recent reading.
+    pub fn last(&self) -> Option<&GlucosePoint> {
+        self.points.last()
+    }
+
+    /// Change between the two most recent readings (mg/dL).
+    ///
+    /// `None` with fewer than two readings or when they are more than 15 minutes apart.
+    pub fn delta(&self) -> Option<f64> {
+        let [.., previous, last] = self.points.as_slice() else {
+            return None;
+        };
+        (last.time - previous.time <= Duration::minutes(MAX_DELTA_INTERVAL_MINUTES))
+            .then_some(last.mgdl - previous.mgdl)
+    }
+
+    /// Rate of change over the last 15 minutes (mg/dL per minute).
+    ///
+    /// Fitted by least squares over the readings of the window, so a single noisy reading
+    /// weighs less than in [`delta`](Self::delta). `None` with fewer than two readings in
+    /// the window.
+    pub fn slope(&self) -> Option<f64> {
+        let last = self.last()?;
+        let since = last.time - Duration::minutes(SLOPE_WINDOW_MINUTES);
+        let window: Vec<(f64, f64)> = self
+            .points
+            .iter()
+            .filter(|point| point.time >= since)
+            .map(|point| {
+                let minutes = (point.time - last.time).num_milliseconds() as f64 / 60_000.0;
+                (minutes, point.mgdl)
+            })
+            .collect();
+        if window.len() < 2 {
+            return None;
+        }
+
+        let n = window.len() as f64;
+        let mean_x = window.iter().map(|(x, _)| x).sum::<f64>() / n;
+        let mean_y = window.iter().map(|(_, y)| y).sum::<f64>() / n;
+        let covariance: f64 = window
+            .iter()
+            .map(|(x, y)| (x - mean_x) * (y - mean_y))
+            .sum();
+        let variance: f64 = window.iter().map(|(x, _)| (x - mean_x).powi(2)).sum();
+
+        (variance > 0.0).then(|| covariance / variance)
+    }
+
+    /// Trend arrow derived from [`slope`](Self::slope), using the Dexcom thresholds.
+    pub fn trend(&self) -> Option<Trend> {
+        self.slope().map(Trend::from_slope)
+    }
+
+    /// Finds every interval between consecutive readings longer than `max_interval`.
+    ///
+    /// With a CGM reading every 5 minutes, a `max_interval` of 10-15 minutes reports sensor
+    /// warmups and dropouts while tolerating a single missed reading.
+    pub fn gaps(&self, max_interval: Duration) -> Vec<Gap> {
+        self.points
+            .windows(2)
+            .filter(|pair| pair[1].time - pair[0].time > max_interval)
+            .map(|pair| Gap {
+                start: pair[0].time,
+                end: pair[1].time,
+            })
+            .collect()
+    }
+
+    /// Resamples the series onto a grid of `interval` aligned to the Unix epoch.
+    ///
+    /// Grid points between two readings are linearly interpolated, unless the readings are
+    /// more than `max_gap` apart, in which case the gap is left empty rather than invented.
+    pub fn resample(&self, interval: Duration, max_gap: Duration) -> SgvSeries {
+        let step = interval.num_milliseconds();
+        let (Some(first), Some(last)) = (self.points.first(), self.points.last()) else {
+            return SgvSeries::default();
+        };
+        if step <= 0 {
+            return SgvSeries::default();
+        }
+
+        let mut millis = first.time.timestamp_millis().div_euclid(step) * step;
+        if millis < first.time.timestamp_millis() {
+            millis += step;
+        }
+
+        let mut points = Vec::new();
+        let mut next = 0;
+        while millis <= last.time.timestamp_millis() {
+            let Some(time) = DateTime::from_timestamp_millis(millis) else {
+                break;
+            };
+
+            // Advance to the first reading at or after the grid point.
+            while self.points[next].time < time {
+                next += 1;
+            }
+
+            let after = self.points[next];
+            if after.time == time {
+                points.push(after);
+            } else if next > 0 {
+                let before = self.points[next - 1];
+                let span = after.time - before.time;
+                if span <= max_gap {
+                    let fraction = (time - before.time).num_milliseconds() as f64
+                        / span.num_milliseconds() as f64;
+                    points.push(GlucosePoint {
+                        time,
+                        mgdl: before.mgdl + (after.mgdl - before.mgdl) * fraction,
+                    });
+                }
+            }
+
+            millis += step;
+        }
+
+        SgvSeries { points }
+    }
+}
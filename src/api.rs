@@ -0,0 +1,140 @@
+//! The main operations of the client behind a trait, so applications can substitute their
+//! own implementation in unit tests. Not available on WebAssembly, where the client's
+//! futures are not `Send`.
+
+use crate::client::NightscoutClient;
+use crate::error::NightscoutError;
+use crate::models::devicestatus::DeviceStatus;
+use crate::models::entries::SgvEntry;
+use crate::models::profile::ProfileSet;
+use crate::models::status::Status;
+use crate::models::treatments::Treatment;
+
+use chrono::{DateTime, Utc};
+use futures::TryStreamExt;
+use std::future::Future;
+
+/// Page size used to fetch every document of a date range.
+const RANGE_PAGE_SIZE: usize = 1000;
+
+/// The operations most applications need from a Nightscout site.
+///
+/// [`NightscoutClient`] implements it against a server. Code written against the trait
+/// can be handed a hand-rolled implementation in tests instead of a mock server.
+///
+/// The methods return `Send` futures, so they can be awaited on multi-threaded runtimes
+/// such as `tokio::spawn`. Implementations may still write them as `async fn`. The trait is
+/// used through generics rather than `dyn NightscoutApi`.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// # use cinnamon::api::NightscoutApi;
+/// # use cinnamon::error::NightscoutError;
+/// async fn is_high(api: &impl NightscoutApi) -> Result<bool, NightscoutError> {
+///     Ok(api.latest_sgv().await?.sgv > 180)
+/// }
+/// ```
+pub trait NightscoutApi {
+    /// Retrieves the server status, version and settings.
+    fn server_status(&self) -> impl Future<Output = Result<Status, NightscoutError>> + Send;
+
+    /// Retrieves the most recent glucose reading.
+    fn latest_sgv(&self) -> impl Future<Output = Result<SgvEntry, NightscoutError>> + Send;
+
+    /// Retrieves every glucose reading between `from` and `to`, newest first.
+    fn sgv_between(
+        &self,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> impl Future<Output = Result<Vec<SgvEntry>, NightscoutError>> + Send;
+
+    /// Retrieves every treatment between `from` and `to`, newest first.
+    fn treatments_between(
+        &self,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> impl Future<Output = Result<Vec<Treatment>, NightscoutError>> + Send;
+
+    /// Uploads treatments, returning them as stored by the server.
+    fn create_treatments(
+        &self,
+        treatments: Vec<Treatment>,
+    ) -> impl Future<Output = Result<Vec<Treatment>, NightscoutError>> + Send;
+
+    /// Retrieves the most recent device status, if any.
+    fn latest_device_status(
+        &self,
+    ) -> impl Future<Output = Result<Option<DeviceStatus>, NightscoutError>> + Send;
+
+    /// Retrieves the stored profile sets.
+    fn profile_sets(&self)
+        -> impl Future<Output = Result<Vec<ProfileSet>, NightscoutError>> + Send;
+
+    /// Retrieves the current insulin on board (U).
+    fn iob(&self) -> impl Future<Output = Result<f64, NightscoutError>> + Send;
+
+    /// Retrieves the current carbs on board (g).
+    fn cob(&self) -> impl Future<Output = Result<f64, NightscoutError>> + Send;
+}
+
+impl NightscoutApi for NightscoutClient {
+    async fn server_status(&self) -> Result<Status, NightscoutError> {
+        self.status().get().await
+    }
+
+    async fn latest_sgv(&self) -> Result<SgvEntry, NightscoutError> {
+        self.sgv().latest().await?.ok_or(NightscoutError::NotFound)
+    }
+
+    async fn sgv_between(
+        &self,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> Result<Vec<SgvEntry>, NightscoutError> {
+        self.sgv()
+            .get()
+            .from(from)
+            .to(to)
+            .paginate(RANGE_PAGE_SIZE)
+            .try_collect()
+            .await
+    }
+
+    async fn treatments_between(
+        &self,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> Result<Vec<Treatment>, NightscoutError> {
+        self.treatments()
+            .get()
+            .from(from)
+            .to(to)
+            .paginate(RANGE_PAGE_SIZE)
+            .try_collect()
+            .await
+    }
+
+    async fn create_treatments(
+        &self,
+        treatments: Vec<Treatment>,
+    ) -> Result<Vec<Treatment>, NightscoutError> {
+        self.treatments().create(treatments).await
+    }
+
+    async fn latest_device_status(&self) -> Result<Option<DeviceStatus>, NightscoutError> {
+        self.devicestatus().latest().await
+    }
+
+    async fn profile_sets(&self) -> Result<Vec<ProfileSet>, NightscoutError> {
+        self.profiles().get().await
+    }
+
+    async fn iob(&self) -> Result<f64, NightscoutError> {
+        self.properties().iob().await
+    }
+
+    async fn cob(&self) -> Result<f64, NightscoutError> {
+        self.properties().cob().await
+    }
+}
@@ -0,0 +1,315 @@
+//! Command-line access to a Nightscout site, built with the `cli` feature.
+//!
+//! The site and credentials are read from the environment like
+//! `NightscoutClient::from_env` does (see `cinnamon::config`), the `--url`, `--token` and
+//! `--secret` options take precedence.
+//!
+//! ```text
+//! cinnamon bg
+//! cinnamon treatment add --carbs 15 --notes "Juice"
+//! cinnamon export csv --days 14 --output glucose.csv
+//! ```
+
+use chrono::{DateTime, Local, Utc};
+use cinnamon::client::NightscoutClient;
+use cinnamon::config::ClientConfig;
+use cinnamon::error::NightscoutError;
+use cinnamon::models::glucose::GlucoseUnit;
+use cinnamon::models::properties::PropertyType;
+use cinnamon::models::treatments::{Treatment, TreatmentBuilder};
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use futures::StreamExt;
+use std::process::ExitCode;
+use std::time::Duration;
+use tokio::io::AsyncWrite;
+
+#[derive(Parser)]
+#[command(
+    name = "cinnamon",
+    version,
+    about = "Query and update a Nightscout site"
+)]
+struct Cli {
+    #[command(flatten)]
+    site: Site,
+
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Args)]
+struct Site {
+    /// Address of the site [env: NIGHTSCOUT_URL]
+    #[arg(long, global = true)]
+    url: Option<String>,
+
+    /// Access token, for reading protected sites [env: NIGHTSCOUT_TOKEN]
+    #[arg(long, global = true)]
+    token: Option<String>,
+
+    /// API secret, required for uploads [env: NIGHTSCOUT_API_SECRET or API_SECRET]
+    #[arg(long, global = true)]
+    secret: Option<String>,
+
+    /// Unit glucose is displayed in (mg/dl or mmol).
+    #[arg(long, global = true, env = "NIGHTSCOUT_UNITS")]
+    units: Option<GlucoseUnit>,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Shows the latest glucose reading.
+    Bg,
+    /// Shows the insulin and carbs on board.
+    Iob,
+    /// Lists treatments.
+    Treatments {
+        #[command(subcommand)]
+        command: TreatmentsCommand,
+    },
+    /// Uploads a treatment.
+    Treatment {
+        #[command(subcommand)]
+        command: TreatmentCommand,
+    },
+    /// Exports data.
+    Export {
+        #[command(subcommand)]
+        command: ExportCommand,
+    },
+    /// Prints new glucose readings as they arrive.
+    Watch {
+        /// Seconds between polls.
+        #[arg(long, default_value_t = 60)]
+        interval: u64,
+    },
+}
+
+#[derive(Subcommand)]
+enum TreatmentsCommand {
+    /// Lists the treatments of the last hours, newest first.
+    List {
+        #[arg(long, default_value_t = 24)]
+        hours: i64,
+        #[arg(long, default_value_t = 50)]
+        limit: usize,
+    },
+}
+
+#[derive(Subcommand)]
+enum TreatmentCommand {
+    /// Adds a treatment dated now. The event type follows from the values given.
+    Add {
+        /// Carbs (g).
+        #[arg(long)]
+        carbs: Option<f64>,
+        /// Insulin (U).
+        #[arg(long)]
+        insulin: Option<f64>,
+        #[arg(long)]
+        notes: Option<String>,
+        /// Explicit event type, e.g. "Site Change".
+        #[arg(long)]
+        event_type: Option<String>,
+    },
+}
+
+#[derive(Subcommand)]
+enum ExportCommand {
+    /// Writes a CSV file of readings or treatments.
+    Csv {
+        #[arg(long, value_enum, default_value_t = Collection::Sgv)]
+        collection: Collection,
+        #[arg(long, default_value_t = 14)]
+        days: i64,
+        /// Output file, the standard output by default.
+        #[arg(long)]
+        output: Option<std::path::PathBuf>,
+    },
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Collection {
+    Sgv,
+    Treatments,
+}
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    let cli = Cli::parse();
+    match run(cli).await {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("error: {e}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+async fn run(cli: Cli) -> Result<(), NightscoutError> {
+    let units = cli.site.units.unwrap_or_default();
+    let client = connect(cli.site)?;
+
+    match cli.command {
+        Command::Bg => {
+            let Some(entry) = client.sgv().latest().await? else {
+                println!("No readings");
+                return Ok(());
+            };
+            println!(
+                "{} {} ({})",
+                entry.glucose().to_unit(units),
+                entry.direction,
+                entry.datetime().map(local_time).unwrap_or_default()
+            );
+        }
+        Command::Iob => {
+            let properties = client
+                .properties()
+                .get()
+                .only(&[PropertyType::Iob, PropertyType::Cob])
+                .send()
+                .await?;
+            match properties.active_insulin() {
+                Some(iob) => println!("IOB: {iob:.2} U"),
+                None => println!("IOB: unavailable"),
+            }
+            match properties.carbs_remaining() {
+                Some(cob) => println!("COB: {cob:.0} g"),
+                None => println!("COB: unavailable"),
+            }
+        }
+        Command::Treatments {
+            command: TreatmentsCommand::List { hours, limit },
+        } => {
+            let treatments = client
+                .treatments()
+                .get()
+                .last_hours(hours)
+                .limit(limit)
+                .send()
+                .await?;
+            for treatment in treatments {
+                println!("{}", describe(&treatment));
+            }
+        }
+        Command::Treatment {
+            command:
+                TreatmentCommand::Add {
+                    carbs,
+                    insulin,
+                    notes,
+                    event_type,
+                },
+        } => {
+            let mut builder = match (event_type, carbs, insulin) {
+                (Some(event_type), _, _) => TreatmentBuilder::new(&event_type),
+                (None, Some(grams), _) => Treatment::carbs(grams),
+                (None, None, Some(units)) => Treatment::bolus(units),
+                (None, None, None) => TreatmentBuilder::new("Note"),
+            };
+            if let Some(grams) = carbs {
+                builder = builder.carbs(grams);
+            }
+            if let Some(units) = insulin {
+                builder = builder.insulin(units);
+            }
+            if let Some(notes) = notes {
+                builder = builder.notes(notes);
+            }
+
+            let created = client.treatments().create(vec![builder.build()?]).await?;
+            for treatment in created {
+                println!("Added {}", describe(&treatment));
+            }
+        }
+        Command::Export {
+            command:
+                ExportCommand::Csv {
+                    collection,
+                    days,
+                    output,
+                },
+        } => {
+            let mut writer: Box<dyn AsyncWrite + Unpin> = match output {
+                Some(path) => Box::new(tokio::fs::File::create(path).await?),
+                None => Box::new(tokio::io::stdout()),
+            };
+            let to = Utc::now();
+            let from = to - chrono::Duration::days(days);
+            let export = client.export();
+            match collection {
+                Collection::Sgv => export.sgv_csv(from, to, &mut writer).await?,
+                Collection::Treatments => export.treatments_csv(from, to, &mut writer).await?,
+            };
+        }
+        Command::Watch { interval } => {
+            let mut readings = std::pin::pin!(client.sgv().watch(Duration::from_secs(interval)));
+            while let Some(entry) = readings.next().await {
+                match entry {
+                    Ok(entry) => println!(
+                        "{} {} {}",
+                        entry.datetime().map(local_time).unwrap_or_default(),
+                        entry.glucose().to_unit(units),
+                        entry.direction
+                    ),
+                    // Keep watching through outages.
+                    Err(e) => eprintln!("error: {e}"),
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn connect(site: Site) -> Result<NightscoutClient, NightscoutError> {
+    let mut config = match (site.url, ClientConfig::from_env()) {
+        (Some(url), Ok(config)) => ClientConfig { url, ..config },
+        (Some(url), Err(_)) => ClientConfig {
+            url,
+            token: None,
+            api_secret: None,
+            units: None,
+            timeout_secs: None,
+        },
+        (None, Ok(config)) => config,
+        (None, Err(_)) => {
+            return Err(NightscoutError::InvalidInput(
+                "set NIGHTSCOUT_URL or pass --url".to_string(),
+            ))
+        }
+    };
+    if site.token.is_some() {
+        config.token = site.token;
+    }
+    if site.secret.is_some() {
+        config.api_secret = site.secret;
+    }
+    config.timeout_secs.get_or_insert(30);
+    config.build()
+}
+
+fn local_time(date: DateTime<Utc>) -> String {
+    date.with_timezone(&Local)
+        .format("%Y-%m-%d %H:%M")
+        .to_string()
+}
+
+fn describe(treatment: &Treatment) -> String {
+    let mut line = format!(
+        "{}  {}",
+        local_time(treatment.created_at),
+        treatment.event_type
+    );
+    if let Some(insulin) = treatment.insulin {
+        line.push_str(&format!("  {insulin} U"));
+    }
+    if let Some(carbs) = treatment.carbs {
+        line.push_str(&format!("  {carbs} g"));
+    }
+    if let Some(notes) = &treatment.notes {
+        line.push_str(&format!("  {notes}"));
+    }
+    line
+}
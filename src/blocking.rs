@@ -0,0 +1,537 @@
+//! A blocking (synchronous) Nightscout client, built with the `blocking-runtime` feature.
+//!
+//! This wraps the asynchronous [`crate::client::NightscoutClient`] and drives it on a
+//! current-thread tokio runtime owned by the client, rather than on `reqwest::blocking`, so
+//! every setting of the async client (authentication, retries, rate limits, caching)
+//! applies identically without a second HTTP stack. Scripts and daemons do not set up a
+//! runtime themselves, but tokio is still linked and started, as the feature name says.
+//!
+//! The blocking client must not be used or dropped from within an async context: blocking
+//! the executor thread is not allowed and tokio panics when it is attempted.
+//!
+//! ```rust,no_run
+//! use cinnamon::blocking::NightscoutClient;
+//!
+//! fn main() -> Result<(), Box<dyn std::error::Error>> {
+//!     let client = NightscoutClient::new("https://my-cgm.herokuapp.com")?
+//!         .with_secret("my_secret");
+//!
+//!     if let Some(latest) = client.sgv().latest()? {
+//!         println!("{} {}", latest.sgv, latest.direction);
+//!     }
+//!     Ok(())
+//! }
+//! ```
+
+use crate::client::NightscoutClient as AsyncClient;
+use crate::endpoints::ApiVersion;
+use crate::error::NightscoutError;
+#[cfg(feature = "csv")]
+use crate::export::CsvRecord;
+use crate::models::activity::Activity;
+use crate::models::auth::AuthMode;
+use crate::models::devicestatus::{DeviceLatest, DeviceStatus};
+use crate::models::entries::{MbgEntry, SgvEntry};
+use crate::models::profile::ProfileSet;
+use crate::models::properties::{Properties, PropertyType};
+use crate::models::status::Status;
+use crate::models::treatments::Treatment;
+use crate::query_builder::{Device, HasDate, HasDevice, QueryBuilder as AsyncQueryBuilder};
+use crate::ratelimit::RateLimit;
+use crate::response::WithMeta;
+use crate::retry::RetryPolicy;
+
+use chrono::{DateTime, Utc};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::future::Future;
+use std::sync::Arc;
+use tokio::runtime::Runtime;
+
+/// The blocking counterpart of [`crate::client::NightscoutClient`].
+#[derive(Clone)]
+pub struct NightscoutClient {
+    inner: AsyncClient,
+    runtime: Arc<Runtime>,
+}
+
+impl NightscoutClient {
+    /// Creates a new blocking client without an API secret.
+    ///
+    /// ## Errors
+    ///
+    /// Returns a `NightscoutError` if the URL is invalid or the runtime cannot be started.
+    pub fn new(base_url: &str) -> Result<Self, NightscoutError> {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()?;
+
+        Ok(Self {
+            inner: AsyncClient::new(base_url)?,
+            runtime: Arc::new(runtime),
+        })
+    }
+
+    /// See [`crate::client::NightscoutClient::with_secret`].
+    pub fn with_secret(self, api_secret: impl Into<String>) -> Self {
+        self.map(|c| c.with_secret(api_secret))
+    }
+
+    /// See [`crate::client::NightscoutClient::with_token`].
+    pub fn with_token(self, token: impl Into<String>) -> Self {
+        self.map(|c| c.with_token(token))
+    }
+
+    /// See [`crate::client::NightscoutClient::with_retry_policy`].
+    pub fn with_retry_policy(self, policy: RetryPolicy) -> Self {
+        self.map(|c| c.with_retry_policy(policy))
+    }
+
+    /// See [`crate::client::NightscoutClient::with_rate_limit`].
+    pub fn with_rate_limit(self, limit: RateLimit) -> Self {
+        self.map(|c| c.with_rate_limit(limit))
+    }
+
+    /// See [`crate::client::NightscoutClient::with_api_version`].
+    pub fn with_api_version(self, version: ApiVersion) -> Self {
+        self.map(|c| c.with_api_version(version))
+    }
+
+    /// See [`AsyncClient::with_timezone`].
+    pub fn with_timezone(self, tz: chrono_tz::Tz) -> Self {
+        self.map(|c| c.with_timezone(tz))
+    }
+
+    /// See [`AsyncClient::with_clock_offset`].
+    pub fn with_clock_offset(self, offset: chrono::Duration) -> Self {
+        self.map(|c| c.with_clock_offset(offset))
+    }
+
+    /// See [`AsyncClient::sync_clock`].
+    pub fn sync_clock(self) -> Result<Self, NightscoutError> {
+        let inner = self.runtime.block_on(self.inner.clone().sync_clock())?;
+        Ok(self.map(|_| inner))
+    }
+
+    /// See [`AsyncClient::wait_until_ready`].
+    pub fn wait_until_ready(
+        &self,
+        timeout: std::time::Duration,
+    ) -> Result<Status, NightscoutError> {
+        self.block_on(self.inner.wait_until_ready(timeout))
+    }
+
+    /// See [`crate::client::NightscoutClient::read_only`].
+    pub fn read_only(self) -> Self {
+        self.map(|c| c.read_only())
+    }
+
+    /// See [`crate::client::NightscoutClient::with_conditional_requests`].
+    pub fn with_conditional_requests(self, enabled: bool) -> Self {
+        self.map(|c| c.with_conditional_requests(enabled))
+    }
+
+    /// See [`crate::client::NightscoutClient::with_http_client`].
+    pub fn with_http_client(self, http: reqwest::Client) -> Self {
+        self.map(|c| c.with_http_client(http))
+    }
+
+    /// The underlying asynchronous client.
+    pub fn as_async(&self) -> &AsyncClient {
+        &self.inner
+    }
+
+    fn map(self, f: impl FnOnce(AsyncClient) -> AsyncClient) -> Self {
+        Self {
+            inner: f(self.inner),
+            runtime: self.runtime,
+        }
+    }
+
+    fn block_on<F: Future>(&self, future: F) -> F::Output {
+        self.runtime.block_on(future)
+    }
+
+    /// Access the Sensor Glucose Value (SGV) service.
+    pub fn sgv(&self) -> SgvService<'_> {
+        SgvService { client: self }
+    }
+
+    /// Access the Meter Blood Glucose (MBG) service.
+    pub fn mbg(&self) -> MbgService<'_> {
+        MbgService { client: self }
+    }
+
+    /// Access the Treatments service.
+    pub fn treatments(&self) -> TreatmentsService<'_> {
+        TreatmentsService { client: self }
+    }
+
+    /// Access the Device Status service.
+    pub fn devicestatus(&self) -> DeviceStatusService<'_> {
+        DeviceStatusService { client: self }
+    }
+
+    /// Access the Activity service.
+    pub fn activity(&self) -> ActivityService<'_> {
+        ActivityService { client: self }
+    }
+
+    /// Access the Properties service.
+    pub fn properties(&self) -> PropertiesService<'_> {
+        PropertiesService { client: self }
+    }
+
+    /// Access the Profile service.
+    pub fn profiles(&self) -> ProfileService<'_> {
+        ProfileService { client: self }
+    }
+
+    /// Access the server status service.
+    pub fn status(&self) -> StatusService<'_> {
+        StatusService { client: self }
+    }
+}
+
+/// The blocking counterpart of [`crate::query_builder::QueryBuilder`].
+pub struct QueryBuilder<'a, T> {
+    client: &'a NightscoutClient,
+    inner: AsyncQueryBuilder<T>,
+}
+
+impl<'a, T> QueryBuilder<'a, T> {
+    fn new(client: &'a NightscoutClient, inner: AsyncQueryBuilder<T>) -> Self {
+        Self { client, inner }
+    }
+
+    /// Applies any option of the asynchronous builder.
+    pub fn configure(
+        mut self,
+        f: impl FnOnce(AsyncQueryBuilder<T>) -> AsyncQueryBuilder<T>,
+    ) -> Self {
+        self.inner = f(self.inner);
+        self
+    }
+
+    /// Filters results to entries occurring on or after this date.
+    pub fn from(self, date: DateTime<Utc>) -> Self {
+        self.configure(|q| q.from(date))
+    }
+
+    /// Filters results to entries occurring on or before this date.
+    pub fn to(self, date: DateTime<Utc>) -> Self {
+        self.configure(|q| q.to(date))
+    }
+
+    /// Limits the number of results returned. Default is 10.
+    pub fn limit(self, count: usize) -> Self {
+        self.configure(|q| q.limit(count))
+    }
+
+    /// Targets a specific resource ID.
+    pub fn id(self, id: impl Into<String>) -> Self {
+        self.configure(|q| q.id(id))
+    }
+
+    /// Filters results by device name.
+    pub fn device(self, device: Device) -> Self {
+        self.configure(|q| q.device(device))
+    }
+
+    /// Confirms a delete matching the date range and filters, see
+    /// [`AsyncQueryBuilder::confirm`].
+    pub fn confirm(self) -> Self {
+        self.configure(|q| q.confirm())
+    }
+
+    /// Fails the query if it takes longer than `timeout`, see
+    /// [`AsyncQueryBuilder::timeout`].
+    pub fn timeout(self, timeout: std::time::Duration) -> Self {
+        self.configure(|q| q.timeout(timeout))
+    }
+
+    /// Authenticates this query with other credentials than the client's, see
+    /// [`AsyncQueryBuilder::with_auth`].
+    pub fn with_auth(self, mode: AuthMode) -> Self {
+        self.configure(|q| q.with_auth(mode))
+    }
+}
+
+impl<T> QueryBuilder<'_, T>
+where
+    T: DeserializeOwned + Send + Sync + 'static + HasDevice,
+{
+    /// Executes the built query, blocking until the response is received.
+    pub fn send(self) -> Result<Vec<T>, NightscoutError> {
+        self.client.block_on(self.inner.send())
+    }
+
+    /// Executes the built query, returning the response details along with the items, see
+    /// [`AsyncQueryBuilder::send_with_meta`].
+    pub fn send_with_meta(self) -> Result<WithMeta<Vec<T>>, NightscoutError> {
+        self.client.block_on(self.inner.send_with_meta())
+    }
+
+    /// Executes the query asking for tab-separated values, see
+    /// [`AsyncQueryBuilder::send_tsv`].
+    #[cfg(feature = "csv")]
+    pub fn send_tsv(self) -> Result<Vec<T>, NightscoutError>
+    where
+        T: CsvRecord,
+    {
+        self.client.block_on(self.inner.send_tsv())
+    }
+
+    /// Fetches the date range in chunks fetched concurrently, see
+    /// [`AsyncQueryBuilder::send_chunked`].
+    pub fn send_chunked(
+        self,
+        chunk: std::time::Duration,
+        concurrency: usize,
+    ) -> Result<Vec<T>, NightscoutError>
+    where
+        T: Serialize + HasDate,
+    {
+        self.client
+            .block_on(self.inner.send_chunked(chunk, concurrency))
+    }
+
+    /// Executes the query for the most recent matching document, see
+    /// [`AsyncQueryBuilder::first`].
+    pub fn first(self) -> Result<Option<T>, NightscoutError> {
+        self.client.block_on(self.inner.first())
+    }
+}
+
+pub struct SgvService<'a> {
+    client: &'a NightscoutClient,
+}
+
+impl<'a> SgvService<'a> {
+    pub fn get(&self) -> QueryBuilder<'a, SgvEntry> {
+        QueryBuilder::new(self.client, self.client.inner.sgv().get())
+    }
+
+    pub fn get_by_id(&self, id: &str) -> Result<Option<SgvEntry>, NightscoutError> {
+        self.client.block_on(self.client.inner.sgv().get_by_id(id))
+    }
+
+    pub fn delete(&self) -> QueryBuilder<'a, SgvEntry> {
+        QueryBuilder::new(self.client, self.client.inner.sgv().delete())
+    }
+
+    pub fn latest(&self) -> Result<Option<SgvEntry>, NightscoutError> {
+        self.client.block_on(self.client.inner.sgv().latest())
+    }
+
+    pub fn create(&self, entries: Vec<SgvEntry>) -> Result<Vec<SgvEntry>, NightscoutError> {
+        self.client
+            .block_on(self.client.inner.sgv().create(entries))
+    }
+
+    pub fn update(&self, id: &str, entry: SgvEntry) -> Result<SgvEntry, NightscoutError> {
+        self.client
+            .block_on(self.client.inner.sgv().update(id, entry))
+    }
+}
+
+pub struct MbgService<'a> {
+    client: &'a NightscoutClient,
+}
+
+impl<'a> MbgService<'a> {
+    pub fn get(&self) -> QueryBuilder<'a, MbgEntry> {
+        QueryBuilder::new(self.client, self.client.inner.mbg().get())
+    }
+
+    pub fn get_by_id(&self, id: &str) -> Result<Option<MbgEntry>, NightscoutError> {
+        self.client.block_on(self.client.inner.mbg().get_by_id(id))
+    }
+
+    pub fn delete(&self) -> QueryBuilder<'a, MbgEntry> {
+        QueryBuilder::new(self.client, self.client.inner.mbg().delete())
+    }
+
+    pub fn latest(&self) -> Result<Option<MbgEntry>, NightscoutError> {
+        self.client.block_on(self.client.inner.mbg().latest())
+    }
+
+    pub fn create(&self, entries: Vec<MbgEntry>) -> Result<Vec<MbgEntry>, NightscoutError> {
+        self.client
+            .block_on(self.client.inner.mbg().create(entries))
+    }
+
+    pub fn update(&self, id: &str, entry: MbgEntry) -> Result<MbgEntry, NightscoutError> {
+        self.client
+            .block_on(self.client.inner.mbg().update(id, entry))
+    }
+}
+
+pub struct TreatmentsService<'a> {
+    client: &'a NightscoutClient,
+}
+
+impl<'a> TreatmentsService<'a> {
+    pub fn get(&self) -> QueryBuilder<'a, Treatment> {
+        QueryBuilder::new(self.client, self.client.inner.treatments().get())
+    }
+
+    pub fn get_by_id(&self, id: &str) -> Result<Option<Treatment>, NightscoutError> {
+        self.client
+            .block_on(self.client.inner.treatments().get_by_id(id))
+    }
+
+    pub fn latest(&self) -> Result<Option<Treatment>, NightscoutError> {
+        self.client
+            .block_on(self.client.inner.treatments().latest())
+    }
+
+    pub fn delete(&self) -> QueryBuilder<'a, Treatment> {
+        QueryBuilder::new(self.client, self.client.inner.treatments().delete())
+    }
+
+    pub fn create(&self, treatments: Vec<Treatment>) -> Result<Vec<Treatment>, NightscoutError> {
+        self.client
+            .block_on(self.client.inner.treatments().create(treatments))
+    }
+
+    pub fn update(&self, id: &str, treatment: Treatment) -> Result<Treatment, NightscoutError> {
+        self.client
+            .block_on(self.client.inner.treatments().update(id, treatment))
+    }
+}
+
+pub struct DeviceStatusService<'a> {
+    client: &'a NightscoutClient,
+}
+
+impl<'a> DeviceStatusService<'a> {
+    pub fn get(&self) -> QueryBuilder<'a, DeviceStatus> {
+        QueryBuilder::new(self.client, self.client.inner.devicestatus().get())
+    }
+
+    pub fn get_by_id(&self, id: &str) -> Result<Option<DeviceStatus>, NightscoutError> {
+        self.client
+            .block_on(self.client.inner.devicestatus().get_by_id(id))
+    }
+
+    pub fn latest(&self) -> Result<Option<DeviceStatus>, NightscoutError> {
+        self.client
+            .block_on(self.client.inner.devicestatus().latest())
+    }
+
+    pub fn latest_for_each_device(&self) -> Result<Vec<DeviceLatest>, NightscoutError> {
+        self.client
+            .block_on(self.client.inner.devicestatus().latest_for_each_device())
+    }
+
+    pub fn delete(&self) -> QueryBuilder<'a, DeviceStatus> {
+        QueryBuilder::new(self.client, self.client.inner.devicestatus().delete())
+    }
+
+    pub fn create(&self, entries: Vec<DeviceStatus>) -> Result<Vec<DeviceStatus>, NightscoutError> {
+        self.client
+            .block_on(self.client.inner.devicestatus().create(entries))
+    }
+}
+
+pub struct ActivityService<'a> {
+    client: &'a NightscoutClient,
+}
+
+impl<'a> ActivityService<'a> {
+    pub fn get(&self) -> QueryBuilder<'a, Activity> {
+        QueryBuilder::new(self.client, self.client.inner.activity().get())
+    }
+
+    pub fn get_by_id(&self, id: &str) -> Result<Option<Activity>, NightscoutError> {
+        self.client
+            .block_on(self.client.inner.activity().get_by_id(id))
+    }
+
+    pub fn latest(&self) -> Result<Option<Activity>, NightscoutError> {
+        self.client.block_on(self.client.inner.activity().latest())
+    }
+
+    pub fn delete(&self) -> QueryBuilder<'a, Activity> {
+        QueryBuilder::new(self.client, self.client.inner.activity().delete())
+    }
+
+    pub fn create(&self, records: Vec<Activity>) -> Result<Vec<Activity>, NightscoutError> {
+        self.client
+            .block_on(self.client.inner.activity().create(records))
+    }
+}
+
+pub struct PropertiesService<'a> {
+    client: &'a NightscoutClient,
+}
+
+impl<'a> PropertiesService<'a> {
+    pub fn get(&self) -> PropertiesRequest<'a> {
+        PropertiesRequest {
+            client: self.client,
+            inner: self.client.inner.properties().get(),
+        }
+    }
+
+    /// Fetches a single property and deserializes it into `T`.
+    pub fn get_as<T: DeserializeOwned>(
+        &self,
+        property: PropertyType,
+    ) -> Result<T, NightscoutError> {
+        self.client
+            .block_on(self.client.inner.properties().get_as(property))
+    }
+}
+
+/// The blocking counterpart of [`crate::models::properties::PropertiesRequest`].
+pub struct PropertiesRequest<'a> {
+    client: &'a NightscoutClient,
+    inner: crate::models::properties::PropertiesRequest,
+}
+
+impl PropertiesRequest<'_> {
+    /// Specifies which properties to retrieve.
+    pub fn only(mut self, properties: &[PropertyType]) -> Self {
+        self.inner = self.inner.only(properties);
+        self
+    }
+
+    /// Requests the system state as it was at a specific time.
+    pub fn at(mut self, time: DateTime<Utc>) -> Self {
+        self.inner = self.inner.at(time);
+        self
+    }
+
+    /// Computes IOB and COB locally when the server cannot answer [`at`](Self::at).
+    pub fn local_fallback(mut self) -> Self {
+        self.inner = self.inner.local_fallback();
+        self
+    }
+
+    /// Executes the request, blocking until the response is received.
+    pub fn send(self) -> Result<Properties, NightscoutError> {
+        self.client.block_on(self.inner.send())
+    }
+}
+
+pub struct ProfileService<'a> {
+    client: &'a NightscoutClient,
+}
+
+impl ProfileService<'_> {
+    pub fn get(&self) -> Result<Vec<ProfileSet>, NightscoutError> {
+        self.client.block_on(self.client.inner.profiles().get())
+    }
+}
+
+pub struct StatusService<'a> {
+    client: &'a NightscoutClient,
+}
+
+impl StatusService<'_> {
+    pub fn get(&self) -> Result<Status, NightscoutError> {
+        self.client.block_on(self.client.inner.status().get())
+    }
+}
@@ -0,0 +1,224 @@
+//! Chunked uploads for large sets of documents.
+//!
+//! Posting a whole backfill in one request times out on most servers once it reaches a few
+//! thousand documents. [`BulkRequest`] splits the upload into batches, sends a few of them at
+//! a time, and reports which batches failed so only those need to be retried.
+
+use crate::client::NightscoutClient;
+use crate::endpoints::Endpoint;
+use crate::error::NightscoutError;
+use crate::models::auth::AuthMode;
+
+use futures::{stream, StreamExt};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::Value;
+
+/// Number of documents per request used by the `create` methods.
+pub const DEFAULT_BATCH_SIZE: usize = 500;
+
+/// A batch that could not be uploaded.
+#[derive(Debug)]
+pub struct ChunkFailure<T> {
+    /// Position of the batch in the upload, starting at 0.
+    pub chunk: usize,
+    /// The documents of the batch, to retry them.
+    pub documents: Vec<T>,
+    pub error: NightscoutError,
+}
+
+/// The outcome of a [`BulkRequest`].
+#[derive(Debug)]
+pub struct BulkReport<T> {
+    /// Documents returned by the server for the successful batches, in upload order.
+    pub created: Vec<T>,
+    /// Batches that failed, in upload order.
+    pub failures: Vec<ChunkFailure<T>>,
+    /// Documents not sent because an earlier batch failed, see
+    /// [`BulkRequest::stop_on_failure`].
+    pub unsent: Vec<T>,
+}
+
+impl<T> BulkReport<T> {
+    /// Whether every batch was uploaded.
+    pub fn is_complete(&self) -> bool {
+        self.failures.is_empty() && self.unsent.is_empty()
+    }
+}
+
+impl<T: Serialize> BulkReport<T> {
+    /// The created documents, or the error of the first failed batch.
+    ///
+    /// When some documents were created before the failure, the error is a
+    /// [`NightscoutError::PartialUpload`] with their ids, so a retry can leave them out.
+    pub fn into_result(self) -> Result<Vec<T>, NightscoutError> {
+        let Some(failure) = self.failures.into_iter().next() else {
+            return Ok(self.created);
+        };
+        if self.created.is_empty() {
+            return Err(failure.error);
+        }
+
+        let created_ids = self
+            .created
+            .iter()
+            .filter_map(
+                |document| match serde_json::to_value(document).ok()?.get("_id") {
+                    Some(Value::String(id)) => Some(id.clone()),
+                    _ => None,
+                },
+            )
+            .collect();
+        Err(NightscoutError::PartialUpload {
+            created_ids,
+            source: Box::new(failure.error),
+        })
+    }
+}
+
+/// Builder for a chunked upload, created by the `bulk` method of the collection services.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// # use cinnamon::client::NightscoutClient;
+/// # use cinnamon::models::entries::SgvEntry;
+/// # async fn run(backfill: Vec<SgvEntry>) -> Result<(), Box<dyn std::error::Error>> {
+/// let client = NightscoutClient::new("https://ns.example.com")?.with_secret("secret");
+/// let report = client.sgv()
+///     .bulk(backfill)
+///     .batch_size(1000)
+///     .concurrency(4)
+///     .send()
+///     .await?;
+///
+/// for failure in report.failures {
+///     eprintln!("batch {} failed: {}", failure.chunk, failure.error);
+/// }
+/// # Ok(())
+/// # }
+/// ```
+pub struct BulkRequest<T> {
+    client: NightscoutClient,
+    endpoint: Endpoint,
+    documents: Vec<T>,
+    batch_size: usize,
+    concurrency: usize,
+    stop_on_failure: bool,
+}
+
+impl<T> BulkRequest<T>
+where
+    T: Serialize + DeserializeOwned,
+{
+    pub(crate) fn new(client: NightscoutClient, endpoint: Endpoint, documents: Vec<T>) -> Self {
+        Self {
+            client,
+            endpoint,
+            documents,
+            batch_size: DEFAULT_BATCH_SIZE,
+            concurrency: 1,
+            stop_on_failure: false,
+        }
+    }
+
+    /// Number of documents per request. Defaults to [`DEFAULT_BATCH_SIZE`].
+    pub fn batch_size(mut self, batch_size: usize) -> Self {
+        self.batch_size = batch_size.max(1);
+        self
+    }
+
+    /// Number of batches uploaded at the same time. Defaults to 1.
+    pub fn concurrency(mut self, concurrency: usize) -> Self {
+        self.concurrency = concurrency.max(1);
+        self
+    }
+
+    /// Stops at the first failed batch, returning the documents of the next ones in
+    /// [`BulkReport::unsent`] instead of sending them. Batches are then sent one at a time.
+    ///
+    /// Used by the `create` methods, so a retry after a failure only has to send the
+    /// documents from the failed batch on.
+    pub fn stop_on_failure(mut self) -> Self {
+        self.stop_on_failure = true;
+        self
+    }
+
+    /// Uploads with other credentials than the client's, see
+    /// [`QueryBuilder::with_auth`](crate::query_builder::QueryBuilder::with_auth).
+    pub fn with_auth(mut self, mode: AuthMode) -> Self {
+        self.client = self.client.with_query_auth(mode);
+        self
+    }
+
+    /// Uploads every batch, continuing past failed ones unless
+    /// [`stop_on_failure`](Self::stop_on_failure) is set.
+    pub async fn send(self) -> Result<BulkReport<T>, NightscoutError> {
+        let url = self.client.endpoint_url(self.endpoint)?;
+
+        let mut chunks = Vec::new();
+        let mut documents = self.documents.into_iter().peekable();
+        while documents.peek().is_some() {
+            chunks.push(documents.by_ref().take(self.batch_size).collect::<Vec<T>>());
+        }
+
+        let mut report = BulkReport {
+            created: Vec::new(),
+            failures: Vec::new(),
+            unsent: Vec::new(),
+        };
+
+        if self.stop_on_failure {
+            let mut chunks = chunks.into_iter().enumerate();
+            while let Some((chunk, documents)) = chunks.next() {
+                match upload(&self.client, &url, &documents).await {
+                    Ok(created) => report.created.extend(created),
+                    Err(error) => {
+                        report.failures.push(ChunkFailure {
+                            chunk,
+                            documents,
+                            error,
+                        });
+                        report.unsent = chunks.flat_map(|(_, documents)| documents).collect();
+                        break;
+                    }
+                }
+            }
+            return Ok(report);
+        }
+
+        let uploads = chunks.into_iter().enumerate().map(|(chunk, documents)| {
+            let client = &self.client;
+            let url = &url;
+            async move {
+                let result = upload(client, url, &documents).await;
+                (chunk, documents, result)
+            }
+        });
+
+        // `buffered` keeps the batches in upload order.
+        let mut results = stream::iter(uploads).buffered(self.concurrency);
+        while let Some((chunk, documents, result)) = results.next().await {
+            match result {
+                Ok(created) => report.created.extend(created),
+                Err(error) => report.failures.push(ChunkFailure {
+                    chunk,
+                    documents,
+                    error,
+                }),
+            }
+        }
+
+        Ok(report)
+    }
+}
+
+async fn upload<T: Serialize + DeserializeOwned>(
+    client: &NightscoutClient,
+    url: &url::Url,
+    documents: &[T],
+) -> Result<Vec<T>, NightscoutError> {
+    let request = client.authorize(client.http.post(url.clone())).await?;
+    let response = client.send_checked(request.json(documents)).await?;
+    Ok(response.json::<Vec<T>>().await?)
+}
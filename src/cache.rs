@@ -0,0 +1,175 @@
+//! In-memory response cache.
+//!
+//! Enabled with the `cache` feature and [`crate::client::NightscoutClient::with_cache`].
+//! Successful GET responses are kept for a time-to-live chosen per endpoint, so repeated
+//! `status()` or `properties()` calls within a few seconds are answered locally. Any write
+//! sent through the client clears the cache once it completes, and reads that were in
+//! flight meanwhile are not stored.
+
+use crate::endpoints::{ApiVersion, Endpoint};
+
+use chrono::{DateTime, Utc};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use url::Url;
+
+/// How long responses are cached, per endpoint.
+///
+/// # Example
+///
+/// ```rust
+/// # use cinnamon::cache::CacheConfig;
+/// # use cinnamon::endpoints::Endpoint;
+/// # use std::time::Duration;
+/// let config = CacheConfig::new(Duration::from_secs(5))
+///     .ttl(Endpoint::Status, Duration::from_secs(300))
+///     .ttl(Endpoint::Treatments, Duration::ZERO);
+/// ```
+#[derive(Debug, Clone)]
+pub struct CacheConfig {
+    default_ttl: Duration,
+    ttls: Vec<(Endpoint, Duration)>,
+    max_entries: usize,
+}
+
+impl CacheConfig {
+    /// Caches every endpoint for `default_ttl`. A zero duration disables caching.
+    pub fn new(default_ttl: Duration) -> Self {
+        Self {
+            default_ttl,
+            ttls: Vec::new(),
+            max_entries: 256,
+        }
+    }
+
+    /// Overrides the time-to-live of one endpoint. A zero duration disables caching it.
+    pub fn ttl(mut self, endpoint: Endpoint, ttl: Duration) -> Self {
+        self.ttls.retain(|(e, _)| *e != endpoint);
+        self.ttls.push((endpoint, ttl));
+        self
+    }
+
+    /// Maximum number of cached responses. Defaults to 256.
+    pub fn max_entries(mut self, max_entries: usize) -> Self {
+        self.max_entries = max_entries;
+        self
+    }
+}
+
+impl Default for CacheConfig {
+    /// Caches responses for 5 seconds, and the server status for a minute.
+    fn default() -> Self {
+        CacheConfig::new(Duration::from_secs(5)).ttl(Endpoint::Status, Duration::from_secs(60))
+    }
+}
+
+struct CachedResponse {
+    body: Arc<[u8]>,
+    expires: DateTime<Utc>,
+}
+
+/// The cache shared between clones of a client.
+pub(crate) struct ResponseCache {
+    config: CacheConfig,
+    entries: Mutex<HashMap<String, CachedResponse>>,
+    /// Incremented on every clear, while holding the `entries` lock.
+    generation: AtomicU64,
+}
+
+impl ResponseCache {
+    pub(crate) fn new(config: CacheConfig) -> Self {
+        Self {
+            config,
+            entries: Mutex::new(HashMap::new()),
+            generation: AtomicU64::new(0),
+        }
+    }
+
+    pub(crate) fn config(&self) -> &CacheConfig {
+        &self.config
+    }
+
+    /// The time-to-live of `url`, `None` if it is not cached.
+    ///
+    /// The endpoint is recognized from the path, the most specific configured one wins.
+    pub(crate) fn ttl_for(&self, url: &Url) -> Option<Duration> {
+        let path = url.path().trim_start_matches('/');
+        let ttl = self
+            .config
+            .ttls
+            .iter()
+            .filter(|(endpoint, _)| {
+                [ApiVersion::V1, ApiVersion::V2, ApiVersion::V3]
+                    .into_iter()
+                    .any(|version| path.starts_with(&*endpoint.path_for(version)))
+            })
+            .max_by_key(|(endpoint, _)| endpoint.as_path().len())
+            .map_or(self.config.default_ttl, |(_, ttl)| *ttl);
+
+        (!ttl.is_zero()).then_some(ttl)
+    }
+
+    pub(crate) fn get(&self, key: &str) -> Option<Arc<[u8]>> {
+        let entries = self.entries.lock().ok()?;
+        let entry = entries.get(key)?;
+        (entry.expires > Utc::now()).then(|| entry.body.clone())
+    }
+
+    /// The number of clears so far, to take before sending a request whose response is
+    /// passed to [`insert`](Self::insert).
+    pub(crate) fn generation(&self) -> u64 {
+        self.generation.load(Ordering::Acquire)
+    }
+
+    /// Stores a response, unless the cache was cleared since `generation` was taken: the
+    /// response may then predate a write.
+    pub(crate) fn insert(&self, key: String, body: Arc<[u8]>, ttl: Duration, generation: u64) {
+        let Ok(mut entries) = self.entries.lock() else {
+            return;
+        };
+        if self.generation.load(Ordering::Acquire) != generation {
+            return;
+        }
+
+        let now = Utc::now();
+        if entries.len() >= self.config.max_entries {
+            entries.retain(|_, entry| entry.expires > now);
+        }
+        if entries.len() >= self.config.max_entries {
+            return;
+        }
+
+        let ttl = chrono::Duration::from_std(ttl).unwrap_or(chrono::Duration::MAX);
+        entries.insert(
+            key,
+            CachedResponse {
+                body,
+                expires: now
+                    .checked_add_signed(ttl)
+                    .unwrap_or(DateTime::<Utc>::MAX_UTC),
+            },
+        );
+    }
+
+    pub(crate) fn clear(&self) {
+        let mut entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        entries.clear();
+        self.generation.fetch_add(1, Ordering::AcqRel);
+    }
+}
+
+/// The cache key of a URL: the URL with its query parameters sorted.
+pub(crate) fn normalize(url: &Url) -> String {
+    let mut pairs: Vec<(String, String)> = url.query_pairs().into_owned().collect();
+    pairs.sort();
+
+    let mut normalized = url.clone();
+    normalized.set_fragment(None);
+    normalized.set_query(None);
+    if !pairs.is_empty() {
+        normalized.query_pairs_mut().extend_pairs(pairs);
+    }
+    normalized.into()
+}
@@ -0,0 +1,298 @@
+//! Server capability detection.
+//!
+//! Nightscout instances differ in version and in which plugins are enabled (the `ENABLE`
+//! setting). [`Capabilities`] is derived from the status endpoint and cached by the client,
+//! see [`crate::client::NightscoutClient::capabilities`].
+
+use crate::error::NightscoutError;
+use crate::models::status::Status;
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::cmp::Ordering;
+use std::collections::HashSet;
+use std::fmt;
+use std::str::FromStr;
+
+/// First version whose properties endpoint honours the `time` parameter, the
+/// cgm-remote-monitor 15.0.0 release. Older servers ignore it and return the current state.
+const PROPERTIES_AT_TIME_VERSION: (u64, u64, u64) = (15, 0, 0);
+
+/// A semantic version, as reported by `Status.version` (e.g. `15.0.2` or `14.2.6-dev`).
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Version {
+    pub major: u64,
+    pub minor: u64,
+    pub patch: u64,
+    /// Pre-release tag, e.g. `dev` or `beta.1`.
+    pub pre: Option<String>,
+}
+
+impl Version {
+    pub fn new(major: u64, minor: u64, patch: u64) -> Self {
+        Self {
+            major,
+            minor,
+            patch,
+            pre: None,
+        }
+    }
+}
+
+impl FromStr for Version {
+    type Err = NightscoutError;
+
+    /// Parses `major[.minor[.patch]][-pre][+build]`, with an optional leading `v`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || NightscoutError::InvalidInput(format!("invalid version {:?}", s));
+
+        let trimmed = s.trim().trim_start_matches('v');
+        let trimmed = trimmed.split('+').next().unwrap_or_default();
+        let (core, pre) = match trimmed.split_once('-') {
+            Some((core, pre)) => (core, Some(pre.to_string())),
+            None => (trimmed, None),
+        };
+
+        let mut parts = core.split('.');
+        let mut next = |required: bool| match parts.next() {
+            Some(part) => part.parse::<u64>().map_err(|_| invalid()),
+            None if required => Err(invalid()),
+            None => Ok(0),
+        };
+
+        let version = Version {
+            major: next(true)?,
+            minor: next(false)?,
+            patch: next(false)?,
+            pre,
+        };
+
+        if parts.next().is_some() {
+            return Err(invalid());
+        }
+        Ok(version)
+    }
+}
+
+impl Ord for Version {
+    fn cmp(&self, other: &Self) -> Ordering {
+        (self.major, self.minor, self.patch)
+            .cmp(&(other.major, other.minor, other.patch))
+            // A pre-release sorts before the release itself.
+            .then_with(|| match (&self.pre, &other.pre) {
+                (None, None) => Ordering::Equal,
+                (None, Some(_)) => Ordering::Greater,
+                (Some(_), None) => Ordering::Less,
+                (Some(a), Some(b)) => a.cmp(b),
+            })
+    }
+}
+
+impl PartialOrd for Version {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl fmt::Display for Version {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}.{}", self.major, self.minor, self.patch)?;
+        if let Some(pre) = &self.pre {
+            write!(f, "-{}", pre)?;
+        }
+        Ok(())
+    }
+}
+
+/// A plugin or feature listed in the server's `ENABLE` setting.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum Feature {
+    Api,
+    Careportal,
+    Boluscalc,
+    Food,
+    Rawbg,
+    Iob,
+    Cob,
+    Bwp,
+    Cage,
+    Sage,
+    Iage,
+    Bage,
+    Basal,
+    Profile,
+    Pump,
+    Openaps,
+    Loop,
+    Override,
+    Upbat,
+    Ar2,
+    Simplealarms,
+    Treatmentnotify,
+    Errorcodes,
+    Bridge,
+    Mmconnect,
+    Pushover,
+    Maker,
+    Alexa,
+    Googlehome,
+    Speech,
+    Cors,
+    Dbsize,
+    Custom(String),
+}
+
+impl Feature {
+    pub fn as_str(&self) -> &str {
+        match self {
+            Feature::Api => "api",
+            Feature::Careportal => "careportal",
+            Feature::Boluscalc => "boluscalc",
+            Feature::Food => "food",
+            Feature::Rawbg => "rawbg",
+            Feature::Iob => "iob",
+            Feature::Cob => "cob",
+            Feature::Bwp => "bwp",
+            Feature::Cage => "cage",
+            Feature::Sage => "sage",
+            Feature::Iage => "iage",
+            Feature::Bage => "bage",
+            Feature::Basal => "basal",
+            Feature::Profile => "profile",
+            Feature::Pump => "pump",
+            Feature::Openaps => "openaps",
+            Feature::Loop => "loop",
+            Feature::Override => "override",
+            Feature::Upbat => "upbat",
+            Feature::Ar2 => "ar2",
+            Feature::Simplealarms => "simplealarms",
+            Feature::Treatmentnotify => "treatmentnotify",
+            Feature::Errorcodes => "errorcodes",
+            Feature::Bridge => "bridge",
+            Feature::Mmconnect => "mmconnect",
+            Feature::Pushover => "pushover",
+            Feature::Maker => "maker",
+            Feature::Alexa => "alexa",
+            Feature::Googlehome => "googlehome",
+            Feature::Speech => "speech",
+            Feature::Cors => "cors",
+            Feature::Dbsize => "dbsize",
+            Feature::Custom(s) => s.as_str(),
+        }
+    }
+}
+
+impl From<&str> for Feature {
+    fn from(s: &str) -> Self {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "api" => Feature::Api,
+            "careportal" => Feature::Careportal,
+            "boluscalc" => Feature::Boluscalc,
+            "food" => Feature::Food,
+            "rawbg" => Feature::Rawbg,
+            "iob" => Feature::Iob,
+            "cob" => Feature::Cob,
+            "bwp" => Feature::Bwp,
+            "cage" => Feature::Cage,
+            "sage" => Feature::Sage,
+            "iage" => Feature::Iage,
+            "bage" => Feature::Bage,
+            "basal" => Feature::Basal,
+            "profile" => Feature::Profile,
+            "pump" => Feature::Pump,
+            "openaps" => Feature::Openaps,
+            "loop" => Feature::Loop,
+            "override" => Feature::Override,
+            "upbat" => Feature::Upbat,
+            "ar2" => Feature::Ar2,
+            "simplealarms" => Feature::Simplealarms,
+            "treatmentnotify" => Feature::Treatmentnotify,
+            "errorcodes" => Feature::Errorcodes,
+            "bridge" => Feature::Bridge,
+            "mmconnect" => Feature::Mmconnect,
+            "pushover" => Feature::Pushover,
+            "maker" => Feature::Maker,
+            "alexa" => Feature::Alexa,
+            "googlehome" => Feature::Googlehome,
+            "speech" => Feature::Speech,
+            "cors" => Feature::Cors,
+            "dbsize" => Feature::Dbsize,
+            other => Feature::Custom(other.to_string()),
+        }
+    }
+}
+
+impl fmt::Display for Feature {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl Serialize for Feature {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+impl<'de> Deserialize<'de> for Feature {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        Ok(Feature::from(s.as_str()))
+    }
+}
+
+/// What a Nightscout server supports, derived from its status.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Capabilities {
+    /// The server version, `None` if it could not be parsed.
+    pub version: Option<Version>,
+    /// The features listed in the `ENABLE` setting.
+    pub features: HashSet<Feature>,
+}
+
+impl Capabilities {
+    /// Builds the capabilities from a status response.
+    pub fn from_status(status: &Status) -> Self {
+        let features = status
+            .settings
+            .as_ref()
+            .and_then(|settings| settings.enable.clone())
+            .unwrap_or_default();
+
+        Self {
+            version: status.version.parse().ok(),
+            features,
+        }
+    }
+
+    /// Whether the feature is enabled on the server.
+    pub fn has(&self, feature: &Feature) -> bool {
+        self.features.contains(feature)
+    }
+
+    /// Returns [`NightscoutError::FeatureNotEnabled`] unless the feature is enabled.
+    pub fn require(&self, feature: Feature) -> Result<(), NightscoutError> {
+        if self.has(&feature) {
+            Ok(())
+        } else {
+            Err(NightscoutError::FeatureNotEnabled(feature))
+        }
+    }
+
+    /// Whether the server runs at least the given version.
+    pub fn version_at_least(&self, major: u64, minor: u64, patch: u64) -> bool {
+        self.version
+            .as_ref()
+            .is_some_and(|v| *v >= Version::new(major, minor, patch))
+    }
+
+    /// Whether the properties endpoint can return the state at a past time, see
+    /// [`PropertiesRequest::at`](crate::models::properties::PropertiesRequest::at).
+    /// `None` when the server version is unknown.
+    pub fn supports_properties_at(&self) -> Option<bool> {
+        let (major, minor, patch) = PROPERTIES_AT_TIME_VERSION;
+        self.version
+            .as_ref()
+            .map(|v| *v >= Version::new(major, minor, patch))
+    }
+}
@@ -1,34 +1,430 @@
-use super::error::NightscoutError;
+use super::error::{ApiErrorBody, NightscoutError};
 
+use chrono::Utc;
+use chrono_tz::Tz;
 use reqwest::{Client as HttpClient, Response};
-use sha1::{Digest, Sha1};
+use tokio::sync::Mutex;
 use url::Url;
 
+use crate::alarms::AlarmsService;
+use crate::analysis::AnalysisService;
+#[cfg(feature = "cache")]
+use crate::cache::{self, CacheConfig, ResponseCache};
+use crate::capabilities::Capabilities;
+use crate::conditional::{Conditional, ValidatorCache, Validators};
+#[cfg(not(target_arch = "wasm32"))]
+use crate::config::ClientConfig;
+use crate::endpoints::{ApiVersion, Endpoint};
+use crate::export::ExportService;
+use crate::forwarder::Forwarder;
+use crate::import::ImportService;
+#[cfg(all(feature = "local-store", not(target_arch = "wasm32")))]
+use crate::local::{LocalService, LocalStore};
+#[cfg(feature = "metrics")]
+use crate::metrics::MetricsService;
+use crate::middleware::Interceptor;
+use crate::models::activity::ActivityService;
+use crate::models::auth::{AuthMode, AuthService, AuthorizationToken};
 use crate::models::devicestatus::DeviceStatusService;
-use crate::models::entries::{MbgService, SgvService};
+use crate::models::entries::{CalService, EntriesService, MbgService, SgvService};
+use crate::models::notifications::NotificationsService;
+use crate::models::pebble::PebbleService;
+use crate::models::predictions::PredictionsService;
 use crate::models::profile::ProfileService;
 use crate::models::properties::PropertiesService;
-use crate::models::status::StatusService;
+use crate::models::status::{Status, StatusService};
 use crate::models::treatments::TreatmentsService;
+use crate::monitor::Monitor;
+#[cfg(all(feature = "mqtt", not(target_arch = "wasm32")))]
+use crate::mqtt::MqttPublisher;
+use crate::ratelimit::{RateLimit, RateLimiter};
+use crate::reports::ReportsService;
+use crate::response::ResponseMeta;
+use crate::retry::RetryPolicy;
+use crate::runtime;
+use crate::snapshot::{self, Snapshot};
+use crate::sync::SyncManager;
 
+use std::collections::HashMap;
 use std::ops::Deref;
 use std::sync::Arc;
+use std::time::Duration;
 
 #[derive(Clone)]
 pub struct NightscoutClient {
     pub inner: Arc<NightscoutClientInner>,
 }
 
+impl std::fmt::Debug for NightscoutClient {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.inner.fmt(f)
+    }
+}
+
 #[derive(Clone)]
 pub struct NightscoutClientInner {
     /// The base URL of the Nightscout instance.
     pub base_url: Url,
     /// The internal HTTP client used for requests.
     pub http: HttpClient,
-    /// The SHA1 hash of the API secret, used for authentication headers.
-    pub api_secret_hash: Option<String>,
+    /// How requests are authenticated, see [`NightscoutClient::with_secret`] and
+    /// [`NightscoutClient::with_token`].
+    pub auth_mode: AuthMode,
+    /// The JWTs obtained per access token, shared between clones of the client, so
+    /// switching credentials back and forth does not exchange the tokens again.
+    pub(crate) jwt: Arc<Mutex<HashMap<String, AuthorizationToken>>>,
+    /// The server capabilities, fetched on first use and shared between clones.
+    pub(crate) capabilities: Arc<Mutex<Option<Capabilities>>>,
+    /// How transient failures are retried, see [`NightscoutClient::with_retry_policy`].
+    pub retry_policy: RetryPolicy,
+    /// Throttles requests, shared between clones, see [`NightscoutClient::with_rate_limit`].
+    pub(crate) rate_limiter: Option<Arc<RateLimiter>>,
+    /// Whether writes are refused, see [`NightscoutClient::read_only`].
+    pub read_only: bool,
+    /// The REST API version requests are sent to, see [`NightscoutClient::with_api_version`].
+    pub api_version: ApiVersion,
+    /// The timezone of the user, see [`NightscoutClient::with_timezone`].
+    pub timezone: Option<Tz>,
+    /// How far the server clock is ahead of the local one, see
+    /// [`NightscoutClient::sync_clock`].
+    pub clock_offset: Option<chrono::Duration>,
+    /// Whether GET requests reuse cached responses the server reports as not modified,
+    /// see [`NightscoutClient::with_conditional_requests`].
+    pub conditional_requests: bool,
+    /// The `ETag` / `Last-Modified` validators of previous responses, per URL.
+    pub(crate) validators: Arc<ValidatorCache>,
+    /// The local copy of the data, see [`NightscoutClient::with_local_store`].
+    #[cfg(all(feature = "local-store", not(target_arch = "wasm32")))]
+    pub local_store: Option<LocalStore>,
+    /// Cached responses, see [`NightscoutClient::with_cache`].
+    #[cfg(feature = "cache")]
+    pub(crate) cache: Option<Arc<ResponseCache>>,
+    /// Hooks run around every request, see [`NightscoutClient::with_interceptor`].
+    pub interceptors: Vec<Arc<dyn Interceptor>>,
+}
+
+/// Lists the settings of the client, with the credentials and the secrets of the base
+/// URL redacted.
+impl std::fmt::Debug for NightscoutClientInner {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("NightscoutClient")
+            .field("base_url", &redact_url(&self.base_url))
+            .field("auth_mode", &self.auth_mode)
+            .field("api_version", &self.api_version)
+            .field("retry_policy", &self.retry_policy)
+            .field(
+                "rate_limit",
+                &self.rate_limiter.as_ref().map(|limiter| limiter.limit()),
+            )
+            .field("read_only", &self.read_only)
+            .field("timezone", &self.timezone)
+            .field("clock_offset", &self.clock_offset)
+            .field("conditional_requests", &self.conditional_requests)
+            .field("interceptors", &self.interceptors.len())
+            .finish_non_exhaustive()
+    }
+}
+
+/// Builder for a [`NightscoutClient`] with custom HTTP settings.
+///
+/// Created by [`NightscoutClient::builder`]. Timeouts, proxies and TLS options are not
+/// available on WebAssembly, where the browser controls them.
+pub struct ClientBuilder {
+    base_url: String,
+    api_secret: Option<String>,
+    access_token: Option<String>,
+    retry_policy: RetryPolicy,
+    rate_limit: Option<RateLimit>,
+    read_only: bool,
+    api_version: ApiVersion,
+    timezone: Option<Tz>,
+    interceptors: Vec<Arc<dyn Interceptor>>,
+    http: reqwest::ClientBuilder,
+    #[cfg(not(target_arch = "wasm32"))]
+    proxy: Option<String>,
+}
+
+impl ClientBuilder {
+    fn new(base_url: &str) -> Self {
+        Self {
+            base_url: base_url.to_string(),
+            api_secret: None,
+            access_token: None,
+            retry_policy: RetryPolicy::none(),
+            rate_limit: None,
+            read_only: false,
+            api_version: ApiVersion::default(),
+            timezone: None,
+            interceptors: Vec::new(),
+            http: HttpClient::builder().user_agent(DEFAULT_USER_AGENT),
+            #[cfg(not(target_arch = "wasm32"))]
+            proxy: None,
+        }
+    }
+
+    /// See [`NightscoutClient::with_secret`].
+    pub fn secret(mut self, api_secret: impl Into<String>) -> Self {
+        self.api_secret = Some(api_secret.into());
+        self
+    }
+
+    /// See [`NightscoutClient::with_token`].
+    pub fn token(mut self, token: impl Into<String>) -> Self {
+        self.access_token = Some(token.into());
+        self
+    }
+
+    /// See [`NightscoutClient::with_retry_policy`].
+    pub fn retry_policy(mut self, policy: RetryPolicy) -> Self {
+        self.retry_policy = policy;
+        self
+    }
+
+    /// See [`NightscoutClient::with_rate_limit`].
+    pub fn rate_limit(mut self, limit: RateLimit) -> Self {
+        self.rate_limit = Some(limit);
+        self
+    }
+
+    /// See [`NightscoutClient::read_only`].
+    pub fn read_only(mut self) -> Self {
+        self.read_only = true;
+        self
+    }
+
+    /// See [`NightscoutClient::with_api_version`].
+    pub fn api_version(mut self, version: ApiVersion) -> Self {
+        self.api_version = version;
+        self
+    }
+
+    /// See [`NightscoutClient::with_timezone`].
+    pub fn timezone(mut self, tz: Tz) -> Self {
+        self.timezone = Some(tz);
+        self
+    }
+
+    /// See [`NightscoutClient::with_interceptor`].
+    pub fn interceptor(mut self, interceptor: impl Interceptor + 'static) -> Self {
+        self.interceptors.push(Arc::new(interceptor));
+        self
+    }
+
+    /// Sets the `User-Agent` header. Defaults to `cinnamon/<version>`.
+    pub fn user_agent(mut self, user_agent: &str) -> Self {
+        self.http = self.http.user_agent(user_agent.to_string());
+        self
+    }
+
+    /// Sets headers sent with every request.
+    pub fn default_headers(mut self, headers: reqwest::header::HeaderMap) -> Self {
+        self.http = self.http.default_headers(headers);
+        self
+    }
+
+    /// Sets the timeout of a whole request, from connecting to reading the body.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.http = self.http.timeout(timeout);
+        self
+    }
+
+    /// Sets the timeout for establishing a connection.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn connect_timeout(mut self, timeout: Duration) -> Self {
+        self.http = self.http.connect_timeout(timeout);
+        self
+    }
+
+    /// Routes HTTP and HTTPS traffic through a proxy (e.g. `http://proxy.corp:3128`).
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn proxy(mut self, url: &str) -> Self {
+        self.proxy = Some(url.to_string());
+        self
+    }
+
+    /// Trusts an additional root certificate, e.g. a corporate or self-signed CA.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn add_root_certificate(mut self, certificate: reqwest::Certificate) -> Self {
+        self.http = self.http.add_root_certificate(certificate);
+        self
+    }
+
+    /// Disables certificate validation. Only use this for local testing.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn danger_accept_invalid_certs(mut self, accept: bool) -> Self {
+        self.http = self.http.danger_accept_invalid_certs(accept);
+        self
+    }
+
+    /// Builds the client.
+    ///
+    /// ## Errors
+    ///
+    /// Returns a `NightscoutError` if the URL or proxy is invalid, or the HTTP client
+    /// cannot be created.
+    pub fn build(self) -> Result<NightscoutClient, NightscoutError> {
+        #[allow(unused_mut)]
+        let mut http = self.http;
+
+        #[cfg(not(target_arch = "wasm32"))]
+        if let Some(proxy) = &self.proxy {
+            http = http.proxy(reqwest::Proxy::all(proxy)?);
+        }
+
+        let mut client = NightscoutClient::with_http(&self.base_url, http.build()?)?
+            .with_retry_policy(self.retry_policy)
+            .with_api_version(self.api_version);
+
+        if let Some(tz) = self.timezone {
+            client = client.with_timezone(tz);
+        }
+        if let Some(limit) = self.rate_limit {
+            client = client.with_rate_limit(limit);
+        }
+        if self.read_only {
+            client = client.read_only();
+        }
+        if let Some(secret) = self.api_secret {
+            client = client.with_secret(secret);
+        }
+        if let Some(token) = self.access_token {
+            client = client.with_token(token);
+        }
+        if !self.interceptors.is_empty() {
+            let mut inner = (*client.inner).clone();
+            inner.interceptors = self.interceptors;
+            client = NightscoutClient {
+                inner: Arc::new(inner),
+            };
+        }
+
+        Ok(client)
+    }
 }
 
+/// Parses the URL of a site, adding the default scheme and the trailing slash the
+/// endpoint paths are joined to.
+fn normalize_base_url(base_url: &str) -> Result<Url, NightscoutError> {
+    let base_url = base_url.trim();
+    if base_url.is_empty() {
+        return Err(NightscoutError::InvalidInput(
+            "the base URL is empty".to_string(),
+        ));
+    }
+
+    let mut url = match base_url.contains("://") {
+        true => Url::parse(base_url)?,
+        false => Url::parse(&format!("https://{base_url}"))?,
+    };
+
+    if !matches!(url.scheme(), "http" | "https") || url.host().is_none() {
+        return Err(NightscoutError::InvalidInput(format!(
+            "{} is not the http(s) address of a Nightscout site",
+            redact_url(&url)
+        )));
+    }
+    if url
+        .path_segments()
+        .into_iter()
+        .flatten()
+        .any(|segment| segment == "api")
+    {
+        return Err(NightscoutError::InvalidInput(format!(
+            "{} includes an API path, use the address of the site instead",
+            redact_url(&url)
+        )));
+    }
+
+    url.set_fragment(None);
+    if !url.path().ends_with('/') {
+        let path = format!("{}/", url.path());
+        url.set_path(&path);
+    }
+    Ok(url)
+}
+
+/// Whether credentials sent to `url` travel unencrypted to another machine.
+#[cfg(feature = "tracing")]
+fn is_insecure(url: &Url) -> bool {
+    let loopback = match url.host() {
+        Some(url::Host::Domain(domain)) => domain == "localhost",
+        Some(url::Host::Ipv4(ip)) => ip.is_loopback(),
+        Some(url::Host::Ipv6(ip)) => ip.is_loopback(),
+        None => false,
+    };
+    url.scheme() == "http" && !loopback
+}
+
+/// Parses a `Retry-After` header, given either in seconds or as an HTTP date.
+fn parse_retry_after(value: &str) -> Option<std::time::Duration> {
+    if let Ok(seconds) = value.trim().parse::<u64>() {
+        return Some(std::time::Duration::from_secs(seconds));
+    }
+
+    let date = chrono::DateTime::parse_from_rfc2822(value.trim()).ok()?;
+    Some(
+        (date.with_timezone(&Utc) - Utc::now())
+            .to_std()
+            .unwrap_or_default(),
+    )
+}
+
+/// Formats a URL for logs, hiding access tokens and secrets.
+///
+/// Tokens appear in the path of JWT exchanges, as `token`/`secret` query parameters and
+/// as the password of the URL. The URLs in the client's `Debug` output and in
+/// [`NightscoutError::RequestError`] are redacted the same way.
+///
+/// # Example
+///
+/// ```rust
+/// # use cinnamon::client::redact_url;
+/// let url = url::Url::parse("https://ns.example.com/api/v1/entries.json?token=reader-1a2b").unwrap();
+/// assert!(!redact_url(&url).contains("reader-1a2b"));
+/// ```
+pub fn redact_url(url: &Url) -> String {
+    redacted_url(url).to_string()
+}
+
+pub(crate) fn redacted_url(url: &Url) -> Url {
+    const REDACTED: &str = "<redacted>";
+    const SECRET_PARAMS: [&str; 4] = ["token", "secret", "api_secret", "api-secret"];
+
+    let mut redacted = url.clone();
+    redacted.set_query(None);
+
+    let exchange = Endpoint::AuthorizationRequest.as_path();
+    if let Some(rest) = url.path().trim_start_matches('/').strip_prefix(exchange) {
+        if rest.len() > 1 {
+            redacted.set_path(&format!("/{exchange}/{REDACTED}"));
+        }
+    }
+
+    if url.query().is_some() {
+        let pairs = url.query_pairs().map(|(key, value)| {
+            if SECRET_PARAMS.contains(&key.as_ref()) {
+                (key, REDACTED.into())
+            } else {
+                (key, value)
+            }
+        });
+        redacted.query_pairs_mut().extend_pairs(pairs);
+    }
+
+    let _ = redacted.set_password(None);
+    redacted
+}
+
+/// `User-Agent` sent unless a custom HTTP client or user agent is set.
+const DEFAULT_USER_AGENT: &str = concat!("cinnamon/", env!("CARGO_PKG_VERSION"));
+
+/// Refresh the JWT when it expires within this many seconds.
+const JWT_REFRESH_MARGIN_SECS: i64 = 60;
+
+/// Time between status requests in [`NightscoutClient::wait_until_ready`].
+const READY_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
 impl Deref for NightscoutClient {
     type Target = NightscoutClientInner;
 
@@ -38,6 +434,46 @@ impl Deref for NightscoutClient {
 }
 
 impl NightscoutClient {
+    /// Starts configuring a client with custom HTTP settings.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use cinnamon::client::NightscoutClient;
+    /// # use std::time::Duration;
+    /// let client = NightscoutClient::builder("https://example.com")
+    ///     .secret("my-password-123")
+    ///     .timeout(Duration::from_secs(30))
+    ///     .user_agent("my-app/1.0")
+    ///     .build()
+    ///     .unwrap();
+    /// ```
+    pub fn builder(base_url: &str) -> ClientBuilder {
+        ClientBuilder::new(base_url)
+    }
+
+    /// Creates a client from the `NIGHTSCOUT_*` environment variables, see
+    /// [`crate::config`].
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// # use cinnamon::client::NightscoutClient;
+    /// // NIGHTSCOUT_URL=https://my-cgm.herokuapp.com NIGHTSCOUT_TOKEN=reader-1a2b3c
+    /// let client = NightscoutClient::from_env()?;
+    /// # Ok::<(), cinnamon::error::NightscoutError>(())
+    /// ```
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn from_env() -> Result<Self, NightscoutError> {
+        ClientConfig::from_env()?.build()
+    }
+
+    /// Creates a client from a TOML file, see [`ClientConfig::from_file`].
+    #[cfg(all(feature = "config", not(target_arch = "wasm32")))]
+    pub fn from_config(path: impl AsRef<std::path::Path>) -> Result<Self, NightscoutError> {
+        ClientConfig::from_file(path)?.build()
+    }
+
     /// Creates a new `NightscoutClient` without an API secret.
     ///
     /// This client will only be able to access public endpoints. To perform write operations
@@ -46,15 +482,38 @@ impl NightscoutClient {
     /// ## Arguments
     ///
     /// * `base_url` - The full URL to the Nightscout instance (e.g., `https://my-site.herokuapp.com`).
+    ///   The scheme defaults to `https://` and the trailing slash is optional, sites hosted
+    ///   under a path (`https://example.com/nightscout`) are supported.
     ///
     /// ## Errors
     ///
-    /// Returns a `NightscoutError` if the URL is invalid.
+    /// Returns a `NightscoutError` if the URL is invalid, is not `http(s)`, or already
+    /// points into the API (`https://my-site.herokuapp.com/api/v1`).
     pub fn new(base_url: &str) -> Result<Self, NightscoutError> {
+        Self::builder(base_url).build()
+    }
+
+    /// A client with the default settings sending its requests through `http`.
+    fn with_http(base_url: &str, http: HttpClient) -> Result<Self, NightscoutError> {
         let inner = NightscoutClientInner {
-            base_url: Url::parse(base_url)?,
-            http: HttpClient::new(),
-            api_secret_hash: None,
+            base_url: normalize_base_url(base_url)?,
+            http,
+            auth_mode: AuthMode::None,
+            jwt: Arc::new(Mutex::new(HashMap::new())),
+            capabilities: Arc::new(Mutex::new(None)),
+            retry_policy: RetryPolicy::none(),
+            rate_limiter: None,
+            read_only: false,
+            api_version: ApiVersion::default(),
+            timezone: None,
+            clock_offset: None,
+            conditional_requests: false,
+            validators: Arc::new(ValidatorCache::default()),
+            #[cfg(feature = "cache")]
+            cache: None,
+            #[cfg(all(feature = "local-store", not(target_arch = "wasm32")))]
+            local_store: None,
+            interceptors: Vec::new(),
         };
         let client = Self {
             inner: Arc::new(inner),
@@ -74,29 +533,500 @@ impl NightscoutClient {
     ///     .with_secret("my-password-123");
     /// ```
     pub fn with_secret(self, api_secret: impl Into<String>) -> Self {
-        let secret = api_secret.into();
+        self.with_auth(AuthMode::secret(&api_secret.into()))
+    }
 
-        let mut hasher = Sha1::new();
-        hasher.update(secret.as_bytes());
-        let hash = format!("{:x}", hasher.finalize());
+    /// Authenticates using a Nightscout access token instead of the API secret.
+    ///
+    /// The token is exchanged for a JWT through `/api/v2/authorization/request/{token}`
+    /// on the first authenticated request. The JWT is cached, refreshed shortly before
+    /// it expires, and sent as an `Authorization: Bearer` header.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use cinnamon::client::NightscoutClient;
+    /// let client = NightscoutClient::new("https://example.com").unwrap()
+    ///     .with_token("readable-1a2b3c4d5e6f7a8b");
+    /// ```
+    pub fn with_token(self, token: impl Into<String>) -> Self {
+        self.with_auth(AuthMode::token(token))
+    }
 
-        let inner = NightscoutClientInner {
-            base_url: self.base_url.clone(),
-            http: self.http.clone(),
-            api_secret_hash: Some(hash),
-        };
+    /// Sets how requests are authenticated, replacing any previous secret or token.
+    ///
+    /// To use other credentials for a single query or upload, see
+    /// [`QueryBuilder::with_auth`](crate::query_builder::QueryBuilder::with_auth).
+    pub fn with_auth(self, mode: AuthMode) -> Self {
+        let mut inner = (*self.with_query_auth(mode).inner).clone();
+        // The settings a server reveals can depend on the credentials.
+        inner.capabilities = Arc::new(Mutex::new(None));
+        inner.validators = Arc::new(ValidatorCache::default());
+        #[cfg(feature = "cache")]
+        {
+            inner.cache = inner
+                .cache
+                .as_ref()
+                .map(|cache| Arc::new(ResponseCache::new(cache.config().clone())));
+        }
 
         Self {
             inner: Arc::new(inner),
         }
     }
 
-    /// Adds authentication headers to a request if a secret is present.
-    pub fn auth(&self, request: reqwest::RequestBuilder) -> reqwest::RequestBuilder {
-        if let Some(hash) = &self.api_secret_hash {
-            request.header("api-secret", hash)
-        } else {
-            request
+    /// A clone that authenticates with `mode`, for a single query or upload.
+    ///
+    /// Unlike [`with_auth`](Self::with_auth), the clone shares the response cache and the
+    /// server capabilities, so its writes invalidate the client's cached reads.
+    pub(crate) fn with_query_auth(&self, mode: AuthMode) -> Self {
+        #[cfg(feature = "tracing")]
+        if mode.is_authenticated() && is_insecure(&self.base_url) {
+            tracing::warn!(
+                base_url = %redact_url(&self.base_url),
+                "credentials are sent over plain http, use https"
+            );
+        }
+
+        let mut inner = (*self.inner).clone();
+        inner.auth_mode = mode;
+        Self {
+            inner: Arc::new(inner),
+        }
+    }
+
+    /// Uses a pre-configured `reqwest::Client` for every request.
+    ///
+    /// Useful to share a connection pool with the rest of an application, or to set up
+    /// proxies, TLS roots and pool limits. The client is cheap to clone: clones of the
+    /// `NightscoutClient` and the services created from it all reuse its connections.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use cinnamon::client::NightscoutClient;
+    /// let http = reqwest::Client::builder()
+    ///     .pool_max_idle_per_host(4)
+    ///     .build()
+    ///     .unwrap();
+    ///
+    /// let client = NightscoutClient::new("https://example.com").unwrap()
+    ///     .with_http_client(http);
+    /// ```
+    pub fn with_http_client(self, http: HttpClient) -> Self {
+        let mut inner = (*self.inner).clone();
+        inner.http = http;
+
+        Self {
+            inner: Arc::new(inner),
+        }
+    }
+
+    /// Registers a hook run around every request, see [`crate::middleware`].
+    ///
+    /// Interceptors run in the order they were added.
+    pub fn with_interceptor(self, interceptor: impl Interceptor + 'static) -> Self {
+        let mut inner = (*self.inner).clone();
+        inner.interceptors.push(Arc::new(interceptor));
+
+        Self {
+            inner: Arc::new(inner),
+        }
+    }
+
+    /// Sets the policy used to retry requests failing with transient errors.
+    ///
+    /// By default, requests are not retried.
+    pub fn with_retry_policy(self, policy: RetryPolicy) -> Self {
+        let mut inner = (*self.inner).clone();
+        inner.retry_policy = policy;
+
+        Self {
+            inner: Arc::new(inner),
+        }
+    }
+
+    /// Limits how many requests the client sends, see [`RateLimit`].
+    ///
+    /// The limit applies to every service and to the clones of the returned client, and
+    /// each retry counts as a request. By default, requests are not throttled.
+    pub fn with_rate_limit(self, limit: RateLimit) -> Self {
+        let mut inner = (*self.inner).clone();
+        inner.rate_limiter = Some(Arc::new(RateLimiter::new(limit)));
+
+        Self {
+            inner: Arc::new(inner),
+        }
+    }
+
+    /// Sets the REST API version requests are sent to. Defaults to [`ApiVersion::V2`].
+    ///
+    /// Use [`NightscoutClient::detect_api_version`] to pick it from the server instead.
+    pub fn with_api_version(self, version: ApiVersion) -> Self {
+        let mut inner = (*self.inner).clone();
+        inner.api_version = version;
+
+        Self {
+            inner: Arc::new(inner),
+        }
+    }
+
+    /// Sets the timezone of the user, so days start at their local midnight.
+    ///
+    /// It is the default timezone of [`crate::query_builder::QueryBuilder::today`], AGPs and
+    /// daily statistics, and takes precedence over the profile timezone in reports. Without
+    /// it, days are split in UTC.
+    pub fn with_timezone(self, tz: Tz) -> Self {
+        let mut inner = (*self.inner).clone();
+        inner.timezone = Some(tz);
+
+        Self {
+            inner: Arc::new(inner),
+        }
+    }
+
+    /// Sets how far the server clock is ahead of the local one (negative when behind).
+    ///
+    /// Relative date ranges such as [`crate::query_builder::QueryBuilder::last_hours`] are
+    /// then measured on the server clock. See [`NightscoutClient::sync_clock`] to measure
+    /// the offset.
+    pub fn with_clock_offset(self, offset: chrono::Duration) -> Self {
+        let mut inner = (*self.inner).clone();
+        inner.clock_offset = Some(offset);
+
+        Self {
+            inner: Arc::new(inner),
+        }
+    }
+
+    /// Measures the server clock offset from `serverTimeEpoch` in the status, and uses it
+    /// for relative date ranges, see [`NightscoutClient::with_clock_offset`].
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// # use cinnamon::client::NightscoutClient;
+    /// # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+    /// let client = NightscoutClient::new("https://ns.example.com")?
+    ///     .sync_clock()
+    ///     .await?;
+    ///
+    /// // The last 3 hours of the server clock, even if the local clock is off.
+    /// let entries = client.sgv().get().last_hours(3).limit(36).send().await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn sync_clock(self) -> Result<Self, NightscoutError> {
+        let sent = Utc::now();
+        let status = self.status().get().await?;
+        let received = Utc::now();
+
+        match status.clock_offset(sent, received) {
+            Some(offset) => Ok(self.with_clock_offset(offset)),
+            None => Err(NightscoutError::InvalidInput(
+                "the server reported an invalid serverTimeEpoch".to_string(),
+            )),
+        }
+    }
+
+    /// Waits for the server to report that it has booted, e.g. after a restart, returning
+    /// its status.
+    ///
+    /// The status is polled every second, bypassing the response cache. Connection
+    /// failures and `5xx` responses, common while the server starts, are polled through;
+    /// other errors are returned at once.
+    /// Fails with [`NightscoutError::Timeout`] if the server is not ready within `timeout`.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// # use cinnamon::client::NightscoutClient;
+    /// # use std::time::Duration;
+    /// # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+    /// let client = NightscoutClient::new("https://ns.example.com")?;
+    /// client.wait_until_ready(Duration::from_secs(120)).await?;
+    ///
+    /// let entries = client.sgv().get().last_hours(24).send().await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn wait_until_ready(&self, timeout: Duration) -> Result<Status, NightscoutError> {
+        let transient = RetryPolicy::default();
+        let url = self.endpoint_url(Endpoint::Status)?;
+
+        runtime::timeout(Some(timeout), async {
+            loop {
+                // Read from the server, the cached status would not change while polling.
+                let status = self
+                    .fetch_with_meta(url.clone())
+                    .await
+                    .and_then(|(body, _)| Ok(serde_json::from_slice::<Status>(&body)?));
+                match status {
+                    Ok(status) if status.is_ready() => return Ok(status),
+                    Ok(_) => {}
+                    Err(e) if transient.should_retry(&e) => {}
+                    Err(e) => return Err(e),
+                }
+                runtime::sleep(READY_POLL_INTERVAL).await;
+            }
+        })
+        .await
+    }
+
+    /// The current time on the server clock, or the local clock when the offset is not
+    /// known.
+    pub fn server_now(&self) -> chrono::DateTime<Utc> {
+        let now = Utc::now();
+        self.clock_offset
+            .and_then(|offset| now.checked_add_signed(offset))
+            .unwrap_or(now)
+    }
+
+    /// Refuses every create, update and delete with [`NightscoutError::ReadOnlyViolation`],
+    /// before anything is sent.
+    ///
+    /// Meant for follower apps, which must never change the data they display. The
+    /// restriction cannot be lifted, clients derived from this one keep it.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use cinnamon::client::NightscoutClient;
+    /// let client = NightscoutClient::new("https://example.com").unwrap()
+    ///     .with_token("readable-1a2b3c4d5e6f7a8b")
+    ///     .read_only();
+    /// ```
+    pub fn read_only(self) -> Self {
+        let mut inner = (*self.inner).clone();
+        inner.read_only = true;
+
+        Self {
+            inner: Arc::new(inner),
+        }
+    }
+
+    /// Fails with [`NightscoutError::ReadOnlyViolation`] if the client is read-only.
+    pub(crate) fn ensure_writable(&self, operation: &str) -> Result<(), NightscoutError> {
+        match self.read_only {
+            true => Err(NightscoutError::ReadOnlyViolation(operation.to_string())),
+            false => Ok(()),
+        }
+    }
+
+    /// Revalidates GET requests with `If-None-Match` / `If-Modified-Since` and reuses the
+    /// previous response when the server answers `304 Not Modified`.
+    ///
+    /// This saves bandwidth for frequent pollers, at the cost of keeping the last response
+    /// of each URL in memory. Disabled by default.
+    pub fn with_conditional_requests(self, enabled: bool) -> Self {
+        let mut inner = (*self.inner).clone();
+        inner.conditional_requests = enabled;
+
+        Self {
+            inner: Arc::new(inner),
+        }
+    }
+
+    /// Caches GET responses in memory, for the time-to-live configured per endpoint.
+    ///
+    /// The cache is shared between clones of the client and cleared by any write.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use cinnamon::cache::CacheConfig;
+    /// # use cinnamon::client::NightscoutClient;
+    /// let client = NightscoutClient::new("https://example.com").unwrap()
+    ///     .with_cache(CacheConfig::default());
+    /// ```
+    #[cfg(feature = "cache")]
+    pub fn with_cache(self, config: CacheConfig) -> Self {
+        let mut inner = (*self.inner).clone();
+        inner.cache = Some(Arc::new(ResponseCache::new(config)));
+
+        Self {
+            inner: Arc::new(inner),
+        }
+    }
+
+    /// Drops every cached response.
+    #[cfg(feature = "cache")]
+    pub fn clear_cache(&self) {
+        if let Some(cache) = &self.cache {
+            cache.clear();
+        }
+    }
+
+    /// Attaches a [`LocalStore`] kept up to date by [`LocalService::sync`], see
+    /// [`NightscoutClient::local`].
+    #[cfg(all(feature = "local-store", not(target_arch = "wasm32")))]
+    pub fn with_local_store(self, store: LocalStore) -> Self {
+        let mut inner = (*self.inner).clone();
+        inner.local_store = Some(store);
+
+        Self {
+            inner: Arc::new(inner),
+        }
+    }
+
+    /// Probes the status endpoint and switches to [`ApiVersion::V1`] when the server does
+    /// not expose the v2 API.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// # use cinnamon::client::NightscoutClient;
+    /// # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+    /// let client = NightscoutClient::new("https://ns.example.com")?
+    ///     .detect_api_version()
+    ///     .await?;
+    ///
+    /// println!("Using {:?}", client.api_version);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn detect_api_version(self) -> Result<Self, NightscoutError> {
+        for version in [ApiVersion::V2, ApiVersion::V1] {
+            let url = self.base_url.join(&Endpoint::Status.path_for(version))?;
+            let request = self.authorize(self.http.get(url)).await?;
+
+            match self.send_checked(request).await {
+                Ok(_) if version == self.api_version.legacy() => return Ok(self),
+                Ok(_) => return Ok(self.with_api_version(version)),
+                Err(e) if e.status() == Some(reqwest::StatusCode::NOT_FOUND) => continue,
+                Err(e) => return Err(e),
+            }
+        }
+
+        Err(NightscoutError::NotFound)
+    }
+
+    /// The URL of `endpoint` for the configured API version.
+    ///
+    /// Endpoints without a v3 counterpart, and writes, use v2 when v3 is configured.
+    pub(crate) fn endpoint_url(&self, endpoint: Endpoint) -> Result<Url, NightscoutError> {
+        let path = endpoint.path_for(self.api_version.legacy());
+        Ok(self.base_url.join(&path)?)
+    }
+
+    /// Returns a valid JWT for the configured access token, exchanging or
+    /// refreshing it if needed.
+    ///
+    /// Returns `Ok(None)` when the client has no access token.
+    pub async fn bearer_token(&self) -> Result<Option<String>, NightscoutError> {
+        let AuthMode::Token(access_token) = &self.auth_mode else {
+            return Ok(None);
+        };
+
+        let mut cached = self.jwt.lock().await;
+
+        if let Some(jwt) = cached.get(access_token) {
+            if !jwt.expires_within(Utc::now(), JWT_REFRESH_MARGIN_SECS) {
+                return Ok(Some(jwt.token.clone()));
+            }
+        }
+
+        let path = format!(
+            "{}/{}",
+            Endpoint::AuthorizationRequest.as_path(),
+            access_token
+        );
+        let url = self.base_url.join(&path)?;
+        let response = self.send_checked(self.http.get(url)).await?;
+        let jwt = response.json::<AuthorizationToken>().await?;
+        let token = jwt.token.clone();
+        cached.insert(access_token.clone(), jwt);
+
+        Ok(Some(token))
+    }
+
+    /// Adds authentication headers to a request.
+    ///
+    /// This is the single place credentials are attached: the hashed `api-secret` header
+    /// for [`AuthMode::Secret`], or an `Authorization: Bearer` JWT for [`AuthMode::Token`].
+    pub(crate) async fn authorize(
+        &self,
+        request: reqwest::RequestBuilder,
+    ) -> Result<reqwest::RequestBuilder, NightscoutError> {
+        match &self.auth_mode {
+            AuthMode::None => Ok(request),
+            AuthMode::Secret(hash) => Ok(request.header("api-secret", hash)),
+            AuthMode::Token(_) => match self.bearer_token().await? {
+                Some(jwt) => Ok(request.bearer_auth(jwt)),
+                None => Ok(request),
+            },
+        }
+    }
+
+    /// Returns the server version and enabled features.
+    ///
+    /// The status endpoint is queried on first use only, the result is cached for the
+    /// lifetime of the client. Use [`NightscoutClient::refresh_capabilities`] to re-read it.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// # use cinnamon::client::NightscoutClient;
+    /// # use cinnamon::capabilities::Feature;
+    /// # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+    /// let client = NightscoutClient::new("https://ns.example.com")?;
+    /// let capabilities = client.capabilities().await?;
+    ///
+    /// if capabilities.has(&Feature::Careportal) {
+    ///     println!("Treatments can be entered on this site");
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn capabilities(&self) -> Result<Capabilities, NightscoutError> {
+        let mut cached = self.capabilities.lock().await;
+        if let Some(capabilities) = cached.as_ref() {
+            return Ok(capabilities.clone());
+        }
+
+        let status = self.status().get().await?;
+        let capabilities = Capabilities::from_status(&status);
+        *cached = Some(capabilities.clone());
+        Ok(capabilities)
+    }
+
+    /// Discards the cached capabilities and queries the server again.
+    pub async fn refresh_capabilities(&self) -> Result<Capabilities, NightscoutError> {
+        *self.capabilities.lock().await = None;
+        self.capabilities().await
+    }
+
+    /// Fetches the latest reading, delta, IOB, COB, batteries and profile concurrently.
+    ///
+    /// Only a failure to fetch the readings is returned as an error, the other values are
+    /// `None` when they cannot be fetched.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// # use cinnamon::client::NightscoutClient;
+    /// # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+    /// let client = NightscoutClient::new("https://ns.example.com")?;
+    /// let snapshot = client.snapshot().await?;
+    ///
+    /// println!(
+    ///     "{} {:?} ({:+}) IOB {:.2} U",
+    ///     snapshot.sgv.sgv,
+    ///     snapshot.direction,
+    ///     snapshot.delta.unwrap_or_default(),
+    ///     snapshot.iob.unwrap_or_default()
+    /// );
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn snapshot(&self) -> Result<Snapshot, NightscoutError> {
+        snapshot::fetch(self).await
+    }
+
+    /// Access the authentication service, to verify credentials and their permissions.
+    pub fn auth(&self) -> AuthService {
+        AuthService {
+            client: self.clone(),
         }
     }
 
@@ -121,6 +1051,27 @@ impl NightscoutClient {
         }
     }
 
+    /// Access the calibration (CAL) service.
+    pub fn cal(&self) -> CalService {
+        CalService {
+            client: self.clone(),
+        }
+    }
+
+    /// Access the entries collection, grouping the SGV, MBG and calibration services.
+    pub fn entries(&self) -> EntriesService {
+        EntriesService {
+            client: self.clone(),
+        }
+    }
+
+    /// Access the glucose forecasts uploaded by Loop, OpenAPS, AndroidAPS and Trio.
+    pub fn predictions(&self) -> PredictionsService {
+        PredictionsService {
+            client: self.clone(),
+        }
+    }
+
     /// Access the Properties service for system status (IOB, COB, Pump).
     pub fn properties(&self) -> PropertiesService {
         PropertiesService {
@@ -142,6 +1093,98 @@ impl NightscoutClient {
         }
     }
 
+    /// Access the Activity service for heart rate, steps and exercise records.
+    pub fn activity(&self) -> ActivityService {
+        ActivityService {
+            client: self.clone(),
+        }
+    }
+
+    /// Access the notifications service, to acknowledge active alarms.
+    pub fn notifications(&self) -> NotificationsService {
+        NotificationsService {
+            client: self.clone(),
+        }
+    }
+
+    /// Access the compact Pebble endpoint, suited to watch faces and tray widgets.
+    pub fn pebble(&self) -> PebbleService {
+        PebbleService {
+            client: self.clone(),
+        }
+    }
+
+    /// Creates a [`SyncManager`] pulling changes incrementally through the v3 API.
+    pub fn sync(&self) -> SyncManager {
+        SyncManager::new(self.clone())
+    }
+
+    /// Access the local store for offline queries, see [`NightscoutClient::with_local_store`].
+    #[cfg(all(feature = "local-store", not(target_arch = "wasm32")))]
+    pub fn local(&self) -> LocalService {
+        LocalService {
+            client: self.clone(),
+        }
+    }
+
+    /// Access the Prometheus gauges of the site, built from [`NightscoutClient::snapshot`].
+    #[cfg(feature = "metrics")]
+    pub fn metrics(&self) -> MetricsService {
+        MetricsService {
+            client: self.clone(),
+        }
+    }
+
+    /// Access the alarms evaluated locally from the server's thresholds and latest data.
+    pub fn alarms(&self) -> AlarmsService {
+        AlarmsService {
+            client: self.clone(),
+        }
+    }
+
+    /// Access the JSON Lines export of whole collections, for backups.
+    pub fn export(&self) -> ExportService {
+        ExportService {
+            client: self.clone(),
+        }
+    }
+
+    /// Access the import of Nightscout backups.
+    pub fn import(&self) -> ImportService {
+        ImportService {
+            client: self.clone(),
+        }
+    }
+
+    /// Creates a forwarder posting new readings and treatments to webhooks.
+    pub fn forwarder(&self) -> Forwarder {
+        Forwarder::new(self.clone())
+    }
+
+    /// Creates a heartbeat monitor reporting availability, clock drift and stale data.
+    pub fn monitor(&self) -> Monitor {
+        Monitor::new(self.clone())
+    }
+
+    /// Creates a publisher sending the site's state to the MQTT broker at `host:port`,
+    /// for Home Assistant.
+    #[cfg(all(feature = "mqtt", not(target_arch = "wasm32")))]
+    pub fn mqtt(&self, host: &str, port: u16) -> MqttPublisher {
+        MqttPublisher::new(self.clone(), host, port)
+    }
+
+    /// Access local analysis (IOB, COB, basal) computed from treatments and entries.
+    pub fn analysis(&self) -> AnalysisService {
+        AnalysisService::new(self.clone())
+    }
+
+    /// Access aggregated reports such as daily insulin and carb totals.
+    pub fn reports(&self) -> ReportsService {
+        ReportsService {
+            client: self.clone(),
+        }
+    }
+
     /// Access the server status service (version, settings, capabilities).
     pub fn status(&self) -> StatusService {
         StatusService {
@@ -151,29 +1194,428 @@ impl NightscoutClient {
 
     /// Sends a request and checks the response status.
     ///
+    /// Transient failures are retried according to the client's [`RetryPolicy`].
+    ///
     /// Returns `NightscoutError::AuthError` if the server returns 401 Unauthorized,
     /// or `NightscoutError::ApiError` for other non-success codes.
     pub(crate) async fn send_checked(
         &self,
         request: reqwest::RequestBuilder,
     ) -> Result<Response, NightscoutError> {
-        let response = request.send().await?;
+        let (http, request) = request.build_split();
+        let request = request?;
+
+        if !matches!(
+            *request.method(),
+            reqwest::Method::GET | reqwest::Method::HEAD
+        ) {
+            self.ensure_writable(&format!("{} {}", request.method(), request.url().path()))?;
+        }
+
+        #[cfg(feature = "cache")]
+        let write = request.method() != reqwest::Method::GET;
+
+        #[cfg(feature = "tracing")]
+        let result = {
+            use tracing::Instrument;
+
+            let span = tracing::debug_span!(
+                "nightscout.request",
+                method = %request.method(),
+                endpoint = %redact_url(request.url()),
+                status = tracing::field::Empty,
+                latency_ms = tracing::field::Empty,
+                attempts = tracing::field::Empty,
+            );
+            self.send_with_retries(http, request).instrument(span).await
+        };
+
+        #[cfg(not(feature = "tracing"))]
+        let result = self.send_with_retries(http, request).await;
+
+        // Cleared once the write is done, so a read sent meanwhile cannot cache the data
+        // from before it. A failed write may still have been applied.
+        #[cfg(feature = "cache")]
+        if let Some(cache) = self.cache.as_ref().filter(|_| write) {
+            cache.clear();
+        }
+
+        result
+    }
+
+    async fn send_with_retries(
+        &self,
+        http: HttpClient,
+        request: reqwest::Request,
+    ) -> Result<Response, NightscoutError> {
+        let policy = &self.retry_policy;
+        let mut request = request;
+        let mut attempt = 1;
+
+        loop {
+            // Streaming bodies cannot be cloned, those requests are sent only once.
+            let retry_request = if attempt < policy.max_attempts {
+                request.try_clone()
+            } else {
+                None
+            };
+
+            if let Some(limiter) = &self.rate_limiter {
+                limiter.acquire().await;
+            }
+            let result = self.send_once(&http, request).await;
+
+            match (result, retry_request) {
+                (Err(e), Some(next)) if policy.should_retry_request(next.method(), &e) => {
+                    #[cfg(feature = "tracing")]
+                    tracing::debug!(attempt, error = %e, "retrying request");
+
+                    runtime::sleep(policy.delay_for(&e, attempt)).await;
+                    request = next;
+                    attempt += 1;
+                }
+                (result, _) => {
+                    #[cfg(feature = "tracing")]
+                    tracing::Span::current().record("attempts", attempt);
+
+                    return result;
+                }
+            }
+        }
+    }
+
+    /// Sends a request once, running the interceptors around it.
+    async fn send_once(
+        &self,
+        http: &HttpClient,
+        request: reqwest::Request,
+    ) -> Result<Response, NightscoutError> {
+        let mut request = request;
+
+        for interceptor in &self.interceptors {
+            interceptor.on_request(&mut request);
+        }
 
-        if response.status().is_success() {
+        let method = request.method().clone();
+        let url = request.url().clone();
+        let conditional = [
+            reqwest::header::IF_NONE_MATCH,
+            reqwest::header::IF_MODIFIED_SINCE,
+        ]
+        .iter()
+        .any(|name| request.headers().contains_key(name));
+        let started = Utc::now();
+
+        let result = match http.execute(request).await {
+            Ok(response) => {
+                let elapsed = (Utc::now() - started).to_std().unwrap_or_default();
+
+                #[cfg(feature = "tracing")]
+                {
+                    let span = tracing::Span::current();
+                    span.record("status", response.status().as_u16());
+                    span.record("latency_ms", elapsed.as_millis() as u64);
+                }
+
+                for interceptor in &self.interceptors {
+                    interceptor.on_response(&method, &response, elapsed);
+                }
+                self.check_response(response, conditional).await
+            }
+            Err(e) => Err(e.into()),
+        };
+
+        if let Err(e) = &result {
+            for interceptor in &self.interceptors {
+                interceptor.on_error(&method, &url, e);
+            }
+        }
+
+        result
+    }
+
+    /// Maps non-success statuses to errors.
+    ///
+    /// `304 Not Modified` is only accepted for `conditional` requests, which handle it
+    /// themselves; any other request needs a body.
+    async fn check_response(
+        &self,
+        response: Response,
+        conditional: bool,
+    ) -> Result<Response, NightscoutError> {
+        let not_modified = response.status() == reqwest::StatusCode::NOT_MODIFIED;
+        if response.status().is_success() || (conditional && not_modified) {
             Ok(response)
         } else {
             let status = response.status();
+            let retry_after = response
+                .headers()
+                .get(reqwest::header::RETRY_AFTER)
+                .and_then(|value| value.to_str().ok())
+                .and_then(parse_retry_after);
             let message = response
                 .text()
                 .await
                 .unwrap_or_else(|_| "Unknown API error".to_string());
 
             if status == reqwest::StatusCode::UNAUTHORIZED {
+                // The JWT may have been revoked server side, exchange it again next time.
+                if let AuthMode::Token(access_token) = &self.auth_mode {
+                    if let Ok(mut cached) = self.jwt.try_lock() {
+                        cached.remove(access_token);
+                    }
+                }
                 return Err(NightscoutError::AuthError);
             }
 
-            Err(NightscoutError::ApiError { status, message })
+            Err(NightscoutError::ApiError {
+                status,
+                body: ApiErrorBody::parse(&message),
+                message,
+                retry_after,
+            })
+        }
+    }
+
+    /// Helper to replace an existing document through a `PUT` request.
+    ///
+    /// The `_id` of the serialized document is set to `id`. Nightscout answers either
+    /// with the updated document or a single-element array, both are accepted.
+    pub(crate) async fn update_document<T>(
+        &self,
+        endpoint: Endpoint,
+        id: &str,
+        document: &T,
+    ) -> Result<T, NightscoutError>
+    where
+        T: serde::Serialize + serde::de::DeserializeOwned,
+    {
+        let mut body = serde_json::to_value(document)?;
+        if let Some(object) = body.as_object_mut() {
+            object.insert("_id".to_string(), serde_json::Value::String(id.to_string()));
+        }
+
+        let url = self.endpoint_url(endpoint)?;
+        let request = self.authorize(self.http.put(url)).await?;
+        let response = self.send_checked(request.json(&body)).await?;
+
+        let value = match response.json::<serde_json::Value>().await? {
+            serde_json::Value::Array(mut items) if !items.is_empty() => items.swap_remove(0),
+            serde_json::Value::Array(_) => return Err(NightscoutError::NotFound),
+            // Some servers only acknowledge the write, fall back to what was sent.
+            serde_json::Value::Object(object) if !object.contains_key("_id") => body,
+            value => value,
+        };
+
+        Ok(serde_json::from_value(value)?)
+    }
+
+    /// Helper to create a single document through a `POST` request.
+    ///
+    /// Nightscout answers either with the created document or with an array holding it.
+    pub(crate) async fn create_document<T>(
+        &self,
+        endpoint: Endpoint,
+        document: &T,
+    ) -> Result<T, NightscoutError>
+    where
+        T: serde::Serialize + serde::de::DeserializeOwned,
+    {
+        let url = self.endpoint_url(endpoint)?;
+        let request = self.authorize(self.http.post(url)).await?;
+        let response = self.send_checked(request.json(document)).await?;
+
+        let value = match response.json::<serde_json::Value>().await? {
+            serde_json::Value::Array(mut items) if !items.is_empty() => items.swap_remove(0),
+            serde_json::Value::Array(_) => return Err(NightscoutError::NotFound),
+            value => value,
+        };
+
+        Ok(serde_json::from_value(value)?)
+    }
+
+    /// Helper to delete a single document by id.
+    pub(crate) async fn delete_document(
+        &self,
+        endpoint: Endpoint,
+        id: &str,
+    ) -> Result<(), NightscoutError> {
+        let path = endpoint.path_for(self.api_version.legacy());
+        let url = self.base_url.join(&format!("{}/{}", path, id))?;
+        let request = self.authorize(self.http.delete(url)).await?;
+        self.send_checked(request).await?;
+        Ok(())
+    }
+
+    /// Helper to fetch a single document by id, `None` if it does not exist.
+    ///
+    /// The v3 API has a route for it; the older ones are queried with `find[_id]`, which
+    /// every collection supports.
+    pub(crate) async fn get_document<T>(
+        &self,
+        endpoint: Endpoint,
+        id: &str,
+    ) -> Result<Option<T>, NightscoutError>
+    where
+        T: serde::de::DeserializeOwned,
+    {
+        let url = match endpoint.v3_collection() {
+            Some((collection, _)) if self.api_version == ApiVersion::V3 => self
+                .base_url
+                .join(&format!("api/v3/{}/{}", collection, id))?,
+            _ => {
+                let mut url = self.endpoint_url(endpoint)?;
+                url.query_pairs_mut()
+                    .append_pair("find[_id]", id)
+                    .append_pair("count", "1");
+                url
+            }
+        };
+
+        let value = match self.fetch::<serde_json::Value>(url).await {
+            Ok(value) => value,
+            Err(NightscoutError::ApiError { status, .. })
+                if status == reqwest::StatusCode::NOT_FOUND =>
+            {
+                return Ok(None)
+            }
+            Err(e) => return Err(e),
+        };
+
+        let document = match value {
+            serde_json::Value::Array(mut items) if !items.is_empty() => items.swap_remove(0),
+            serde_json::Value::Array(_) | serde_json::Value::Null => return Ok(None),
+            serde_json::Value::Object(mut object) if object.contains_key("result") => {
+                match object.remove("result") {
+                    Some(serde_json::Value::Null) | None => return Ok(None),
+                    Some(result) => result,
+                }
+            }
+            value => value,
+        };
+
+        Ok(Some(serde_json::from_value(document)?))
+    }
+
+    /// Sends a GET request with the validators remembered for `url`.
+    ///
+    /// Returns the response body, or `None` if the server answered `304 Not Modified`.
+    /// With `keep_body`, the body is remembered too, and validators are only sent when a
+    /// body to fall back on is available.
+    async fn get_conditional(
+        &self,
+        url: &Url,
+        keep_body: bool,
+    ) -> Result<Option<Arc<[u8]>>, NightscoutError> {
+        let key = url.as_str();
+        let cached = self
+            .validators
+            .get(key)
+            .filter(|v| !keep_body || v.body.is_some());
+
+        let mut request = self.authorize(self.http.get(url.clone())).await?;
+        if let Some(validators) = &cached {
+            request = validators.apply(request);
+        }
+
+        let response = self.send_checked(request).await?;
+        if response.status() == reqwest::StatusCode::NOT_MODIFIED {
+            return match cached {
+                Some(validators) if keep_body => Ok(validators.body),
+                Some(_) => Ok(None),
+                // Not asked for, the server should not have sent it.
+                None => Err(NightscoutError::Unknown),
+            };
         }
+
+        let validators = Validators::from_headers(response.headers());
+        let body: Arc<[u8]> = Arc::from(response.bytes().await?.as_ref());
+
+        match validators {
+            Some(mut validators) => {
+                if keep_body {
+                    validators.body = Some(body.clone());
+                }
+                self.validators.insert(key.to_string(), validators);
+            }
+            None => self.validators.remove(key),
+        }
+
+        Ok(Some(body))
+    }
+
+    /// Fetches `url` conditionally, returning [`Conditional::NotModified`] if the response
+    /// did not change since the previous request to the same URL.
+    pub(crate) async fn fetch_conditional<T: serde::de::DeserializeOwned>(
+        &self,
+        url: Url,
+    ) -> Result<Conditional<T>, NightscoutError> {
+        match self.get_conditional(&url, false).await? {
+            Some(body) => Ok(Conditional::Modified(serde_json::from_slice(&body)?)),
+            None => Ok(Conditional::NotModified),
+        }
+    }
+
+    /// Fetches the raw body of a GET request, revalidating it if conditional requests are on.
+    async fn fetch_body(&self, url: &Url) -> Result<Arc<[u8]>, NightscoutError> {
+        if self.conditional_requests {
+            return self
+                .get_conditional(url, true)
+                .await?
+                .ok_or(NightscoutError::Unknown);
+        }
+
+        let request = self.authorize(self.http.get(url.clone())).await?;
+        let response = self.send_checked(request).await?;
+        Ok(Arc::from(response.bytes().await?.as_ref()))
+    }
+
+    /// Fetches the raw body of a GET request along with the response details.
+    ///
+    /// Always goes to the server, bypassing the response cache and conditional requests.
+    pub(crate) async fn fetch_with_meta(
+        &self,
+        url: Url,
+    ) -> Result<(Vec<u8>, ResponseMeta), NightscoutError> {
+        let started = Utc::now();
+        let request = self.authorize(self.http.get(url)).await?;
+        let response = self.send_checked(request).await?;
+
+        let status = response.status();
+        let headers = response.headers().clone();
+        let url = response.url().clone();
+        let body = response.bytes().await?.to_vec();
+
+        let meta = ResponseMeta {
+            status,
+            headers,
+            url,
+            elapsed: (Utc::now() - started).to_std().unwrap_or_default(),
+        };
+        Ok((body, meta))
+    }
+
+    /// Fetches the body of a GET request as text, asking for the `accept` media type.
+    ///
+    /// Returns the body with the media type the server answered with. Bypasses the
+    /// response cache and conditional requests, which only handle JSON.
+    #[cfg(feature = "csv")]
+    pub(crate) async fn fetch_text(
+        &self,
+        url: Url,
+        accept: &str,
+    ) -> Result<(String, Option<String>), NightscoutError> {
+        let request = self
+            .authorize(self.http.get(url).header(reqwest::header::ACCEPT, accept))
+            .await?;
+        let response = self.send_checked(request).await?;
+
+        let content_type = response
+            .headers()
+            .get(reqwest::header::CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .map(|value| value.to_string());
+        Ok((response.text().await?, content_type))
     }
 
     /// Helper to fetch and deserialize a JSON response from a URL.
@@ -181,9 +1623,40 @@ impl NightscoutClient {
         &self,
         url: Url,
     ) -> Result<T, NightscoutError> {
-        let req = self.auth(self.http.get(url));
+        #[cfg(feature = "cache")]
+        if let Some(cache) = &self.cache {
+            if let Some(ttl) = cache.ttl_for(&url) {
+                let key = cache::normalize(&url);
+                if let Some(body) = cache.get(&key) {
+                    return Ok(serde_json::from_slice(&body)?);
+                }
+
+                let generation = cache.generation();
+                let body = self.fetch_body(&url).await?;
+                let data = serde_json::from_slice(&body)?;
+                cache.insert(key, body, ttl, generation);
+                return Ok(data);
+            }
+        }
+
+        if self.conditional_requests {
+            let body = self.fetch_body(&url).await?;
+            return Ok(serde_json::from_slice(&body)?);
+        }
+
+        let req = self.authorize(self.http.get(url.clone())).await?;
         let res = self.send_checked(req).await?;
-        let data = res.json::<T>().await?;
-        Ok(data)
+        let data = res.json::<T>().await;
+
+        #[cfg(feature = "tracing")]
+        if let Err(e) = &data {
+            tracing::debug!(
+                endpoint = %redact_url(&url),
+                error = %e,
+                "failed to deserialize response"
+            );
+        }
+
+        Ok(data?)
     }
 }
@@ -0,0 +1,109 @@
+//! Conditional requests using `ETag` and `Last-Modified` validators.
+//!
+//! For conditional requests, the client remembers the validators of each response and sends
+//! them back as `If-None-Match` / `If-Modified-Since`. A `304 Not Modified` answer is either
+//! surfaced as [`Conditional::NotModified`] or, with
+//! [`crate::client::NightscoutClient::with_conditional_requests`], replaced by the cached
+//! copy of the previous response.
+
+use reqwest::header::{
+    HeaderMap, HeaderValue, ETAG, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED,
+};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+/// Maximum number of URLs whose validators are remembered.
+const MAX_ENTRIES: usize = 256;
+
+/// The outcome of a conditional request.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Conditional<T> {
+    /// The resource changed since the last request, or was requested for the first time.
+    Modified(T),
+    /// The server answered `304 Not Modified`.
+    NotModified,
+}
+
+impl<T> Conditional<T> {
+    pub fn is_modified(&self) -> bool {
+        matches!(self, Conditional::Modified(_))
+    }
+
+    /// The new value, `None` if the resource did not change.
+    pub fn into_option(self) -> Option<T> {
+        match self {
+            Conditional::Modified(value) => Some(value),
+            Conditional::NotModified => None,
+        }
+    }
+
+    pub fn map<U>(self, f: impl FnOnce(T) -> U) -> Conditional<U> {
+        match self {
+            Conditional::Modified(value) => Conditional::Modified(f(value)),
+            Conditional::NotModified => Conditional::NotModified,
+        }
+    }
+}
+
+/// Validators of a previous response, with its body when it is kept for reuse.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Validators {
+    etag: Option<HeaderValue>,
+    last_modified: Option<HeaderValue>,
+    pub(crate) body: Option<Arc<[u8]>>,
+}
+
+impl Validators {
+    /// Reads the validators of a response, `None` if it has neither.
+    pub(crate) fn from_headers(headers: &HeaderMap) -> Option<Self> {
+        let etag = headers.get(ETAG).cloned();
+        let last_modified = headers.get(LAST_MODIFIED).cloned();
+
+        (etag.is_some() || last_modified.is_some()).then_some(Self {
+            etag,
+            last_modified,
+            body: None,
+        })
+    }
+
+    /// Adds the conditional headers to a request.
+    pub(crate) fn apply(&self, request: reqwest::RequestBuilder) -> reqwest::RequestBuilder {
+        let mut request = request;
+        if let Some(etag) = &self.etag {
+            request = request.header(IF_NONE_MATCH, etag.clone());
+        }
+        if let Some(last_modified) = &self.last_modified {
+            request = request.header(IF_MODIFIED_SINCE, last_modified.clone());
+        }
+        request
+    }
+}
+
+/// Validators remembered per URL, shared between clones of a client.
+#[derive(Debug, Default)]
+pub(crate) struct ValidatorCache {
+    entries: Mutex<HashMap<String, Validators>>,
+}
+
+impl ValidatorCache {
+    pub(crate) fn get(&self, url: &str) -> Option<Validators> {
+        self.entries.lock().ok()?.get(url).cloned()
+    }
+
+    pub(crate) fn insert(&self, url: String, validators: Validators) {
+        if let Ok(mut entries) = self.entries.lock() {
+            // Pollers moving their date range produce a new URL every time, start over
+            // rather than growing without bound.
+            if entries.len() >= MAX_ENTRIES && !entries.contains_key(&url) {
+                entries.clear();
+            }
+            entries.insert(url, validators);
+        }
+    }
+
+    pub(crate) fn remove(&self, url: &str) {
+        if let Ok(mut entries) = self.entries.lock() {
+            entries.remove(url);
+        }
+    }
+}
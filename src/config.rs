@@ -0,0 +1,127 @@
+//! Client settings read from the environment or a configuration file.
+//!
+//! Both sources use the same settings, so a binary can take a file in development and
+//! environment variables when deployed:
+//!
+//! | Setting        | Variable                                  | TOML key       |
+//! |----------------|-------------------------------------------|----------------|
+//! | Site URL       | `NIGHTSCOUT_URL`                          | `url`          |
+//! | Access token   | `NIGHTSCOUT_TOKEN`                        | `token`        |
+//! | API secret     | `NIGHTSCOUT_API_SECRET` or `API_SECRET`   | `api_secret`   |
+//! | Glucose unit   | `NIGHTSCOUT_UNITS`                        | `units`        |
+//! | Timeout (s)    | `NIGHTSCOUT_TIMEOUT`                      | `timeout_secs` |
+
+use crate::client::{ClientBuilder, NightscoutClient};
+use crate::error::NightscoutError;
+use crate::models::glucose::GlucoseUnit;
+
+use serde::Deserialize;
+use std::time::Duration;
+
+/// The settings of a client, see [`NightscoutClient::from_env`] and
+/// [`NightscoutClient::from_config`].
+#[derive(Clone, PartialEq, Default, Deserialize)]
+pub struct ClientConfig {
+    /// The address of the site.
+    pub url: String,
+    /// An access token, used instead of the API secret when both are set.
+    #[serde(default)]
+    pub token: Option<String>,
+    #[serde(default)]
+    pub api_secret: Option<String>,
+    /// The unit the user reads glucose in. The client itself always works in mg/dL, this
+    /// is for applications to display values with.
+    #[serde(default)]
+    pub units: Option<GlucoseUnit>,
+    /// The timeout of a whole request, in seconds.
+    #[serde(default)]
+    pub timeout_secs: Option<u64>,
+}
+
+impl ClientConfig {
+    /// Reads the settings from the environment. Only `NIGHTSCOUT_URL` is required.
+    pub fn from_env() -> Result<Self, NightscoutError> {
+        let var = |name: &str| {
+            std::env::var(name)
+                .ok()
+                .map(|value| value.trim().to_string())
+                .filter(|value| !value.is_empty())
+        };
+
+        let url = var("NIGHTSCOUT_URL").ok_or_else(|| {
+            NightscoutError::InvalidInput("NIGHTSCOUT_URL is not set".to_string())
+        })?;
+        let units = var("NIGHTSCOUT_UNITS")
+            .map(|units| units.parse())
+            .transpose()?;
+        let timeout_secs = var("NIGHTSCOUT_TIMEOUT")
+            .map(|timeout| {
+                timeout.parse().map_err(|_| {
+                    NightscoutError::InvalidInput(format!(
+                        "NIGHTSCOUT_TIMEOUT is not a number of seconds: {timeout:?}"
+                    ))
+                })
+            })
+            .transpose()?;
+
+        Ok(Self {
+            url,
+            token: var("NIGHTSCOUT_TOKEN"),
+            api_secret: var("NIGHTSCOUT_API_SECRET").or_else(|| var("API_SECRET")),
+            units,
+            timeout_secs,
+        })
+    }
+
+    /// Reads the settings from a TOML file.
+    ///
+    /// ```toml
+    /// url = "https://my-cgm.herokuapp.com"
+    /// token = "reader-1a2b3c4d5e6f7a8b"
+    /// units = "mmol"
+    /// timeout_secs = 30
+    /// ```
+    #[cfg(feature = "config")]
+    pub fn from_file(path: impl AsRef<std::path::Path>) -> Result<Self, NightscoutError> {
+        let text = std::fs::read_to_string(path.as_ref())?;
+        toml::from_str(&text).map_err(|e| {
+            NightscoutError::InvalidInput(format!(
+                "invalid configuration in {}: {e}",
+                path.as_ref().display()
+            ))
+        })
+    }
+
+    /// A builder with these settings, to customize the client further.
+    pub fn builder(&self) -> ClientBuilder {
+        let mut builder = NightscoutClient::builder(&self.url);
+        if let Some(timeout) = self.timeout_secs {
+            builder = builder.timeout(Duration::from_secs(timeout));
+        }
+        if let Some(secret) = &self.api_secret {
+            builder = builder.secret(secret);
+        }
+        if let Some(token) = &self.token {
+            builder = builder.token(token);
+        }
+        builder
+    }
+
+    /// Creates a client with these settings.
+    pub fn build(&self) -> Result<NightscoutClient, NightscoutError> {
+        self.builder().build()
+    }
+}
+
+impl std::fmt::Debug for ClientConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let redacted = |value: &Option<String>| value.as_ref().map(|_| "<redacted>");
+        f.debug_struct("ClientConfig")
+            .field("url", &self.url)
+            .field("token", &redacted(&self.token))
+            .field("api_secret", &redacted(&self.api_secret))
+            .field("units", &self.units)
+            .field("timeout_secs", &self.timeout_secs)
+            .finish()
+    }
+}
@@ -1,6 +1,34 @@
+use std::borrow::Cow;
+
+/// Version of the Nightscout REST API requests are sent to.
+///
+/// Most servers expose both v1 and v2, older self-hosted instances only v1. With
+/// [`ApiVersion::V3`], queries of the v3 collections (entries, treatments, device status and
+/// profiles) use the v3 API while everything else, including writes, goes through v2.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[non_exhaustive]
+pub enum ApiVersion {
+    V1,
+    #[default]
+    V2,
+    V3,
+}
+
+impl ApiVersion {
+    /// The version used for endpoints without a v3 counterpart.
+    pub(crate) fn legacy(self) -> Self {
+        match self {
+            ApiVersion::V3 => ApiVersion::V2,
+            version => version,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Endpoint {
     Sgv,
     Mbg,
+    Cal,
     Iob,
     Entries,
     Treatments,
@@ -9,21 +37,88 @@ pub enum Endpoint {
     DeviceStatus,
     Profile,
     Status,
+    AuthorizationRequest,
+    Activity,
+    VerifyAuth,
+    StatusV3,
+    AuthorizationSubjects,
+    AuthorizationRoles,
+    NotificationsAck,
+    Pebble,
+    LastModified,
 }
 
 impl Endpoint {
+    /// The path of this endpoint on the v2 API.
     pub fn as_path(&self) -> &'static str {
         match self {
             Endpoint::Entries => "api/v2/entries.json",
             Endpoint::Current => "api/v2/entries/current.json",
             Endpoint::Sgv => "api/v2/entries/sgv.json",
             Endpoint::Mbg => "api/v2/entries/mbg.json",
+            Endpoint::Cal => "api/v2/entries/cal.json",
             Endpoint::Iob => "api/v2/properties/iob.json",
             Endpoint::Treatments => "api/v2/treatments.json",
             Endpoint::Properties => "api/v2/properties",
             Endpoint::DeviceStatus => "api/v2/devicestatus.json",
             Endpoint::Profile => "api/v2/profile.json",
             Endpoint::Status => "api/v2/status.json",
+            Endpoint::AuthorizationRequest => "api/v2/authorization/request",
+            Endpoint::Activity => "api/v2/activity.json",
+            Endpoint::VerifyAuth => "api/v1/verifyauth",
+            Endpoint::StatusV3 => "api/v3/status",
+            Endpoint::AuthorizationSubjects => "api/v2/authorization/subjects",
+            Endpoint::AuthorizationRoles => "api/v2/authorization/roles",
+            Endpoint::NotificationsAck => "api/v1/notifications/ack",
+            Endpoint::Pebble => "pebble",
+            Endpoint::LastModified => "api/v3/lastModified",
+        }
+    }
+
+    /// The path of this endpoint on a server speaking `version`.
+    ///
+    /// Endpoints that only exist in one API version keep their path.
+    pub fn path_for(&self, version: ApiVersion) -> Cow<'static, str> {
+        match version {
+            ApiVersion::V2 => Cow::Borrowed(self.as_path()),
+            ApiVersion::V1 if self.in_v1() => {
+                Cow::Owned(self.as_path().replacen("api/v2/", "api/v1/", 1))
+            }
+            ApiVersion::V1 => Cow::Borrowed(self.as_path()),
+            ApiVersion::V3 => match self.v3_collection() {
+                Some((collection, _)) => Cow::Owned(format!("api/v3/{}", collection)),
+                None => Cow::Borrowed(self.as_path()),
+            },
+        }
+    }
+
+    fn in_v1(&self) -> bool {
+        matches!(
+            self,
+            Endpoint::Entries
+                | Endpoint::Current
+                | Endpoint::Sgv
+                | Endpoint::Mbg
+                | Endpoint::Cal
+                | Endpoint::Treatments
+                | Endpoint::DeviceStatus
+                | Endpoint::Profile
+                | Endpoint::Status
+                | Endpoint::Activity
+        )
+    }
+
+    /// The v3 collection of this endpoint, with the entry `type` it is restricted to.
+    pub(crate) fn v3_collection(&self) -> Option<(&'static str, Option<&'static str>)> {
+        match self {
+            Endpoint::Entries => Some(("entries", None)),
+            Endpoint::Sgv => Some(("entries", Some("sgv"))),
+            Endpoint::Mbg => Some(("entries", Some("mbg"))),
+            Endpoint::Cal => Some(("entries", Some("cal"))),
+            Endpoint::Treatments => Some(("treatments", None)),
+            Endpoint::DeviceStatus => Some(("devicestatus", None)),
+            Endpoint::Profile => Some(("profile", None)),
+            _ => None,
         }
     }
 }
@@ -1,22 +1,74 @@
+use crate::capabilities::Feature;
+use crate::client::redacted_url;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::time::Duration;
 use thiserror::Error;
 
 #[derive(Error, Debug)]
+#[non_exhaustive]
 pub enum NightscoutError {
     #[error("Invalid URL format: {0}")]
     UrlParseError(#[from] url::ParseError),
 
+    /// The URL of the error is redacted, see [`redact_url`](crate::client::redact_url).
     #[error("Network or HTTP error: {0}")]
-    RequestError(#[from] reqwest::Error),
+    RequestError(#[source] reqwest::Error),
 
     #[error("Failed to parse JSON response: {0}")]
     JsonError(#[from] serde_json::Error),
 
+    #[error("I/O error: {0}")]
+    IoError(#[from] std::io::Error),
+
+    #[cfg(feature = "csv")]
+    #[error("Failed to read or write CSV: {0}")]
+    CsvError(#[from] csv::Error),
+
+    #[cfg(all(feature = "local-store", not(target_arch = "wasm32")))]
+    #[error("Local store error: {0}")]
+    StoreError(#[from] rusqlite::Error),
+
+    #[cfg(all(feature = "mqtt", not(target_arch = "wasm32")))]
+    #[error("MQTT error: {0}")]
+    MqttError(#[from] rumqttc::ClientError),
+
     #[error("Nightscout API Error {status}: {message}")]
     ApiError {
         status: reqwest::StatusCode,
+        /// The raw response body.
         message: String,
+        /// The response body, when it is a JSON error document.
+        body: Option<Box<ApiErrorBody>>,
+        /// The delay requested by a `Retry-After` header.
+        retry_after: Option<Duration>,
     },
 
+    /// An upload stopped at a failed batch after earlier ones were stored. Their
+    /// documents must not be sent again, only those after them.
+    #[error("Upload failed after {} documents were created: {source}", created_ids.len())]
+    PartialUpload {
+        /// The `_id`s of the documents the server stored.
+        created_ids: Vec<String>,
+        /// The error of the failed batch.
+        source: Box<NightscoutError>,
+    },
+
+    #[error("Request timed out after {0:?}")]
+    Timeout(Duration),
+
+    #[error("The client is read-only, refused {0}")]
+    ReadOnlyViolation(String),
+
+    #[error("Invalid input: {0}")]
+    InvalidInput(String),
+
+    #[error("The `{0}` feature is not enabled on this Nightscout server")]
+    FeatureNotEnabled(Feature),
+
+    #[error("Not supported by this Nightscout server: {0}")]
+    Unsupported(String),
+
     #[error("Authentication failed: API secret is missing or invalid")]
     AuthError,
 
@@ -26,3 +78,84 @@ pub enum NightscoutError {
     #[error("Unknown error occurred")]
     Unknown,
 }
+
+impl From<reqwest::Error> for NightscoutError {
+    fn from(mut error: reqwest::Error) -> Self {
+        // reqwest prints the URL, which can carry an access token.
+        if let Some(url) = error.url_mut() {
+            *url = redacted_url(url);
+        }
+        NightscoutError::RequestError(error)
+    }
+}
+
+/// The JSON error document Nightscout returns with non-success statuses.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct ApiErrorBody {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub status: Option<u16>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+
+    #[serde(flatten)]
+    pub extra: Value,
+}
+
+impl ApiErrorBody {
+    /// Parses a response body, returning `None` if it isn't a JSON object.
+    pub(crate) fn parse(text: &str) -> Option<Box<Self>> {
+        match serde_json::from_str::<Value>(text).ok()? {
+            value @ Value::Object(_) => serde_json::from_value(value).ok().map(Box::new),
+            _ => None,
+        }
+    }
+}
+
+impl NightscoutError {
+    /// The HTTP status of an `ApiError` (or 401 for `AuthError`).
+    pub fn status(&self) -> Option<reqwest::StatusCode> {
+        match self {
+            NightscoutError::ApiError { status, .. } => Some(*status),
+            NightscoutError::AuthError => Some(reqwest::StatusCode::UNAUTHORIZED),
+            NightscoutError::RequestError(e) => e.status(),
+            _ => None,
+        }
+    }
+
+    /// Whether the request was rejected for missing or insufficient credentials (401/403).
+    pub fn is_auth_error(&self) -> bool {
+        matches!(
+            self.status(),
+            Some(reqwest::StatusCode::UNAUTHORIZED | reqwest::StatusCode::FORBIDDEN)
+        )
+    }
+
+    /// Whether the server rejected the request for exceeding its rate limit (429).
+    pub fn is_rate_limited(&self) -> bool {
+        self.status() == Some(reqwest::StatusCode::TOO_MANY_REQUESTS)
+    }
+
+    /// How long the server asked to wait before retrying, from the `Retry-After` header.
+    pub fn retry_after(&self) -> Option<Duration> {
+        match self {
+            NightscoutError::ApiError { retry_after, .. } => *retry_after,
+            _ => None,
+        }
+    }
+
+    /// The most descriptive message the server gave, preferring the JSON `message` or
+    /// `description` fields over the raw body.
+    pub fn server_message(&self) -> Option<&str> {
+        let NightscoutError::ApiError { message, body, .. } = self else {
+            return None;
+        };
+
+        body.as_ref()
+            .and_then(|body| body.message.as_deref().or(body.description.as_deref()))
+            .or(Some(message.as_str()))
+    }
+}
@@ -0,0 +1,416 @@
+//! Export and import of Nightscout data.
+//!
+//! With the `csv` feature, [`to_csv`] and [`from_csv`] convert entries and treatments to
+//! CSV spreadsheets, and [`to_tsv`] and [`from_tsv`] to tab-separated values. Columns are
+//! named after the Nightscout fields and follow the order of the server's entries export
+//! and of the treatment reports. Despite its name, the server's `entries.csv` is
+//! tab-separated: read it with [`from_tsv`].
+//!
+//! [`ExportService`] streams whole collections as JSON Lines, for backups.
+
+use crate::client::NightscoutClient;
+use crate::error::NightscoutError;
+
+use chrono::{DateTime, Utc};
+use futures::{Stream, StreamExt};
+use serde::Serialize;
+use tokio::io::{AsyncWrite, AsyncWriteExt};
+
+#[cfg(feature = "csv")]
+use crate::models::{entries::SgvEntry, treatments::Treatment};
+#[cfg(feature = "csv")]
+use serde::de::DeserializeOwned;
+#[cfg(feature = "csv")]
+use serde_json::{Map, Number, Value};
+
+/// Page size of the queries behind [`ExportService`].
+const EXPORT_PAGE_SIZE: usize = 1000;
+
+/// A column of a CSV export.
+#[cfg(feature = "csv")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Column {
+    /// The JSON field, also used as the header.
+    pub name: &'static str,
+    /// Whether values are parsed as numbers on import.
+    pub numeric: bool,
+}
+
+#[cfg(feature = "csv")]
+const fn text(name: &'static str) -> Column {
+    Column {
+        name,
+        numeric: false,
+    }
+}
+
+#[cfg(feature = "csv")]
+const fn number(name: &'static str) -> Column {
+    Column {
+        name,
+        numeric: true,
+    }
+}
+
+/// A document with a CSV layout, see [`to_csv`] and [`from_csv`].
+#[cfg(feature = "csv")]
+pub trait CsvRecord: Serialize + DeserializeOwned {
+    /// The exported fields, in column order.
+    const COLUMNS: &'static [Column];
+
+    /// Fields set on imported documents that the layout leaves out.
+    const DEFAULTS: &'static [(&'static str, &'static str)] = &[];
+}
+
+#[cfg(feature = "csv")]
+impl CsvRecord for SgvEntry {
+    const COLUMNS: &'static [Column] = &[
+        text("dateString"),
+        number("date"),
+        number("sgv"),
+        text("direction"),
+        text("device"),
+    ];
+
+    const DEFAULTS: &'static [(&'static str, &'static str)] = &[("type", "sgv")];
+}
+
+#[cfg(feature = "csv")]
+impl CsvRecord for Treatment {
+    const COLUMNS: &'static [Column] = &[
+        text("created_at"),
+        text("eventType"),
+        number("glucose"),
+        text("glucoseType"),
+        number("carbs"),
+        number("insulin"),
+        text("units"),
+        number("duration"),
+        number("absolute"),
+        number("percent"),
+        text("profile"),
+        text("notes"),
+        text("enteredBy"),
+    ];
+}
+
+/// Writes `records` as CSV, with a header row.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// # use cinnamon::client::NightscoutClient;
+/// # use cinnamon::export::to_csv;
+/// # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+/// let client = NightscoutClient::new("https://ns.example.com")?;
+/// let entries = client.sgv().get().last_days(14).limit(5000).send().await?;
+/// std::fs::write("glucose.csv", to_csv(&entries)?)?;
+/// # Ok(())
+/// # }
+/// ```
+#[cfg(feature = "csv")]
+pub fn to_csv<T: CsvRecord>(records: &[T]) -> Result<String, NightscoutError> {
+    write_delimited(records, b',')
+}
+
+/// Writes `records` as tab-separated values, with a header row.
+#[cfg(feature = "csv")]
+pub fn to_tsv<T: CsvRecord>(records: &[T]) -> Result<String, NightscoutError> {
+    write_delimited(records, b'\t')
+}
+
+#[cfg(feature = "csv")]
+fn write_delimited<T: CsvRecord>(records: &[T], delimiter: u8) -> Result<String, NightscoutError> {
+    let mut writer = csv::WriterBuilder::new()
+        .delimiter(delimiter)
+        .from_writer(Vec::new());
+    writer.write_record(T::COLUMNS.iter().map(|column| column.name))?;
+
+    for record in records {
+        write_row(&mut writer, record)?;
+    }
+
+    let bytes = writer
+        .into_inner()
+        .map_err(|e| NightscoutError::IoError(e.into_error()))?;
+    String::from_utf8(bytes).map_err(|e| NightscoutError::InvalidInput(e.to_string()))
+}
+
+#[cfg(feature = "csv")]
+fn write_row<T: CsvRecord, W: std::io::Write>(
+    writer: &mut csv::Writer<W>,
+    record: &T,
+) -> Result<(), NightscoutError> {
+    let document = serde_json::to_value(record)?;
+    writer.write_record(
+        T::COLUMNS
+            .iter()
+            .map(|column| match &document[column.name] {
+                Value::Null => String::new(),
+                Value::String(value) => value.clone(),
+                value => value.to_string(),
+            }),
+    )?;
+    Ok(())
+}
+
+/// Reads documents written by [`to_csv`]. Use [`from_tsv`] for the server's own exports.
+///
+/// With a header row, columns are matched by name, so they may come in any order and
+/// unknown ones are ignored. Without one, they are expected in the order of
+/// [`CsvRecord::COLUMNS`]. Empty cells are treated as missing fields.
+#[cfg(feature = "csv")]
+pub fn from_csv<T: CsvRecord>(data: &str) -> Result<Vec<T>, NightscoutError> {
+    read_delimited(data, b',')
+}
+
+/// Reads tab-separated documents, such as the server's `entries.csv` export and the default
+/// text output of the v1 entries endpoints. See [`from_csv`] for the handling of columns.
+#[cfg(feature = "csv")]
+pub fn from_tsv<T: CsvRecord>(data: &str) -> Result<Vec<T>, NightscoutError> {
+    read_delimited(data, b'\t')
+}
+
+#[cfg(feature = "csv")]
+fn read_delimited<T: CsvRecord>(data: &str, delimiter: u8) -> Result<Vec<T>, NightscoutError> {
+    let mut reader = csv::ReaderBuilder::new()
+        .delimiter(delimiter)
+        .has_headers(false)
+        .flexible(true)
+        .from_reader(data.as_bytes());
+    let mut rows = reader.records().peekable();
+
+    let mut positions: Vec<Option<usize>> = (0..T::COLUMNS.len()).map(Some).collect();
+    if let Some(Ok(first)) = rows.peek() {
+        let header: Vec<&str> = first.iter().map(str::trim).collect();
+        if T::COLUMNS
+            .iter()
+            .any(|column| header.contains(&column.name))
+        {
+            positions = T::COLUMNS
+                .iter()
+                .map(|column| header.iter().position(|name| *name == column.name))
+                .collect();
+            rows.next();
+        }
+    }
+
+    let mut records = Vec::new();
+    for (line, row) in rows.enumerate() {
+        let row = row?;
+        let mut document = Map::new();
+        for (name, value) in T::DEFAULTS {
+            document.insert(name.to_string(), Value::String(value.to_string()));
+        }
+
+        for (column, position) in T::COLUMNS.iter().zip(&positions) {
+            let Some(cell) = position.and_then(|i| row.get(i)).map(str::trim) else {
+                continue;
+            };
+            if cell.is_empty() {
+                continue;
+            }
+            let value = match column.numeric {
+                true => parse_number(cell).ok_or_else(|| {
+                    NightscoutError::InvalidInput(format!(
+                        "row {}: {} is not a number: {cell:?}",
+                        line + 1,
+                        column.name
+                    ))
+                })?,
+                false => Value::String(cell.to_string()),
+            };
+            document.insert(column.name.to_string(), value);
+        }
+
+        records.push(serde_json::from_value(Value::Object(document))?);
+    }
+
+    Ok(records)
+}
+
+#[cfg(feature = "csv")]
+fn parse_number(cell: &str) -> Option<Value> {
+    match cell.parse::<i64>() {
+        Ok(integer) => Some(Value::from(integer)),
+        Err(_) => cell
+            .parse::<f64>()
+            .ok()
+            .and_then(Number::from_f64)
+            .map(Value::Number),
+    }
+}
+
+/// Streams collections as newline-delimited JSON, one document per line.
+///
+/// Documents are written as their pages arrive, so memory use does not grow with the
+/// size of the range. The output is the format of `mongoexport` and can be read back
+/// line by line with `serde_json`.
+pub struct ExportService {
+    pub client: NightscoutClient,
+}
+
+impl ExportService {
+    /// Writes every entry (SGV, MBG and calibrations) between `from` and `to`, newest
+    /// first, returning the number of documents written.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// # use cinnamon::client::NightscoutClient;
+    /// # use chrono::{Duration, Utc};
+    /// # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+    /// let client = NightscoutClient::new("https://ns.example.com")?.with_secret("secret");
+    /// let mut file = tokio::fs::File::create("entries.jsonl").await?;
+    ///
+    /// let to = Utc::now();
+    /// let written = client.export().entries(to - Duration::days(365), to, &mut file).await?;
+    /// println!("{written} entries backed up");
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn entries<W>(
+        &self,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+        writer: &mut W,
+    ) -> Result<u64, NightscoutError>
+    where
+        W: AsyncWrite + Unpin,
+    {
+        let query = self.client.entries().all().from(from).to(to);
+        write_lines(query.paginate(EXPORT_PAGE_SIZE), writer).await
+    }
+
+    /// Writes every treatment between `from` and `to`, see [`entries`](Self::entries).
+    pub async fn treatments<W>(
+        &self,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+        writer: &mut W,
+    ) -> Result<u64, NightscoutError>
+    where
+        W: AsyncWrite + Unpin,
+    {
+        let query = self.client.treatments().get().from(from).to(to);
+        write_lines(query.paginate(EXPORT_PAGE_SIZE), writer).await
+    }
+
+    /// Writes every device status between `from` and `to`, see [`entries`](Self::entries).
+    pub async fn devicestatus<W>(
+        &self,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+        writer: &mut W,
+    ) -> Result<u64, NightscoutError>
+    where
+        W: AsyncWrite + Unpin,
+    {
+        let query = self.client.devicestatus().get().from(from).to(to);
+        write_lines(query.paginate(EXPORT_PAGE_SIZE), writer).await
+    }
+}
+
+#[cfg(feature = "csv")]
+impl ExportService {
+    /// Writes the glucose readings between `from` and `to` as CSV, newest first, in the
+    /// layout of [`to_csv`]. Returns the number of readings written.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// # use cinnamon::client::NightscoutClient;
+    /// # use chrono::{Duration, Utc};
+    /// # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+    /// let client = NightscoutClient::new("https://ns.example.com")?;
+    /// let mut file = tokio::fs::File::create("glucose.csv").await?;
+    ///
+    /// let to = Utc::now();
+    /// client.export().sgv_csv(to - Duration::days(90), to, &mut file).await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn sgv_csv<W>(
+        &self,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+        writer: &mut W,
+    ) -> Result<u64, NightscoutError>
+    where
+        W: AsyncWrite + Unpin,
+    {
+        let query = self.client.sgv().get().from(from).to(to);
+        write_csv(query.paginate(EXPORT_PAGE_SIZE), writer).await
+    }
+
+    /// Writes the treatments between `from` and `to` as CSV, see [`sgv_csv`](Self::sgv_csv).
+    pub async fn treatments_csv<W>(
+        &self,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+        writer: &mut W,
+    ) -> Result<u64, NightscoutError>
+    where
+        W: AsyncWrite + Unpin,
+    {
+        let query = self.client.treatments().get().from(from).to(to);
+        write_csv(query.paginate(EXPORT_PAGE_SIZE), writer).await
+    }
+}
+
+async fn write_lines<T, W>(
+    documents: impl Stream<Item = Result<T, NightscoutError>>,
+    writer: &mut W,
+) -> Result<u64, NightscoutError>
+where
+    T: Serialize,
+    W: AsyncWrite + Unpin,
+{
+    let mut documents = std::pin::pin!(documents);
+    let mut written = 0;
+    let mut line = Vec::new();
+
+    while let Some(document) = documents.next().await {
+        line.clear();
+        serde_json::to_writer(&mut line, &document?)?;
+        line.push(b'\n');
+        writer.write_all(&line).await?;
+        written += 1;
+    }
+
+    writer.flush().await?;
+    Ok(written)
+}
+
+#[cfg(feature = "csv")]
+async fn write_csv<T, W>(
+    documents: impl Stream<Item = Result<T, NightscoutError>>,
+    writer: &mut W,
+) -> Result<u64, NightscoutError>
+where
+    T: CsvRecord,
+    W: AsyncWrite + Unpin,
+{
+    let mut documents = std::pin::pin!(documents);
+    let mut written = 0;
+    let mut line = Vec::new();
+
+    let mut header = csv::Writer::from_writer(&mut line);
+    header.write_record(T::COLUMNS.iter().map(|column| column.name))?;
+    header.flush()?;
+    drop(header);
+    writer.write_all(&line).await?;
+
+    while let Some(document) = documents.next().await {
+        line.clear();
+        let mut row = csv::Writer::from_writer(&mut line);
+        write_row(&mut row, &document?)?;
+        row.flush()?;
+        drop(row);
+        writer.write_all(&line).await?;
+        written += 1;
+    }
+
+    writer.flush().await?;
+    Ok(written)
+}
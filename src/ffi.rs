@@ -0,0 +1,285 @@
+//! Swift and Kotlin bindings, generated with [UniFFI](https://mozilla.github.io/uniffi-rs/)
+//! from the `uniffi` feature.
+//!
+//! Mobile companion apps (widgets, watch complications) get the core of the client:
+//! readings, treatments, properties and status, as plain records with epoch-millisecond
+//! dates. Every method is `async` and runs on a Tokio runtime managed by UniFFI.
+//!
+//! Build the library as a `cdylib` and generate the sources with `uniffi-bindgen`:
+//!
+//! ```text
+//! cargo rustc --release --features uniffi --crate-type cdylib
+//! uniffi-bindgen generate --library target/release/libcinnamon.so --language swift --out-dir out
+//! ```
+
+use crate::client::NightscoutClient;
+use crate::error::NightscoutError;
+use crate::models::entries::SgvEntry;
+use crate::models::properties::Properties;
+use crate::models::status::Status;
+use crate::models::treatments::{Treatment, TreatmentBuilder};
+
+use chrono::{DateTime, Utc};
+use std::sync::Arc;
+
+/// The errors of [`Client`], flattened for foreign languages.
+#[derive(Debug, thiserror::Error, uniffi::Error)]
+pub enum FfiError {
+    #[error("Network or HTTP error: {message}")]
+    Network { message: String },
+    #[error("Authentication failed")]
+    Auth,
+    #[error("Invalid input: {message}")]
+    InvalidInput { message: String },
+    #[error("No data found")]
+    NotFound,
+    #[error("{message}")]
+    Other { message: String },
+}
+
+impl From<NightscoutError> for FfiError {
+    fn from(error: NightscoutError) -> Self {
+        if error.is_auth_error() {
+            return FfiError::Auth;
+        }
+        match error {
+            NightscoutError::NotFound => FfiError::NotFound,
+            NightscoutError::InvalidInput(message) => FfiError::InvalidInput { message },
+            NightscoutError::RequestError(_)
+            | NightscoutError::ApiError { .. }
+            | NightscoutError::Timeout(_) => FfiError::Network {
+                message: error.to_string(),
+            },
+            error => FfiError::Other {
+                message: error.to_string(),
+            },
+        }
+    }
+}
+
+fn from_millis(millis: i64) -> Result<DateTime<Utc>, FfiError> {
+    DateTime::from_timestamp_millis(millis).ok_or_else(|| FfiError::InvalidInput {
+        message: format!("{millis} is not a valid timestamp"),
+    })
+}
+
+/// A glucose reading.
+#[derive(Debug, Clone, PartialEq, uniffi::Record)]
+pub struct GlucoseReading {
+    pub id: Option<String>,
+    pub mgdl: i32,
+    pub date_ms: i64,
+    /// The Nightscout trend name, e.g. `FortyFiveUp`, `NONE` when unknown.
+    pub direction: String,
+    pub device: Option<String>,
+}
+
+impl From<SgvEntry> for GlucoseReading {
+    fn from(entry: SgvEntry) -> Self {
+        Self {
+            id: entry.id,
+            mgdl: entry.sgv,
+            date_ms: entry.date,
+            direction: entry.direction.as_str().to_string(),
+            device: entry.device,
+        }
+    }
+}
+
+/// A care event: bolus, carbs, temporary basal, note...
+#[derive(Debug, Clone, PartialEq, uniffi::Record)]
+pub struct TreatmentRecord {
+    pub id: Option<String>,
+    pub event_type: String,
+    pub created_at_ms: i64,
+    pub insulin: Option<f64>,
+    pub carbs: Option<f64>,
+    /// Minutes.
+    pub duration: Option<f64>,
+    pub notes: Option<String>,
+    pub entered_by: Option<String>,
+}
+
+impl From<Treatment> for TreatmentRecord {
+    fn from(treatment: Treatment) -> Self {
+        Self {
+            id: treatment.id,
+            event_type: treatment.event_type,
+            created_at_ms: treatment.created_at.timestamp_millis(),
+            insulin: treatment.insulin,
+            carbs: treatment.carbs,
+            duration: treatment.duration,
+            notes: treatment.notes,
+            entered_by: treatment.entered_by,
+        }
+    }
+}
+
+impl TryFrom<TreatmentRecord> for Treatment {
+    type Error = FfiError;
+
+    fn try_from(record: TreatmentRecord) -> Result<Self, FfiError> {
+        let mut builder =
+            TreatmentBuilder::new(&record.event_type).at(from_millis(record.created_at_ms)?);
+        if let Some(units) = record.insulin {
+            builder = builder.insulin(units);
+        }
+        if let Some(grams) = record.carbs {
+            builder = builder.carbs(grams);
+        }
+        if let Some(minutes) = record.duration {
+            builder = builder.duration(minutes);
+        }
+        if let Some(notes) = record.notes {
+            builder = builder.notes(notes);
+        }
+        if let Some(name) = record.entered_by {
+            builder = builder.entered_by(name);
+        }
+        Ok(builder.build()?)
+    }
+}
+
+/// The values a glanceable view shows, from the server's properties.
+#[derive(Debug, Clone, PartialEq, uniffi::Record)]
+pub struct CurrentProperties {
+    pub bg_mgdl: Option<f64>,
+    pub delta_mgdl: Option<f64>,
+    pub direction: Option<String>,
+    /// Insulin on board (U).
+    pub iob: Option<f64>,
+    /// Carbs on board (g).
+    pub cob: Option<f64>,
+}
+
+impl From<Properties> for CurrentProperties {
+    fn from(properties: Properties) -> Self {
+        Self {
+            bg_mgdl: properties.current_bg(),
+            delta_mgdl: properties.delta.as_ref().map(|delta| delta.mgdl),
+            direction: properties
+                .direction
+                .as_ref()
+                .map(|direction| direction.value.clone()),
+            iob: properties.active_insulin(),
+            cob: properties.carbs_remaining(),
+        }
+    }
+}
+
+/// The identity and settings of the server.
+#[derive(Debug, Clone, PartialEq, uniffi::Record)]
+pub struct ServerStatus {
+    pub name: String,
+    pub version: String,
+    pub server_time_ms: i64,
+    /// The display unit of the site, `mg/dl` or `mmol`.
+    pub units: Option<String>,
+}
+
+impl From<Status> for ServerStatus {
+    fn from(status: Status) -> Self {
+        Self {
+            units: status
+                .settings
+                .and_then(|settings| settings.units)
+                .map(|units| units.as_str().to_string()),
+            name: status.name,
+            version: status.version,
+            server_time_ms: status.server_time_epoch,
+        }
+    }
+}
+
+/// A Nightscout client.
+#[derive(uniffi::Object)]
+pub struct Client {
+    inner: NightscoutClient,
+}
+
+#[uniffi::export(async_runtime = "tokio")]
+impl Client {
+    /// Creates a client, authenticated with an access token or the API secret if given.
+    #[uniffi::constructor]
+    pub fn new(
+        url: String,
+        token: Option<String>,
+        secret: Option<String>,
+    ) -> Result<Arc<Self>, FfiError> {
+        let mut inner = NightscoutClient::new(&url)?;
+        if let Some(secret) = secret {
+            inner = inner.with_secret(secret);
+        }
+        if let Some(token) = token {
+            inner = inner.with_token(token);
+        }
+        Ok(Arc::new(Self { inner }))
+    }
+
+    /// The latest glucose reading, `None` if there is none.
+    pub async fn latest_sgv(&self) -> Result<Option<GlucoseReading>, FfiError> {
+        Ok(self.inner.sgv().latest().await?.map(GlucoseReading::from))
+    }
+
+    /// Glucose readings between two optional dates, newest first.
+    pub async fn sgv(
+        &self,
+        from_ms: Option<i64>,
+        to_ms: Option<i64>,
+        limit: u32,
+    ) -> Result<Vec<GlucoseReading>, FfiError> {
+        let mut query = self.inner.sgv().get().limit(limit as usize);
+        if let Some(from) = from_ms {
+            query = query.from(from_millis(from)?);
+        }
+        if let Some(to) = to_ms {
+            query = query.to(from_millis(to)?);
+        }
+        let entries = query.send().await?;
+        Ok(entries.into_iter().map(GlucoseReading::from).collect())
+    }
+
+    /// Treatments between two optional dates, newest first.
+    pub async fn treatments(
+        &self,
+        from_ms: Option<i64>,
+        to_ms: Option<i64>,
+        limit: u32,
+    ) -> Result<Vec<TreatmentRecord>, FfiError> {
+        let mut query = self.inner.treatments().get().limit(limit as usize);
+        if let Some(from) = from_ms {
+            query = query.from(from_millis(from)?);
+        }
+        if let Some(to) = to_ms {
+            query = query.to(from_millis(to)?);
+        }
+        let treatments = query.send().await?;
+        Ok(treatments.into_iter().map(TreatmentRecord::from).collect())
+    }
+
+    /// Uploads a treatment, returning it as stored by the server.
+    pub async fn create_treatment(
+        &self,
+        treatment: TreatmentRecord,
+    ) -> Result<TreatmentRecord, FfiError> {
+        let created = self
+            .inner
+            .treatments()
+            .create(vec![treatment.try_into()?])
+            .await?;
+        created
+            .into_iter()
+            .next()
+            .map(TreatmentRecord::from)
+            .ok_or(FfiError::NotFound)
+    }
+
+    /// The current glucose, trend, IOB and COB.
+    pub async fn properties(&self) -> Result<CurrentProperties, FfiError> {
+        Ok(self.inner.properties().get().send().await?.into())
+    }
+
+    pub async fn status(&self) -> Result<ServerStatus, FfiError> {
+        Ok(self.inner.status().get().await?.into())
+    }
+}
@@ -0,0 +1,323 @@
+//! Forwarding of new readings and treatments to webhooks.
+//!
+//! A [`Forwarder`] watches the site and POSTs each new document to the webhooks of its
+//! collection, as is or through a JSON template, to feed Discord, Slack or Home Assistant
+//! without a bridge of one's own.
+
+use crate::client::NightscoutClient;
+use crate::error::NightscoutError;
+use crate::runtime;
+
+use futures::stream::{self, StreamExt};
+use reqwest::header::{HeaderName, HeaderValue};
+use serde_json::Value;
+use std::time::Duration;
+use tokio::sync::mpsc;
+use url::Url;
+
+/// Capacity of the channel returned by [`Forwarder::spawn`].
+#[cfg(not(target_arch = "wasm32"))]
+const CHANNEL_CAPACITY: usize = 64;
+
+/// A URL documents are POSTed to, see [`Forwarder::sgv`] and [`Forwarder::treatments`].
+///
+/// Without a template the body is the document itself, with its Nightscout field names.
+/// A template is any JSON value whose strings can refer to fields of the document with
+/// `{{field}}`, or `{{parent.child}}` for nested ones:
+///
+/// - a string that is exactly one placeholder is replaced by the field's value, keeping
+///   its JSON type, or `null` when the document does not have it;
+/// - placeholders within a longer string are replaced by the field's text, or by nothing.
+///
+/// # Example
+///
+/// ```rust
+/// # use cinnamon::forwarder::Webhook;
+/// # use serde_json::json;
+/// let webhook = Webhook::new("https://discord.com/api/webhooks/123/abc")?
+///     .name("discord")
+///     .template(json!({ "content": "{{sgv}} mg/dL {{direction}}" }));
+///
+/// let body = webhook.render(&json!({ "sgv": 128, "direction": "Flat" }));
+/// assert_eq!(body, json!({ "content": "128 mg/dL Flat" }));
+/// # Ok::<(), cinnamon::error::NightscoutError>(())
+/// ```
+#[derive(Debug, Clone)]
+pub struct Webhook {
+    url: Url,
+    name: String,
+    template: Option<Value>,
+    headers: Vec<(HeaderName, HeaderValue)>,
+}
+
+impl Webhook {
+    /// Creates a webhook for an `http(s)` URL, named after its host.
+    pub fn new(url: &str) -> Result<Self, NightscoutError> {
+        let url = Url::parse(url)?;
+        if !matches!(url.scheme(), "http" | "https") {
+            return Err(NightscoutError::InvalidInput(format!(
+                "webhook URLs must be http(s), got {}",
+                url.scheme()
+            )));
+        }
+
+        Ok(Self {
+            name: url.host_str().unwrap_or_default().to_string(),
+            url,
+            template: None,
+            headers: Vec::new(),
+        })
+    }
+
+    /// The name identifying the webhook in [`ForwardEvent`]s, instead of its URL which
+    /// often holds a secret.
+    pub fn name(mut self, name: impl Into<String>) -> Self {
+        self.name = name.into();
+        self
+    }
+
+    /// The template of the body, see [`Webhook`].
+    pub fn template(mut self, template: Value) -> Self {
+        self.template = Some(template);
+        self
+    }
+
+    /// Adds a header to every request, e.g. `Authorization` for Home Assistant.
+    pub fn header(mut self, name: &str, value: &str) -> Result<Self, NightscoutError> {
+        let name = HeaderName::from_bytes(name.as_bytes())
+            .map_err(|e| NightscoutError::InvalidInput(format!("invalid header name: {e}")))?;
+        let value = HeaderValue::from_str(value)
+            .map_err(|e| NightscoutError::InvalidInput(format!("invalid header value: {e}")))?;
+        self.headers.push((name, value));
+        Ok(self)
+    }
+
+    /// The body sent for `document`.
+    pub fn render(&self, document: &Value) -> Value {
+        match &self.template {
+            Some(template) => render(template, document),
+            None => document.clone(),
+        }
+    }
+
+    async fn post(&self, client: &NightscoutClient, document: &Value) -> ForwardEvent {
+        let mut request = client
+            .http
+            .post(self.url.clone())
+            .json(&self.render(document));
+        for (name, value) in &self.headers {
+            request = request.header(name, value);
+        }
+
+        match request.send().await {
+            Ok(response) if response.status().is_success() => ForwardEvent::Delivered {
+                webhook: self.name.clone(),
+                status: response.status().as_u16(),
+            },
+            Ok(response) => ForwardEvent::Failed {
+                webhook: self.name.clone(),
+                error: format!("HTTP {}", response.status()),
+            },
+            // Webhook URLs carry their secret in the path, which redaction keeps.
+            Err(e) => ForwardEvent::Failed {
+                webhook: self.name.clone(),
+                error: NightscoutError::from(e.without_url()).to_string(),
+            },
+        }
+    }
+}
+
+fn render(template: &Value, document: &Value) -> Value {
+    match template {
+        Value::String(text) => match placeholder(text) {
+            Some(path) if text.len() == path.len() + 4 => {
+                lookup(document, path).cloned().unwrap_or(Value::Null)
+            }
+            _ => Value::String(interpolate(text, document)),
+        },
+        Value::Array(items) => Value::Array(items.iter().map(|v| render(v, document)).collect()),
+        Value::Object(fields) => Value::Object(
+            fields
+                .iter()
+                .map(|(key, value)| (key.clone(), render(value, document)))
+                .collect(),
+        ),
+        value => value.clone(),
+    }
+}
+
+/// The path of the first `{{path}}` placeholder of `text`, when it starts with one.
+fn placeholder(text: &str) -> Option<&str> {
+    let rest = text.strip_prefix("{{")?;
+    rest.find("}}").map(|end| &rest[..end])
+}
+
+fn interpolate(text: &str, document: &Value) -> String {
+    let mut output = String::with_capacity(text.len());
+    let mut rest = text;
+
+    while let Some(start) = rest.find("{{") {
+        output.push_str(&rest[..start]);
+        let Some(path) = placeholder(&rest[start..]) else {
+            rest = &rest[start..];
+            break;
+        };
+        match lookup(document, path) {
+            Some(Value::String(value)) => output.push_str(value),
+            Some(Value::Null) | None => {}
+            Some(value) => output.push_str(&value.to_string()),
+        }
+        rest = &rest[start + path.len() + 4..];
+    }
+
+    output.push_str(rest);
+    output
+}
+
+fn lookup<'a>(document: &'a Value, path: &str) -> Option<&'a Value> {
+    path.trim()
+        .split('.')
+        .try_fold(document, |value, key| value.get(key))
+}
+
+/// What a [`Forwarder`] reports.
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub enum ForwardEvent {
+    /// A document was accepted by a webhook.
+    Delivered { webhook: String, status: u16 },
+    /// A webhook rejected a document or could not be reached. The document is not sent
+    /// again.
+    Failed { webhook: String, error: String },
+    /// Polling Nightscout failed. The forwarder keeps polling.
+    WatchError(String),
+}
+
+/// Posts new documents to webhooks, created by [`NightscoutClient::forwarder`].
+///
+/// # Example
+///
+/// ```rust,no_run
+/// # use cinnamon::client::NightscoutClient;
+/// # use cinnamon::forwarder::{ForwardEvent, Webhook};
+/// # use serde_json::json;
+/// # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+/// let client = NightscoutClient::from_env()?;
+/// let slack = Webhook::new("https://hooks.slack.com/services/T000/B000/XXXX")?
+///     .template(json!({ "text": "{{eventType}}: {{insulin}} U {{carbs}} g" }));
+///
+/// let (_handle, mut events) = client.forwarder().treatments(slack).spawn();
+/// while let Some(event) = events.recv().await {
+///     if let ForwardEvent::Failed { webhook, error } = event {
+///         eprintln!("{webhook}: {error}");
+///     }
+/// }
+/// # Ok(())
+/// # }
+/// ```
+pub struct Forwarder {
+    client: NightscoutClient,
+    interval: Duration,
+    sgv: Vec<Webhook>,
+    treatments: Vec<Webhook>,
+}
+
+/// A new document and the webhooks of its collection.
+enum Document {
+    Sgv(Value),
+    Treatment(Value),
+}
+
+impl Forwarder {
+    pub(crate) fn new(client: NightscoutClient) -> Self {
+        Self {
+            client,
+            interval: Duration::from_secs(60),
+            sgv: Vec::new(),
+            treatments: Vec::new(),
+        }
+    }
+
+    /// Time between polls. Defaults to 1 minute.
+    pub fn interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    /// Sends new glucose readings to `webhook`.
+    pub fn sgv(mut self, webhook: Webhook) -> Self {
+        self.sgv.push(webhook);
+        self
+    }
+
+    /// Sends new treatments to `webhook`.
+    pub fn treatments(mut self, webhook: Webhook) -> Self {
+        self.treatments.push(webhook);
+        self
+    }
+
+    /// Forwards documents, sending events to `events` until the receiver is dropped.
+    ///
+    /// The latest document of each collection is forwarded on start, then the ones
+    /// created since. Collections without webhooks are not polled.
+    pub async fn run(self, events: mpsc::Sender<ForwardEvent>) {
+        if self.sgv.is_empty() && self.treatments.is_empty() {
+            // Nothing to forward; wait for the receiver to go away like a running forwarder.
+            while !events.is_closed() {
+                runtime::sleep(self.interval).await;
+            }
+            return;
+        }
+
+        let sgv = self.client.sgv();
+        let treatments = self.client.treatments();
+
+        let readings = (!self.sgv.is_empty()).then(|| {
+            sgv.watch(self.interval)
+                .map(|entry| entry.and_then(|e| Ok(Document::Sgv(serde_json::to_value(e)?))))
+        });
+        let created = (!self.treatments.is_empty()).then(|| {
+            treatments.watch(self.interval).map(|treatment| {
+                treatment.and_then(|t| Ok(Document::Treatment(serde_json::to_value(t)?)))
+            })
+        });
+        let documents = stream::select(
+            stream::iter(readings).flatten(),
+            stream::iter(created).flatten(),
+        );
+        let mut documents = std::pin::pin!(documents);
+
+        while let Some(document) = documents.next().await {
+            let (webhooks, document) = match document {
+                Ok(Document::Sgv(document)) => (&self.sgv, document),
+                Ok(Document::Treatment(document)) => (&self.treatments, document),
+                Err(e) => {
+                    if events
+                        .send(ForwardEvent::WatchError(e.to_string()))
+                        .await
+                        .is_err()
+                    {
+                        return;
+                    }
+                    continue;
+                }
+            };
+
+            for webhook in webhooks {
+                let event = webhook.post(&self.client, &document).await;
+                if events.send(event).await.is_err() {
+                    return;
+                }
+            }
+        }
+    }
+
+    /// Forwards documents on a background task, returning the task and its events.
+    ///
+    /// The task stops when the receiver is dropped or the handle is aborted.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn spawn(self) -> (tokio::task::JoinHandle<()>, mpsc::Receiver<ForwardEvent>) {
+        let (sender, receiver) = mpsc::channel(CHANNEL_CAPACITY);
+        (tokio::spawn(self.run(sender)), receiver)
+    }
+}
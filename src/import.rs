@@ -0,0 +1,290 @@
+//! Import of Nightscout backups into a site.
+//!
+//! Backups made with `mongoexport` (JSON Lines or a JSON array, in MongoDB Extended JSON)
+//! and, with the `bson` feature, `mongodump` archives of a single collection are read
+//! into cinnamon models and uploaded through the bulk APIs. Documents already on the
+//! server, or repeated in the backup, are skipped, so an interrupted migration can be
+//! restarted from the beginning.
+
+use crate::bulk::{BulkReport, BulkRequest};
+use crate::client::NightscoutClient;
+use crate::endpoints::Endpoint;
+use crate::error::NightscoutError;
+use crate::models::de::format_datetime;
+use crate::models::devicestatus::DeviceStatus;
+use crate::models::entries::Entry;
+use crate::models::treatments::Treatment;
+use crate::query_builder::{HasDate, PartialResult};
+
+use futures::{Stream, TryStreamExt};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::{Map, Value};
+use std::collections::HashSet;
+
+/// Page size of the queries listing the documents already on the server.
+const EXISTING_PAGE_SIZE: usize = 1000;
+
+/// The outcome of an import.
+#[derive(Debug)]
+pub struct ImportReport<T> {
+    /// Documents of the backup that could not be read, with their position in it.
+    pub invalid: Vec<(usize, serde_json::Error)>,
+    /// Documents skipped because the server or the backup already had them.
+    pub duplicates: usize,
+    /// The upload of the remaining documents.
+    pub upload: BulkReport<T>,
+}
+
+/// Parses a backup of one collection into documents of type `T`.
+///
+/// Accepts a JSON array, JSON Lines, and with the `bson` feature concatenated BSON
+/// documents as written by `mongodump`. Extended JSON wrappers such as `{"$oid": ...}`,
+/// `{"$date": ...}` and `{"$numberLong": ...}` are unwrapped to the values Nightscout
+/// serves. Documents that do not fit `T` are reported instead of failing the whole file.
+pub fn parse_dump<T: DeserializeOwned>(dump: &[u8]) -> Result<PartialResult<T>, NightscoutError> {
+    let mut result = PartialResult {
+        items: Vec::new(),
+        errors: Vec::new(),
+    };
+
+    for (index, document) in read_documents(dump)?.into_iter().enumerate() {
+        match serde_json::from_value(normalize(document)) {
+            Ok(item) => result.items.push(item),
+            Err(e) => result.errors.push((index, e)),
+        }
+    }
+
+    Ok(result)
+}
+
+fn read_documents(dump: &[u8]) -> Result<Vec<Value>, NightscoutError> {
+    let text = match std::str::from_utf8(dump) {
+        Ok(text) => text.trim_start(),
+        Err(_) => return read_bson(dump),
+    };
+
+    match text.chars().next() {
+        Some('[') => Ok(serde_json::from_str(text)?),
+        Some('{') => text
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(|line| Ok(serde_json::from_str(line)?))
+            .collect(),
+        None => Ok(Vec::new()),
+        Some(_) => read_bson(dump),
+    }
+}
+
+#[cfg(feature = "bson")]
+fn read_bson(dump: &[u8]) -> Result<Vec<Value>, NightscoutError> {
+    let mut reader = dump;
+    let mut documents = Vec::new();
+    while !reader.is_empty() {
+        let document = bson::Document::from_reader(&mut reader)
+            .map_err(|e| NightscoutError::InvalidInput(format!("invalid BSON dump: {e}")))?;
+        documents.push(bson::Bson::Document(document).into_relaxed_extjson());
+    }
+    Ok(documents)
+}
+
+#[cfg(not(feature = "bson"))]
+fn read_bson(_dump: &[u8]) -> Result<Vec<Value>, NightscoutError> {
+    Err(NightscoutError::InvalidInput(
+        "the dump is not JSON; BSON dumps require the `bson` feature".to_string(),
+    ))
+}
+
+/// Replaces the MongoDB Extended JSON wrappers by plain values.
+fn normalize(value: Value) -> Value {
+    match value {
+        Value::Array(items) => Value::Array(items.into_iter().map(normalize).collect()),
+        Value::Object(map) => {
+            if map.len() == 1 {
+                let (key, inner) = map.iter().next().expect("map has one entry");
+                match key.as_str() {
+                    "$oid" | "$numberDecimal" => return inner.clone(),
+                    "$numberLong" | "$numberInt" | "$numberDouble" => {
+                        return match inner.as_str() {
+                            Some(n) => n
+                                .parse::<i64>()
+                                .map(Value::from)
+                                .or_else(|_| n.parse::<f64>().map(Value::from))
+                                .unwrap_or_else(|_| inner.clone()),
+                            None => inner.clone(),
+                        };
+                    }
+                    "$date" => {
+                        let millis = normalize(inner.clone());
+                        return match millis
+                            .as_i64()
+                            .and_then(chrono::DateTime::from_timestamp_millis)
+                        {
+                            Some(date) => Value::String(format_datetime(&date)),
+                            None => millis,
+                        };
+                    }
+                    _ => {}
+                }
+            }
+            Value::Object(
+                map.into_iter()
+                    .map(|(key, value)| (key, normalize(value)))
+                    .collect::<Map<_, _>>(),
+            )
+        }
+        value => value,
+    }
+}
+
+/// Identity of a document for deduplication: its date and main value.
+trait DedupKey {
+    fn dedup_key(&self) -> Option<String>;
+}
+
+impl DedupKey for Entry {
+    fn dedup_key(&self) -> Option<String> {
+        Some(match self {
+            Entry::Sgv(e) => format!("sgv|{}|{}", e.date, e.sgv),
+            Entry::Mbg(e) => format!("mbg|{}|{}", e.date, e.mbg),
+            Entry::Cal(e) => format!("cal|{}|{}", e.date, e.slope),
+            Entry::Unknown(_) => return None,
+        })
+    }
+}
+
+impl DedupKey for Treatment {
+    fn dedup_key(&self) -> Option<String> {
+        Some(format!(
+            "{}|{}|{:?}|{:?}",
+            self.created_at.timestamp_millis(),
+            self.event_type,
+            self.insulin,
+            self.carbs
+        ))
+    }
+}
+
+impl DedupKey for DeviceStatus {
+    fn dedup_key(&self) -> Option<String> {
+        Some(format!(
+            "{}|{}",
+            self.created_at.timestamp_millis(),
+            self.device.as_deref().unwrap_or_default()
+        ))
+    }
+}
+
+/// Uploads backups of the entries, treatments and device status collections.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// # use cinnamon::client::NightscoutClient;
+/// # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+/// let client = NightscoutClient::new("https://new-site.example.com")?.with_secret("secret");
+///
+/// let dump = std::fs::read("backup/treatments.json")?;
+/// let report = client.import().treatments(&dump).await?;
+/// println!(
+///     "{} uploaded, {} already present",
+///     report.upload.created.len(),
+///     report.duplicates
+/// );
+/// # Ok(())
+/// # }
+/// ```
+pub struct ImportService {
+    pub client: NightscoutClient,
+}
+
+impl ImportService {
+    /// Imports a backup of the entries collection (SGV, MBG and calibrations).
+    pub async fn entries(&self, dump: &[u8]) -> Result<ImportReport<Entry>, NightscoutError> {
+        let client = self.client.clone();
+        self.import(dump, Endpoint::Entries, move |from, to| {
+            client
+                .entries()
+                .all()
+                .from(from)
+                .to(to)
+                .paginate(EXISTING_PAGE_SIZE)
+        })
+        .await
+    }
+
+    /// Imports a backup of the treatments collection.
+    pub async fn treatments(
+        &self,
+        dump: &[u8],
+    ) -> Result<ImportReport<Treatment>, NightscoutError> {
+        let client = self.client.clone();
+        self.import(dump, Endpoint::Treatments, move |from, to| {
+            client
+                .treatments()
+                .get()
+                .from(from)
+                .to(to)
+                .paginate(EXISTING_PAGE_SIZE)
+        })
+        .await
+    }
+
+    /// Imports a backup of the device status collection.
+    pub async fn devicestatus(
+        &self,
+        dump: &[u8],
+    ) -> Result<ImportReport<DeviceStatus>, NightscoutError> {
+        let client = self.client.clone();
+        self.import(dump, Endpoint::DeviceStatus, move |from, to| {
+            client
+                .devicestatus()
+                .get()
+                .from(from)
+                .to(to)
+                .paginate(EXISTING_PAGE_SIZE)
+        })
+        .await
+    }
+
+    async fn import<T, S>(
+        &self,
+        dump: &[u8],
+        endpoint: Endpoint,
+        existing: impl FnOnce(chrono::DateTime<chrono::Utc>, chrono::DateTime<chrono::Utc>) -> S,
+    ) -> Result<ImportReport<T>, NightscoutError>
+    where
+        T: Serialize + DeserializeOwned + HasDate + DedupKey,
+        S: Stream<Item = Result<T, NightscoutError>>,
+    {
+        let PartialResult { items, errors } = parse_dump::<T>(dump)?;
+
+        let dates = items.iter().filter_map(HasDate::timestamp);
+        let mut seen: HashSet<String> = match (dates.clone().min(), dates.max()) {
+            (Some(from), Some(to)) => {
+                existing(from, to)
+                    .try_filter_map(|document| async move { Ok(document.dedup_key()) })
+                    .try_collect()
+                    .await?
+            }
+            _ => HashSet::new(),
+        };
+
+        let total = items.len();
+        let documents: Vec<T> = items
+            .into_iter()
+            .filter(|document| match document.dedup_key() {
+                Some(key) => seen.insert(key),
+                None => true,
+            })
+            .collect();
+
+        Ok(ImportReport {
+            invalid: errors,
+            duplicates: total - documents.len(),
+            upload: BulkRequest::new(self.client.clone(), endpoint, documents)
+                .send()
+                .await?,
+        })
+    }
+}
@@ -18,8 +18,7 @@
 //! ## Example
 //!
 //! ```rust,no_run
-//! use cinnamon::client::NightscoutClient;
-//! use cinnamon::models::properties::PropertyType;
+//! use cinnamon::prelude::*;
 //!
 //! #[tokio::main]
 //! async fn main() -> Result<(), Box<dyn std::error::Error>> {
@@ -38,8 +37,131 @@
 //!     Ok(())
 //! }
 //! ```
+//!
+//! ## Configuration
+//!
+//! [`client::NightscoutClient::from_env`] creates a client from the `NIGHTSCOUT_URL`,
+//! `NIGHTSCOUT_TOKEN` and `NIGHTSCOUT_API_SECRET` variables, and with the `config` feature
+//! [`client::NightscoutClient::from_config`] reads the same settings from a TOML file.
+//! Not available on WebAssembly.
+//!
+//! ## API versions
+//!
+//! Requests go to the v2 API by default. Servers that only expose v1 are supported through
+//! [`client::NightscoutClient::with_api_version`] or
+//! [`client::NightscoutClient::detect_api_version`], and queries can opt into v3 with
+//! [`endpoints::ApiVersion::V3`]. The same builder code works against all of them.
+//!
+//! ## Caching
+//!
+//! With the `cache` feature, [`client::NightscoutClient::with_cache`] keeps GET responses in
+//! memory for a configurable time per endpoint.
+//!
+//! ## Spreadsheets
+//!
+//! With the `csv` feature, [`export::to_csv`] and [`export::from_csv`] convert entries and
+//! treatments to CSV, and [`query_builder::QueryBuilder::send_tsv`] reads the
+//! tab-separated output of the entries endpoints.
+//!
+//! ## Offline access
+//!
+//! With the `local-store` feature, [`client::NightscoutClient::with_local_store`] attaches a
+//! SQLite database kept up to date by the v3 sync engine, queried through
+//! [`client::NightscoutClient::local`]. Not available on WebAssembly.
+//!
+//! ## Metrics
+//!
+//! With the `metrics` feature, [`client::NightscoutClient::metrics`] exports the latest
+//! reading, IOB, COB, batteries and data age as Prometheus gauges, and can serve them for
+//! scraping.
+//!
+//! ## Home Assistant
+//!
+//! With the `mqtt` feature, [`client::NightscoutClient::mqtt`] publishes glucose, trend,
+//! IOB, COB and batteries to an MQTT broker, with discovery topics so Home Assistant
+//! creates the sensors itself. Not available on WebAssembly.
+//!
+//! ## WebAssembly
+//!
+//! The asynchronous client builds for `wasm32-unknown-unknown`, using reqwest's browser
+//! backend, so it can be used from Leptos or Yew dashboards. The `blocking-runtime`
+//! feature is not available on that target.
+//!
+//! ## Tracing
+//!
+//! With the `tracing` feature, every HTTP request runs in a `nightscout.request` span
+//! recording the method, endpoint, status, latency and number of attempts. Access tokens
+//! and secrets are redacted from the recorded endpoint.
+//!
+//! ## Python
+//!
+//! With the `python` feature, [`python`] exposes the client, query builders and models to
+//! Python through PyO3. Build the extension module with `maturin develop --release`.
+//!
+//! ## Swift and Kotlin
+//!
+//! With the `uniffi` feature, [`ffi`] exports the core of the client through UniFFI, for
+//! iOS and Android apps.
+//!
+//! ## Command line
+//!
+//! With the `cli` feature, the `cinnamon` binary shows the latest reading (`cinnamon bg`),
+//! IOB and COB, lists and adds treatments, exports CSV files and watches for new readings.
+//! Install it with `cargo install cinnamon --features cli`.
+//!
+//! ## Testing
+//!
+//! Application code written against [`api::NightscoutApi`] can be given a stub in unit
+//! tests. With the `testing` feature, [`testing::MockNightscout`] serves canned fixtures
+//! from a local mock server for tests going through the real client. Not available on
+//! WebAssembly.
+pub mod agp;
+pub mod alarms;
+pub mod analysis;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod api;
+#[cfg(all(feature = "blocking-runtime", not(target_arch = "wasm32")))]
+pub mod blocking;
+pub mod bulk;
+#[cfg(feature = "cache")]
+pub mod cache;
+pub mod capabilities;
 pub mod client;
+pub mod conditional;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod config;
 pub mod endpoints;
 pub mod error;
+pub mod export;
+#[cfg(all(feature = "uniffi", not(target_arch = "wasm32")))]
+pub mod ffi;
+pub mod forwarder;
+pub mod import;
+#[cfg(all(feature = "local-store", not(target_arch = "wasm32")))]
+pub mod local;
+#[cfg(feature = "metrics")]
+pub mod metrics;
+pub mod middleware;
 pub mod models;
+pub mod monitor;
+#[cfg(all(feature = "mqtt", not(target_arch = "wasm32")))]
+pub mod mqtt;
+pub mod prelude;
+#[cfg(all(feature = "python", not(target_arch = "wasm32")))]
+pub mod python;
 pub mod query_builder;
+pub mod queue;
+pub mod ratelimit;
+pub mod reports;
+pub mod response;
+pub mod retry;
+pub(crate) mod runtime;
+pub mod snapshot;
+pub mod stats;
+pub mod sync;
+#[cfg(all(feature = "testing", not(target_arch = "wasm32")))]
+pub mod testing;
+pub(crate) mod watch;
+
+#[cfg(all(feature = "uniffi", not(target_arch = "wasm32")))]
+uniffi::setup_scaffolding!();
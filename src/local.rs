@@ -0,0 +1,315 @@
+//! A persistent local copy of the synchronized data.
+//!
+//! Enabled with the `local-store` feature. A [`LocalStore`] is a SQLite database filled by
+//! the [`crate::sync::SyncManager`], so weeks of entries and treatments can be queried
+//! offline through [`crate::client::NightscoutClient::local`]:
+//!
+//! ```rust,no_run
+//! use cinnamon::client::NightscoutClient;
+//! use cinnamon::local::LocalStore;
+//! use chrono::{Duration, Utc};
+//!
+//! # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+//! let client = NightscoutClient::new("https://ns.example.com")?
+//!     .with_token("sync-0123456789abcdef")
+//!     .with_local_store(LocalStore::open("nightscout.db")?);
+//!
+//! // Keep the store up to date every five minutes.
+//! client.local().spawn_sync(std::time::Duration::from_secs(300));
+//!
+//! let to = Utc::now();
+//! let readings = client.local().sgv_between(to - Duration::days(14), to)?;
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::client::NightscoutClient;
+use crate::error::NightscoutError;
+use crate::models::devicestatus::DeviceStatus;
+use crate::models::entries::{Entry, SgvEntry};
+use crate::models::profile::ProfileSet;
+use crate::models::treatments::Treatment;
+use crate::query_builder::HasDate;
+use crate::sync::{Change, Collection, SyncEvent, SyncReport};
+
+use chrono::{DateTime, Utc};
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS documents (
+        collection TEXT NOT NULL,
+        id TEXT NOT NULL,
+        kind TEXT,
+        date INTEGER,
+        body TEXT NOT NULL,
+        PRIMARY KEY (collection, id)
+    );
+    CREATE INDEX IF NOT EXISTS documents_by_date ON documents (collection, kind, date);
+    CREATE TABLE IF NOT EXISTS cursors (
+        collection TEXT PRIMARY KEY,
+        srv_modified INTEGER NOT NULL
+    );
+";
+
+/// A SQLite database holding synchronized documents and the sync cursors.
+///
+/// Cheap to clone, clones share the connection.
+#[derive(Clone)]
+pub struct LocalStore {
+    conn: Arc<Mutex<Connection>>,
+}
+
+impl LocalStore {
+    /// Opens or creates the database at `path`.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, NightscoutError> {
+        Self::init(Connection::open(path)?)
+    }
+
+    /// Creates a database living in memory only, mostly useful for tests.
+    pub fn in_memory() -> Result<Self, NightscoutError> {
+        Self::init(Connection::open_in_memory()?)
+    }
+
+    fn init(conn: Connection) -> Result<Self, NightscoutError> {
+        conn.execute_batch(SCHEMA)?;
+        Ok(Self {
+            conn: Arc::new(Mutex::new(conn)),
+        })
+    }
+
+    fn lock(&self) -> Result<std::sync::MutexGuard<'_, Connection>, NightscoutError> {
+        self.conn
+            .lock()
+            .map_err(|_| NightscoutError::InvalidInput("local store lock poisoned".to_string()))
+    }
+
+    /// The sync cursors saved by the last [`LocalStore::apply`].
+    pub fn cursors(&self) -> Result<HashMap<Collection, i64>, NightscoutError> {
+        let conn = self.lock()?;
+        let mut cursors = HashMap::new();
+
+        for collection in Collection::ALL {
+            let cursor: Option<i64> = conn
+                .query_row(
+                    "SELECT srv_modified FROM cursors WHERE collection = ?1",
+                    params![collection.as_str()],
+                    |row| row.get(0),
+                )
+                .optional()?;
+            if let Some(cursor) = cursor {
+                cursors.insert(collection, cursor);
+            }
+        }
+
+        Ok(cursors)
+    }
+
+    /// Stores the changes of a sync run and the cursors it reached, in one transaction.
+    pub fn apply(
+        &self,
+        report: &SyncReport,
+        cursors: &HashMap<Collection, i64>,
+    ) -> Result<(), NightscoutError> {
+        let mut conn = self.lock()?;
+        let tx = conn.transaction()?;
+
+        for event in &report.events {
+            match event {
+                SyncEvent::Entry(change) => upsert(
+                    &tx,
+                    Collection::Entries,
+                    change,
+                    |e| e.type_(),
+                    |e| e.timestamp(),
+                )?,
+                SyncEvent::Treatment(change) => upsert(
+                    &tx,
+                    Collection::Treatments,
+                    change,
+                    |t| Some(t.event_type.as_str()),
+                    |t| t.timestamp(),
+                )?,
+                SyncEvent::DeviceStatus(change) => upsert(
+                    &tx,
+                    Collection::DeviceStatus,
+                    change,
+                    |_| None,
+                    |d| d.timestamp(),
+                )?,
+                SyncEvent::Profile(change) => {
+                    upsert(&tx, Collection::Profile, change, |_| None, |p| p.start())?
+                }
+            }
+        }
+
+        for (collection, cursor) in cursors {
+            tx.execute(
+                "INSERT OR REPLACE INTO cursors (collection, srv_modified) VALUES (?1, ?2)",
+                params![collection.as_str(), cursor],
+            )?;
+        }
+
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Documents of `collection` (optionally of one `kind`) dated within `range`, or all
+    /// of them without a range, newest first.
+    fn query<T: DeserializeOwned>(
+        &self,
+        collection: Collection,
+        kind: Option<&str>,
+        range: Option<(DateTime<Utc>, DateTime<Utc>)>,
+    ) -> Result<Vec<T>, NightscoutError> {
+        let conn = self.lock()?;
+        let mut statement = conn.prepare(
+            "SELECT body FROM documents
+             WHERE collection = ?1 AND (?2 IS NULL OR kind = ?2)
+               AND (?3 IS NULL OR date BETWEEN ?3 AND ?4)
+             ORDER BY date DESC",
+        )?;
+
+        let rows = statement.query_map(
+            params![
+                collection.as_str(),
+                kind,
+                range.map(|(from, _)| from.timestamp_millis()),
+                range.map(|(_, to)| to.timestamp_millis())
+            ],
+            |row| row.get::<_, String>(0),
+        )?;
+
+        let mut documents = Vec::new();
+        for body in rows {
+            documents.push(serde_json::from_str(&body?)?);
+        }
+        Ok(documents)
+    }
+}
+
+fn upsert<T: Serialize>(
+    tx: &rusqlite::Transaction<'_>,
+    collection: Collection,
+    change: &Change<T>,
+    kind: impl Fn(&T) -> Option<&str>,
+    date: impl Fn(&T) -> Option<DateTime<Utc>>,
+) -> Result<(), NightscoutError> {
+    match change {
+        Change::Upserted { id, document } => {
+            tx.execute(
+                "INSERT OR REPLACE INTO documents (collection, id, kind, date, body)
+                 VALUES (?1, ?2, ?3, ?4, ?5)",
+                params![
+                    collection.as_str(),
+                    id,
+                    kind(document),
+                    date(document).map(|d| d.timestamp_millis()),
+                    serde_json::to_string(document)?
+                ],
+            )?;
+        }
+        Change::Deleted { id } => {
+            tx.execute(
+                "DELETE FROM documents WHERE collection = ?1 AND id = ?2",
+                params![collection.as_str(), id],
+            )?;
+        }
+    }
+    Ok(())
+}
+
+/// Offline queries over the client's [`LocalStore`], see
+/// [`crate::client::NightscoutClient::local`].
+pub struct LocalService {
+    pub client: NightscoutClient,
+}
+
+impl LocalService {
+    fn store(&self) -> Result<&LocalStore, NightscoutError> {
+        self.client.local_store.as_ref().ok_or_else(|| {
+            NightscoutError::InvalidInput(
+                "no local store configured, see NightscoutClient::with_local_store".to_string(),
+            )
+        })
+    }
+
+    /// Pulls the changes since the last sync into the store.
+    pub async fn sync(&self) -> Result<SyncReport, NightscoutError> {
+        let store = self.store()?;
+        let mut manager = self.client.sync().with_cursors(store.cursors()?);
+
+        let report = manager.sync().await?;
+        store.apply(&report, manager.cursors())?;
+        Ok(report)
+    }
+
+    /// Syncs the store every `interval` on a background task, until the handle is aborted.
+    ///
+    /// Failed runs are retried at the next interval.
+    pub fn spawn_sync(&self, interval: Duration) -> tokio::task::JoinHandle<()> {
+        let service = LocalService {
+            client: self.client.clone(),
+        };
+
+        tokio::spawn(async move {
+            loop {
+                if let Err(_e) = service.sync().await {
+                    #[cfg(feature = "tracing")]
+                    tracing::warn!(error = %_e, "local store sync failed");
+                }
+                tokio::time::sleep(interval).await;
+            }
+        })
+    }
+
+    /// Sensor glucose readings within `[from, to]`, newest first.
+    pub fn sgv_between(
+        &self,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> Result<Vec<SgvEntry>, NightscoutError> {
+        self.store()?
+            .query(Collection::Entries, Some("sgv"), Some((from, to)))
+    }
+
+    /// Entries of any type within `[from, to]`, newest first.
+    pub fn entries_between(
+        &self,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> Result<Vec<Entry>, NightscoutError> {
+        self.store()?
+            .query(Collection::Entries, None, Some((from, to)))
+    }
+
+    /// Treatments within `[from, to]`, newest first.
+    pub fn treatments_between(
+        &self,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> Result<Vec<Treatment>, NightscoutError> {
+        self.store()?
+            .query(Collection::Treatments, None, Some((from, to)))
+    }
+
+    /// Device status updates within `[from, to]`, newest first.
+    pub fn devicestatus_between(
+        &self,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> Result<Vec<DeviceStatus>, NightscoutError> {
+        self.store()?
+            .query(Collection::DeviceStatus, None, Some((from, to)))
+    }
+
+    /// Every stored profile set, most recent first.
+    pub fn profiles(&self) -> Result<Vec<ProfileSet>, NightscoutError> {
+        self.store()?.query(Collection::Profile, None, None)
+    }
+}
@@ -0,0 +1,183 @@
+//! Prometheus gauges of the current state of a site, built with the `metrics` feature.
+//!
+//! [`MetricsService::render`] returns the latest reading, IOB, COB, batteries and the age
+//! of the data in the Prometheus text format, and [`MetricsService::serve`] answers
+//! scrapes of `/metrics` directly, so the data can be graphed in Grafana without another
+//! exporter:
+//!
+//! ```yaml
+//! scrape_configs:
+//!   - job_name: nightscout
+//!     scrape_interval: 60s
+//!     static_configs:
+//!       - targets: ["localhost:9797"]
+//! ```
+
+use crate::client::NightscoutClient;
+use crate::error::NightscoutError;
+use crate::snapshot::Snapshot;
+
+use std::fmt::Write;
+
+/// How long a scraper has to send its request line before the connection is dropped.
+#[cfg(not(target_arch = "wasm32"))]
+const READ_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(10);
+
+/// A single Prometheus gauge.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Gauge {
+    pub name: &'static str,
+    pub help: &'static str,
+    pub value: f64,
+}
+
+impl Gauge {
+    fn new(name: &'static str, help: &'static str, value: f64) -> Self {
+        Self { name, help, value }
+    }
+}
+
+/// The gauges of a snapshot. Values the site does not report are left out, instead of
+/// being exported as zero.
+pub fn gauges(snapshot: &Snapshot) -> Vec<Gauge> {
+    let mut gauges = vec![
+        Gauge::new("nightscout_up", "Whether the site could be read.", 1.0),
+        Gauge::new(
+            "nightscout_sgv_mgdl",
+            "Latest sensor glucose (mg/dL).",
+            f64::from(snapshot.sgv.sgv),
+        ),
+    ];
+
+    if let Some(date) = snapshot.sgv.datetime() {
+        let age = (snapshot.fetched_at - date).num_milliseconds() as f64 / 1000.0;
+        gauges.push(Gauge::new(
+            "nightscout_sgv_age_seconds",
+            "Time since the latest sensor glucose reading.",
+            age.max(0.0),
+        ));
+    }
+
+    let optional = [
+        (
+            "nightscout_sgv_delta_mgdl",
+            "Change since the previous reading (mg/dL).",
+            snapshot.delta,
+        ),
+        (
+            "nightscout_iob_units",
+            "Insulin on board (U).",
+            snapshot.iob,
+        ),
+        ("nightscout_cob_grams", "Carbs on board (g).", snapshot.cob),
+        (
+            "nightscout_pump_battery_percent",
+            "Pump battery (%).",
+            snapshot.pump_battery,
+        ),
+        (
+            "nightscout_uploader_battery_percent",
+            "Uploader battery (%).",
+            snapshot.uploader_battery,
+        ),
+    ];
+    gauges.extend(
+        optional
+            .into_iter()
+            .filter_map(|(name, help, value)| Some(Gauge::new(name, help, value?))),
+    );
+
+    gauges
+}
+
+/// Formats gauges in the Prometheus text exposition format.
+pub fn encode(gauges: &[Gauge]) -> String {
+    let mut text = String::new();
+    for gauge in gauges {
+        // Writing to a String cannot fail.
+        let _ = writeln!(text, "# HELP {} {}", gauge.name, gauge.help);
+        let _ = writeln!(text, "# TYPE {} gauge", gauge.name);
+        let _ = writeln!(text, "{} {}", gauge.name, gauge.value);
+    }
+    text
+}
+
+/// Collects the gauges of a site, see [`NightscoutClient::metrics`].
+pub struct MetricsService {
+    pub client: NightscoutClient,
+}
+
+impl MetricsService {
+    /// Fetches a snapshot of the site and returns its gauges.
+    ///
+    /// A failed fetch does not fail the scrape: only `nightscout_up` is returned, set to
+    /// 0, so the outage shows up in the graphs and alerts can be set on it. A site without
+    /// any reading is up, and only reports `nightscout_up`.
+    pub async fn collect(&self) -> Vec<Gauge> {
+        let up = |value| Gauge::new("nightscout_up", "Whether the site could be read.", value);
+        match self.client.snapshot().await {
+            Ok(snapshot) => gauges(&snapshot),
+            Err(NightscoutError::NotFound) => vec![up(1.0)],
+            Err(_) => vec![up(0.0)],
+        }
+    }
+
+    /// The gauges of the site in the Prometheus text format.
+    pub async fn render(&self) -> String {
+        encode(&self.collect().await)
+    }
+
+    /// Serves the gauges at `http://<addr>/metrics` until an error occurs accepting
+    /// connections. Each scrape fetches the site again, and is answered on its own task so a
+    /// slow scraper does not hold up the others.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// # use cinnamon::client::NightscoutClient;
+    /// # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+    /// let client = NightscoutClient::from_env()?;
+    /// client.metrics().serve("0.0.0.0:9797").await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg(not(target_arch = "wasm32"))]
+    pub async fn serve(&self, addr: impl tokio::net::ToSocketAddrs) -> Result<(), NightscoutError> {
+        let listener = tokio::net::TcpListener::bind(addr).await?;
+        loop {
+            let (stream, _) = listener.accept().await?;
+            let service = MetricsService {
+                client: self.client.clone(),
+            };
+            tokio::spawn(async move { service.answer(stream).await });
+        }
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    async fn answer(&self, mut stream: tokio::net::TcpStream) {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        // Only the request line matters, which fits in the first read.
+        let mut buffer = [0u8; 1024];
+        let read = match tokio::time::timeout(READ_TIMEOUT, stream.read(&mut buffer)).await {
+            Ok(Ok(read)) => read,
+            _ => return,
+        };
+        let request = String::from_utf8_lossy(&buffer[..read]);
+        let path = request.split_whitespace().nth(1).unwrap_or_default();
+
+        let response = if path == "/metrics" || path.starts_with("/metrics?") {
+            let body = self.render().await;
+            format!(
+                "HTTP/1.1 200 OK\r\nContent-Type: text/plain; version=0.0.4\r\n\
+                 Content-Length: {}\r\nConnection: close\r\n\r\n{body}",
+                body.len()
+            )
+        } else {
+            "HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n".to_string()
+        };
+        // A scraper hanging up early is not an error of the exporter.
+        let _ = stream.write_all(response.as_bytes()).await;
+        let _ = stream.shutdown().await;
+    }
+}
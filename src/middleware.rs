@@ -0,0 +1,63 @@
+//! Hooks observing or modifying every HTTP request made by the client.
+//!
+//! Register an [`Interceptor`] with [`NightscoutClient::with_interceptor`] to add logging,
+//! metrics or custom headers. Interceptors run for every attempt of every request, including
+//! retries, deletes issued by query builders and JWT exchanges.
+//!
+//! [`NightscoutClient::with_interceptor`]: crate::client::NightscoutClient::with_interceptor
+
+use crate::error::NightscoutError;
+
+use reqwest::{Method, Request, Response};
+use std::sync::Arc;
+use std::time::Duration;
+use url::Url;
+
+/// Hooks called around each HTTP request. Every method has an empty default.
+///
+/// # Example
+///
+/// ```rust
+/// use cinnamon::middleware::Interceptor;
+/// use reqwest::{Method, Response};
+/// use std::time::Duration;
+///
+/// struct Logger;
+///
+/// impl Interceptor for Logger {
+///     fn on_response(&self, method: &Method, response: &Response, elapsed: Duration) {
+///         println!("{method} {} -> {} in {elapsed:?}", response.url(), response.status());
+///     }
+/// }
+/// ```
+pub trait Interceptor: Send + Sync {
+    /// Called before the request is sent. The request can be modified, e.g. to add headers.
+    fn on_request(&self, request: &mut Request) {
+        let _ = request;
+    }
+
+    /// Called when a response is received, whatever its status.
+    fn on_response(&self, method: &Method, response: &Response, elapsed: Duration) {
+        let _ = (method, response, elapsed);
+    }
+
+    /// Called when the request fails, including non-success statuses mapped to errors.
+    fn on_error(&self, method: &Method, url: &Url, error: &NightscoutError) {
+        let _ = (method, url, error);
+    }
+}
+
+/// Shares an interceptor with the caller, e.g. to read the metrics it collects.
+impl<T: Interceptor + ?Sized> Interceptor for Arc<T> {
+    fn on_request(&self, request: &mut Request) {
+        (**self).on_request(request)
+    }
+
+    fn on_response(&self, method: &Method, response: &Response, elapsed: Duration) {
+        (**self).on_response(method, response, elapsed)
+    }
+
+    fn on_error(&self, method: &Method, url: &Url, error: &NightscoutError) {
+        (**self).on_error(method, url, error)
+    }
+}
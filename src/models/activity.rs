@@ -0,0 +1,156 @@
+use chrono::{DateTime, Utc};
+use reqwest::Method;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::bulk::BulkRequest;
+use crate::client::NightscoutClient;
+use crate::endpoints::Endpoint;
+use crate::error::NightscoutError;
+use crate::query_builder::{HasDate, HasDevice, QueryBuilder};
+
+/// Activity
+/// Represents a heart rate, step count or exercise record.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Activity {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+
+    #[serde(rename = "created_at")]
+    pub created_at: String,
+
+    /// Heart rate in beats per minute.
+    #[serde(
+        default,
+        rename = "heartRate",
+        alias = "heartrate",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub heart_rate: Option<f64>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub steps: Option<i64>,
+
+    /// Duration of the activity in minutes.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub duration: Option<f64>,
+
+    /// Free form activity kind, e.g. `"running"`.
+    #[serde(
+        default,
+        rename = "activityType",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub activity_type: Option<String>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub notes: Option<String>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub device: Option<String>,
+
+    #[serde(default, rename = "enteredBy", skip_serializing_if = "Option::is_none")]
+    pub entered_by: Option<String>,
+
+    #[serde(flatten)]
+    pub extra: Value,
+}
+
+impl Activity {
+    /// Creates an empty activity record at the given time.
+    pub fn new(date: DateTime<Utc>) -> Self {
+        Activity {
+            id: None,
+            created_at: date.to_rfc3339(),
+            heart_rate: None,
+            steps: None,
+            duration: None,
+            activity_type: None,
+            notes: None,
+            device: None,
+            entered_by: Some("cinnamon".to_string()),
+            extra: Value::Object(Default::default()),
+        }
+    }
+}
+
+impl HasDevice for Activity {
+    fn device(&self) -> Option<&str> {
+        self.device.as_deref().or(self.entered_by.as_deref())
+    }
+}
+
+impl HasDate for Activity {
+    fn timestamp(&self) -> Option<DateTime<Utc>> {
+        DateTime::parse_from_rfc3339(&self.created_at)
+            .ok()
+            .map(|dt| dt.with_timezone(&Utc))
+    }
+}
+
+pub struct ActivityService {
+    pub client: NightscoutClient,
+}
+
+impl ActivityService {
+    /// Initiates a query for Activity records.
+    ///
+    /// This returns a `QueryBuilder`. You can chain methods like `.limit()`, `.from()`, and `.to()`
+    /// before calling `.send()` to execute the request.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// # use cinnamon::client::NightscoutClient;
+    /// # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+    /// let client = NightscoutClient::new("https://ns.example.com")?;
+    /// let records = client.activity()
+    ///     .get()
+    ///     .limit(10)
+    ///     .send()
+    ///     .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn get(&self) -> QueryBuilder<Activity> {
+        QueryBuilder::<Activity>::new(self.client.clone(), Endpoint::Activity, Method::GET)
+            .with_date_field("created_at")
+    }
+
+    /// Fetches a single activity record by its `_id`, `None` if it does not exist.
+    pub async fn get_by_id(&self, id: &str) -> Result<Option<Activity>, NightscoutError> {
+        self.client.get_document(Endpoint::Activity, id).await
+    }
+
+    /// Fetches the most recent activity record, `None` if there is none.
+    ///
+    /// This is a convenience wrapper around `.get().first()`.
+    pub async fn latest(&self) -> Result<Option<Activity>, NightscoutError> {
+        self.get().first().await
+    }
+
+    /// Initiates a delete request for Activity records.
+    ///
+    /// Use the builder to specify which records to delete (e.g. by ID or date range).
+    pub fn delete(&self) -> QueryBuilder<Activity> {
+        QueryBuilder::<Activity>::new(self.client.clone(), Endpoint::Activity, Method::DELETE)
+            .with_date_field("created_at")
+    }
+
+    /// Uploads new Activity records to Nightscout.
+    ///
+    /// Large sets are split into batches of [`crate::bulk::DEFAULT_BATCH_SIZE`], see
+    /// [`ActivityService::bulk`] to report failed batches individually.
+    pub async fn create(&self, records: Vec<Activity>) -> Result<Vec<Activity>, NightscoutError> {
+        self.bulk(records)
+            .stop_on_failure()
+            .send()
+            .await?
+            .into_result()
+    }
+
+    /// Initiates a chunked upload of Activity records.
+    pub fn bulk(&self, records: Vec<Activity>) -> BulkRequest<Activity> {
+        BulkRequest::new(self.client.clone(), Endpoint::Activity, records)
+    }
+}
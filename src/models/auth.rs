@@ -0,0 +1,367 @@
+use crate::client::NightscoutClient;
+use crate::endpoints::Endpoint;
+use crate::error::NightscoutError;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha1::{Digest, Sha1};
+use std::collections::HashMap;
+use std::marker::PhantomData;
+
+/// How the client authenticates its requests.
+///
+/// Every request path (queries, uploads, deletes, properties) goes through the same
+/// authorization step, so the credentials are always sent in the same form.
+#[derive(Clone, PartialEq, Eq, Default)]
+#[non_exhaustive]
+pub enum AuthMode {
+    /// No credentials, only public endpoints are available.
+    #[default]
+    None,
+    /// The SHA1 hex digest of the API secret, sent as the `api-secret` header.
+    Secret(String),
+    /// An access token, exchanged for a JWT sent as an `Authorization: Bearer` header.
+    Token(String),
+}
+
+impl AuthMode {
+    /// Authenticates with the API secret, hashing it as Nightscout expects.
+    pub fn secret(api_secret: &str) -> Self {
+        let mut hasher = Sha1::new();
+        hasher.update(api_secret.as_bytes());
+        AuthMode::Secret(format!("{:x}", hasher.finalize()))
+    }
+
+    /// Authenticates with an access token.
+    pub fn token(token: impl Into<String>) -> Self {
+        AuthMode::Token(token.into())
+    }
+
+    /// Whether any credentials are configured.
+    pub fn is_authenticated(&self) -> bool {
+        !matches!(self, AuthMode::None)
+    }
+}
+
+impl std::fmt::Debug for AuthMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AuthMode::None => f.write_str("None"),
+            AuthMode::Secret(_) => f.write_str("Secret(<redacted>)"),
+            AuthMode::Token(_) => f.write_str("Token(<redacted>)"),
+        }
+    }
+}
+
+/// Response of `/api/v2/authorization/request/{token}`.
+///
+/// Nightscout exchanges an access token (as created in the admin tools) for a
+/// short-lived JWT which is then sent as a `Bearer` token.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct AuthorizationToken {
+    /// The signed JWT.
+    pub token: String,
+
+    /// The subject the access token belongs to.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sub: Option<String>,
+
+    #[serde(
+        default,
+        rename = "permissionGroups",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub permission_groups: Option<Vec<Vec<String>>>,
+
+    /// Issued at (epoch seconds).
+    pub iat: i64,
+
+    /// Expires at (epoch seconds).
+    pub exp: i64,
+}
+
+impl AuthorizationToken {
+    /// The expiry time of the JWT as UTC.
+    pub fn expires_at(&self) -> Option<DateTime<Utc>> {
+        DateTime::from_timestamp(self.exp, 0)
+    }
+
+    /// Whether the JWT expires within `margin_secs` seconds of `now`.
+    pub fn expires_within(&self, now: DateTime<Utc>, margin_secs: i64) -> bool {
+        self.exp - margin_secs <= now.timestamp()
+    }
+}
+
+pub struct AuthService {
+    pub client: NightscoutClient,
+}
+
+impl AuthService {
+    /// Checks the configured credentials and discovers what they are allowed to do.
+    ///
+    /// Calls `/api/v1/verifyauth`, then `/api/v3/status` for the per-collection API v3
+    /// permissions. The latter is optional: servers without API v3 leave
+    /// [`AuthVerification::api_permissions`] empty.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// # use cinnamon::client::NightscoutClient;
+    /// # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+    /// let client = NightscoutClient::new("https://ns.example.com")?.with_token("app-1a2b3c");
+    /// let auth = client.auth().verify().await?;
+    ///
+    /// if !auth.can_write {
+    ///     println!("Read-only access, uploads are disabled");
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn verify(&self) -> Result<AuthVerification, NightscoutError> {
+        let url = self.client.endpoint_url(Endpoint::VerifyAuth)?;
+        let response: VerifyAuthResponse = self.client.fetch(url).await?;
+        let mut verification = AuthVerification::from(response);
+
+        let url = self.client.endpoint_url(Endpoint::StatusV3)?;
+        if let Ok(status) = self.client.fetch::<StatusV3Response>(url).await {
+            verification.api_permissions = status.result.api_permissions;
+        }
+
+        Ok(verification)
+    }
+}
+
+/// What the configured credentials grant, see [`AuthService::verify`].
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Default)]
+pub struct AuthVerification {
+    pub can_read: bool,
+    pub can_write: bool,
+    pub is_admin: bool,
+    /// The server's verdict, e.g. `"OK"` or `"UNAUTHORIZED"`.
+    pub message: Option<String>,
+    /// Whether a role was found for the credentials (`"FOUND"` / `"NOTFOUND"`).
+    pub role_found: Option<String>,
+    /// How permissions were granted, e.g. `"ROLE"` or `"DEFAULT"`.
+    pub permissions: Option<String>,
+    /// API v3 permissions per collection, as CRUD letters (e.g. `"entries": "crud"`).
+    pub api_permissions: HashMap<String, String>,
+}
+
+impl AuthVerification {
+    /// Whether the server accepted the credentials.
+    pub fn is_valid(&self) -> bool {
+        self.can_read || self.can_write || self.is_admin
+    }
+
+    /// Whether API v3 grants `operation` (one of `c`, `r`, `u`, `d`) on `collection`.
+    pub fn can(&self, collection: &str, operation: char) -> bool {
+        self.api_permissions
+            .get(collection)
+            .is_some_and(|grants| grants.contains(operation))
+    }
+}
+
+impl AuthService {
+    /// Manage authorization subjects (the owners of access tokens). Requires admin rights.
+    pub fn subjects(&self) -> AdminCollection<Subject> {
+        AdminCollection::new(self.client.clone(), Endpoint::AuthorizationSubjects)
+    }
+
+    /// Manage authorization roles and their permissions. Requires admin rights.
+    pub fn roles(&self) -> AdminCollection<Role> {
+        AdminCollection::new(self.client.clone(), Endpoint::AuthorizationRoles)
+    }
+}
+
+/// CRUD access to an authorization collection, see [`AuthService::subjects`] and
+/// [`AuthService::roles`].
+pub struct AdminCollection<T> {
+    pub client: NightscoutClient,
+    endpoint: Endpoint,
+    _marker: PhantomData<T>,
+}
+
+impl<T> AdminCollection<T>
+where
+    T: Serialize + serde::de::DeserializeOwned,
+{
+    fn new(client: NightscoutClient, endpoint: Endpoint) -> Self {
+        Self {
+            client,
+            endpoint,
+            _marker: PhantomData,
+        }
+    }
+
+    /// Lists every document of the collection.
+    pub async fn list(&self) -> Result<Vec<T>, NightscoutError> {
+        let url = self.client.endpoint_url(self.endpoint)?;
+        self.client.fetch(url).await
+    }
+
+    /// Creates a document and returns it as stored by the server.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// # use cinnamon::client::NightscoutClient;
+    /// # use cinnamon::models::auth::Subject;
+    /// # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+    /// let client = NightscoutClient::new("https://ns.example.com")?.with_secret("secret");
+    /// let subject = client.auth().subjects()
+    ///     .create(&Subject::new("follower-app", &["readable"]))
+    ///     .await?;
+    ///
+    /// println!("Token: {:?}", subject.access_token);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn create(&self, document: &T) -> Result<T, NightscoutError> {
+        self.client.create_document(self.endpoint, document).await
+    }
+
+    /// Replaces the document with the given id.
+    pub async fn update(&self, id: &str, document: &T) -> Result<T, NightscoutError> {
+        self.client
+            .update_document(self.endpoint, id, document)
+            .await
+    }
+
+    /// Deletes the document with the given id.
+    pub async fn delete(&self, id: &str) -> Result<(), NightscoutError> {
+        self.client.delete_document(self.endpoint, id).await
+    }
+}
+
+/// An authorization subject: a named owner of an access token with a set of roles.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct Subject {
+    #[serde(default, rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+
+    pub name: String,
+
+    #[serde(default)]
+    pub roles: Vec<String>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub notes: Option<String>,
+
+    /// The access token generated by the server for this subject.
+    #[serde(
+        default,
+        rename = "accessToken",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub access_token: Option<String>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub created_at: Option<String>,
+}
+
+impl Subject {
+    pub fn new(name: &str, roles: &[&str]) -> Self {
+        Self {
+            id: None,
+            name: name.to_string(),
+            roles: roles.iter().map(|role| role.to_string()).collect(),
+            notes: None,
+            access_token: None,
+            created_at: None,
+        }
+    }
+}
+
+/// An authorization role: a named set of Shiro-style permissions (e.g. `api:entries:read`).
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct Role {
+    #[serde(default, rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+
+    pub name: String,
+
+    #[serde(default)]
+    pub permissions: Vec<String>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub notes: Option<String>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub created_at: Option<String>,
+}
+
+impl Role {
+    pub fn new(name: &str, permissions: &[&str]) -> Self {
+        Self {
+            id: None,
+            name: name.to_string(),
+            permissions: permissions.iter().map(|p| p.to_string()).collect(),
+            notes: None,
+            created_at: None,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct VerifyAuthResponse {
+    #[serde(default)]
+    message: Value,
+}
+
+#[derive(Debug, Deserialize, Default)]
+struct VerifyAuthMessage {
+    #[serde(default, rename = "canRead")]
+    can_read: bool,
+    #[serde(default, rename = "canWrite")]
+    can_write: bool,
+    #[serde(default, rename = "isAdmin")]
+    is_admin: bool,
+    #[serde(default)]
+    message: Option<String>,
+    #[serde(default, rename = "rolefound")]
+    role_found: Option<String>,
+    #[serde(default)]
+    permissions: Option<String>,
+}
+
+impl From<VerifyAuthResponse> for AuthVerification {
+    fn from(response: VerifyAuthResponse) -> Self {
+        match response.message {
+            // Older servers only answer "OK" for a valid API secret.
+            Value::String(message) => {
+                let ok = message == "OK";
+                AuthVerification {
+                    can_read: ok,
+                    can_write: ok,
+                    is_admin: ok,
+                    message: Some(message),
+                    ..Default::default()
+                }
+            }
+            value => {
+                let message: VerifyAuthMessage = serde_json::from_value(value).unwrap_or_default();
+                AuthVerification {
+                    can_read: message.can_read,
+                    can_write: message.can_write,
+                    is_admin: message.is_admin,
+                    message: message.message,
+                    role_found: message.role_found,
+                    permissions: message.permissions,
+                    api_permissions: HashMap::new(),
+                }
+            }
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct StatusV3Response {
+    result: StatusV3Result,
+}
+
+#[derive(Debug, Deserialize)]
+struct StatusV3Result {
+    #[serde(default, rename = "apiPermissions")]
+    api_permissions: HashMap<String, String>,
+}
@@ -0,0 +1,167 @@
+//! Serde helpers shared by the models.
+
+use chrono::{DateTime, NaiveDateTime, SecondsFormat, Utc};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Deserializer, Serializer};
+use serde_json::Value;
+use std::collections::HashMap;
+
+/// Deserializes an optional field, mapping values of an unexpected shape to `None`
+/// instead of failing the whole document.
+///
+/// Uploaders disagree on the shape of some nested blocks, so a malformed block should
+/// not prevent reading the rest of the record. Types with an `extra` field keep the raw
+/// block there, see [`keep_unparsed!`].
+pub(crate) fn lenient<'de, D, T>(deserializer: D) -> Result<Option<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: DeserializeOwned,
+{
+    let value = Option::<Value>::deserialize(deserializer)?;
+    Ok(value.and_then(|v| serde_json::from_value(v).ok()))
+}
+
+/// Copies the members of `raw` that did not deserialize into `extra`, so values of an
+/// unexpected shape are kept instead of dropped, and sent back unchanged on upload.
+///
+/// `keys` pairs each member with whether its field was filled. See [`keep_unparsed!`].
+pub(crate) fn copy_unparsed(raw: &Value, extra: &mut impl Extra, keys: &[(&str, bool)]) {
+    for (key, parsed) in keys {
+        match raw.get(key) {
+            Some(value) if !parsed && !value.is_null() => extra.keep(key, value.clone()),
+            _ => {}
+        }
+    }
+}
+
+/// The field collecting the members of a document without a dedicated field.
+pub(crate) trait Extra {
+    fn keep(&mut self, key: &str, value: Value);
+}
+
+impl Extra for Value {
+    fn keep(&mut self, key: &str, value: Value) {
+        if let Value::Object(extra) = self {
+            extra.insert(key.to_string(), value);
+        }
+    }
+}
+
+impl Extra for HashMap<String, Value> {
+    fn keep(&mut self, key: &str, value: Value) {
+        self.insert(key.to_string(), value);
+    }
+}
+
+/// Implements `Serialize` and `Deserialize` for a type deriving them with
+/// `#[serde(remote = "Self")]`, keeping the listed members in its `$extra` field when their
+/// [`lenient`] or [`optional_datetime`] field could not be read.
+macro_rules! keep_unparsed {
+    ($type:ty, $extra:ident, [$($key:literal => $field:ident),* $(,)?]) => {
+        impl serde::Serialize for $type {
+            fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                <$type>::serialize(self, serializer)
+            }
+        }
+
+        impl<'de> serde::Deserialize<'de> for $type {
+            fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                let raw = <serde_json::Value as serde::Deserialize>::deserialize(deserializer)?;
+                let mut item = <$type>::deserialize(&raw).map_err(serde::de::Error::custom)?;
+                $crate::models::de::copy_unparsed(
+                    &raw,
+                    &mut item.$extra,
+                    &[$(($key, item.$field.is_some())),*],
+                );
+                Ok(item)
+            }
+        }
+    };
+}
+pub(crate) use keep_unparsed;
+
+/// Deserializes an optional number that some endpoints send as a string (e.g. `"1.20"`).
+///
+/// Strings that are not numbers, such as `"???"`, map to `None`.
+pub(crate) fn number_or_string<'de, D>(deserializer: D) -> Result<Option<f64>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    Ok(match Option::<Value>::deserialize(deserializer)? {
+        Some(Value::Number(n)) => n.as_f64(),
+        Some(Value::String(s)) => s.trim().parse().ok(),
+        _ => None,
+    })
+}
+
+/// Parses a date in one of the formats found in Nightscout documents: RFC 3339, ISO 8601
+/// with a `+hhmm` offset or without any (taken as UTC), or epoch milliseconds.
+pub(crate) fn parse_datetime(value: &Value) -> Option<DateTime<Utc>> {
+    match value {
+        Value::Number(n) => DateTime::from_timestamp_millis(n.as_i64()?),
+        Value::String(s) => {
+            let s = s.trim();
+            DateTime::parse_from_rfc3339(s)
+                .or_else(|_| DateTime::parse_from_str(s, "%Y-%m-%dT%H:%M:%S%.f%z"))
+                .map(|dt| dt.with_timezone(&Utc))
+                .ok()
+                .or_else(|| {
+                    NaiveDateTime::parse_from_str(s, "%Y-%m-%dT%H:%M:%S%.f")
+                        .ok()
+                        .map(|naive| naive.and_utc())
+                })
+                .or_else(|| DateTime::from_timestamp_millis(s.parse().ok()?))
+        }
+        _ => None,
+    }
+}
+
+/// Formats a date the way Nightscout writes them, e.g. `2024-01-01T08:30:00.000Z`.
+pub(crate) fn format_datetime(date: &DateTime<Utc>) -> String {
+    date.to_rfc3339_opts(SecondsFormat::Millis, true)
+}
+
+/// (De)serializes a required date, see [`parse_datetime`] for the accepted formats.
+pub(crate) mod datetime {
+    use super::*;
+
+    pub(crate) fn serialize<S: Serializer>(
+        date: &DateTime<Utc>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&format_datetime(date))
+    }
+
+    pub(crate) fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<DateTime<Utc>, D::Error> {
+        let value = Value::deserialize(deserializer)?;
+        parse_datetime(&value)
+            .ok_or_else(|| serde::de::Error::custom(format!("invalid date: {}", value)))
+    }
+}
+
+/// (De)serializes an optional date, mapping unparseable values to `None`.
+///
+/// Pair it with [`keep_unparsed!`] to keep the original value.
+pub(crate) mod optional_datetime {
+    use super::*;
+
+    pub(crate) fn serialize<S: Serializer>(
+        date: &Option<DateTime<Utc>>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        match date {
+            Some(date) => serializer.serialize_str(&format_datetime(date)),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    pub(crate) fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<DateTime<Utc>>, D::Error> {
+        Ok(Option::<Value>::deserialize(deserializer)?
+            .as_ref()
+            .and_then(parse_datetime))
+    }
+}
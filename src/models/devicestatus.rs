@@ -1,11 +1,19 @@
+use crate::bulk::BulkRequest;
 use crate::client::NightscoutClient;
 use crate::endpoints::Endpoint;
 use crate::error::NightscoutError;
-use crate::query_builder::{HasDevice, QueryBuilder};
+use crate::models::de::{datetime, keep_unparsed, lenient};
+use crate::query_builder::{HasDate, HasDevice, QueryBuilder};
 
+use chrono::{DateTime, Utc};
 use reqwest::Method;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
+use std::collections::HashSet;
+use std::time::Duration;
+
+/// Number of recent statuses searched by [`DeviceStatusService::latest_for_each_device`].
+const PER_DEVICE_SCAN: usize = 500;
 
 pub struct DeviceStatusService {
     pub client: NightscoutClient,
@@ -36,6 +44,53 @@ impl DeviceStatusService {
             .with_date_field("created_at")
     }
 
+    /// Fetches a single device status by its `_id`, `None` if it does not exist.
+    pub async fn get_by_id(&self, id: &str) -> Result<Option<DeviceStatus>, NightscoutError> {
+        self.client.get_document(Endpoint::DeviceStatus, id).await
+    }
+
+    /// Fetches the most recent device status, `None` if there is none.
+    ///
+    /// This is a convenience wrapper around `.get().first()`.
+    pub async fn latest(&self) -> Result<Option<DeviceStatus>, NightscoutError> {
+        self.get().first().await
+    }
+
+    /// Fetches the most recent status of every device (uploader, pump bridge, loop
+    /// phone), newest first.
+    ///
+    /// Devices are found among the last 500 statuses, so one that stopped uploading long
+    /// ago may be missing. Statuses without a `device` are grouped together.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// # use cinnamon::client::NightscoutClient;
+    /// # use std::time::Duration;
+    /// # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+    /// let client = NightscoutClient::new("https://ns.example.com")?;
+    /// for latest in client.devicestatus().latest_for_each_device().await? {
+    ///     if latest.is_stale(Duration::from_secs(15 * 60)) {
+    ///         println!("{:?} last uploaded {} minutes ago", latest.device, latest.age.num_minutes());
+    ///     }
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn latest_for_each_device(&self) -> Result<Vec<DeviceLatest>, NightscoutError> {
+        let statuses = self.get().limit(PER_DEVICE_SCAN).send().await?;
+        let now = Utc::now();
+
+        Ok(latest_per_device(statuses)
+            .into_iter()
+            .map(|status| DeviceLatest {
+                device: status.device.clone(),
+                age: now - status.created_at,
+                status,
+            })
+            .collect())
+    }
+
     /// Initiates a delete request for Device Status entries.
     ///
     /// Use the builder to specify which entries to delete (e.g. by ID or date range).
@@ -49,22 +104,53 @@ impl DeviceStatusService {
     }
 
     /// Uploads new Device Status entries to Nightscout.
+    ///
+    /// Large sets are split into batches of [`crate::bulk::DEFAULT_BATCH_SIZE`], see
+    /// [`DeviceStatusService::bulk`] to report failed batches individually.
     pub async fn create(
         &self,
         entries: Vec<DeviceStatus>,
     ) -> Result<Vec<DeviceStatus>, NightscoutError> {
-        let url = self
-            .client
-            .base_url
-            .join(Endpoint::DeviceStatus.as_path())?;
-        let mut request = self.client.http.post(url);
-        request = self.client.auth(request);
-        let response = self.client.send_checked(request.json(&entries)).await?;
-        Ok(response.json::<Vec<DeviceStatus>>().await?)
+        self.bulk(entries)
+            .stop_on_failure()
+            .send()
+            .await?
+            .into_result()
+    }
+
+    /// Initiates a chunked upload of Device Status entries.
+    pub fn bulk(&self, entries: Vec<DeviceStatus>) -> BulkRequest<DeviceStatus> {
+        BulkRequest::new(self.client.clone(), Endpoint::DeviceStatus, entries)
+    }
+}
+
+/// The most recent status of a device, see
+/// [`DeviceStatusService::latest_for_each_device`].
+#[derive(Debug, Clone)]
+pub struct DeviceLatest {
+    pub device: Option<String>,
+    pub status: DeviceStatus,
+    /// Time since the status was created.
+    pub age: chrono::Duration,
+}
+
+impl DeviceLatest {
+    /// Whether the device has not uploaded for longer than `max_age`.
+    pub fn is_stale(&self, max_age: Duration) -> bool {
+        self.age.to_std().unwrap_or_default() > max_age
     }
 }
 
+/// Keeps the newest status of each device, newest first.
+fn latest_per_device(mut statuses: Vec<DeviceStatus>) -> Vec<DeviceStatus> {
+    statuses.sort_by_key(|status| std::cmp::Reverse(status.created_at));
+    let mut seen = HashSet::new();
+    statuses.retain(|status| seen.insert(status.device.clone()));
+    statuses
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(remote = "Self")]
 pub struct DeviceStatus {
     #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
     pub id: Option<String>,
@@ -72,17 +158,30 @@ pub struct DeviceStatus {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub device: Option<String>,
 
-    #[serde(rename = "created_at")]
-    pub created_at: String,
+    #[serde(rename = "created_at", with = "datetime")]
+    pub created_at: DateTime<Utc>,
 
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub pump: Option<Value>,
+    #[serde(
+        default,
+        deserialize_with = "lenient",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub pump: Option<PumpStatus>,
 
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub openaps: Option<Value>,
+    #[serde(
+        default,
+        deserialize_with = "lenient",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub openaps: Option<OpenApsStatus>,
 
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub loop_: Option<Value>,
+    #[serde(
+        default,
+        rename = "loop",
+        deserialize_with = "lenient",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub loop_: Option<LoopStatus>,
 
     #[serde(skip_serializing_if = "Option::is_none")]
     pub uploader: Option<Value>,
@@ -91,8 +190,485 @@ pub struct DeviceStatus {
     pub extra: Value,
 }
 
+keep_unparsed!(DeviceStatus, extra, ["pump" => pump, "openaps" => openaps, "loop" => loop_]);
+
+impl DeviceStatus {
+    /// The uploader phone or bridge, from the `uploader` block.
+    ///
+    /// Older uploaders send the battery level alone instead of an object.
+    pub fn uploader_status(&self) -> Option<UploaderStatus> {
+        match self.uploader.as_ref()? {
+            Value::Number(battery) => Some(UploaderStatus {
+                battery: battery.as_f64(),
+                name: None,
+            }),
+            Value::Object(uploader) => Some(UploaderStatus {
+                battery: uploader.get("battery").and_then(Value::as_f64),
+                name: uploader
+                    .get("name")
+                    .and_then(Value::as_str)
+                    .map(str::to_string),
+            }),
+            _ => None,
+        }
+    }
+
+    /// The pump battery level (%), when the pump reports one.
+    pub fn pump_battery(&self) -> Option<f64> {
+        self.pump.as_ref()?.battery.as_ref()?.percent
+    }
+}
+
+/// The uploader phone or bridge of a [`DeviceStatus`], see
+/// [`DeviceStatus::uploader_status`].
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct UploaderStatus {
+    /// Battery level (%).
+    pub battery: Option<f64>,
+    pub name: Option<String>,
+}
+
+impl UploaderStatus {
+    /// Whether the battery is at or below `threshold` percent. `false` when the
+    /// uploader does not report its battery.
+    pub fn is_battery_low(&self, threshold: f64) -> bool {
+        self.battery.is_some_and(|battery| battery <= threshold)
+    }
+}
+
+/// The `pump` block uploaded by closed loop systems and pump bridges.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(remote = "Self")]
+pub struct PumpStatus {
+    /// The pump clock, as reported by the pump.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub clock: Option<String>,
+
+    /// Remaining insulin in the reservoir (U).
+    #[serde(
+        default,
+        deserialize_with = "lenient",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub reservoir: Option<f64>,
+
+    #[serde(
+        default,
+        deserialize_with = "lenient",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub battery: Option<PumpBattery>,
+
+    #[serde(
+        default,
+        deserialize_with = "lenient",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub status: Option<PumpState>,
+
+    #[serde(flatten)]
+    pub extra: Value,
+}
+
+keep_unparsed!(
+    PumpStatus,
+    extra,
+    [
+        "reservoir" => reservoir,
+        "battery" => battery,
+        "status" => status,
+    ]
+);
+
+impl PumpStatus {
+    /// Whether insulin delivery is suspended, from either the `suspended` flag or the
+    /// `"suspended"` state.
+    pub fn is_suspended(&self) -> bool {
+        self.status.as_ref().is_some_and(|state| {
+            state.suspended == Some(true)
+                || state
+                    .status
+                    .as_deref()
+                    .is_some_and(|status| status.eq_ignore_ascii_case("suspended"))
+        })
+    }
+
+    /// Whether the battery is at or below `threshold` percent, or reported `"low"` by
+    /// pumps that only send a state. `false` when the pump does not report its battery.
+    pub fn is_battery_low(&self, threshold: f64) -> bool {
+        self.battery
+            .as_ref()
+            .is_some_and(|battery| match battery.percent {
+                Some(percent) => percent <= threshold,
+                None => battery
+                    .status
+                    .as_deref()
+                    .is_some_and(|status| status.eq_ignore_ascii_case("low")),
+            })
+    }
+
+    /// Whether the reservoir holds `units` or less. `false` when it is not reported.
+    pub fn is_reservoir_low(&self, units: f64) -> bool {
+        self.reservoir.is_some_and(|reservoir| reservoir <= units)
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(remote = "Self")]
+pub struct PumpBattery {
+    #[serde(
+        default,
+        deserialize_with = "lenient",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub percent: Option<f64>,
+
+    #[serde(
+        default,
+        deserialize_with = "lenient",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub voltage: Option<f64>,
+
+    /// Battery state reported by some pumps, e.g. `"normal"` or `"low"`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub status: Option<String>,
+
+    #[serde(flatten)]
+    pub extra: Value,
+}
+
+keep_unparsed!(PumpBattery, extra, ["percent" => percent, "voltage" => voltage]);
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct PumpState {
+    /// e.g. `"normal"`, `"suspended"` or `"bolusing"`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub status: Option<String>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bolusing: Option<bool>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub suspended: Option<bool>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timestamp: Option<String>,
+
+    #[serde(flatten)]
+    pub extra: Value,
+}
+
+/// The `openaps` block uploaded by OpenAPS, AndroidAPS and Trio.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(remote = "Self")]
+pub struct OpenApsStatus {
+    /// The latest determination, whether or not it was enacted.
+    #[serde(
+        default,
+        deserialize_with = "lenient",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub suggested: Option<OpenApsDetermination>,
+
+    /// The latest determination that was applied to the pump.
+    #[serde(
+        default,
+        deserialize_with = "lenient",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub enacted: Option<OpenApsDetermination>,
+
+    /// IOB data, either a single object or an array depending on the uploader.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub iob: Option<Value>,
+
+    #[serde(flatten)]
+    pub extra: Value,
+}
+
+keep_unparsed!(OpenApsStatus, extra, ["suggested" => suggested, "enacted" => enacted]);
+
+/// An OpenAPS `suggested` or `enacted` determination.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(remote = "Self")]
+pub struct OpenApsDetermination {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timestamp: Option<String>,
+
+    #[serde(
+        default,
+        deserialize_with = "lenient",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub bg: Option<f64>,
+
+    #[serde(
+        default,
+        rename = "eventualBG",
+        deserialize_with = "lenient",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub eventual_bg: Option<f64>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+
+    /// Temp basal rate (U/h).
+    #[serde(
+        default,
+        deserialize_with = "lenient",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub rate: Option<f64>,
+
+    /// Temp basal duration (minutes).
+    #[serde(
+        default,
+        deserialize_with = "lenient",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub duration: Option<f64>,
+
+    /// Super micro bolus (U).
+    #[serde(
+        default,
+        deserialize_with = "lenient",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub units: Option<f64>,
+
+    #[serde(
+        default,
+        rename = "insulinReq",
+        deserialize_with = "lenient",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub insulin_req: Option<f64>,
+
+    #[serde(
+        default,
+        rename = "IOB",
+        deserialize_with = "lenient",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub iob: Option<f64>,
+
+    #[serde(
+        default,
+        rename = "COB",
+        deserialize_with = "lenient",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub cob: Option<f64>,
+
+    #[serde(
+        default,
+        rename = "sensitivityRatio",
+        deserialize_with = "lenient",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub sensitivity_ratio: Option<f64>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub received: Option<bool>,
+
+    #[serde(
+        default,
+        rename = "predBGs",
+        deserialize_with = "lenient",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub pred_bgs: Option<PredBgs>,
+
+    #[serde(flatten)]
+    pub extra: Value,
+}
+
+keep_unparsed!(
+    OpenApsDetermination,
+    extra,
+    [
+        "bg" => bg,
+        "eventualBG" => eventual_bg,
+        "rate" => rate,
+        "duration" => duration,
+        "units" => units,
+        "insulinReq" => insulin_req,
+        "IOB" => iob,
+        "COB" => cob,
+        "sensitivityRatio" => sensitivity_ratio,
+        "predBGs" => pred_bgs,
+    ]
+);
+
+/// OpenAPS prediction curves, one value every 5 minutes starting at the determination time.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct PredBgs {
+    #[serde(rename = "IOB", default, skip_serializing_if = "Option::is_none")]
+    pub iob: Option<Vec<f64>>,
+
+    #[serde(rename = "COB", default, skip_serializing_if = "Option::is_none")]
+    pub cob: Option<Vec<f64>>,
+
+    #[serde(rename = "ZT", default, skip_serializing_if = "Option::is_none")]
+    pub zt: Option<Vec<f64>>,
+
+    #[serde(rename = "UAM", default, skip_serializing_if = "Option::is_none")]
+    pub uam: Option<Vec<f64>>,
+
+    #[serde(flatten)]
+    pub extra: Value,
+}
+
+/// The `loop` block uploaded by Loop.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(remote = "Self")]
+pub struct LoopStatus {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timestamp: Option<String>,
+
+    #[serde(
+        default,
+        deserialize_with = "lenient",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub iob: Option<LoopIob>,
+
+    #[serde(
+        default,
+        deserialize_with = "lenient",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub cob: Option<LoopCob>,
+
+    #[serde(
+        default,
+        deserialize_with = "lenient",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub predicted: Option<LoopPredicted>,
+
+    #[serde(
+        default,
+        deserialize_with = "lenient",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub enacted: Option<LoopEnacted>,
+
+    #[serde(
+        default,
+        rename = "recommendedBolus",
+        deserialize_with = "lenient",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub recommended_bolus: Option<f64>,
+
+    #[serde(
+        default,
+        rename = "failureReason",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub failure_reason: Option<String>,
+
+    #[serde(flatten)]
+    pub extra: Value,
+}
+
+keep_unparsed!(
+    LoopStatus,
+    extra,
+    [
+        "iob" => iob,
+        "cob" => cob,
+        "predicted" => predicted,
+        "enacted" => enacted,
+        "recommendedBolus" => recommended_bolus,
+    ]
+);
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct LoopIob {
+    #[serde(
+        default,
+        deserialize_with = "lenient",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub iob: Option<f64>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timestamp: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct LoopCob {
+    #[serde(
+        default,
+        deserialize_with = "lenient",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub cob: Option<f64>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timestamp: Option<String>,
+}
+
+/// Loop's predicted glucose curve, one value every 5 minutes from `start_date`.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct LoopPredicted {
+    #[serde(rename = "startDate")]
+    pub start_date: String,
+
+    pub values: Vec<f64>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct LoopEnacted {
+    /// Temp basal rate (U/h).
+    #[serde(
+        default,
+        deserialize_with = "lenient",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub rate: Option<f64>,
+
+    /// Temp basal duration (minutes).
+    #[serde(
+        default,
+        deserialize_with = "lenient",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub duration: Option<f64>,
+
+    #[serde(
+        default,
+        rename = "bolusVolume",
+        deserialize_with = "lenient",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub bolus_volume: Option<f64>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timestamp: Option<String>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub received: Option<bool>,
+}
+
 impl HasDevice for DeviceStatus {
     fn device(&self) -> Option<&str> {
         self.device.as_deref()
     }
 }
+
+impl HasDate for DeviceStatus {
+    fn timestamp(&self) -> Option<DateTime<Utc>> {
+        Some(self.created_at)
+    }
+}
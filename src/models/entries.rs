@@ -1,12 +1,24 @@
+use crate::agp::AgpRequest;
+use crate::bulk::BulkRequest;
 use crate::client::NightscoutClient;
 use crate::endpoints::Endpoint;
 use crate::error::NightscoutError;
+use crate::models::de::{keep_unparsed, optional_datetime};
+use crate::models::glucose::Glucose;
 use crate::models::trends::Trend;
-use crate::query_builder::{HasDevice, QueryBuilder};
+use crate::query_builder::{HasDate, HasDevice, QueryBuilder};
+use crate::stats::StatsRequest;
+use crate::watch;
 
 use chrono::{DateTime, Utc};
+use futures::Stream;
 use reqwest::Method;
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use serde_json::Value;
+use std::time::Duration;
+
+/// Maximum number of readings fetched per poll by [`SgvService::watch`].
+const WATCH_BATCH_SIZE: usize = 100;
 
 pub struct SgvService {
     pub client: NightscoutClient,
@@ -16,6 +28,58 @@ pub struct MbgService {
     pub client: NightscoutClient,
 }
 
+pub struct CalService {
+    pub client: NightscoutClient,
+}
+
+/// Groups the services of the entries collection (SGV, MBG and calibrations).
+pub struct EntriesService {
+    pub client: NightscoutClient,
+}
+
+impl EntriesService {
+    /// Access the Sensor Glucose Value (SGV) service.
+    pub fn sgv(&self) -> SgvService {
+        self.client.sgv()
+    }
+
+    /// Access the Meter Blood Glucose (MBG) service.
+    pub fn mbg(&self) -> MbgService {
+        self.client.mbg()
+    }
+
+    /// Access the calibration (CAL) service.
+    pub fn cal(&self) -> CalService {
+        self.client.cal()
+    }
+
+    /// Initiates a query over every entry type, returned as a unified timeline.
+    ///
+    /// Each document is deserialized into the [`Entry`] variant matching its `type` field.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// # use cinnamon::client::NightscoutClient;
+    /// # use cinnamon::models::entries::Entry;
+    /// # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+    /// let client = NightscoutClient::new("https://ns.example.com")?;
+    /// for entry in client.entries().all().limit(50).send().await? {
+    ///     match entry {
+    ///         Entry::Sgv(sgv) => println!("CGM: {}", sgv.sgv),
+    ///         Entry::Mbg(mbg) => println!("Fingerstick: {}", mbg.mbg),
+    ///         Entry::Cal(_) | Entry::Unknown(_) => {}
+    ///     }
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn all(&self) -> QueryBuilder<Entry> {
+        QueryBuilder::<Entry>::new(self.client.clone(), Endpoint::Entries, Method::GET)
+            .with_epoch_date_field("date")
+    }
+}
+
 impl SgvService {
     /// Initiates a query for SGV entries.
     ///
@@ -41,6 +105,24 @@ impl SgvService {
             .with_epoch_date_field("date")
     }
 
+    /// Fetches a single SGV entry by its `_id`, `None` if it does not exist.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// # use cinnamon::client::NightscoutClient;
+    /// # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+    /// let client = NightscoutClient::new("https://ns.example.com")?;
+    /// if let Some(entry) = client.sgv().get_by_id("65f1c0ffee0123456789abcd").await? {
+    ///     println!("{} mg/dL", entry.sgv);
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn get_by_id(&self, id: &str) -> Result<Option<SgvEntry>, NightscoutError> {
+        self.client.get_document(Endpoint::Sgv, id).await
+    }
+
     /// Initiates a delete request for SGV entries.
     ///
     /// Use the builder to specify which entries to delete (e.g. by ID or date range).
@@ -49,27 +131,115 @@ impl SgvService {
             .with_epoch_date_field("date")
     }
 
-    /// Fetches the single latest available SGV entry.
+    /// Fetches the most recent SGV entry, `None` if there is none.
+    ///
+    /// This is a convenience wrapper around `.get().first()`.
+    pub async fn latest(&self) -> Result<Option<SgvEntry>, NightscoutError> {
+        self.get().first().await
+    }
+
+    /// Watches for new SGV readings, polling Nightscout every `interval`.
+    ///
+    /// The first item is the latest reading available; after that, only readings that
+    /// arrived since are emitted, oldest first and deduplicated by `_id` and date.
+    /// Errors are yielded without ending the stream.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// # use cinnamon::client::NightscoutClient;
+    /// # use futures::StreamExt;
+    /// # use std::time::Duration;
+    /// # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+    /// let client = NightscoutClient::new("https://ns.example.com")?;
+    /// let mut readings = std::pin::pin!(client.sgv().watch(Duration::from_secs(60)));
     ///
-    /// This is a convenience wrapper around `.get().limit(1)`.
-    pub async fn latest(&self) -> Result<SgvEntry, NightscoutError> {
-        let builder = self.get().limit(1);
-        let result = builder.send().await?;
+    /// while let Some(entry) = readings.next().await {
+    ///     println!("New reading: {}", entry?.sgv);
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn watch(
+        &self,
+        interval: Duration,
+    ) -> impl Stream<Item = Result<SgvEntry, NightscoutError>> {
+        let client = self.client.clone();
 
-        result.first().cloned().ok_or(NightscoutError::NotFound)
+        watch::poll(
+            interval,
+            move |since| {
+                let query = match since {
+                    Some(since) => client.sgv().get().from(since).limit(WATCH_BATCH_SIZE),
+                    None => client.sgv().get().limit(1),
+                };
+                query.send()
+            },
+            |entry: &SgvEntry| entry.id.clone(),
+        )
     }
 
-    /// Uploads new SGV entries to Nightscout.
-    pub async fn create(&self, entries: Vec<SgvEntry>) -> Result<Vec<SgvEntry>, NightscoutError> {
-        let url = self.client.base_url.join(Endpoint::Entries.as_path())?;
+    /// Computes glucose statistics (time in range, mean, SD, CV, GMI) over recent history.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// # use cinnamon::client::NightscoutClient;
+    /// # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+    /// let client = NightscoutClient::new("https://ns.example.com")?;
+    /// let stats = client.sgv().stats().last_days(14).send().await?;
+    /// println!("TIR: {:.1}%", stats.time_in_range.in_range);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn stats(&self) -> StatsRequest {
+        StatsRequest::new(self.client.clone())
+    }
 
-        let mut request = self.client.http.post(url);
+    /// Computes an Ambulatory Glucose Profile (percentile bands per time of day).
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// # use cinnamon::client::NightscoutClient;
+    /// # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+    /// let client = NightscoutClient::new("https://ns.example.com")?;
+    /// let agp = client.sgv().agp()
+    ///     .last_days(14)
+    ///     .timezone(chrono_tz::Europe::Rome)
+    ///     .send()
+    ///     .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn agp(&self) -> AgpRequest {
+        AgpRequest::new(self.client.clone())
+    }
 
-        request = self.client.auth(request);
+    /// Replaces an existing SGV entry on Nightscout.
+    ///
+    /// Issues a `PUT` with the entry's `_id` set to `id` and returns the updated document.
+    pub async fn update(&self, id: &str, entry: SgvEntry) -> Result<SgvEntry, NightscoutError> {
+        self.client
+            .update_document(Endpoint::Entries, id, &entry)
+            .await
+    }
 
-        let response = self.client.send_checked(request.json(&entries)).await?;
+    /// Uploads new SGV entries to Nightscout.
+    ///
+    /// Large sets are split into batches of [`crate::bulk::DEFAULT_BATCH_SIZE`], see
+    /// [`SgvService::bulk`] to report failed batches individually.
+    pub async fn create(&self, entries: Vec<SgvEntry>) -> Result<Vec<SgvEntry>, NightscoutError> {
+        self.bulk(entries)
+            .stop_on_failure()
+            .send()
+            .await?
+            .into_result()
+    }
 
-        Ok(response.json::<Vec<SgvEntry>>().await?)
+    /// Initiates a chunked upload of SGV entries.
+    pub fn bulk(&self, entries: Vec<SgvEntry>) -> BulkRequest<SgvEntry> {
+        BulkRequest::new(self.client.clone(), Endpoint::Entries, entries)
     }
 }
 
@@ -98,6 +268,11 @@ impl MbgService {
             .with_epoch_date_field("date")
     }
 
+    /// Fetches a single MBG entry by its `_id`, `None` if it does not exist.
+    pub async fn get_by_id(&self, id: &str) -> Result<Option<MbgEntry>, NightscoutError> {
+        self.client.get_document(Endpoint::Mbg, id).await
+    }
+
     /// Initiates a delete request for MBG entries.
     ///
     /// Use the builder to specify which entries to delete (e.g. by ID or date range).
@@ -106,26 +281,110 @@ impl MbgService {
             .with_epoch_date_field("date")
     }
 
-    /// Fetches the single latest available MBG entry.
+    /// Fetches the most recent MBG entry, `None` if there is none.
     ///
-    /// This is a convenience wrapper around `.get().limit(1)`.
-    pub async fn latest(&self) -> Result<MbgEntry, NightscoutError> {
-        let builder = self.get().limit(1);
-        let result = builder.send().await?;
+    /// This is a convenience wrapper around `.get().first()`.
+    pub async fn latest(&self) -> Result<Option<MbgEntry>, NightscoutError> {
+        self.get().first().await
+    }
 
-        result.first().cloned().ok_or(NightscoutError::NotFound)
+    /// Replaces an existing MBG entry on Nightscout.
+    ///
+    /// Issues a `PUT` with the entry's `_id` set to `id` and returns the updated document.
+    pub async fn update(&self, id: &str, entry: MbgEntry) -> Result<MbgEntry, NightscoutError> {
+        self.client
+            .update_document(Endpoint::Entries, id, &entry)
+            .await
     }
 
     /// Uploads new MBG entries to Nightscout.
+    ///
+    /// Large sets are split into batches of [`crate::bulk::DEFAULT_BATCH_SIZE`], see
+    /// [`MbgService::bulk`] to report failed batches individually.
     pub async fn create(&self, entries: Vec<MbgEntry>) -> Result<Vec<MbgEntry>, NightscoutError> {
-        let url = self.client.base_url.join(Endpoint::Entries.as_path())?;
+        self.bulk(entries)
+            .stop_on_failure()
+            .send()
+            .await?
+            .into_result()
+    }
+
+    /// Initiates a chunked upload of MBG entries.
+    pub fn bulk(&self, entries: Vec<MbgEntry>) -> BulkRequest<MbgEntry> {
+        BulkRequest::new(self.client.clone(), Endpoint::Entries, entries)
+    }
+}
+
+impl CalService {
+    /// Initiates a query for calibration entries.
+    ///
+    /// This returns a `QueryBuilder`. You can chain methods like `.limit()`, `.from()`, and `.to()`
+    /// before calling `.send()` to execute the request.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// # use cinnamon::client::NightscoutClient;
+    /// # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+    /// let client = NightscoutClient::new("https://ns.example.com")?;
+    /// let calibrations = client.entries()
+    ///     .cal()
+    ///     .get()
+    ///     .limit(10)
+    ///     .send()
+    ///     .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn get(&self) -> QueryBuilder<CalEntry> {
+        QueryBuilder::<CalEntry>::new(self.client.clone(), Endpoint::Cal, Method::GET)
+            .with_epoch_date_field("date")
+    }
+
+    /// Fetches a single calibration entry by its `_id`, `None` if it does not exist.
+    pub async fn get_by_id(&self, id: &str) -> Result<Option<CalEntry>, NightscoutError> {
+        self.client.get_document(Endpoint::Cal, id).await
+    }
+
+    /// Initiates a delete request for calibration entries.
+    ///
+    /// Use the builder to specify which entries to delete (e.g. by ID or date range).
+    pub fn delete(&self) -> QueryBuilder<CalEntry> {
+        QueryBuilder::<CalEntry>::new(self.client.clone(), Endpoint::Cal, Method::DELETE)
+            .with_epoch_date_field("date")
+    }
 
-        let mut request = self.client.http.post(url);
-        request = self.client.auth(request);
+    /// Fetches the most recent calibration entry, `None` if there is none.
+    ///
+    /// This is a convenience wrapper around `.get().first()`.
+    pub async fn latest(&self) -> Result<Option<CalEntry>, NightscoutError> {
+        self.get().first().await
+    }
+
+    /// Replaces an existing calibration entry on Nightscout.
+    ///
+    /// Issues a `PUT` with the entry's `_id` set to `id` and returns the updated document.
+    pub async fn update(&self, id: &str, entry: CalEntry) -> Result<CalEntry, NightscoutError> {
+        self.client
+            .update_document(Endpoint::Entries, id, &entry)
+            .await
+    }
 
-        let response = self.client.send_checked(request.json(&entries)).await?;
+    /// Uploads new calibration entries to Nightscout.
+    ///
+    /// Large sets are split into batches of [`crate::bulk::DEFAULT_BATCH_SIZE`], see
+    /// [`CalService::bulk`] to report failed batches individually.
+    pub async fn create(&self, entries: Vec<CalEntry>) -> Result<Vec<CalEntry>, NightscoutError> {
+        self.bulk(entries)
+            .stop_on_failure()
+            .send()
+            .await?
+            .into_result()
+    }
 
-        Ok(response.json::<Vec<MbgEntry>>().await?)
+    /// Initiates a chunked upload of calibration entries.
+    pub fn bulk(&self, entries: Vec<CalEntry>) -> BulkRequest<CalEntry> {
+        BulkRequest::new(self.client.clone(), Endpoint::Entries, entries)
     }
 }
 
@@ -133,6 +392,7 @@ impl MbgService {
 ///
 /// This struct represents blood glucose values automatically entered by a CGM (continuous glucose monitor)
 #[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(remote = "Self")]
 pub struct SgvEntry {
     #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
     pub id: Option<String>,
@@ -141,14 +401,41 @@ pub struct SgvEntry {
     #[serde(
         rename = "dateString",
         default,
+        with = "optional_datetime",
         skip_serializing_if = "Option::is_none"
     )]
-    pub date_string: Option<String>,
+    pub date_string: Option<DateTime<Utc>>,
     pub direction: Trend,
     #[serde(rename = "type")]
     pub type_: String,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub device: Option<String>,
+    /// Signal noise reported by the CGM, from 1 (clean) to 4 (heavy).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub noise: Option<i32>,
+    /// Filtered raw sensor value.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub filtered: Option<f64>,
+    /// Unfiltered raw sensor value.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub unfiltered: Option<f64>,
+    /// Received signal strength of the transmitter (dBm).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rssi: Option<i32>,
+    /// Offset of the uploader's local time from UTC, in minutes.
+    #[serde(rename = "utcOffset", default, skip_serializing_if = "Option::is_none")]
+    pub utc_offset: Option<i32>,
+    /// Clock time of the uploader when the reading was taken.
+    #[serde(
+        rename = "sysTime",
+        default,
+        with = "optional_datetime",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub sys_time: Option<DateTime<Utc>>,
+    /// Fields without a dedicated member, kept so that uploads round-trip unchanged.
+    #[serde(flatten)]
+    pub extra: Value,
 }
 
 impl SgvEntry {
@@ -157,10 +444,17 @@ impl SgvEntry {
             id: None,
             sgv,
             date: date.timestamp_millis(),
-            date_string: Some(date.to_rfc3339()),
+            date_string: Some(date),
             direction,
             type_: "sgv".to_string(),
             device: Some("cinnamon".to_string()),
+            noise: None,
+            filtered: None,
+            unfiltered: None,
+            rssi: None,
+            utc_offset: None,
+            sys_time: None,
+            extra: Value::Object(Default::default()),
         }
     }
 
@@ -178,20 +472,46 @@ impl SgvEntry {
     pub fn datetime(&self) -> Option<DateTime<Utc>> {
         DateTime::from_timestamp_millis(self.date)
     }
+
+    /// Moves the entry to `date`, keeping `date` and `date_string` in sync.
+    pub fn set_datetime(&mut self, date: DateTime<Utc>) {
+        self.date = date.timestamp_millis();
+        self.date_string = Some(date);
+        if let Value::Object(extra) = &mut self.extra {
+            extra.remove("dateString");
+        }
+    }
+
+    /// The reading as a unit-aware [`Glucose`] value (Nightscout stores mg/dL).
+    pub fn glucose(&self) -> Glucose {
+        Glucose::Mgdl(self.sgv as f64)
+    }
 }
 
+// Entries keep a `dateString` (or `sysTime`) that cannot be parsed in `extra`.
+keep_unparsed!(SgvEntry, extra, ["dateString" => date_string, "sysTime" => sys_time]);
+keep_unparsed!(MbgEntry, extra, ["dateString" => date_string]);
+keep_unparsed!(CalEntry, extra, ["dateString" => date_string]);
+
 impl HasDevice for SgvEntry {
     fn device(&self) -> Option<&str> {
         self.device.as_deref()
     }
 }
 
+impl HasDate for SgvEntry {
+    fn timestamp(&self) -> Option<DateTime<Utc>> {
+        self.datetime()
+    }
+}
+
 /// MBG (Meter Blood Glucose)
 ///
 /// This struct represents blood glucose data manually entered by the user, often obtained via a fingerprick.
 ///
 /// https://en.wikipedia.org/wiki/Fingerstick
 #[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(remote = "Self")]
 pub struct MbgEntry {
     #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
     pub id: Option<String>,
@@ -200,13 +520,16 @@ pub struct MbgEntry {
     #[serde(
         rename = "dateString",
         default,
+        with = "optional_datetime",
         skip_serializing_if = "Option::is_none"
     )]
-    pub date_string: Option<String>,
+    pub date_string: Option<DateTime<Utc>>,
     #[serde(rename = "type")]
     pub type_: String,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub device: Option<String>,
+    #[serde(flatten)]
+    pub extra: Value,
 }
 
 impl MbgEntry {
@@ -215,9 +538,10 @@ impl MbgEntry {
             id: None,
             mbg,
             date: date.timestamp_millis(),
-            date_string: Some(date.to_rfc3339()),
+            date_string: Some(date),
             type_: "mbg".to_string(),
             device: Some("cinnamon".to_string()),
+            extra: Value::Object(Default::default()),
         }
     }
 
@@ -235,6 +559,20 @@ impl MbgEntry {
     pub fn datetime(&self) -> Option<DateTime<Utc>> {
         DateTime::from_timestamp_millis(self.date)
     }
+
+    /// Moves the entry to `date`, keeping `date` and `date_string` in sync.
+    pub fn set_datetime(&mut self, date: DateTime<Utc>) {
+        self.date = date.timestamp_millis();
+        self.date_string = Some(date);
+        if let Value::Object(extra) = &mut self.extra {
+            extra.remove("dateString");
+        }
+    }
+
+    /// The reading as a unit-aware [`Glucose`] value (Nightscout stores mg/dL).
+    pub fn glucose(&self) -> Glucose {
+        Glucose::Mgdl(self.mbg as f64)
+    }
 }
 
 impl HasDevice for MbgEntry {
@@ -242,3 +580,170 @@ impl HasDevice for MbgEntry {
         self.device.as_deref()
     }
 }
+
+impl HasDate for MbgEntry {
+    fn timestamp(&self) -> Option<DateTime<Utc>> {
+        self.datetime()
+    }
+}
+
+/// CAL (Calibration)
+///
+/// This struct represents the sensor calibration computed by the uploader (e.g. xDrip), used to
+/// convert raw sensor readings into glucose values: `mg/dL = scale * (raw - intercept) / slope`.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(remote = "Self")]
+pub struct CalEntry {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    pub slope: f64,
+    pub intercept: f64,
+    pub scale: f64,
+    pub date: i64,
+    #[serde(
+        rename = "dateString",
+        default,
+        with = "optional_datetime",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub date_string: Option<DateTime<Utc>>,
+    #[serde(rename = "type")]
+    pub type_: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub device: Option<String>,
+    #[serde(flatten)]
+    pub extra: Value,
+}
+
+impl CalEntry {
+    pub fn new(slope: f64, intercept: f64, scale: f64, date: DateTime<Utc>) -> Self {
+        CalEntry {
+            id: None,
+            slope,
+            intercept,
+            scale,
+            date: date.timestamp_millis(),
+            date_string: Some(date),
+            type_: "cal".to_string(),
+            device: Some("cinnamon".to_string()),
+            extra: Value::Object(Default::default()),
+        }
+    }
+
+    pub fn device(mut self, name: String) -> Self {
+        self.device = Some(name);
+        self
+    }
+
+    /// The entry timestamp as UTC, derived from the always-present `date`
+    /// (epoch milliseconds).
+    pub fn datetime(&self) -> Option<DateTime<Utc>> {
+        DateTime::from_timestamp_millis(self.date)
+    }
+
+    /// Moves the entry to `date`, keeping `date` and `date_string` in sync.
+    pub fn set_datetime(&mut self, date: DateTime<Utc>) {
+        self.date = date.timestamp_millis();
+        self.date_string = Some(date);
+        if let Value::Object(extra) = &mut self.extra {
+            extra.remove("dateString");
+        }
+    }
+
+    /// Converts a raw (unfiltered) sensor reading to mg/dL using this calibration.
+    ///
+    /// Returns `None` if the slope is zero.
+    pub fn calibrate(&self, raw: f64) -> Option<f64> {
+        if self.slope == 0.0 {
+            return None;
+        }
+        Some(self.scale * (raw - self.intercept) / self.slope)
+    }
+}
+
+impl HasDevice for CalEntry {
+    fn device(&self) -> Option<&str> {
+        self.device.as_deref()
+    }
+}
+
+impl HasDate for CalEntry {
+    fn timestamp(&self) -> Option<DateTime<Utc>> {
+        self.datetime()
+    }
+}
+
+/// Any document of the entries collection, discriminated by its `type` field.
+///
+/// Documents of an unknown type, or that do not match the expected shape of their
+/// type, are kept as raw JSON in [`Entry::Unknown`].
+#[derive(Debug, Clone)]
+pub enum Entry {
+    Sgv(SgvEntry),
+    Mbg(MbgEntry),
+    Cal(CalEntry),
+    Unknown(Value),
+}
+
+impl Entry {
+    /// The entry `type` field, e.g. `"sgv"`.
+    pub fn type_(&self) -> Option<&str> {
+        match self {
+            Entry::Sgv(e) => Some(&e.type_),
+            Entry::Mbg(e) => Some(&e.type_),
+            Entry::Cal(e) => Some(&e.type_),
+            Entry::Unknown(v) => v.get("type").and_then(Value::as_str),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Entry {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let value = Value::deserialize(deserializer)?;
+
+        let entry = match value.get("type").and_then(Value::as_str) {
+            Some("sgv") => serde_json::from_value(value.clone()).map(Entry::Sgv).ok(),
+            Some("mbg") => serde_json::from_value(value.clone()).map(Entry::Mbg).ok(),
+            Some("cal") => serde_json::from_value(value.clone()).map(Entry::Cal).ok(),
+            _ => None,
+        };
+
+        Ok(entry.unwrap_or(Entry::Unknown(value)))
+    }
+}
+
+impl Serialize for Entry {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            Entry::Sgv(e) => e.serialize(serializer),
+            Entry::Mbg(e) => e.serialize(serializer),
+            Entry::Cal(e) => e.serialize(serializer),
+            Entry::Unknown(v) => v.serialize(serializer),
+        }
+    }
+}
+
+impl HasDevice for Entry {
+    fn device(&self) -> Option<&str> {
+        match self {
+            Entry::Sgv(e) => e.device.as_deref(),
+            Entry::Mbg(e) => e.device.as_deref(),
+            Entry::Cal(e) => e.device.as_deref(),
+            Entry::Unknown(v) => v.get("device").and_then(Value::as_str),
+        }
+    }
+}
+
+impl HasDate for Entry {
+    fn timestamp(&self) -> Option<DateTime<Utc>> {
+        match self {
+            Entry::Sgv(e) => e.datetime(),
+            Entry::Mbg(e) => e.datetime(),
+            Entry::Cal(e) => e.datetime(),
+            Entry::Unknown(v) => v
+                .get("date")
+                .and_then(Value::as_i64)
+                .and_then(DateTime::from_timestamp_millis),
+        }
+    }
+}
@@ -0,0 +1,149 @@
+use crate::error::NightscoutError;
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt;
+use std::str::FromStr;
+
+/// Conversion factor between mmol/L and mg/dL, as used by Nightscout.
+pub const MMOL_TO_MGDL: f64 = 18.0;
+
+/// The unit a glucose value is expressed in.
+///
+/// Nightscout writes these as `"mg/dl"` and `"mmol"`; parsing is case-insensitive
+/// and also accepts the `/L` suffixed spellings.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum GlucoseUnit {
+    #[default]
+    Mgdl,
+    Mmol,
+}
+
+impl GlucoseUnit {
+    /// The identifier Nightscout uses for this unit in settings and profiles.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            GlucoseUnit::Mgdl => "mg/dl",
+            GlucoseUnit::Mmol => "mmol",
+        }
+    }
+
+    /// The human readable label, e.g. for display next to a value.
+    pub fn label(&self) -> &'static str {
+        match self {
+            GlucoseUnit::Mgdl => "mg/dL",
+            GlucoseUnit::Mmol => "mmol/L",
+        }
+    }
+}
+
+impl fmt::Display for GlucoseUnit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.label())
+    }
+}
+
+impl FromStr for GlucoseUnit {
+    type Err = NightscoutError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "mg/dl" | "mgdl" | "mg" => Ok(GlucoseUnit::Mgdl),
+            "mmol" | "mmol/l" | "mmoll" => Ok(GlucoseUnit::Mmol),
+            other => Err(NightscoutError::InvalidInput(format!(
+                "Unknown glucose unit: {}",
+                other
+            ))),
+        }
+    }
+}
+
+impl Serialize for GlucoseUnit {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+impl<'de> Deserialize<'de> for GlucoseUnit {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        s.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+/// A glucose value tagged with its unit.
+///
+/// Nightscout stores every reading in mg/dL; use [`Glucose::to_unit`] to convert
+/// for display in the user's preferred unit.
+///
+/// # Example
+///
+/// ```rust
+/// # use cinnamon::models::glucose::{Glucose, GlucoseUnit};
+/// let bg = Glucose::Mgdl(126.0);
+/// assert_eq!(bg.to_unit(GlucoseUnit::Mmol).to_string(), "7.0 mmol/L");
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Glucose {
+    Mgdl(f64),
+    Mmol(f64),
+}
+
+impl Glucose {
+    /// Creates a value in the given unit.
+    pub fn new(value: f64, unit: GlucoseUnit) -> Self {
+        match unit {
+            GlucoseUnit::Mgdl => Glucose::Mgdl(value),
+            GlucoseUnit::Mmol => Glucose::Mmol(value),
+        }
+    }
+
+    /// The unit this value is expressed in.
+    pub fn unit(&self) -> GlucoseUnit {
+        match self {
+            Glucose::Mgdl(_) => GlucoseUnit::Mgdl,
+            Glucose::Mmol(_) => GlucoseUnit::Mmol,
+        }
+    }
+
+    /// The raw number, in this value's own unit.
+    pub fn value(&self) -> f64 {
+        match self {
+            Glucose::Mgdl(v) | Glucose::Mmol(v) => *v,
+        }
+    }
+
+    /// The value in mg/dL.
+    pub fn mgdl(&self) -> f64 {
+        match self {
+            Glucose::Mgdl(v) => *v,
+            Glucose::Mmol(v) => v * MMOL_TO_MGDL,
+        }
+    }
+
+    /// The value in mmol/L.
+    pub fn mmol(&self) -> f64 {
+        match self {
+            Glucose::Mgdl(v) => v / MMOL_TO_MGDL,
+            Glucose::Mmol(v) => *v,
+        }
+    }
+
+    /// Converts the value to the given unit.
+    pub fn to_unit(self, unit: GlucoseUnit) -> Self {
+        match unit {
+            GlucoseUnit::Mgdl => Glucose::Mgdl(self.mgdl()),
+            GlucoseUnit::Mmol => Glucose::Mmol(self.mmol()),
+        }
+    }
+}
+
+impl fmt::Display for Glucose {
+    /// mg/dL values are shown as integers, mmol/L values with one decimal,
+    /// matching the Nightscout web UI.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Glucose::Mgdl(v) => write!(f, "{:.0} {}", v, GlucoseUnit::Mgdl),
+            Glucose::Mmol(v) => write!(f, "{:.1} {}", v, GlucoseUnit::Mmol),
+        }
+    }
+}
@@ -1,5 +1,12 @@
+pub mod activity;
+pub mod auth;
+pub(crate) mod de;
 pub mod devicestatus;
 pub mod entries;
+pub mod glucose;
+pub mod notifications;
+pub mod pebble;
+pub mod predictions;
 pub mod profile;
 pub mod properties;
 pub mod status;
@@ -0,0 +1,162 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::time::Duration;
+
+use crate::client::NightscoutClient;
+use crate::endpoints::Endpoint;
+use crate::error::NightscoutError;
+
+/// How long an alarm stays silenced by [`NotificationsService::ack`], matching the
+/// default snooze of the web UI.
+pub const DEFAULT_SILENCE: Duration = Duration::from_secs(30 * 60);
+
+/// Notification group used by the core glucose alarms.
+pub const DEFAULT_GROUP: &str = "default";
+
+/// Nightscout notification levels, as numbered by the server.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(try_from = "i64", into = "i64")]
+#[non_exhaustive]
+pub enum AlarmLevel {
+    Lowest,
+    Low,
+    Info,
+    Warn,
+    Urgent,
+}
+
+impl AlarmLevel {
+    /// The numeric level used by the Nightscout API.
+    pub fn as_i64(&self) -> i64 {
+        match self {
+            AlarmLevel::Lowest => -2,
+            AlarmLevel::Low => -1,
+            AlarmLevel::Info => 0,
+            AlarmLevel::Warn => 1,
+            AlarmLevel::Urgent => 2,
+        }
+    }
+
+    /// Whether this level is raised as an audible alarm by the web UI.
+    pub fn is_alarm(&self) -> bool {
+        *self >= AlarmLevel::Warn
+    }
+}
+
+impl TryFrom<i64> for AlarmLevel {
+    type Error = NightscoutError;
+
+    fn try_from(value: i64) -> Result<Self, Self::Error> {
+        match value {
+            -2 => Ok(AlarmLevel::Lowest),
+            -1 => Ok(AlarmLevel::Low),
+            0 => Ok(AlarmLevel::Info),
+            1 => Ok(AlarmLevel::Warn),
+            2 => Ok(AlarmLevel::Urgent),
+            other => Err(NightscoutError::InvalidInput(format!(
+                "unknown notification level {}",
+                other
+            ))),
+        }
+    }
+}
+
+impl From<AlarmLevel> for i64 {
+    fn from(level: AlarmLevel) -> Self {
+        level.as_i64()
+    }
+}
+
+/// An active alarm or announcement, as emitted by the Nightscout notification system.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Alarm {
+    pub level: AlarmLevel,
+
+    #[serde(default)]
+    pub title: String,
+
+    #[serde(default)]
+    pub message: String,
+
+    /// Alarms are acknowledged per level and group.
+    #[serde(default = "default_group")]
+    pub group: String,
+
+    /// The event that raised the alarm, e.g. `"high"` or `"low"`.
+    #[serde(default, rename = "eventName", skip_serializing_if = "Option::is_none")]
+    pub event_name: Option<String>,
+
+    /// The plugin that raised the alarm, e.g. `{"name": "simplealarms"}`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub plugin: Option<Value>,
+
+    /// Time the alarm was raised, in milliseconds since the epoch.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timestamp: Option<i64>,
+
+    #[serde(default, rename = "isAnnouncement")]
+    pub is_announcement: bool,
+
+    #[serde(default)]
+    pub clear: bool,
+}
+
+impl Alarm {
+    /// Name of the plugin that raised the alarm.
+    pub fn plugin_name(&self) -> Option<&str> {
+        self.plugin.as_ref()?.get("name")?.as_str()
+    }
+}
+
+fn default_group() -> String {
+    DEFAULT_GROUP.to_string()
+}
+
+pub struct NotificationsService {
+    pub client: NightscoutClient,
+}
+
+impl NotificationsService {
+    /// Acknowledges (snoozes) alarms of `level` in `group` for [`DEFAULT_SILENCE`],
+    /// the same way the web UI does. Requires the `notifications:*:ack` permission.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// # use cinnamon::client::NightscoutClient;
+    /// # use cinnamon::models::notifications::{AlarmLevel, DEFAULT_GROUP};
+    /// # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+    /// let client = NightscoutClient::new("https://ns.example.com")?.with_secret("secret");
+    /// client.notifications().ack(AlarmLevel::Urgent, DEFAULT_GROUP).await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn ack(&self, level: AlarmLevel, group: &str) -> Result<(), NightscoutError> {
+        self.ack_for(level, group, DEFAULT_SILENCE).await
+    }
+
+    /// Acknowledges alarms of `level` in `group`, silencing them for `silence`.
+    pub async fn ack_for(
+        &self,
+        level: AlarmLevel,
+        group: &str,
+        silence: Duration,
+    ) -> Result<(), NightscoutError> {
+        // Acknowledging is a GET request, but it silences the alarm for every follower.
+        self.client.ensure_writable("acknowledging alarms")?;
+        let mut url = self.client.endpoint_url(Endpoint::NotificationsAck)?;
+        url.query_pairs_mut()
+            .append_pair("level", &level.as_i64().to_string())
+            .append_pair("group", group)
+            .append_pair("time", &silence.as_millis().to_string());
+
+        let request = self.client.authorize(self.client.http.get(url)).await?;
+        self.client.send_checked(request).await?;
+        Ok(())
+    }
+
+    /// Acknowledges the given alarm for `silence`.
+    pub async fn ack_alarm(&self, alarm: &Alarm, silence: Duration) -> Result<(), NightscoutError> {
+        self.ack_for(alarm.level, &alarm.group, silence).await
+    }
+}
@@ -0,0 +1,192 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::client::NightscoutClient;
+use crate::endpoints::Endpoint;
+use crate::error::NightscoutError;
+use crate::models::de::number_or_string;
+use crate::models::glucose::{Glucose, GlucoseUnit};
+use crate::models::trends::Trend;
+
+/// The compact `/pebble` payload: the latest readings with delta, IOB and COB.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Pebble {
+    #[serde(default)]
+    pub status: Vec<PebbleStatus>,
+
+    #[serde(default)]
+    pub bgs: Vec<PebbleBg>,
+
+    #[serde(default)]
+    pub cals: Vec<PebbleCal>,
+
+    /// The unit the readings were requested in.
+    #[serde(skip)]
+    pub units: GlucoseUnit,
+}
+
+impl Pebble {
+    /// The most recent reading.
+    pub fn current(&self) -> Option<&PebbleBg> {
+        self.bgs.first()
+    }
+
+    /// The most recent reading as a unit-aware [`Glucose`] value.
+    pub fn current_glucose(&self) -> Option<Glucose> {
+        self.current()?.glucose(self.units)
+    }
+
+    /// The server time of the response.
+    pub fn server_time(&self) -> Option<DateTime<Utc>> {
+        DateTime::from_timestamp_millis(self.status.first()?.now)
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PebbleStatus {
+    /// Server time in milliseconds since the epoch.
+    pub now: i64,
+}
+
+/// A reading of the `/pebble` payload. Only the first reading carries delta, IOB and COB.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PebbleBg {
+    /// The reading, in the requested unit, formatted as a string by the server.
+    pub sgv: String,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub trend: Option<i64>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub direction: Option<Trend>,
+
+    /// Time of the reading in milliseconds since the epoch.
+    pub datetime: i64,
+
+    #[serde(
+        default,
+        deserialize_with = "number_or_string",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub bgdelta: Option<f64>,
+
+    /// Uploader battery (%).
+    #[serde(
+        default,
+        deserialize_with = "number_or_string",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub battery: Option<f64>,
+
+    /// Insulin on board (U), when the IOB plugin is enabled.
+    #[serde(
+        default,
+        deserialize_with = "number_or_string",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub iob: Option<f64>,
+
+    /// Carbs on board (g), when the COB plugin is enabled.
+    #[serde(
+        default,
+        deserialize_with = "number_or_string",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub cob: Option<f64>,
+
+    /// Bolus wizard preview, when the BWP plugin is enabled.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bwp: Option<String>,
+
+    #[serde(
+        default,
+        deserialize_with = "number_or_string",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub bwpo: Option<f64>,
+}
+
+impl PebbleBg {
+    pub fn datetime(&self) -> Option<DateTime<Utc>> {
+        DateTime::from_timestamp_millis(self.datetime)
+    }
+
+    /// The reading in `unit`, the unit it was requested in. `None` for sensor error codes.
+    pub fn glucose(&self, unit: GlucoseUnit) -> Option<Glucose> {
+        self.sgv.trim().parse().ok().map(|v| Glucose::new(v, unit))
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PebbleCal {
+    pub slope: f64,
+    pub intercept: f64,
+    pub scale: f64,
+}
+
+pub struct PebbleService {
+    pub client: NightscoutClient,
+}
+
+impl PebbleService {
+    /// Begins building a request for the `/pebble` payload.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// # use cinnamon::client::NightscoutClient;
+    /// # use cinnamon::models::glucose::GlucoseUnit;
+    /// # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+    /// let client = NightscoutClient::new("https://ns.example.com")?;
+    /// let pebble = client.pebble().get().units(GlucoseUnit::Mmol).send().await?;
+    ///
+    /// if let Some(bg) = pebble.current_glucose() {
+    ///     println!("{}", bg);
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn get(&self) -> PebbleRequest {
+        PebbleRequest {
+            client: self.client.clone(),
+            count: None,
+            units: GlucoseUnit::Mgdl,
+        }
+    }
+}
+
+/// A builder for constructing a `/pebble` request.
+pub struct PebbleRequest {
+    client: NightscoutClient,
+    count: Option<usize>,
+    units: GlucoseUnit,
+}
+
+impl PebbleRequest {
+    /// Number of readings to return. The server default is 1.
+    pub fn count(mut self, count: usize) -> Self {
+        self.count = Some(count);
+        self
+    }
+
+    /// Unit of the returned readings and deltas.
+    pub fn units(mut self, units: GlucoseUnit) -> Self {
+        self.units = units;
+        self
+    }
+
+    /// Executes the request.
+    pub async fn send(self) -> Result<Pebble, NightscoutError> {
+        let mut url = self.client.endpoint_url(Endpoint::Pebble)?;
+        url.query_pairs_mut()
+            .append_pair("units", self.units.as_str());
+        if let Some(count) = self.count {
+            url.query_pairs_mut()
+                .append_pair("count", &count.to_string());
+        }
+
+        let mut pebble = self.client.fetch::<Pebble>(url).await?;
+        pebble.units = self.units;
+        Ok(pebble)
+    }
+}
@@ -0,0 +1,183 @@
+//! Glucose forecasts uploaded by closed loop systems.
+//!
+//! OpenAPS, AndroidAPS and Trio upload up to four curves in `openaps.suggested.predBGs`,
+//! one per scenario, and Loop a single one in `loop.predicted`. Both are lists of values
+//! 5 minutes apart, which [`Prediction`] turns into dated points.
+
+use crate::client::NightscoutClient;
+use crate::error::NightscoutError;
+use crate::models::de::parse_datetime;
+use crate::models::devicestatus::{DeviceStatus, OpenApsDetermination};
+
+use chrono::{DateTime, Utc};
+use serde_json::Value;
+
+/// Interval between the values of a prediction curve.
+const STEP_MINUTES: i64 = 5;
+
+/// Number of recent device statuses searched by [`PredictionsService::latest`].
+const SEARCH_COUNT: usize = 10;
+
+/// The scenario a prediction curve assumes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum PredictionKind {
+    /// Insulin on board only (`IOB`).
+    Iob,
+    /// Insulin and carbs on board (`COB`).
+    Cob,
+    /// Zero temp basal from now on (`ZT`).
+    ZeroTemp,
+    /// Unannounced meal (`UAM`).
+    Uam,
+    /// Loop's combined forecast.
+    Loop,
+}
+
+/// A dated value of a prediction curve.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PredictionPoint {
+    pub date: DateTime<Utc>,
+    pub mgdl: f64,
+}
+
+/// One prediction curve.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Prediction {
+    pub kind: PredictionKind,
+    /// The points, 5 minutes apart, from the time of the determination.
+    pub points: Vec<PredictionPoint>,
+}
+
+impl Prediction {
+    fn new(kind: PredictionKind, start: DateTime<Utc>, values: &[f64]) -> Self {
+        let points = (0i64..)
+            .zip(values)
+            .map(|(i, &mgdl)| PredictionPoint {
+                date: start + chrono::Duration::minutes(i * STEP_MINUTES),
+                mgdl,
+            })
+            .collect();
+        Self { kind, points }
+    }
+
+    /// The last predicted value.
+    pub fn eventual(&self) -> Option<f64> {
+        self.points.last().map(|point| point.mgdl)
+    }
+}
+
+/// The prediction curves of a device status, see [`DeviceStatus::predictions`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct Predictions {
+    pub device: Option<String>,
+    /// When the curves were computed.
+    pub date: DateTime<Utc>,
+    pub curves: Vec<Prediction>,
+}
+
+impl Predictions {
+    /// The curve of a scenario, if it was uploaded.
+    pub fn get(&self, kind: PredictionKind) -> Option<&Prediction> {
+        self.curves.iter().find(|curve| curve.kind == kind)
+    }
+}
+
+impl DeviceStatus {
+    /// The prediction curves of the status, `None` if it has none.
+    ///
+    /// For OpenAPS, the most recent of the `suggested` and `enacted` determinations
+    /// carrying curves is used, like Nightscout does.
+    pub fn predictions(&self) -> Option<Predictions> {
+        let (date, curves) = self
+            .openaps_predictions()
+            .or_else(|| self.loop_predictions())?;
+
+        Some(Predictions {
+            device: self.device.clone(),
+            date,
+            curves,
+        })
+    }
+
+    fn openaps_predictions(&self) -> Option<(DateTime<Utc>, Vec<Prediction>)> {
+        let openaps = self.openaps.as_ref()?;
+        let determination_date = |d: &OpenApsDetermination| {
+            d.timestamp
+                .as_ref()
+                .and_then(|ts| parse_datetime(&Value::String(ts.clone())))
+                .unwrap_or(self.created_at)
+        };
+
+        let (determination, date) = [openaps.suggested.as_ref(), openaps.enacted.as_ref()]
+            .into_iter()
+            .flatten()
+            .filter(|d| d.pred_bgs.is_some())
+            .map(|d| (d, determination_date(d)))
+            .max_by_key(|(_, date)| *date)?;
+        let pred_bgs = determination.pred_bgs.as_ref()?;
+
+        let curves = [
+            (PredictionKind::Iob, &pred_bgs.iob),
+            (PredictionKind::Cob, &pred_bgs.cob),
+            (PredictionKind::ZeroTemp, &pred_bgs.zt),
+            (PredictionKind::Uam, &pred_bgs.uam),
+        ]
+        .into_iter()
+        .filter_map(|(kind, values)| Some(Prediction::new(kind, date, values.as_ref()?)))
+        .collect();
+
+        Some((date, curves))
+    }
+
+    fn loop_predictions(&self) -> Option<(DateTime<Utc>, Vec<Prediction>)> {
+        let predicted = self.loop_.as_ref()?.predicted.as_ref()?;
+        let date = parse_datetime(&Value::String(predicted.start_date.clone()))?;
+
+        Some((
+            date,
+            vec![Prediction::new(
+                PredictionKind::Loop,
+                date,
+                &predicted.values,
+            )],
+        ))
+    }
+}
+
+/// Access to the forecasts of closed loop systems, see
+/// [`NightscoutClient::predictions`].
+pub struct PredictionsService {
+    pub client: NightscoutClient,
+}
+
+impl PredictionsService {
+    /// The most recent prediction curves, `None` if no recent device status has any.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// # use cinnamon::client::NightscoutClient;
+    /// # use cinnamon::models::predictions::PredictionKind;
+    /// # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+    /// let client = NightscoutClient::new("https://ns.example.com")?;
+    /// if let Some(predictions) = client.predictions().latest().await? {
+    ///     if let Some(cob) = predictions.get(PredictionKind::Cob) {
+    ///         println!("Eventual BG with carbs: {:?}", cob.eventual());
+    ///     }
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn latest(&self) -> Result<Option<Predictions>, NightscoutError> {
+        let statuses = self
+            .client
+            .devicestatus()
+            .get()
+            .limit(SEARCH_COUNT)
+            .send()
+            .await?;
+
+        Ok(statuses.iter().find_map(DeviceStatus::predictions))
+    }
+}
@@ -1,7 +1,11 @@
 use crate::client::NightscoutClient;
 use crate::endpoints::Endpoint;
 use crate::error::NightscoutError;
+use crate::models::treatments::Treatment;
+use chrono::{DateTime, Duration, SecondsFormat, TimeZone, Timelike, Utc};
+use chrono_tz::Tz;
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
 use std::collections::HashMap;
 
 pub struct ProfileService {
@@ -20,22 +24,69 @@ impl ProfileService {
     /// # use cinnamon::client::NightscoutClient;
     /// # async fn run() -> Result<(), Box<dyn std::error::Error>> {
     /// let client = NightscoutClient::new("https://ns.example.com")?;
-    /// let status = client.profiles().get().await?;
-    /// println!("Nightscout Name: {}", status.default_profile_name);
+    /// let profiles = client.profiles().get().await?;
+    /// println!("Default profile: {}", profiles[0].default_profile_name);
     /// # Ok(())
     /// # }
     /// ```
     pub async fn get(&self) -> Result<Vec<ProfileSet>, NightscoutError> {
-        let url = self.client.base_url.join(Endpoint::Profile.as_path())?;
+        let url = self.client.endpoint_url(Endpoint::Profile)?;
         self.client.fetch::<Vec<ProfileSet>>(url).await
     }
+
+    /// Fetches a single profile set by its `_id`, `None` if it does not exist.
+    pub async fn get_by_id(&self, id: &str) -> Result<Option<ProfileSet>, NightscoutError> {
+        self.client.get_document(Endpoint::Profile, id).await
+    }
+
+    /// Uploads a new profile set to Nightscout.
+    ///
+    /// The profile set is validated before anything is sent, see [`ProfileSet::validate`].
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// # use cinnamon::client::NightscoutClient;
+    /// # use cinnamon::models::profile::{ProfileConfig, ProfileSetBuilder};
+    /// # async fn run(config: ProfileConfig) -> Result<(), Box<dyn std::error::Error>> {
+    /// let client = NightscoutClient::new("https://ns.example.com")?.with_secret("secret");
+    /// let profile = ProfileSetBuilder::new("Default")
+    ///     .profile("Default", config)
+    ///     .build()?;
+    ///
+    /// let created = client.profiles().create(profile).await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn create(&self, profile: ProfileSet) -> Result<ProfileSet, NightscoutError> {
+        profile.validate()?;
+
+        self.client
+            .create_document(Endpoint::Profile, &profile)
+            .await
+    }
+
+    /// Replaces an existing profile set on Nightscout.
+    ///
+    /// Issues a validated `PUT` with the profile set's `_id` set to `id`.
+    pub async fn update(
+        &self,
+        id: &str,
+        profile: ProfileSet,
+    ) -> Result<ProfileSet, NightscoutError> {
+        profile.validate()?;
+
+        self.client
+            .update_document(Endpoint::Profile, id, &profile)
+            .await
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 
 pub struct ProfileSet {
-    #[serde(rename = "_id")]
-    pub id: String,
+    #[serde(default, rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
 
     #[serde(rename = "defaultProfile")]
     pub default_profile_name: String,
@@ -53,6 +104,9 @@ pub struct ProfileSet {
     pub units: Option<String>,
 
     pub created_at: String,
+
+    #[serde(flatten)]
+    pub extra: Value,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -78,3 +132,382 @@ pub struct TimeSchedule {
     #[serde(rename = "timeAsSeconds")]
     pub time_as_seconds: Option<i64>,
 }
+
+impl TimeSchedule {
+    /// Seconds after local midnight at which this entry starts.
+    ///
+    /// Uses `timeAsSeconds` when present and falls back to parsing the `"HH:MM"` time.
+    pub fn seconds(&self) -> Option<u32> {
+        if let Some(seconds) = self.time_as_seconds {
+            return u32::try_from(seconds).ok();
+        }
+
+        let (hours, minutes) = self.time.split_once(':')?;
+        let hours: u32 = hours.trim().parse().ok()?;
+        let minutes: u32 = minutes.trim().parse().ok()?;
+        (hours < 24 && minutes < 60).then_some(hours * 3600 + minutes * 60)
+    }
+}
+
+impl ProfileConfig {
+    /// The profile's timezone, or `None` if it isn't a known IANA name.
+    pub fn tz(&self) -> Option<Tz> {
+        self.timezone.parse().ok()
+    }
+
+    /// Seconds after local midnight for `at`, in the profile's timezone (UTC if unknown).
+    fn local_seconds(&self, at: DateTime<Utc>) -> u32 {
+        match self.tz() {
+            Some(tz) => tz
+                .from_utc_datetime(&at.naive_utc())
+                .num_seconds_from_midnight(),
+            None => at.num_seconds_from_midnight(),
+        }
+    }
+
+    /// Resolves the value of `schedule` that is active at `at`.
+    fn resolve(&self, schedule: &[TimeSchedule], at: DateTime<Utc>) -> Option<f64> {
+        let now = self.local_seconds(at);
+        let mut entries: Vec<(u32, f64)> = schedule
+            .iter()
+            .filter_map(|entry| Some((entry.seconds()?, entry.value)))
+            .collect();
+        entries.sort_by_key(|(seconds, _)| *seconds);
+
+        // A schedule that doesn't start at midnight carries over its last entry from the day before.
+        entries
+            .iter()
+            .rev()
+            .find(|(seconds, _)| *seconds <= now)
+            .or(entries.last())
+            .map(|(_, value)| *value)
+    }
+
+    /// Scheduled basal rate (U/h) at `at`.
+    pub fn basal_at(&self, at: DateTime<Utc>) -> Option<f64> {
+        self.resolve(&self.basal, at)
+    }
+
+    /// Insulin sensitivity factor at `at`, in the profile's units per U.
+    pub fn isf_at(&self, at: DateTime<Utc>) -> Option<f64> {
+        self.resolve(&self.sens, at)
+    }
+
+    /// Carb ratio (g/U) at `at`.
+    pub fn carb_ratio_at(&self, at: DateTime<Utc>) -> Option<f64> {
+        self.resolve(&self.carbratio, at)
+    }
+
+    /// Target range `(low, high)` at `at`, in the profile's units.
+    pub fn target_range_at(&self, at: DateTime<Utc>) -> Option<(f64, f64)> {
+        Some((
+            self.resolve(&self.target_low, at)?,
+            self.resolve(&self.target_high, at)?,
+        ))
+    }
+}
+
+/// Seconds in a day, the span every schedule must cover.
+const SECONDS_PER_DAY: u32 = 24 * 60 * 60;
+
+impl ProfileSet {
+    /// The profile named by `defaultProfile`, if it is present in the store.
+    pub fn default_profile(&self) -> Option<&ProfileConfig> {
+        self.store.get(&self.default_profile_name)
+    }
+
+    /// When the profile set became active, from `mills` or else `startDate`.
+    pub fn start(&self) -> Option<DateTime<Utc>> {
+        self.mills
+            .and_then(DateTime::from_timestamp_millis)
+            .or_else(|| {
+                DateTime::parse_from_rfc3339(&self.start_date)
+                    .ok()
+                    .map(|dt| dt.with_timezone(&Utc))
+            })
+    }
+
+    /// Checks that the profile set is safe to upload.
+    ///
+    /// The default profile must exist in the store, and every profile needs a positive DIA,
+    /// a known IANA timezone and schedules that start at 00:00 and stay within the day.
+    pub fn validate(&self) -> Result<(), NightscoutError> {
+        if !self.store.contains_key(&self.default_profile_name) {
+            return Err(NightscoutError::InvalidInput(format!(
+                "default profile '{}' is missing from the store",
+                self.default_profile_name
+            )));
+        }
+
+        for (name, config) in &self.store {
+            config.validate().map_err(|reason| {
+                NightscoutError::InvalidInput(format!("profile '{name}': {reason}"))
+            })?;
+        }
+
+        Ok(())
+    }
+}
+
+impl ProfileConfig {
+    /// A copy scaled to `percentage` of the insulin needs and shifted by `timeshift` hours.
+    ///
+    /// Basal rates are multiplied by the percentage, sensitivity and carb ratios divided by
+    /// it, as loop systems do for a Profile Switch.
+    pub fn adjusted(mut self, percentage: f64, timeshift: f64) -> Self {
+        let scale = percentage / 100.0;
+        if scale.is_finite() && scale > 0.0 && scale != 1.0 {
+            self.basal.iter_mut().for_each(|entry| entry.value *= scale);
+            self.sens.iter_mut().for_each(|entry| entry.value /= scale);
+            self.carbratio
+                .iter_mut()
+                .for_each(|entry| entry.value /= scale);
+        }
+
+        let shift = (timeshift * 3600.0).round() as i64;
+        if shift != 0 {
+            for schedule in [
+                &mut self.carbratio,
+                &mut self.sens,
+                &mut self.basal,
+                &mut self.target_low,
+                &mut self.target_high,
+            ] {
+                shift_schedule(schedule, shift);
+            }
+        }
+
+        self
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        if self.dia <= 0.0 {
+            return Err(format!("dia must be positive, got {}", self.dia));
+        }
+
+        if self.tz().is_none() {
+            return Err(format!("unknown timezone '{}'", self.timezone));
+        }
+
+        for (name, schedule) in [
+            ("carbratio", &self.carbratio),
+            ("sens", &self.sens),
+            ("basal", &self.basal),
+            ("target_low", &self.target_low),
+            ("target_high", &self.target_high),
+        ] {
+            validate_schedule(schedule).map_err(|reason| format!("{name} {reason}"))?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Moves every entry of a schedule `shift` seconds later, wrapping around midnight.
+fn shift_schedule(schedule: &mut Vec<TimeSchedule>, shift: i64) {
+    let day = SECONDS_PER_DAY as i64;
+    let mut entries: Vec<(i64, f64)> = schedule
+        .iter()
+        .filter_map(|entry| Some((entry.seconds()? as i64, entry.value)))
+        .map(|(seconds, value)| ((seconds + shift).rem_euclid(day), value))
+        .collect();
+    if entries.is_empty() {
+        return;
+    }
+    entries.sort_by_key(|(seconds, _)| *seconds);
+
+    // Keep the schedule starting at midnight with the value running across it.
+    if entries[0].0 != 0 {
+        let (_, value) = entries[entries.len() - 1];
+        entries.insert(0, (0, value));
+    }
+
+    *schedule = entries
+        .into_iter()
+        .map(|(seconds, value)| TimeSchedule {
+            time: format!("{:02}:{:02}", seconds / 3600, seconds % 3600 / 60),
+            value,
+            time_as_seconds: Some(seconds),
+        })
+        .collect();
+}
+
+fn validate_schedule(schedule: &[TimeSchedule]) -> Result<(), String> {
+    let mut previous = None;
+
+    for entry in schedule {
+        let seconds = entry
+            .seconds()
+            .filter(|seconds| *seconds < SECONDS_PER_DAY)
+            .ok_or_else(|| format!("has an invalid time '{}'", entry.time))?;
+
+        match previous {
+            None if seconds != 0 => return Err("must start at 00:00".to_string()),
+            Some(previous) if seconds <= previous => {
+                return Err(format!("is not in ascending order at '{}'", entry.time));
+            }
+            _ => previous = Some(seconds),
+        }
+    }
+
+    if previous.is_none() {
+        return Err("is empty".to_string());
+    }
+
+    Ok(())
+}
+
+/// Event type of the treatments switching the active profile.
+pub const PROFILE_SWITCH_EVENT: &str = "Profile Switch";
+
+/// A `Profile Switch` treatment, see [`Treatment::profile_switch`].
+#[derive(Debug, Clone)]
+pub struct ProfileSwitch {
+    pub start: DateTime<Utc>,
+    /// Name of the profile switched to.
+    pub name: String,
+    /// The profile embedded in the treatment by loop systems, when present.
+    pub config: Option<ProfileConfig>,
+    /// Duration in minutes, `None` for a permanent switch.
+    pub duration: Option<f64>,
+    /// Scale of the insulin needs in percent, 100 leaves the profile unchanged.
+    pub percentage: f64,
+    /// Shift of the schedules in hours, e.g. after travelling.
+    pub timeshift: f64,
+}
+
+impl ProfileSwitch {
+    /// Whether the switch is in effect at `at`.
+    pub fn is_active_at(&self, at: DateTime<Utc>) -> bool {
+        if at < self.start {
+            return false;
+        }
+        match self.duration {
+            Some(minutes) => {
+                let length = Duration::milliseconds((minutes * 60_000.0) as i64);
+                self.start
+                    .checked_add_signed(length)
+                    .is_none_or(|end| at < end)
+            }
+            None => true,
+        }
+    }
+
+    /// The profile switched to, with the percentage and timeshift applied.
+    ///
+    /// Uses the embedded profile, or else looks `name` up in `set`.
+    pub fn resolve(&self, set: Option<&ProfileSet>) -> Option<ProfileConfig> {
+        let config = self
+            .config
+            .clone()
+            .or_else(|| set?.store.get(&self.name).cloned())?;
+        Some(config.adjusted(self.percentage, self.timeshift))
+    }
+}
+
+/// The profile in effect at `at`, combining the uploaded profile sets with the Profile
+/// Switch treatments among `treatments`.
+///
+/// The most recent switch still running at `at` wins; when none is, the default profile of
+/// the profile set active at `at` applies.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// # use cinnamon::client::NightscoutClient;
+/// # use cinnamon::models::profile::{effective_profile, PROFILE_SWITCH_EVENT};
+/// # use cinnamon::query_builder::FilterOp;
+/// # use chrono::Utc;
+/// # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+/// let client = NightscoutClient::new("https://ns.example.com")?;
+/// let sets = client.profiles().get().await?;
+/// let switches = client.treatments()
+///     .get()
+///     .filter("eventType", FilterOp::Eq, PROFILE_SWITCH_EVENT)
+///     .last_days(30)
+///     .send()
+///     .await?;
+///
+/// if let Some(profile) = effective_profile(&sets, &switches, Utc::now()) {
+///     println!("Basal now: {:?} U/h", profile.basal_at(Utc::now()));
+/// }
+/// # Ok(())
+/// # }
+/// ```
+pub fn effective_profile(
+    sets: &[ProfileSet],
+    treatments: &[Treatment],
+    at: DateTime<Utc>,
+) -> Option<ProfileConfig> {
+    let set = sets
+        .iter()
+        .filter(|set| set.start().is_none_or(|start| start <= at))
+        .max_by_key(|set| set.start())
+        .or(sets.first());
+
+    let switch = treatments
+        .iter()
+        .filter_map(Treatment::profile_switch)
+        .filter(|switch| switch.is_active_at(at))
+        .max_by_key(|switch| switch.start);
+
+    switch
+        .and_then(|switch| switch.resolve(set))
+        .or_else(|| set?.default_profile().cloned())
+}
+
+/// Builds a [`ProfileSet`] and validates it before it can be uploaded.
+#[derive(Debug, Clone)]
+pub struct ProfileSetBuilder {
+    default_profile_name: String,
+    start_date: DateTime<Utc>,
+    units: Option<String>,
+    store: HashMap<String, ProfileConfig>,
+}
+
+impl ProfileSetBuilder {
+    /// Starts a profile set whose default profile is `default_profile_name`.
+    pub fn new(default_profile_name: &str) -> Self {
+        Self {
+            default_profile_name: default_profile_name.to_string(),
+            start_date: Utc::now(),
+            units: None,
+            store: HashMap::new(),
+        }
+    }
+
+    /// Sets when the profile set becomes active. Defaults to now.
+    pub fn start_date(mut self, start_date: DateTime<Utc>) -> Self {
+        self.start_date = start_date;
+        self
+    }
+
+    /// Sets the units of the profile set (e.g. "mg/dl").
+    pub fn units(mut self, units: &str) -> Self {
+        self.units = Some(units.to_string());
+        self
+    }
+
+    /// Adds a named profile to the store.
+    pub fn profile(mut self, name: &str, config: ProfileConfig) -> Self {
+        self.store.insert(name.to_string(), config);
+        self
+    }
+
+    /// Validates and returns the profile set.
+    pub fn build(self) -> Result<ProfileSet, NightscoutError> {
+        let profile = ProfileSet {
+            id: None,
+            default_profile_name: self.default_profile_name,
+            start_date: self.start_date.to_rfc3339_opts(SecondsFormat::Millis, true),
+            store: self.store,
+            mills: Some(self.start_date.timestamp_millis()),
+            units: self.units,
+            created_at: Utc::now().to_rfc3339_opts(SecondsFormat::Millis, true),
+            extra: Value::Object(Default::default()),
+        };
+
+        profile.validate()?;
+        Ok(profile)
+    }
+}
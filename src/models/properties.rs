@@ -1,14 +1,38 @@
+use crate::capabilities::Feature;
 use crate::client::NightscoutClient;
+use crate::conditional::Conditional;
 use crate::endpoints::Endpoint;
 use crate::error::NightscoutError;
+use crate::models::de::{keep_unparsed, lenient};
+use crate::models::devicestatus::{
+    LoopEnacted, LoopPredicted, LoopStatus, OpenApsDetermination, PumpStatus,
+};
+use crate::models::glucose::Glucose;
 use crate::models::treatments::Treatment;
+use crate::query_builder::HasDate;
 use chrono::{DateTime, Utc};
-use serde::{Deserialize, Serialize};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use serde_json::Value;
 use std::collections::HashMap;
 use std::fmt;
-
-#[derive(Debug, Clone, PartialEq)]
+use std::str::FromStr;
+
+/// A property of `/api/v2/properties`, named after the plugin computing it.
+///
+/// Parsing is case-insensitive and maps unknown names to [`PropertyType::Custom`], so
+/// the names of a configuration file or a foreign binding can be passed through:
+///
+/// ```rust
+/// # use cinnamon::models::properties::PropertyType;
+/// assert_eq!("IOB".parse::<PropertyType>()?, PropertyType::Iob);
+/// assert_eq!(
+///     "myplugin".parse::<PropertyType>()?,
+///     PropertyType::Custom("myplugin".to_string())
+/// );
+/// # Ok::<(), cinnamon::error::NightscoutError>(())
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub enum PropertyType {
     Iob,
     Cob,
@@ -34,6 +58,29 @@ pub enum PropertyType {
     Custom(String),
 }
 
+impl PropertyType {
+    /// The plugin that must be enabled on the server for this property to be returned.
+    pub fn feature(&self) -> Option<Feature> {
+        match self {
+            PropertyType::Iob => Some(Feature::Iob),
+            PropertyType::Cob => Some(Feature::Cob),
+            PropertyType::Pump => Some(Feature::Pump),
+            PropertyType::Basal => Some(Feature::Basal),
+            PropertyType::Bage => Some(Feature::Bage),
+            PropertyType::Cage => Some(Feature::Cage),
+            PropertyType::Iage => Some(Feature::Iage),
+            PropertyType::Sage => Some(Feature::Sage),
+            PropertyType::Upbat => Some(Feature::Upbat),
+            PropertyType::Rawbg => Some(Feature::Rawbg),
+            PropertyType::Ar2 => Some(Feature::Ar2),
+            PropertyType::Openaps => Some(Feature::Openaps),
+            PropertyType::Loop => Some(Feature::Loop),
+            PropertyType::DbSize => Some(Feature::Dbsize),
+            _ => None,
+        }
+    }
+}
+
 impl fmt::Display for PropertyType {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         let s = match self {
@@ -64,8 +111,62 @@ impl fmt::Display for PropertyType {
     }
 }
 
+impl FromStr for PropertyType {
+    type Err = NightscoutError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let name = s.trim();
+        Ok(match name.to_ascii_lowercase().as_str() {
+            "" => {
+                return Err(NightscoutError::InvalidInput(
+                    "Empty property name".to_string(),
+                ))
+            }
+            "iob" => PropertyType::Iob,
+            "cob" => PropertyType::Cob,
+            "pump" => PropertyType::Pump,
+            "basal" => PropertyType::Basal,
+            "profile" => PropertyType::Profile,
+            "bage" => PropertyType::Bage,
+            "cage" => PropertyType::Cage,
+            "iage" => PropertyType::Iage,
+            "sage" => PropertyType::Sage,
+            "upbat" => PropertyType::Upbat,
+            "rawbg" => PropertyType::Rawbg,
+            "delta" => PropertyType::Delta,
+            "direction" => PropertyType::Direction,
+            "ar2" => PropertyType::Ar2,
+            "devicestatus" => PropertyType::Devicestatus,
+            "openaps" => PropertyType::Openaps,
+            "loop" => PropertyType::Loop,
+            "bgnow" => PropertyType::BgNow,
+            "buckets" => PropertyType::Buckets,
+            "dbsize" => PropertyType::DbSize,
+            "runtimestate" => PropertyType::RuntimeState,
+            _ => PropertyType::Custom(name.to_string()),
+        })
+    }
+}
+
+impl Serialize for PropertyType {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for PropertyType {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        s.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+/// The `source` of properties computed by [`PropertiesRequest::local_fallback`].
+const LOCAL_SOURCE: &str = "cinnamon";
+
 /// The main response object for /api/v2/properties
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(remote = "Self")]
 pub struct Properties {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub bgnow: Option<BgNow>,
@@ -97,11 +198,73 @@ pub struct Properties {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub runtimestate: Option<RuntimeState>,
 
-    /// Captures any other fields (like "pump" or custom plugins) generically
+    #[serde(
+        default,
+        deserialize_with = "lenient",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub pump: Option<PumpProperty>,
+
+    #[serde(
+        default,
+        rename = "loop",
+        deserialize_with = "lenient",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub loop_: Option<LoopProperty>,
+
+    #[serde(
+        default,
+        deserialize_with = "lenient",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub openaps: Option<OpenApsProperty>,
+
+    #[serde(
+        default,
+        deserialize_with = "lenient",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub ar2: Option<Ar2Property>,
+
+    /// Captures the properties of custom plugins generically
     #[serde(flatten)]
     pub unknown: HashMap<String, Value>,
 }
 
+keep_unparsed!(
+    Properties,
+    unknown,
+    [
+        "pump" => pump,
+        "loop" => loop_,
+        "openaps" => openaps,
+        "ar2" => ar2,
+    ]
+);
+
+impl Properties {
+    /// Insulin on board (U), if the IOB plugin returned data.
+    pub fn active_insulin(&self) -> Option<f64> {
+        self.iob.as_ref().map(|iob| iob.iob)
+    }
+
+    /// Current insulin activity (U/min), if the IOB plugin returned data.
+    pub fn insulin_activity(&self) -> Option<f64> {
+        self.iob.as_ref().map(|iob| iob.activity)
+    }
+
+    /// Carbs on board (g), if the COB plugin returned data.
+    pub fn carbs_remaining(&self) -> Option<f64> {
+        self.cob.as_ref().map(|cob| cob.cob)
+    }
+
+    /// The latest glucose reading (mg/dL), if the bgnow plugin returned data.
+    pub fn current_bg(&self) -> Option<f64> {
+        self.bgnow.as_ref().map(|bg| bg.last)
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct BgNow {
     pub mean: f64,
@@ -110,6 +273,13 @@ pub struct BgNow {
     pub sgvs: Vec<PropertySgv>,
 }
 
+impl BgNow {
+    /// The last reading as a unit-aware [`Glucose`] value.
+    pub fn glucose(&self) -> Glucose {
+        Glucose::Mgdl(self.last)
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Delta {
     pub absolute: f64,
@@ -123,6 +293,13 @@ pub struct Delta {
     pub display: String,
 }
 
+impl Delta {
+    /// The change between readings as a unit-aware [`Glucose`] value.
+    pub fn glucose(&self) -> Glucose {
+        Glucose::Mgdl(self.mgdl)
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Bucket {
     pub mean: f64,
@@ -150,6 +327,13 @@ pub struct PropertySgv {
     pub scaled: f64,
 }
 
+impl PropertySgv {
+    /// The reading as a unit-aware [`Glucose`] value.
+    pub fn glucose(&self) -> Glucose {
+        Glucose::Mgdl(self.mgdl)
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Direction {
     pub display: Option<String>,
@@ -177,6 +361,19 @@ pub struct IobProperty {
     pub last_bolus: Option<Treatment>,
 }
 
+impl IobProperty {
+    /// The time of the last bolus, if the server reported one.
+    pub fn last_bolus_time(&self) -> Option<DateTime<Utc>> {
+        self.last_bolus.as_ref().and_then(|bolus| bolus.timestamp())
+    }
+
+    /// Whole minutes elapsed since the last bolus, if the server reported one.
+    pub fn minutes_since_last_bolus(&self) -> Option<i64> {
+        self.last_bolus_time()
+            .map(|time| (Utc::now() - time).num_minutes())
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Cob {
     pub cob: f64,
@@ -245,6 +442,75 @@ impl PropertiesService {
     pub fn get(&self) -> PropertiesRequest {
         PropertiesRequest::new(self.client.clone())
     }
+
+    /// Current insulin on board (U).
+    ///
+    /// Read from the server's IOB plugin when it is enabled, otherwise computed locally
+    /// from the treatments with [`crate::analysis::AnalysisService::iob_at`].
+    pub async fn iob(&self) -> Result<f64, NightscoutError> {
+        if self.client.capabilities().await?.has(&Feature::Iob) {
+            let properties = self.get().only(&[PropertyType::Iob]).send().await?;
+            return properties.active_insulin().ok_or(NightscoutError::NotFound);
+        }
+
+        Ok(self.client.analysis().iob_at(Utc::now()).await?.iob)
+    }
+
+    /// Current carbs on board (g).
+    ///
+    /// Read from the server's COB plugin when it is enabled, otherwise computed locally
+    /// from the treatments with [`crate::analysis::AnalysisService::cob_at`].
+    pub async fn cob(&self) -> Result<f64, NightscoutError> {
+        if self.client.capabilities().await?.has(&Feature::Cob) {
+            let properties = self.get().only(&[PropertyType::Cob]).send().await?;
+            return properties
+                .carbs_remaining()
+                .ok_or(NightscoutError::NotFound);
+        }
+
+        Ok(self.client.analysis().cob_at(Utc::now()).await?.cob)
+    }
+
+    /// Fetches a single property and deserializes it into `T`, typically the property of
+    /// a third-party plugin that cinnamon does not model.
+    ///
+    /// Returns [`NightscoutError::NotFound`] when the server does not return the property,
+    /// e.g. because the plugin is not enabled.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// # use cinnamon::client::NightscoutClient;
+    /// # use cinnamon::models::properties::PropertyType;
+    /// #[derive(serde::Deserialize)]
+    /// struct Sensor {
+    ///     display: String,
+    ///     days: f64,
+    /// }
+    ///
+    /// # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+    /// let client = NightscoutClient::new("https://ns.example.com")?;
+    /// let sensor: Sensor = client
+    ///     .properties()
+    ///     .get_as(PropertyType::Custom("mysensorplugin".to_string()))
+    ///     .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn get_as<T: DeserializeOwned>(
+        &self,
+        property: PropertyType,
+    ) -> Result<T, NightscoutError> {
+        let name = property.to_string();
+        let url = self.get().only(&[property]).url().await?;
+        let mut properties: HashMap<String, Value> = self.client.fetch(url).await?;
+
+        let value = properties
+            .remove(&name)
+            .filter(|value| !value.is_null())
+            .ok_or(NightscoutError::NotFound)?;
+        Ok(serde_json::from_value(value)?)
+    }
 }
 
 /// A builder for constructing a properties request.
@@ -252,6 +518,8 @@ pub struct PropertiesRequest {
     client: NightscoutClient,
     requested_properties: Vec<PropertyType>,
     at_time: Option<DateTime<Utc>>,
+    require_enabled: bool,
+    local_fallback: bool,
 }
 
 impl PropertiesRequest {
@@ -260,6 +528,8 @@ impl PropertiesRequest {
             client,
             requested_properties: Vec::new(),
             at_time: None,
+            require_enabled: false,
+            local_fallback: false,
         }
     }
 
@@ -297,14 +567,123 @@ impl PropertiesRequest {
 
     /// Requests the system state as it was at a specific time.
     ///
-    /// If omitted, the current system state is returned.
+    /// If omitted, the current system state is returned. Servers older than Nightscout 15
+    /// ignore the time and would return the current state, so the request fails with
+    /// [`NightscoutError::Unsupported`] on them, unless [`local_fallback`] is set.
+    ///
+    /// [`local_fallback`]: Self::local_fallback
     pub fn at(mut self, time: DateTime<Utc>) -> Self {
         self.at_time = Some(time);
         self
     }
 
+    /// When the server cannot answer [`at`](Self::at), computes IOB and COB at that
+    /// time locally from the treatments instead, with the default models of
+    /// [`AnalysisService`](crate::analysis::AnalysisService). Other properties are
+    /// left empty.
+    pub fn local_fallback(mut self) -> Self {
+        self.local_fallback = true;
+        self
+    }
+
+    /// Fails with [`NightscoutError::FeatureNotEnabled`] when a requested property comes
+    /// from a plugin that is disabled on the server, instead of silently omitting it.
+    pub fn require_enabled(mut self) -> Self {
+        self.require_enabled = true;
+        self
+    }
+
     /// Executes the request.
     pub async fn send(self) -> Result<Properties, NightscoutError> {
+        if let Some(properties) = self.unsupported_at().await? {
+            return Ok(properties);
+        }
+        let url = self.url().await?;
+        self.client.fetch::<Properties>(url).await
+    }
+
+    /// Executes the request conditionally, returning [`Conditional::NotModified`] when the
+    /// properties did not change since the previous identical request.
+    pub async fn send_if_modified(self) -> Result<Conditional<Properties>, NightscoutError> {
+        if let Some(properties) = self.unsupported_at().await? {
+            return Ok(Conditional::Modified(properties));
+        }
+        let url = self.url().await?;
+        self.client.fetch_conditional(url).await
+    }
+
+    /// Handles a time the server cannot answer: the locally computed properties with
+    /// [`local_fallback`](Self::local_fallback), an error otherwise. `None` when the
+    /// server can answer, or when its version is unknown and the request is sent anyway.
+    async fn unsupported_at(&self) -> Result<Option<Properties>, NightscoutError> {
+        let Some(time) = self.at_time else {
+            return Ok(None);
+        };
+        match self.client.capabilities().await {
+            Ok(capabilities) => match capabilities.supports_properties_at() {
+                Some(true) => return Ok(None),
+                Some(false) => {}
+                None => {
+                    #[cfg(feature = "tracing")]
+                    tracing::warn!("unknown server version, properties may ignore the time");
+                    return Ok(None);
+                }
+            },
+            Err(_e) => {
+                #[cfg(feature = "tracing")]
+                tracing::warn!(
+                    error = %_e,
+                    "server version unavailable, properties may ignore the time"
+                );
+                return Ok(None);
+            }
+        }
+        if !self.local_fallback {
+            return Err(NightscoutError::Unsupported(
+                "properties at a past time need Nightscout 15 or later".to_string(),
+            ));
+        }
+
+        let wanted = |property: PropertyType| {
+            self.requested_properties.is_empty() || self.requested_properties.contains(&property)
+        };
+        let analysis = self.client.analysis();
+        let mut properties = Properties::default();
+
+        if wanted(PropertyType::Iob) {
+            let iob = analysis.iob_at(time).await?;
+            properties.iob = Some(IobProperty {
+                iob: iob.iob,
+                activity: iob.activity,
+                source: LOCAL_SOURCE.to_string(),
+                display: format!("{:.2}", iob.iob),
+                display_line: format!("IOB: {:.2}U", iob.iob),
+                last_bolus: None,
+            });
+        }
+        if wanted(PropertyType::Cob) {
+            let cob = analysis.cob_at(time).await?;
+            properties.cob = Some(Cob {
+                cob: cob.cob,
+                is_decaying: 0,
+                decayed_by: String::new(),
+                source: LOCAL_SOURCE.to_string(),
+                display: Value::from(cob.cob.round()),
+                display_line: format!("COB: {}g", cob.cob.round()),
+            });
+        }
+
+        Ok(Some(properties))
+    }
+
+    async fn url(&self) -> Result<url::Url, NightscoutError> {
+        if self.require_enabled {
+            let capabilities = self.client.capabilities().await?;
+            for feature in self.requested_properties.iter().filter_map(|p| p.feature()) {
+                capabilities.require(feature)?;
+            }
+        }
+
         let base_path = Endpoint::Properties.as_path();
 
         let path = if self.requested_properties.is_empty() {
@@ -326,7 +705,308 @@ impl PropertiesRequest {
                 .append_pair("time", &time.to_rfc3339());
         }
 
-        let data = self.client.fetch::<Properties>(url).await?;
-        Ok(data)
+        Ok(url)
     }
 }
+
+/// The `pump` property: the latest device status with a pump block, and its values
+/// checked against the pump plugin's thresholds in `data`.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(remote = "Self")]
+pub struct PumpProperty {
+    #[serde(
+        default,
+        deserialize_with = "lenient",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub data: Option<PumpPropertyData>,
+
+    /// The raw pump block of the device status.
+    #[serde(
+        default,
+        deserialize_with = "lenient",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub pump: Option<PumpStatus>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub device: Option<String>,
+
+    #[serde(flatten)]
+    pub extra: Value,
+}
+
+keep_unparsed!(PumpProperty, extra, ["data" => data, "pump" => pump]);
+
+impl PumpProperty {
+    /// Remaining insulin in the reservoir (U).
+    pub fn reservoir(&self) -> Option<f64> {
+        self.data
+            .as_ref()?
+            .reservoir
+            .as_ref()?
+            .value
+            .as_ref()?
+            .as_f64()
+    }
+
+    /// Pump battery, in the unit of [`PumpPropertyData::battery`] (% or V).
+    pub fn battery(&self) -> Option<f64> {
+        self.data
+            .as_ref()?
+            .battery
+            .as_ref()?
+            .value
+            .as_ref()?
+            .as_f64()
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(remote = "Self")]
+pub struct PumpPropertyData {
+    /// The most severe level of the values: 0 none, 1 info, 2 warn, 3 urgent.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub level: Option<i32>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub manufacturer: Option<String>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+
+    #[serde(
+        default,
+        deserialize_with = "lenient",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub clock: Option<PumpValue>,
+
+    #[serde(
+        default,
+        deserialize_with = "lenient",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub reservoir: Option<PumpValue>,
+
+    #[serde(
+        default,
+        deserialize_with = "lenient",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub battery: Option<PumpValue>,
+
+    #[serde(
+        default,
+        deserialize_with = "lenient",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub status: Option<PumpValue>,
+
+    #[serde(flatten)]
+    pub extra: Value,
+}
+
+keep_unparsed!(
+    PumpPropertyData,
+    extra,
+    [
+        "clock" => clock,
+        "reservoir" => reservoir,
+        "battery" => battery,
+        "status" => status,
+    ]
+);
+
+/// A value of the pump plugin with its display text and alert level.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct PumpValue {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub value: Option<Value>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub display: Option<String>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub level: Option<i32>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+
+    /// `%` or `v` for the battery.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub unit: Option<String>,
+}
+
+/// The state symbol shown in the pill of the loop and openaps plugins.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct LoopDisplay {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub symbol: Option<String>,
+
+    /// e.g. `"enacted"`, `"looping"`, `"waiting"` or `"warning"`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub code: Option<String>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+}
+
+/// The `loop` property: the latest Loop status, enactment and forecast.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(remote = "Self")]
+pub struct LoopProperty {
+    #[serde(
+        default,
+        rename = "lastLoop",
+        deserialize_with = "lenient",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub last_loop: Option<LoopStatus>,
+
+    #[serde(
+        default,
+        rename = "lastEnacted",
+        deserialize_with = "lenient",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub last_enacted: Option<LoopEnacted>,
+
+    #[serde(
+        default,
+        rename = "lastPredicted",
+        deserialize_with = "lenient",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub last_predicted: Option<LoopPredicted>,
+
+    #[serde(
+        default,
+        rename = "lastOkMoment",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub last_ok_moment: Option<String>,
+
+    #[serde(
+        default,
+        deserialize_with = "lenient",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub display: Option<LoopDisplay>,
+
+    #[serde(flatten)]
+    pub extra: Value,
+}
+
+keep_unparsed!(
+    LoopProperty,
+    extra,
+    [
+        "lastLoop" => last_loop,
+        "lastEnacted" => last_enacted,
+        "lastPredicted" => last_predicted,
+        "display" => display,
+    ]
+);
+
+/// The `openaps` property: the latest determinations of OpenAPS, AndroidAPS or Trio.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(remote = "Self")]
+pub struct OpenApsProperty {
+    #[serde(
+        default,
+        rename = "lastEnacted",
+        deserialize_with = "lenient",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub last_enacted: Option<OpenApsDetermination>,
+
+    #[serde(
+        default,
+        rename = "lastNotEnacted",
+        deserialize_with = "lenient",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub last_not_enacted: Option<OpenApsDetermination>,
+
+    #[serde(
+        default,
+        rename = "lastSuggested",
+        deserialize_with = "lenient",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub last_suggested: Option<OpenApsDetermination>,
+
+    #[serde(default, rename = "lastIOB", skip_serializing_if = "Option::is_none")]
+    pub last_iob: Option<Value>,
+
+    #[serde(
+        default,
+        deserialize_with = "lenient",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub status: Option<LoopDisplay>,
+
+    #[serde(flatten)]
+    pub extra: Value,
+}
+
+keep_unparsed!(
+    OpenApsProperty,
+    extra,
+    [
+        "lastEnacted" => last_enacted,
+        "lastNotEnacted" => last_not_enacted,
+        "lastSuggested" => last_suggested,
+        "status" => status,
+    ]
+);
+
+/// The `ar2` property: the server's AR2 forecast and the alarm it raised.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(remote = "Self")]
+pub struct Ar2Property {
+    #[serde(
+        default,
+        deserialize_with = "lenient",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub forecast: Option<Ar2PropertyForecast>,
+
+    /// The alarm level: 0 none, 1 info, 2 warn, 3 urgent.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub level: Option<i32>,
+
+    #[serde(default, rename = "eventName", skip_serializing_if = "Option::is_none")]
+    pub event_name: Option<String>,
+
+    #[serde(
+        default,
+        rename = "displayLine",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub display_line: Option<String>,
+
+    #[serde(flatten)]
+    pub extra: Value,
+}
+
+keep_unparsed!(Ar2Property, extra, ["forecast" => forecast]);
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct Ar2PropertyForecast {
+    #[serde(default)]
+    pub predicted: Vec<Ar2PropertyPoint>,
+
+    #[serde(default, rename = "avgLoss", skip_serializing_if = "Option::is_none")]
+    pub avg_loss: Option<f64>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Ar2PropertyPoint {
+    pub mills: i64,
+    pub mgdl: f64,
+}
@@ -1,8 +1,14 @@
+use crate::capabilities::Feature;
 use crate::client::NightscoutClient;
 use crate::endpoints::Endpoint;
 use crate::error::NightscoutError;
-use serde::{Deserialize, Serialize};
+use crate::models::de::{keep_unparsed, lenient};
+use crate::models::glucose::GlucoseUnit;
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use serde_json::Value;
+use std::collections::HashSet;
+use std::fmt;
 
 pub struct StatusService {
     pub client: NightscoutClient,
@@ -26,7 +32,7 @@ impl StatusService {
     /// # }
     /// ```
     pub async fn get(&self) -> Result<Status, NightscoutError> {
-        let url = self.client.base_url.join(Endpoint::Status.as_path())?;
+        let url = self.client.endpoint_url(Endpoint::Status)?;
         self.client.fetch::<Status>(url).await
     }
 }
@@ -66,21 +72,117 @@ pub struct Status {
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub authorized: Option<bool>,
 
+    /// Whether the server has finished starting up, missing on older servers.
     #[serde(
         default,
         rename = "runtimeState",
         skip_serializing_if = "Option::is_none"
     )]
-    pub runtime_state: Option<String>,
+    pub runtime_state: Option<ServerRuntimeState>,
 
     #[serde(flatten)]
     pub extra: Value,
 }
 
+impl Status {
+    /// Whether the server has finished booting and answers queries. Servers not reporting
+    /// their runtime state are taken as ready.
+    pub fn is_ready(&self) -> bool {
+        self.runtime_state
+            .as_ref()
+            .is_none_or(ServerRuntimeState::is_ready)
+    }
+
+    /// The server clock when the status was generated.
+    pub fn server_datetime(&self) -> Option<DateTime<Utc>> {
+        DateTime::from_timestamp_millis(self.server_time_epoch)
+    }
+
+    /// How far the server clock is ahead of the local one, positive when it runs ahead.
+    ///
+    /// `sent` and `received` are the local times around the status request, the server
+    /// time is compared with their midpoint to cancel out the network delay.
+    pub fn clock_offset(&self, sent: DateTime<Utc>, received: DateTime<Utc>) -> Option<Duration> {
+        let midpoint = sent + (received - sent) / 2;
+        Some(self.server_datetime()? - midpoint)
+    }
+}
+
+/// The start-up stage of a Nightscout server, reported as `runtimeState`.
+///
+/// Right after a restart the server answers while it is still connecting to the database,
+/// and queries fail or come back empty until it reports `booted`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum ServerRuntimeState {
+    /// Connecting to the database and setting up plugins.
+    Booting,
+    /// Started, the first data load is in progress.
+    Booted,
+    /// The data has been loaded.
+    Loaded,
+    Other(String),
+}
+
+impl ServerRuntimeState {
+    pub fn as_str(&self) -> &str {
+        match self {
+            ServerRuntimeState::Booting => "booting",
+            ServerRuntimeState::Booted => "booted",
+            ServerRuntimeState::Loaded => "loaded",
+            ServerRuntimeState::Other(s) => s.as_str(),
+        }
+    }
+
+    /// Whether the server has booted and answers queries, `booted` or `loaded`.
+    pub fn is_ready(&self) -> bool {
+        matches!(
+            self,
+            ServerRuntimeState::Booted | ServerRuntimeState::Loaded
+        )
+    }
+}
+
+impl From<&str> for ServerRuntimeState {
+    fn from(s: &str) -> Self {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "booting" => ServerRuntimeState::Booting,
+            "booted" => ServerRuntimeState::Booted,
+            "loaded" => ServerRuntimeState::Loaded,
+            other => ServerRuntimeState::Other(other.to_string()),
+        }
+    }
+}
+
+impl fmt::Display for ServerRuntimeState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl Serialize for ServerRuntimeState {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+impl<'de> Deserialize<'de> for ServerRuntimeState {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        Ok(ServerRuntimeState::from(s.as_str()))
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(remote = "Self")]
 pub struct StatusSettings {
-    #[serde(default, skip_serializing_if = "Option::is_none")]
-    pub units: Option<String>,
+    /// The display unit of the site, `None` if missing or not one Nightscout knows.
+    #[serde(
+        default,
+        deserialize_with = "lenient",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub units: Option<GlucoseUnit>,
 
     #[serde(
         default,
@@ -227,12 +329,15 @@ pub struct StatusSettings {
     #[serde(default, rename = "scaleY", skip_serializing_if = "Option::is_none")]
     pub scale_y: Option<String>,
 
+    /// The plugins shown in the pills of the main page, sent by Nightscout as a
+    /// space-separated string.
     #[serde(
         default,
         rename = "showPlugins",
+        with = "space_separated",
         skip_serializing_if = "Option::is_none"
     )]
-    pub show_plugins: Option<String>,
+    pub show_plugins: Option<HashSet<Feature>>,
 
     #[serde(
         default,
@@ -258,8 +363,13 @@ pub struct StatusSettings {
     )]
     pub default_features: Option<Vec<String>>,
 
-    #[serde(default, skip_serializing_if = "Option::is_none")]
-    pub enable: Option<Vec<String>>,
+    /// The plugins of the `ENABLE` setting, see [`crate::capabilities::Capabilities`].
+    #[serde(
+        default,
+        deserialize_with = "lenient",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub enable: Option<HashSet<Feature>>,
 
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub thresholds: Option<StatusThresholds>,
@@ -267,9 +377,10 @@ pub struct StatusSettings {
     #[serde(
         default,
         rename = "alarmTypes",
+        deserialize_with = "lenient",
         skip_serializing_if = "Option::is_none"
     )]
-    pub alarm_types: Option<Vec<String>>,
+    pub alarm_types: Option<HashSet<AlarmType>>,
 
     #[serde(
         default,
@@ -428,6 +539,120 @@ pub struct StatusSettings {
     pub extra: Value,
 }
 
+keep_unparsed!(
+    StatusSettings,
+    extra,
+    [
+        "units" => units,
+        "enable" => enable,
+        "alarmTypes" => alarm_types,
+    ]
+);
+
+impl StatusSettings {
+    /// Whether the plugin is listed in the `ENABLE` setting.
+    pub fn is_enabled(&self, feature: &Feature) -> bool {
+        self.enable
+            .as_ref()
+            .is_some_and(|enabled| enabled.contains(feature))
+    }
+
+    /// Whether the plugin is shown on the main page. Nightscout shows every enabled
+    /// plugin when `showPlugins` is not set.
+    pub fn is_shown(&self, feature: &Feature) -> bool {
+        match &self.show_plugins {
+            Some(shown) => shown.contains(feature),
+            None => self.is_enabled(feature),
+        }
+    }
+}
+
+/// How Nightscout decides to raise the high and low glucose alarms (`ALARM_TYPES`).
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum AlarmType {
+    /// On crossing the thresholds.
+    Simple,
+    /// On the AR2 forecast crossing the thresholds.
+    Predict,
+    Custom(String),
+}
+
+impl AlarmType {
+    pub fn as_str(&self) -> &str {
+        match self {
+            AlarmType::Simple => "simple",
+            AlarmType::Predict => "predict",
+            AlarmType::Custom(s) => s.as_str(),
+        }
+    }
+}
+
+impl From<&str> for AlarmType {
+    fn from(s: &str) -> Self {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "simple" => AlarmType::Simple,
+            "predict" => AlarmType::Predict,
+            other => AlarmType::Custom(other.to_string()),
+        }
+    }
+}
+
+impl fmt::Display for AlarmType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl Serialize for AlarmType {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+impl<'de> Deserialize<'de> for AlarmType {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        Ok(AlarmType::from(s.as_str()))
+    }
+}
+
+/// (De)serializes a set of plugins written as a space-separated string. A list of names
+/// is accepted too, and any other value maps to `None`.
+mod space_separated {
+    use super::*;
+
+    pub(super) fn serialize<S: Serializer>(
+        features: &Option<HashSet<Feature>>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        let Some(features) = features else {
+            return serializer.serialize_none();
+        };
+        let mut names: Vec<&str> = features.iter().map(Feature::as_str).collect();
+        names.sort_unstable();
+        serializer.serialize_str(&names.join(" "))
+    }
+
+    pub(super) fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<HashSet<Feature>>, D::Error> {
+        Ok(match Option::<Value>::deserialize(deserializer)? {
+            Some(Value::String(names)) => {
+                Some(names.split_whitespace().map(Feature::from).collect())
+            }
+            Some(Value::Array(names)) => Some(
+                names
+                    .iter()
+                    .filter_map(Value::as_str)
+                    .map(Feature::from)
+                    .collect(),
+            ),
+            _ => None,
+        })
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct StatusThresholds {
     #[serde(default, rename = "bgHigh", skip_serializing_if = "Option::is_none")]
@@ -1,10 +1,22 @@
+use chrono::{DateTime, Utc};
+use futures::Stream;
 use reqwest::Method;
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::time::Duration;
 
+use crate::bulk::BulkRequest;
 use crate::client::NightscoutClient;
 use crate::endpoints::Endpoint;
 use crate::error::NightscoutError;
-use crate::query_builder::{HasDevice, QueryBuilder};
+use crate::models::de::datetime;
+use crate::models::glucose::Glucose;
+use crate::models::profile::{ProfileConfig, ProfileSwitch, PROFILE_SWITCH_EVENT};
+use crate::query_builder::{HasDate, HasDevice, QueryBuilder};
+use crate::watch;
+
+/// Maximum number of treatments fetched per poll by [`TreatmentsService::watch`].
+const WATCH_BATCH_SIZE: usize = 100;
 
 #[derive(Debug, Deserialize)]
 pub struct IobWrapper {
@@ -30,8 +42,8 @@ pub struct Treatment {
     #[serde(rename = "eventType")]
     pub event_type: String,
 
-    #[serde(rename = "created_at")]
-    pub created_at: String,
+    #[serde(rename = "created_at", with = "datetime")]
+    pub created_at: DateTime<Utc>,
 
     #[serde(skip_serializing_if = "Option::is_none")]
     pub glucose: Option<f64>,
@@ -48,19 +60,389 @@ pub struct Treatment {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub units: Option<String>,
 
+    /// Duration in minutes (temp basals, temp targets, extended boluses).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub duration: Option<f64>,
+
+    /// Temp basal change relative to the scheduled rate, in percent (e.g. -50).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub percent: Option<f64>,
+
+    /// Absolute temp basal rate (U/h).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub absolute: Option<f64>,
+
+    /// Temp basal rate (U/h), as reported by some uploaders instead of `absolute`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rate: Option<f64>,
+
+    /// Rate added to the scheduled basal (U/h), for temp basals and the extended part of
+    /// combo boluses.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub relative: Option<f64>,
+
+    /// Upper bound of a temporary target, in the treatment's `units`.
+    #[serde(rename = "targetTop", default, skip_serializing_if = "Option::is_none")]
+    pub target_top: Option<f64>,
+
+    /// Lower bound of a temporary target, in the treatment's `units`.
+    #[serde(
+        rename = "targetBottom",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub target_bottom: Option<f64>,
+
+    /// Share of a combo bolus delivered immediately, in percent.
+    #[serde(rename = "splitNow", default, skip_serializing_if = "Option::is_none")]
+    pub split_now: Option<f64>,
+
+    /// Share of a combo bolus delivered over `duration`, in percent.
+    #[serde(rename = "splitExt", default, skip_serializing_if = "Option::is_none")]
+    pub split_ext: Option<f64>,
+
+    /// Name of the profile switched to, for Profile Switch treatments.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub profile: Option<String>,
+
+    /// The profile switched to as a JSON string, written by loop systems along with
+    /// `profile`, see [`Treatment::profile_switch`].
+    #[serde(
+        rename = "profileJson",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub profile_json: Option<String>,
+
     #[serde(skip_serializing_if = "Option::is_none")]
     pub notes: Option<String>,
 
     #[serde(rename = "enteredBy", skip_serializing_if = "Option::is_none")]
     pub entered_by: Option<String>,
+
+    /// Fields without a dedicated member, such as those written by loop systems, kept so
+    /// that uploads round-trip unchanged.
+    #[serde(flatten)]
+    pub extra: Value,
+}
+
+/// A temporary target, see [`Treatment::temp_target`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TempTarget {
+    pub top: f64,
+    pub bottom: f64,
+    /// Duration in minutes, zero when the treatment cancels the running target.
+    pub duration: f64,
+}
+
+impl TempTarget {
+    /// Whether this target cancels the running one rather than setting a new one.
+    pub fn is_cancel(&self) -> bool {
+        self.duration <= 0.0
+    }
+}
+
+/// A bolus split between an immediate and an extended part, see
+/// [`Treatment::combo_bolus`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ComboBolus {
+    /// Insulin delivered immediately (U).
+    pub immediate: f64,
+    /// Insulin delivered over `duration` (U).
+    pub extended: f64,
+    /// Duration of the extended part in minutes.
+    pub duration: f64,
+}
+
+impl ComboBolus {
+    /// Total insulin of the bolus (U).
+    pub fn total(&self) -> f64 {
+        self.immediate + self.extended
+    }
+
+    /// Delivery rate of the extended part (U/h).
+    pub fn extended_rate(&self) -> Option<f64> {
+        (self.duration > 0.0).then(|| self.extended / self.duration * 60.0)
+    }
+}
+
+/// Default `enteredBy` of treatments created with [`TreatmentBuilder`].
+const DEFAULT_ENTERED_BY: &str = "cinnamon";
+
+impl Treatment {
+    /// Starts an insulin bolus of `units`, recorded as a `Correction Bolus`, or a
+    /// `Meal Bolus` once carbs are added.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// # use cinnamon::client::NightscoutClient;
+    /// # use cinnamon::models::treatments::Treatment;
+    /// # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+    /// let client = NightscoutClient::new("https://ns.example.com")?.with_secret("secret");
+    /// let meal = Treatment::bolus(4.5).carbs(45.0).notes("Lunch").build()?;
+    /// client.treatments().create(vec![meal]).await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn bolus(units: f64) -> TreatmentBuilder {
+        TreatmentBuilder::new("Correction Bolus").insulin(units)
+    }
+
+    /// Starts a `Carb Correction` of `grams`, or a `Meal Bolus` once insulin is added.
+    pub fn carbs(grams: f64) -> TreatmentBuilder {
+        TreatmentBuilder::new("Carb Correction").carbs(grams)
+    }
+
+    /// Starts a `Note` holding `text`.
+    pub fn note(text: impl Into<String>) -> TreatmentBuilder {
+        TreatmentBuilder::new("Note").notes(text)
+    }
+
+    /// The temporary target set or cancelled by a `Temporary Target` treatment.
+    pub fn temp_target(&self) -> Option<TempTarget> {
+        if self.event_type != "Temporary Target" {
+            return None;
+        }
+
+        let duration = self.duration.unwrap_or(0.0);
+        match (self.target_top, self.target_bottom) {
+            (Some(top), Some(bottom)) => Some(TempTarget {
+                top,
+                bottom,
+                duration,
+            }),
+            // Cancellations often carry no bounds at all.
+            _ if duration <= 0.0 => Some(TempTarget {
+                top: 0.0,
+                bottom: 0.0,
+                duration: 0.0,
+            }),
+            _ => None,
+        }
+    }
+
+    /// The split of a `Combo Bolus` treatment.
+    ///
+    /// Nightscout records the immediate part as `insulin` and the extended part as a
+    /// `relative` rate over `duration`. When the rate is missing, the extended part is
+    /// derived from the split percentages.
+    pub fn combo_bolus(&self) -> Option<ComboBolus> {
+        if self.event_type != "Combo Bolus" {
+            return None;
+        }
+
+        let duration = self.duration.unwrap_or(0.0);
+        let immediate = self.insulin.unwrap_or(0.0);
+        let extended = self
+            .relative
+            .map(|rate| rate * duration / 60.0)
+            .or_else(|| {
+                let ext = self.split_ext? / 100.0;
+                let entered = self.extra.get("enteredinsulin").and_then(Value::as_f64);
+                match (entered, self.split_now) {
+                    (Some(total), _) => Some(total * ext),
+                    (None, Some(now)) if now > 0.0 => Some(immediate / (now / 100.0) * ext),
+                    _ => None,
+                }
+            })
+            .unwrap_or(0.0);
+
+        Some(ComboBolus {
+            immediate,
+            extended,
+            duration,
+        })
+    }
+
+    /// The switch described by a `Profile Switch` treatment.
+    ///
+    /// The embedded profile is read from `profileJson`, or from `profile` when an uploader
+    /// stored the JSON there instead of the name.
+    pub fn profile_switch(&self) -> Option<ProfileSwitch> {
+        if self.event_type != PROFILE_SWITCH_EVENT {
+            return None;
+        }
+
+        let embedded = |json: &str| serde_json::from_str::<ProfileConfig>(json).ok();
+        let (name, config) = match self.profile.as_deref() {
+            Some(profile) if profile.trim_start().starts_with('{') => (None, embedded(profile)),
+            profile => (
+                profile.map(str::to_string),
+                self.profile_json.as_deref().and_then(embedded),
+            ),
+        };
+
+        let number = |key: &str| self.extra.get(key).and_then(Value::as_f64);
+        Some(ProfileSwitch {
+            start: self.created_at,
+            name: name.unwrap_or_default(),
+            config,
+            duration: self.duration.filter(|minutes| *minutes > 0.0),
+            percentage: number("percentage").unwrap_or(100.0),
+            timeshift: number("timeshift").unwrap_or(0.0),
+        })
+    }
+
+    /// Checks that the treatment makes sense before it is uploaded.
+    ///
+    /// Amounts must be finite and not negative, a bolus needs insulin, a carb correction
+    /// needs carbs and a note needs text.
+    pub fn validate(&self) -> Result<(), NightscoutError> {
+        let invalid = |reason: String| Err(NightscoutError::InvalidInput(reason));
+
+        if self.event_type.trim().is_empty() {
+            return invalid("eventType is empty".to_string());
+        }
+
+        for (name, value) in [
+            ("insulin", self.insulin),
+            ("carbs", self.carbs),
+            ("duration", self.duration),
+        ] {
+            if let Some(value) = value {
+                if !value.is_finite() || value < 0.0 {
+                    return invalid(format!("{name} must be a non-negative amount, got {value}"));
+                }
+            }
+        }
+
+        let required = match self.event_type.as_str() {
+            "Correction Bolus" => Some(("insulin", self.insulin.is_some())),
+            "Carb Correction" => Some(("carbs", self.carbs.is_some())),
+            "Meal Bolus" => Some((
+                "insulin or carbs",
+                self.insulin.is_some() || self.carbs.is_some(),
+            )),
+            "Note" => Some((
+                "notes",
+                self.notes.as_deref().is_some_and(|n| !n.trim().is_empty()),
+            )),
+            _ => None,
+        };
+        if let Some((field, false)) = required {
+            return invalid(format!("a {} requires {}", self.event_type, field));
+        }
+
+        Ok(())
+    }
+}
+
+/// Builds a [`Treatment`] with sensible defaults, created by [`Treatment::bolus`],
+/// [`Treatment::carbs`] and [`Treatment::note`].
+///
+/// The treatment is dated now and entered by `cinnamon` unless told otherwise, and is
+/// validated by [`TreatmentBuilder::build`].
+#[derive(Debug, Clone)]
+pub struct TreatmentBuilder {
+    treatment: Treatment,
+}
+
+impl TreatmentBuilder {
+    /// Starts a treatment of any `eventType`.
+    pub fn new(event_type: &str) -> Self {
+        Self {
+            treatment: Treatment {
+                id: None,
+                event_type: event_type.to_string(),
+                created_at: Utc::now(),
+                glucose: None,
+                glucose_type: None,
+                carbs: None,
+                insulin: None,
+                units: None,
+                duration: None,
+                percent: None,
+                absolute: None,
+                rate: None,
+                relative: None,
+                target_top: None,
+                target_bottom: None,
+                split_now: None,
+                split_ext: None,
+                profile: None,
+                profile_json: None,
+                notes: None,
+                entered_by: Some(DEFAULT_ENTERED_BY.to_string()),
+                extra: Value::Object(Default::default()),
+            },
+        }
+    }
+
+    /// Sets the insulin (U), turning a carb correction into a meal bolus.
+    pub fn insulin(mut self, units: f64) -> Self {
+        if self.treatment.event_type == "Carb Correction" {
+            self.treatment.event_type = "Meal Bolus".to_string();
+        }
+        self.treatment.insulin = Some(units);
+        self
+    }
+
+    /// Sets the carbs (g), turning a correction bolus into a meal bolus.
+    pub fn carbs(mut self, grams: f64) -> Self {
+        if self.treatment.event_type == "Correction Bolus" {
+            self.treatment.event_type = "Meal Bolus".to_string();
+        }
+        self.treatment.carbs = Some(grams);
+        self
+    }
+
+    /// Records a blood glucose check alongside the treatment.
+    pub fn glucose(mut self, glucose: Glucose) -> Self {
+        let (value, units) = match glucose {
+            Glucose::Mgdl(value) => (value, "mg/dl"),
+            Glucose::Mmol(value) => (value, "mmol"),
+        };
+        self.treatment.glucose = Some(value);
+        self.treatment.units = Some(units.to_string());
+        self.treatment.glucose_type = Some("Finger".to_string());
+        self
+    }
+
+    /// Sets the duration in minutes.
+    pub fn duration(mut self, minutes: f64) -> Self {
+        self.treatment.duration = Some(minutes);
+        self
+    }
+
+    pub fn notes(mut self, notes: impl Into<String>) -> Self {
+        self.treatment.notes = Some(notes.into());
+        self
+    }
+
+    /// Dates the treatment. Defaults to now.
+    pub fn at(mut self, date: DateTime<Utc>) -> Self {
+        self.treatment.created_at = date;
+        self
+    }
+
+    /// Sets `enteredBy`. Defaults to `cinnamon`.
+    pub fn entered_by(mut self, name: impl Into<String>) -> Self {
+        self.treatment.entered_by = Some(name.into());
+        self
+    }
+
+    /// Validates and returns the treatment, see [`Treatment::validate`].
+    pub fn build(self) -> Result<Treatment, NightscoutError> {
+        self.treatment.validate()?;
+        Ok(self.treatment)
+    }
 }
 
 impl HasDevice for Treatment {
+    const DEVICE_FIELD: &'static str = "enteredBy";
+
     fn device(&self) -> Option<&str> {
         self.entered_by.as_deref()
     }
 }
 
+impl HasDate for Treatment {
+    fn timestamp(&self) -> Option<DateTime<Utc>> {
+        Some(self.created_at)
+    }
+}
+
 pub struct TreatmentsService {
     pub client: NightscoutClient,
 }
@@ -90,6 +472,46 @@ impl TreatmentsService {
             .with_date_field("created_at")
     }
 
+    /// Fetches a single treatment by its `_id`, `None` if it does not exist.
+    pub async fn get_by_id(&self, id: &str) -> Result<Option<Treatment>, NightscoutError> {
+        self.client.get_document(Endpoint::Treatments, id).await
+    }
+
+    /// Fetches the most recent treatment, `None` if there is none.
+    ///
+    /// This is a convenience wrapper around `.get().first()`.
+    pub async fn latest(&self) -> Result<Option<Treatment>, NightscoutError> {
+        self.get().first().await
+    }
+
+    /// Watches for new treatments, polling Nightscout every `interval`.
+    ///
+    /// Like [`SgvService::watch`](crate::models::entries::SgvService::watch), the first
+    /// item is the latest treatment and later items are the treatments created since,
+    /// oldest first. Errors are yielded without ending the stream.
+    pub fn watch(
+        &self,
+        interval: Duration,
+    ) -> impl Stream<Item = Result<Treatment, NightscoutError>> {
+        let client = self.client.clone();
+
+        watch::poll(
+            interval,
+            move |since| {
+                let query = match since {
+                    Some(since) => client
+                        .treatments()
+                        .get()
+                        .from(since)
+                        .limit(WATCH_BATCH_SIZE),
+                    None => client.treatments().get().limit(1),
+                };
+                query.send()
+            },
+            |treatment: &Treatment| treatment.id.clone(),
+        )
+    }
+
     /// Initiates a delete request for Treatments entries.
     ///
     /// Use the builder to specify which entries to delete (e.g. by ID or date range).
@@ -99,17 +521,50 @@ impl TreatmentsService {
     }
 
     /// Uploads new Treatments entries to Nightscout.
+    ///
+    /// Large sets are split into batches of [`crate::bulk::DEFAULT_BATCH_SIZE`], see
+    /// [`TreatmentsService::bulk`] to report failed batches individually.
     pub async fn create(
         &self,
         treatments: Vec<Treatment>,
     ) -> Result<Vec<Treatment>, NightscoutError> {
-        let url = self.client.base_url.join(Endpoint::Treatments.as_path())?;
-
-        let mut request = self.client.http.post(url);
-        request = self.client.auth(request);
+        self.bulk(treatments)
+            .stop_on_failure()
+            .send()
+            .await?
+            .into_result()
+    }
 
-        let response = self.client.send_checked(request.json(&treatments)).await?;
+    /// Initiates a chunked upload of Treatments entries.
+    pub fn bulk(&self, treatments: Vec<Treatment>) -> BulkRequest<Treatment> {
+        BulkRequest::new(self.client.clone(), Endpoint::Treatments, treatments)
+    }
 
-        Ok(response.json::<Vec<Treatment>>().await?)
+    /// Replaces an existing treatment on Nightscout.
+    ///
+    /// Issues a `PUT` with the treatment's `_id` set to `id` and returns the updated document.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// # use cinnamon::client::NightscoutClient;
+    /// # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+    /// let client = NightscoutClient::new("https://ns.example.com")?.with_secret("secret");
+    /// let mut treatment = client.treatments().get().limit(1).send().await?.remove(0);
+    /// treatment.notes = Some("Pizza".to_string());
+    ///
+    /// let id = treatment.id.clone().unwrap();
+    /// let updated = client.treatments().update(&id, treatment).await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn update(
+        &self,
+        id: &str,
+        treatment: Treatment,
+    ) -> Result<Treatment, NightscoutError> {
+        self.client
+            .update_document(Endpoint::Treatments, id, &treatment)
+            .await
     }
 }
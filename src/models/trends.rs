@@ -1,7 +1,7 @@
 use serde::{Deserialize, Serialize};
 use std::fmt;
 
-#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Trend {
     DoubleUp,
     SingleUp,
@@ -15,6 +15,93 @@ pub enum Trend {
 }
 
 impl Trend {
+    /// Parses the numeric trend codes used by Dexcom receivers and the Share API.
+    ///
+    /// Codes 1 to 7 go from `DoubleUp` to `DoubleDown`. `0` (none), `8` (not computable),
+    /// `9` (rate out of range) and unknown codes map to `Else`.
+    pub fn from_numeric(code: u8) -> Self {
+        match code {
+            1 => Self::DoubleUp,
+            2 => Self::SingleUp,
+            3 => Self::FortyFiveUp,
+            4 => Self::Flat,
+            5 => Self::FortyFiveDown,
+            6 => Self::SingleDown,
+            7 => Self::DoubleDown,
+            _ => Self::Else,
+        }
+    }
+
+    /// Parses an arrow angle in degrees, 0 being flat and 90 straight up, as reported by
+    /// some readers and watch apps. The angle snaps to the closest arrow.
+    pub fn from_degrees(degrees: f64) -> Self {
+        match degrees {
+            d if d.is_nan() => Self::Else,
+            d if d >= 67.5 => Self::SingleUp,
+            d if d >= 22.5 => Self::FortyFiveUp,
+            d if d > -22.5 => Self::Flat,
+            d if d > -67.5 => Self::FortyFiveDown,
+            _ => Self::SingleDown,
+        }
+    }
+
+    /// Maps a rate of change (mg/dL per minute) to its arrow, using the Dexcom thresholds.
+    pub fn from_slope(mgdl_per_min: f64) -> Self {
+        match mgdl_per_min {
+            s if s.is_nan() => Self::Else,
+            s if s > 3.0 => Self::DoubleUp,
+            s if s > 2.0 => Self::SingleUp,
+            s if s > 1.0 => Self::FortyFiveUp,
+            s if s >= -1.0 => Self::Flat,
+            s if s >= -2.0 => Self::FortyFiveDown,
+            s if s >= -3.0 => Self::SingleDown,
+            _ => Self::DoubleDown,
+        }
+    }
+
+    /// The range of rates of change (mg/dL per minute) the arrow stands for, the inverse of
+    /// [`Trend::from_slope`]. `None` for `Else`.
+    pub fn to_slope_range(&self) -> Option<(f64, f64)> {
+        match self {
+            Self::DoubleUp => Some((3.0, f64::INFINITY)),
+            Self::SingleUp => Some((2.0, 3.0)),
+            Self::FortyFiveUp => Some((1.0, 2.0)),
+            Self::Flat => Some((-1.0, 1.0)),
+            Self::FortyFiveDown => Some((-2.0, -1.0)),
+            Self::SingleDown => Some((-3.0, -2.0)),
+            Self::DoubleDown => Some((f64::NEG_INFINITY, -3.0)),
+            Self::Else => None,
+        }
+    }
+
+    /// The direction as Nightscout names it, e.g. `FortyFiveUp`, with `NONE` for `Else`.
+    pub fn as_str(&self) -> &str {
+        match self {
+            Self::DoubleUp => "DoubleUp",
+            Self::SingleUp => "SingleUp",
+            Self::FortyFiveUp => "FortyFiveUp",
+            Self::Flat => "Flat",
+            Self::FortyFiveDown => "FortyFiveDown",
+            Self::SingleDown => "SingleDown",
+            Self::DoubleDown => "DoubleDown",
+            Self::Else => "NONE",
+        }
+    }
+
+    /// A text description of the arrow, e.g. "Rising quickly".
+    pub fn as_label(&self) -> &str {
+        match self {
+            Self::DoubleUp => "Rising quickly",
+            Self::SingleUp => "Rising",
+            Self::FortyFiveUp => "Rising slowly",
+            Self::Flat => "Steady",
+            Self::FortyFiveDown => "Falling slowly",
+            Self::SingleDown => "Falling",
+            Self::DoubleDown => "Falling quickly",
+            Self::Else => "Unknown",
+        }
+    }
+
     pub fn as_arrow(&self) -> &str {
         match self {
             Self::DoubleUp => "↑↑",
@@ -29,8 +116,13 @@ impl Trend {
     }
 }
 
+/// Formats as an arrow, or as a text label with the alternate flag (`{:#}`).
 impl fmt::Display for Trend {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "{}", self.as_arrow())
+        if f.alternate() {
+            write!(f, "{}", self.as_label())
+        } else {
+            write!(f, "{}", self.as_arrow())
+        }
     }
 }
@@ -0,0 +1,269 @@
+//! A heartbeat watching a Nightscout site's availability, clock and data freshness.
+
+use crate::client::NightscoutClient;
+use crate::query_builder::HasDate;
+use crate::runtime;
+
+use chrono::{DateTime, Utc};
+use std::time::Duration;
+use tokio::sync::mpsc;
+
+/// Capacity of the channel returned by [`Monitor::spawn`].
+#[cfg(not(target_arch = "wasm32"))]
+const CHANNEL_CAPACITY: usize = 64;
+
+/// The outcome of one check, see [`HealthEvent::Check`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct HealthReport {
+    pub checked_at: DateTime<Utc>,
+    /// Whether the status endpoint answered.
+    pub reachable: bool,
+    /// Round trip of the status request.
+    pub latency: Option<Duration>,
+    /// The error of the status request when the server was not reachable.
+    pub error: Option<String>,
+    /// How far the server clock is ahead of the local one.
+    pub clock_offset: Option<chrono::Duration>,
+    /// Time of the latest glucose reading.
+    pub last_reading: Option<DateTime<Utc>>,
+    /// The error of the glucose request when the readings could not be fetched.
+    pub readings_error: Option<String>,
+    /// Share of successful checks since the monitor started, from 0 to 1.
+    pub uptime: f64,
+    /// Start of the current run of successful checks.
+    pub up_since: Option<DateTime<Utc>>,
+}
+
+/// What a [`Monitor`] reports.
+///
+/// A `Check` is sent after every check; the other events are only sent when the state
+/// they describe changes, and on the first check.
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub enum HealthEvent {
+    Check(HealthReport),
+    /// The server answered, after failing or for the first time.
+    Up,
+    /// The server stopped answering.
+    Down {
+        error: String,
+    },
+    /// The server clock drifted beyond the tolerated offset.
+    ClockDrift {
+        offset: chrono::Duration,
+    },
+    /// The server clock is back within the tolerated offset.
+    ClockSynced,
+    /// The server answered but its readings could not be fetched, e.g. because reading
+    /// entries needs a token. Freshness is unknown until they can be fetched again.
+    ReadingsUnavailable {
+        error: String,
+    },
+    /// The readings were fetched and none arrived for longer than the staleness threshold.
+    Stale {
+        last_reading: Option<DateTime<Utc>>,
+    },
+    /// Readings arrive again.
+    Fresh,
+}
+
+/// Periodically checks a site, created by [`NightscoutClient::monitor`].
+///
+/// # Example
+///
+/// ```rust,no_run
+/// # use cinnamon::client::NightscoutClient;
+/// # use cinnamon::monitor::HealthEvent;
+/// # use std::time::Duration;
+/// # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+/// let client = NightscoutClient::new("https://ns.example.com")?;
+/// let (_handle, mut events) = client.monitor().interval(Duration::from_secs(30)).spawn();
+///
+/// while let Some(event) = events.recv().await {
+///     match event {
+///         HealthEvent::Down { error } => eprintln!("Nightscout is down: {error}"),
+///         HealthEvent::Stale { .. } => eprintln!("No new readings"),
+///         _ => {}
+///     }
+/// }
+/// # Ok(())
+/// # }
+/// ```
+pub struct Monitor {
+    client: NightscoutClient,
+    interval: Duration,
+    max_clock_offset: Duration,
+    stale_after: Duration,
+}
+
+impl Monitor {
+    pub(crate) fn new(client: NightscoutClient) -> Self {
+        Self {
+            client,
+            interval: Duration::from_secs(60),
+            max_clock_offset: Duration::from_secs(60),
+            stale_after: Duration::from_secs(15 * 60),
+        }
+    }
+
+    /// Time between checks. Defaults to 1 minute.
+    pub fn interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    /// Clock offset tolerated before a [`HealthEvent::ClockDrift`]. Defaults to 1 minute.
+    pub fn max_clock_offset(mut self, offset: Duration) -> Self {
+        self.max_clock_offset = offset;
+        self
+    }
+
+    /// Age of the latest reading before a [`HealthEvent::Stale`]. Defaults to 15 minutes.
+    pub fn stale_after(mut self, age: Duration) -> Self {
+        self.stale_after = age;
+        self
+    }
+
+    /// Runs the checks, sending events to `events` until the receiver is dropped.
+    ///
+    /// Use this on runtimes where [`spawn`](Self::spawn) is not available, e.g. in the
+    /// browser with `wasm_bindgen_futures::spawn_local`.
+    pub async fn run(self, events: mpsc::Sender<HealthEvent>) {
+        let mut state = MonitorState::default();
+
+        loop {
+            let report = self.check(&mut state).await;
+            for event in state.transitions(&report, &self) {
+                if events.send(event).await.is_err() {
+                    return;
+                }
+            }
+            if events.send(HealthEvent::Check(report)).await.is_err() {
+                return;
+            }
+
+            runtime::sleep(self.interval).await;
+        }
+    }
+
+    /// Runs the checks on a background task, returning the task and its events.
+    ///
+    /// The task stops when the receiver is dropped or the handle is aborted.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn spawn(self) -> (tokio::task::JoinHandle<()>, mpsc::Receiver<HealthEvent>) {
+        let (sender, receiver) = mpsc::channel(CHANNEL_CAPACITY);
+        (tokio::spawn(self.run(sender)), receiver)
+    }
+
+    async fn check(&self, state: &mut MonitorState) -> HealthReport {
+        let status_service = self.client.status();
+        let sent = Utc::now();
+        let (status, readings) = futures::join!(
+            async {
+                let status = status_service.get().await;
+                (status, Utc::now())
+            },
+            self.client.sgv().get().limit(1).send(),
+        );
+        let (status, received) = status;
+
+        state.checks += 1;
+        let (reachable, error, clock_offset) = match status {
+            Ok(status) => (true, None, status.clock_offset(sent, received)),
+            Err(e) => (false, Some(e.to_string()), None),
+        };
+        if reachable {
+            state.successes += 1;
+            state.up_since.get_or_insert(received);
+        } else {
+            state.up_since = None;
+        }
+
+        let (last_reading, readings_error) = match readings {
+            Ok(entries) => (entries.first().and_then(|entry| entry.timestamp()), None),
+            Err(e) => (None, Some(e.to_string())),
+        };
+
+        HealthReport {
+            checked_at: received,
+            reachable,
+            latency: reachable.then(|| (received - sent).to_std().unwrap_or_default()),
+            error,
+            clock_offset,
+            last_reading,
+            readings_error,
+            uptime: state.successes as f64 / state.checks as f64,
+            up_since: state.up_since,
+        }
+    }
+}
+
+#[derive(Default)]
+struct MonitorState {
+    checks: u64,
+    successes: u64,
+    up_since: Option<DateTime<Utc>>,
+    reachable: Option<bool>,
+    drifting: Option<bool>,
+    readings_failing: Option<bool>,
+    stale: Option<bool>,
+}
+
+impl MonitorState {
+    /// The events for the states that changed with `report`.
+    fn transitions(&mut self, report: &HealthReport, monitor: &Monitor) -> Vec<HealthEvent> {
+        let mut events = Vec::new();
+
+        if self.reachable.replace(report.reachable) != Some(report.reachable) {
+            events.push(match &report.error {
+                None => HealthEvent::Up,
+                Some(error) => HealthEvent::Down {
+                    error: error.clone(),
+                },
+            });
+        }
+
+        // Clock and freshness are unknown while the server is down.
+        if !report.reachable {
+            return events;
+        }
+
+        if let Some(offset) = report.clock_offset {
+            let drifting = offset.abs().to_std().unwrap_or_default() > monitor.max_clock_offset;
+            if self.drifting.replace(drifting) != Some(drifting) {
+                events.push(match drifting {
+                    true => HealthEvent::ClockDrift { offset },
+                    false => HealthEvent::ClockSynced,
+                });
+            }
+        }
+
+        let failing = report.readings_error.is_some();
+        if self.readings_failing.replace(failing) != Some(failing) {
+            if let Some(error) = &report.readings_error {
+                events.push(HealthEvent::ReadingsUnavailable {
+                    error: error.clone(),
+                });
+            }
+            // Freshness is reported again once the readings are back.
+            self.stale = None;
+        }
+        if failing {
+            return events;
+        }
+
+        let stale = report.last_reading.is_none_or(|time| {
+            (report.checked_at - time).to_std().unwrap_or_default() > monitor.stale_after
+        });
+        if self.stale.replace(stale) != Some(stale) {
+            events.push(match stale {
+                true => HealthEvent::Stale {
+                    last_reading: report.last_reading,
+                },
+                false => HealthEvent::Fresh,
+            });
+        }
+
+        events
+    }
+}
@@ -0,0 +1,304 @@
+//! Publishing of the site's state to Home Assistant over MQTT, built with the `mqtt`
+//! feature.
+//!
+//! An [`MqttPublisher`] announces its sensors with Home Assistant's
+//! [MQTT discovery](https://www.home-assistant.io/integrations/mqtt/#mqtt-discovery), so
+//! they appear under a "Nightscout" device without any YAML, then publishes a new state
+//! whenever a reading arrives:
+//!
+//! | Sensor             | Unit  |
+//! |--------------------|-------|
+//! | `glucose`          | mg/dL |
+//! | `delta`            | mg/dL |
+//! | `trend`            |       |
+//! | `iob`              | U     |
+//! | `cob`              | g     |
+//! | `pump_battery`     | %     |
+//! | `uploader_battery` | %     |
+//! | `last_reading`     |       |
+
+use crate::client::NightscoutClient;
+use crate::error::NightscoutError;
+use crate::snapshot::Snapshot;
+
+use futures::StreamExt;
+use rumqttc::{AsyncClient, Event, LastWill, MqttOptions, Packet, QoS};
+use serde_json::{json, Value};
+use std::time::Duration;
+
+/// Capacity of the request queue of the MQTT client.
+const QUEUE_CAPACITY: usize = 32;
+
+/// Delay before polling the broker connection again after an error.
+const RECONNECT_DELAY: Duration = Duration::from_secs(5);
+
+/// A sensor announced to Home Assistant.
+struct Sensor {
+    key: &'static str,
+    name: &'static str,
+    unit: Option<&'static str>,
+    device_class: Option<&'static str>,
+    icon: Option<&'static str>,
+}
+
+const SENSORS: &[Sensor] = &[
+    Sensor {
+        key: "glucose",
+        name: "Glucose",
+        unit: Some("mg/dL"),
+        device_class: None,
+        icon: Some("mdi:diabetes"),
+    },
+    Sensor {
+        key: "delta",
+        name: "Glucose delta",
+        unit: Some("mg/dL"),
+        device_class: None,
+        icon: Some("mdi:delta"),
+    },
+    Sensor {
+        key: "trend",
+        name: "Glucose trend",
+        unit: None,
+        device_class: None,
+        icon: Some("mdi:trending-up"),
+    },
+    Sensor {
+        key: "iob",
+        name: "Insulin on board",
+        unit: Some("U"),
+        device_class: None,
+        icon: Some("mdi:needle"),
+    },
+    Sensor {
+        key: "cob",
+        name: "Carbs on board",
+        unit: Some("g"),
+        device_class: None,
+        icon: Some("mdi:food-apple"),
+    },
+    Sensor {
+        key: "pump_battery",
+        name: "Pump battery",
+        unit: Some("%"),
+        device_class: Some("battery"),
+        icon: None,
+    },
+    Sensor {
+        key: "uploader_battery",
+        name: "Uploader battery",
+        unit: Some("%"),
+        device_class: Some("battery"),
+        icon: None,
+    },
+    Sensor {
+        key: "last_reading",
+        name: "Last reading",
+        unit: None,
+        device_class: Some("timestamp"),
+        icon: None,
+    },
+];
+
+/// Publishes readings to an MQTT broker, created by [`NightscoutClient::mqtt`].
+///
+/// # Example
+///
+/// ```rust,no_run
+/// # use cinnamon::client::NightscoutClient;
+/// # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+/// let client = NightscoutClient::from_env()?;
+/// client
+///     .mqtt("homeassistant.local", 1883)
+///     .credentials("cinnamon", "mqtt-password")
+///     .run()
+///     .await?;
+/// # Ok(())
+/// # }
+/// ```
+pub struct MqttPublisher {
+    client: NightscoutClient,
+    host: String,
+    port: u16,
+    credentials: Option<(String, String)>,
+    node_id: String,
+    discovery_prefix: String,
+    interval: Duration,
+}
+
+impl MqttPublisher {
+    pub(crate) fn new(client: NightscoutClient, host: &str, port: u16) -> Self {
+        Self {
+            client,
+            host: host.to_string(),
+            port,
+            credentials: None,
+            node_id: "nightscout".to_string(),
+            discovery_prefix: "homeassistant".to_string(),
+            interval: Duration::from_secs(60),
+        }
+    }
+
+    /// Logs in to the broker.
+    pub fn credentials(mut self, username: &str, password: &str) -> Self {
+        self.credentials = Some((username.to_string(), password.to_string()));
+        self
+    }
+
+    /// The identifier of the device in topics and entity ids, to publish several sites
+    /// to one broker. Defaults to `nightscout`.
+    pub fn node_id(mut self, node_id: &str) -> Self {
+        self.node_id = node_id.to_string();
+        self
+    }
+
+    /// The discovery prefix configured in Home Assistant. Defaults to `homeassistant`.
+    pub fn discovery_prefix(mut self, prefix: &str) -> Self {
+        self.discovery_prefix = prefix.trim_end_matches('/').to_string();
+        self
+    }
+
+    /// Time between polls of the site. Defaults to 1 minute.
+    pub fn interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    /// The topic the JSON state is published to.
+    pub fn state_topic(&self) -> String {
+        format!("{}/state", self.node_id)
+    }
+
+    /// The topic `online` and `offline` are published to.
+    pub fn availability_topic(&self) -> String {
+        format!("{}/availability", self.node_id)
+    }
+
+    /// The discovery topics and their configuration payloads, one per sensor.
+    pub fn discovery(&self) -> Vec<(String, Value)> {
+        let device = json!({
+            "identifiers": [format!("cinnamon_{}", self.node_id)],
+            "name": "Nightscout",
+            "manufacturer": "Nightscout",
+            "model": "cinnamon",
+        });
+
+        SENSORS
+            .iter()
+            .map(|sensor| {
+                let unique_id = format!("{}_{}", self.node_id, sensor.key);
+                let mut config = json!({
+                    "name": sensor.name,
+                    "unique_id": unique_id,
+                    "object_id": unique_id,
+                    "state_topic": self.state_topic(),
+                    "availability_topic": self.availability_topic(),
+                    "value_template": format!("{{{{ value_json.{} }}}}", sensor.key),
+                    "device": device,
+                });
+                if let Some(unit) = sensor.unit {
+                    config["unit_of_measurement"] = json!(unit);
+                    config["state_class"] = json!("measurement");
+                }
+                if let Some(class) = sensor.device_class {
+                    config["device_class"] = json!(class);
+                }
+                if let Some(icon) = sensor.icon {
+                    config["icon"] = json!(icon);
+                }
+
+                let topic = format!(
+                    "{}/sensor/{}/{}/config",
+                    self.discovery_prefix, self.node_id, sensor.key
+                );
+                (topic, config)
+            })
+            .collect()
+    }
+
+    /// Publishes the discovery configuration, then the state of the site on every new
+    /// reading. Runs until publishing fails; a lost broker connection is retried.
+    pub async fn run(self) -> Result<(), NightscoutError> {
+        let mut options =
+            MqttOptions::new(format!("cinnamon-{}", self.node_id), &self.host, self.port);
+        options.set_keep_alive(Duration::from_secs(30));
+        if let Some((username, password)) = &self.credentials {
+            options.set_credentials(username, password);
+        }
+        options.set_last_will(LastWill::new(
+            self.availability_topic(),
+            "offline",
+            QoS::AtLeastOnce,
+            true,
+        ));
+        let (mqtt, mut connection) = AsyncClient::new(options, QUEUE_CAPACITY);
+
+        // The connection makes progress only while it is polled. The broker publishes the
+        // last will when the connection drops, so discovery and `online` are sent again on
+        // every (re)connection, not only the first one.
+        let announce = mqtt.clone();
+        let discovery = self.discovery();
+        let availability = self.availability_topic();
+        let driver = tokio::spawn(async move {
+            loop {
+                match connection.poll().await {
+                    Ok(Event::Incoming(Packet::ConnAck(_))) => {
+                        // `publish` would wait on the queue this loop drains.
+                        for (topic, config) in &discovery {
+                            let _ = announce.try_publish(
+                                topic,
+                                QoS::AtLeastOnce,
+                                true,
+                                config.to_string(),
+                            );
+                        }
+                        let _ =
+                            announce.try_publish(&availability, QoS::AtLeastOnce, true, "online");
+                    }
+                    Ok(_) => {}
+                    Err(_) => tokio::time::sleep(RECONNECT_DELAY).await,
+                }
+            }
+        });
+
+        let result = self.publish(&mqtt).await;
+        driver.abort();
+        result
+    }
+
+    async fn publish(&self, mqtt: &AsyncClient) -> Result<(), NightscoutError> {
+        let mut readings = std::pin::pin!(self.client.sgv().watch(self.interval));
+        while let Some(reading) = readings.next().await {
+            // Outages of the site are shown by the last reading getting old.
+            if reading.is_err() {
+                continue;
+            }
+            let Ok(snapshot) = self.client.snapshot().await else {
+                continue;
+            };
+            mqtt.publish(
+                self.state_topic(),
+                QoS::AtLeastOnce,
+                true,
+                state(&snapshot).to_string(),
+            )
+            .await?;
+        }
+
+        Ok(())
+    }
+}
+
+/// The JSON state of a snapshot, with a field per sensor of [`MqttPublisher::discovery`].
+pub fn state(snapshot: &Snapshot) -> Value {
+    json!({
+        "glucose": snapshot.sgv.sgv,
+        "delta": snapshot.delta,
+        "trend": snapshot.direction,
+        "iob": snapshot.iob,
+        "cob": snapshot.cob,
+        "pump_battery": snapshot.pump_battery,
+        "uploader_battery": snapshot.uploader_battery,
+        "last_reading": snapshot.sgv.datetime().map(|date| date.to_rfc3339()),
+    })
+}
@@ -0,0 +1,29 @@
+//! The types most programs need, in one import.
+//!
+//! ```rust,no_run
+//! use cinnamon::prelude::*;
+//!
+//! # async fn run() -> Result<(), NightscoutError> {
+//! let client = NightscoutClient::from_env()?;
+//!
+//! let entries: Vec<SgvEntry> = client.sgv().get().device(Device::All).limit(5).send().await?;
+//! if entries.first().is_some_and(|entry| entry.direction == Trend::DoubleDown) {
+//!     let properties = client.properties().get().only(&[PropertyType::Iob]).send().await?;
+//!     println!("Falling fast, IOB {:?}", properties.active_insulin());
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+pub use crate::client::NightscoutClient;
+pub use crate::error::NightscoutError;
+pub use crate::models::entries::{CalEntry, Entry, MbgEntry, SgvEntry};
+pub use crate::models::glucose::{Glucose, GlucoseUnit};
+pub use crate::models::properties::{
+    Ar2Property, Ar2PropertyForecast, Ar2PropertyPoint, Basal, BasalCurrent, BgNow, Bucket, Cob,
+    DbSize, Delta, Direction, IobProperty, LoopDisplay, LoopProperty, OpenApsProperty, Properties,
+    PropertySgv, PropertyType, PumpProperty, PumpPropertyData, PumpValue, RuntimeState, Upbat,
+};
+pub use crate::models::treatments::{ComboBolus, TempTarget, Treatment, TreatmentBuilder};
+pub use crate::models::trends::Trend;
+pub use crate::query_builder::Device;
@@ -0,0 +1,285 @@
+//! Python bindings, built with [maturin](https://www.maturin.rs) from the `python`
+//! feature.
+//!
+//! The module exposes a synchronous client: every call runs on a runtime owned by the
+//! client and releases the GIL while waiting for the server, so other Python threads keep
+//! running. Documents are returned as `dict`s with the Nightscout field names, ready for
+//! `pandas.DataFrame`.
+//!
+//! ```python
+//! from datetime import datetime, timedelta, timezone
+//! import cinnamon
+//!
+//! client = cinnamon.Client("https://my-cgm.herokuapp.com", token="reader-1a2b3c")
+//! since = datetime.now(timezone.utc) - timedelta(days=1)
+//! entries = client.sgv().from_(since).limit(288).send()
+//! print(entries[0]["sgv"], entries[0]["direction"])
+//! ```
+
+use crate::client::NightscoutClient;
+use crate::error::NightscoutError;
+use crate::models::devicestatus::DeviceStatus;
+use crate::models::entries::{Entry, MbgEntry, SgvEntry};
+use crate::models::properties::PropertyType;
+use crate::models::treatments::Treatment;
+use crate::query_builder::{Device, QueryBuilder};
+
+use chrono::{DateTime, FixedOffset, Utc};
+use pyo3::create_exception;
+use pyo3::exceptions::PyException;
+use pyo3::prelude::*;
+use pyo3::types::PyList;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::future::Future;
+use std::sync::Arc;
+use tokio::runtime::Runtime;
+
+create_exception!(
+    cinnamon,
+    PyNightscoutError,
+    PyException,
+    "Raised when a request to Nightscout fails."
+);
+
+impl From<NightscoutError> for PyErr {
+    fn from(error: NightscoutError) -> Self {
+        PyNightscoutError::new_err(error.to_string())
+    }
+}
+
+/// Converts a document to Python objects through its JSON form.
+fn to_python(py: Python<'_>, value: &impl Serialize) -> PyResult<PyObject> {
+    let json = serde_json::to_string(value).map_err(NightscoutError::from)?;
+    Ok(py.import("json")?.call_method1("loads", (json,))?.unbind())
+}
+
+fn from_python<T: DeserializeOwned>(value: &Bound<'_, PyAny>) -> PyResult<T> {
+    let json: String = value
+        .py()
+        .import("json")?
+        .call_method1("dumps", (value,))?
+        .extract()?;
+    Ok(serde_json::from_str(&json).map_err(NightscoutError::from)?)
+}
+
+/// A Nightscout client.
+#[pyclass(name = "Client", module = "cinnamon", frozen)]
+pub struct PyClient {
+    client: NightscoutClient,
+    runtime: Arc<Runtime>,
+}
+
+/// Runs `future` on the client's runtime without holding the GIL.
+fn block_on<F>(py: Python<'_>, runtime: &Runtime, future: F) -> F::Output
+where
+    F: Future + Send,
+    F::Output: Send,
+{
+    py.allow_threads(|| runtime.block_on(future))
+}
+
+impl PyClient {
+    fn query(&self, builder: Builder) -> PyQuery {
+        PyQuery {
+            builder,
+            runtime: self.runtime.clone(),
+        }
+    }
+}
+
+#[pymethods]
+impl PyClient {
+    #[new]
+    #[pyo3(signature = (url, token = None, secret = None))]
+    fn new(url: &str, token: Option<String>, secret: Option<String>) -> PyResult<Self> {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .map_err(NightscoutError::from)?;
+
+        let mut client = NightscoutClient::new(url)?;
+        if let Some(secret) = secret {
+            client = client.with_secret(secret);
+        }
+        if let Some(token) = token {
+            client = client.with_token(token);
+        }
+
+        Ok(Self {
+            client,
+            runtime: Arc::new(runtime),
+        })
+    }
+
+    /// Creates a client from the `NIGHTSCOUT_*` environment variables.
+    #[staticmethod]
+    fn from_env() -> PyResult<Self> {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .map_err(NightscoutError::from)?;
+
+        Ok(Self {
+            client: NightscoutClient::from_env()?,
+            runtime: Arc::new(runtime),
+        })
+    }
+
+    /// Glucose readings.
+    fn sgv(&self) -> PyQuery {
+        self.query(Builder::Sgv(self.client.sgv().get()))
+    }
+
+    /// Meter readings.
+    fn mbg(&self) -> PyQuery {
+        self.query(Builder::Mbg(self.client.mbg().get()))
+    }
+
+    /// Every kind of entry: readings and calibrations.
+    fn entries(&self) -> PyQuery {
+        self.query(Builder::Entries(self.client.entries().all()))
+    }
+
+    fn treatments(&self) -> PyQuery {
+        self.query(Builder::Treatments(self.client.treatments().get()))
+    }
+
+    fn devicestatus(&self) -> PyQuery {
+        self.query(Builder::DeviceStatus(self.client.devicestatus().get()))
+    }
+
+    /// The server status and settings.
+    fn status(&self, py: Python<'_>) -> PyResult<PyObject> {
+        let status = block_on(py, &self.runtime, self.client.status().get())?;
+        to_python(py, &status)
+    }
+
+    /// The computed properties (`iob`, `cob`, `pump`, ...), all of them by default.
+    #[pyo3(signature = (names = None))]
+    fn properties(&self, py: Python<'_>, names: Option<Vec<String>>) -> PyResult<PyObject> {
+        let mut request = self.client.properties().get();
+        if let Some(names) = names {
+            let types = names
+                .iter()
+                .map(|name| name.parse::<PropertyType>())
+                .collect::<Result<Vec<_>, _>>()?;
+            request = request.only(&types);
+        }
+        let properties = block_on(py, &self.runtime, request.send())?;
+        to_python(py, &properties)
+    }
+
+    /// Uploads treatments given as `dict`s, returning them as stored by the server.
+    fn create_treatments(
+        &self,
+        py: Python<'_>,
+        treatments: &Bound<'_, PyList>,
+    ) -> PyResult<PyObject> {
+        let treatments: Vec<Treatment> = from_python(treatments.as_any())?;
+        let created = block_on(
+            py,
+            &self.runtime,
+            self.client.treatments().create(treatments),
+        )?;
+        to_python(py, &created)
+    }
+}
+
+/// The query builders of the collections, see [`PyQuery`].
+#[derive(Clone)]
+enum Builder {
+    Sgv(QueryBuilder<SgvEntry>),
+    Mbg(QueryBuilder<MbgEntry>),
+    Entries(QueryBuilder<Entry>),
+    Treatments(QueryBuilder<Treatment>),
+    DeviceStatus(QueryBuilder<DeviceStatus>),
+}
+
+/// Applies the same expression to the builder of any collection.
+macro_rules! each_builder {
+    ($builder:expr, $query:ident => $body:expr) => {
+        match $builder {
+            Builder::Sgv($query) => Builder::Sgv($body),
+            Builder::Mbg($query) => Builder::Mbg($body),
+            Builder::Entries($query) => Builder::Entries($body),
+            Builder::Treatments($query) => Builder::Treatments($body),
+            Builder::DeviceStatus($query) => Builder::DeviceStatus($body),
+        }
+    };
+}
+
+/// A query of a collection. The options return the query, so calls can be chained.
+#[pyclass(name = "Query", module = "cinnamon")]
+pub struct PyQuery {
+    builder: Builder,
+    runtime: Arc<Runtime>,
+}
+
+impl PyQuery {
+    fn configure(
+        mut slf: PyRefMut<'_, Self>,
+        f: impl FnOnce(Builder) -> Builder,
+    ) -> PyRefMut<'_, Self> {
+        slf.builder = f(slf.builder.clone());
+        slf
+    }
+}
+
+#[pymethods]
+impl PyQuery {
+    /// Keeps documents dated on or after `date`, a timezone-aware `datetime`.
+    #[pyo3(name = "from_")]
+    fn from_date(slf: PyRefMut<'_, Self>, date: DateTime<FixedOffset>) -> PyRefMut<'_, Self> {
+        let date = date.with_timezone(&Utc);
+        Self::configure(slf, |b| each_builder!(b, q => q.from(date)))
+    }
+
+    /// Keeps documents dated on or before `date`, a timezone-aware `datetime`.
+    fn to(slf: PyRefMut<'_, Self>, date: DateTime<FixedOffset>) -> PyRefMut<'_, Self> {
+        let date = date.with_timezone(&Utc);
+        Self::configure(slf, |b| each_builder!(b, q => q.to(date)))
+    }
+
+    fn last_hours(slf: PyRefMut<'_, Self>, hours: i64) -> PyRefMut<'_, Self> {
+        Self::configure(slf, |b| each_builder!(b, q => q.last_hours(hours)))
+    }
+
+    fn last_days(slf: PyRefMut<'_, Self>, days: i64) -> PyRefMut<'_, Self> {
+        Self::configure(slf, |b| each_builder!(b, q => q.last_days(days)))
+    }
+
+    fn limit(slf: PyRefMut<'_, Self>, count: usize) -> PyRefMut<'_, Self> {
+        Self::configure(slf, |b| each_builder!(b, q => q.limit(count)))
+    }
+
+    /// Keeps the documents of one device, or of every device with `None`.
+    #[pyo3(signature = (name = None))]
+    fn device(slf: PyRefMut<'_, Self>, name: Option<String>) -> PyRefMut<'_, Self> {
+        let device = name.map_or(Device::All, Device::Custom);
+        Self::configure(slf, |b| each_builder!(b, q => q.device(device)))
+    }
+
+    /// Runs the query, returning the documents as `dict`s, newest first.
+    fn send(&self, py: Python<'_>) -> PyResult<PyObject> {
+        let builder = self.builder.clone();
+        let documents = block_on(py, &self.runtime, async move {
+            Ok::<_, NightscoutError>(match builder {
+                Builder::Sgv(q) => serde_json::to_value(q.send().await?)?,
+                Builder::Mbg(q) => serde_json::to_value(q.send().await?)?,
+                Builder::Entries(q) => serde_json::to_value(q.send().await?)?,
+                Builder::Treatments(q) => serde_json::to_value(q.send().await?)?,
+                Builder::DeviceStatus(q) => serde_json::to_value(q.send().await?)?,
+            })
+        })?;
+        to_python(py, &documents)
+    }
+}
+
+#[pymodule]
+fn cinnamon(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_class::<PyClient>()?;
+    m.add_class::<PyQuery>()?;
+    m.add("NightscoutError", m.py().get_type::<PyNightscoutError>())?;
+    Ok(())
+}
@@ -1,13 +1,23 @@
 use super::client::NightscoutClient;
-use crate::endpoints::Endpoint;
+use crate::conditional::Conditional;
+use crate::endpoints::{ApiVersion, Endpoint};
 use crate::error::NightscoutError;
+#[cfg(feature = "csv")]
+use crate::export::{self, CsvRecord};
+use crate::models::auth::AuthMode;
+use crate::response::WithMeta;
+use crate::runtime;
 
+use std::borrow::Cow;
 use std::marker::PhantomData;
 
-use chrono::{DateTime, Utc};
-use futures::stream::{self, StreamExt};
+use chrono::{DateTime, Duration, NaiveDate, NaiveTime, TimeZone, Utc};
+use chrono_tz::Tz;
+use futures::stream::{self, Stream, StreamExt, TryStreamExt};
 use reqwest::Method;
 use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::collections::HashSet;
 
 #[derive(Clone, Debug, PartialEq)]
 /// Specifies target device filtering behavior.
@@ -21,38 +31,212 @@ pub enum Device {
     Custom(String),
 }
 
+/// Comparison operator of a [`QueryBuilder::filter`].
+///
+/// These map to the MongoDB-style `find` query parameters understood by Nightscout.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum FilterOp {
+    /// `find[field]=value`
+    Eq,
+    /// `find[field][$ne]=value`
+    Ne,
+    /// `find[field][$gt]=value`
+    Gt,
+    /// `find[field][$gte]=value`
+    Gte,
+    /// `find[field][$lt]=value`
+    Lt,
+    /// `find[field][$lte]=value`
+    Lte,
+    /// `find[field][$in][]=value`, repeat the filter to match several values.
+    In,
+    /// `find[field][$nin][]=value`, repeat the filter to exclude several values.
+    Nin,
+    /// `find[field][$regex]=value`
+    Regex,
+    /// `find[field][$exists]=true|false`
+    Exists,
+}
+
+impl FilterOp {
+    /// The query parameter key for `field`.
+    pub fn key(&self, field: &str) -> String {
+        match self {
+            FilterOp::Eq => format!("find[{}]", field),
+            FilterOp::Ne => format!("find[{}][$ne]", field),
+            FilterOp::Gt => format!("find[{}][$gt]", field),
+            FilterOp::Gte => format!("find[{}][$gte]", field),
+            FilterOp::Lt => format!("find[{}][$lt]", field),
+            FilterOp::Lte => format!("find[{}][$lte]", field),
+            FilterOp::In => format!("find[{}][$in][]", field),
+            FilterOp::Nin => format!("find[{}][$nin][]", field),
+            FilterOp::Regex => format!("find[{}][$regex]", field),
+            FilterOp::Exists => format!("find[{}][$exists]", field),
+        }
+    }
+
+    /// The operator of the v3 API `field$op=value` syntax, which has no `$exists`.
+    fn v3_operator(&self) -> Option<&'static str> {
+        match self {
+            FilterOp::Eq => Some("eq"),
+            FilterOp::Ne => Some("ne"),
+            FilterOp::Gt => Some("gt"),
+            FilterOp::Gte => Some("gte"),
+            FilterOp::Lt => Some("lt"),
+            FilterOp::Lte => Some("lte"),
+            FilterOp::In => Some("in"),
+            FilterOp::Nin => Some("nin"),
+            FilterOp::Regex => Some("re"),
+            FilterOp::Exists => None,
+        }
+    }
+}
+
+/// Sort direction of a [`QueryBuilder::sort`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Order {
+    Asc,
+    Desc,
+}
+
+/// A single `find` condition added with [`QueryBuilder::filter`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct Filter {
+    pub field: String,
+    pub op: FilterOp,
+    pub value: String,
+}
+
 /// Trait for models that contain a device name field.
 pub trait HasDevice {
+    /// The stored field [`QueryBuilder::device`] filters on, the one `device` reads.
+    const DEVICE_FIELD: &'static str = "device";
+
     fn device(&self) -> Option<&str>;
 }
 
+/// Trait for models that carry a timestamp, used as the cursor when paginating.
+pub trait HasDate {
+    fn timestamp(&self) -> Option<DateTime<Utc>>;
+}
+
+/// `at - delta`, saturating at the earliest representable date instead of panicking.
+pub(crate) fn saturating_sub(at: DateTime<Utc>, delta: Duration) -> DateTime<Utc> {
+    at.checked_sub_signed(delta)
+        .unwrap_or(DateTime::<Utc>::MIN_UTC)
+}
+
+/// The instant `days` days ago, saturating instead of panicking on out of range values.
+pub(crate) fn days_ago(now: DateTime<Utc>, days: i64) -> DateTime<Utc> {
+    let delta = Duration::try_days(days).unwrap_or(if days < 0 {
+        Duration::MIN
+    } else {
+        Duration::MAX
+    });
+    saturating_sub(now, delta)
+}
+
+/// Start of `date` in `tz`, skipping forward over a DST gap at midnight.
+pub(crate) fn local_midnight(tz: &Tz, date: NaiveDate) -> DateTime<Utc> {
+    let midnight = date.and_time(NaiveTime::MIN);
+    tz.from_local_datetime(&midnight)
+        .earliest()
+        .or_else(|| {
+            let later = midnight.checked_add_signed(Duration::hours(1))?;
+            tz.from_local_datetime(&later).earliest()
+        })
+        .map(|dt| dt.with_timezone(&Utc))
+        .unwrap_or_else(|| Utc.from_utc_datetime(&midnight))
+}
+
 pub struct QueryBuilder<T> {
     client: NightscoutClient,
     endpoint: Endpoint,
     from_date: Option<DateTime<Utc>>,
     to_date: Option<DateTime<Utc>>,
+    /// Days back from today selected by [`QueryBuilder::today`] or
+    /// [`QueryBuilder::yesterday`], resolved again when the timezone changes.
+    day: Option<i64>,
+    tz: Tz,
     count: usize,
     method: Method,
     id: Option<String>,
     device: Device,
     date_field: String,
     date_is_epoch_millis: bool,
+    filters: Vec<Filter>,
+    fields: Vec<String>,
+    sort: Option<(String, Order)>,
+    lenient: bool,
+    confirmed: bool,
+    confirmed_all: bool,
+    timeout: Option<std::time::Duration>,
     _marker: PhantomData<T>,
 }
 
+/// Items of a response decoded one by one, see [`QueryBuilder::send_partial`].
+#[derive(Debug)]
+pub struct PartialResult<T> {
+    /// The items that deserialized successfully.
+    pub items: Vec<T>,
+    /// The position in the response and error of every item that did not.
+    pub errors: Vec<(usize, serde_json::Error)>,
+}
+
+/// Page size of the chunks fetched by [`QueryBuilder::send_chunked`].
+const CHUNK_PAGE_SIZE: usize = 1000;
+
+impl<T> Clone for QueryBuilder<T> {
+    fn clone(&self) -> Self {
+        Self {
+            client: self.client.clone(),
+            endpoint: self.endpoint,
+            from_date: self.from_date,
+            to_date: self.to_date,
+            day: self.day,
+            tz: self.tz,
+            count: self.count,
+            method: self.method.clone(),
+            id: self.id.clone(),
+            device: self.device.clone(),
+            date_field: self.date_field.clone(),
+            date_is_epoch_millis: self.date_is_epoch_millis,
+            filters: self.filters.clone(),
+            fields: self.fields.clone(),
+            sort: self.sort.clone(),
+            lenient: self.lenient,
+            confirmed: self.confirmed,
+            confirmed_all: self.confirmed_all,
+            timeout: self.timeout,
+            _marker: PhantomData,
+        }
+    }
+}
+
 impl<T> QueryBuilder<T> {
     pub fn new(client: NightscoutClient, endpoint: Endpoint, method: Method) -> Self {
+        let tz = client.timezone.unwrap_or(Tz::UTC);
         Self {
             client,
             endpoint,
             from_date: None,
             to_date: None,
+            day: None,
+            tz,
             count: 10,
             method,
             id: None,
             device: Device::All,
             date_field: "dateString".to_string(),
             date_is_epoch_millis: false,
+            filters: Vec::new(),
+            fields: Vec::new(),
+            sort: None,
+            lenient: false,
+            confirmed: false,
+            confirmed_all: false,
+            timeout: None,
             _marker: PhantomData,
         }
     }
@@ -60,15 +244,104 @@ impl<T> QueryBuilder<T> {
     /// Filters results to entries occurring on or after this date.
     pub fn from(mut self, date: DateTime<Utc>) -> Self {
         self.from_date = Some(date);
+        self.day = None;
         self
     }
 
     /// Filters results to entries occurring on or before this date.
     pub fn to(mut self, date: DateTime<Utc>) -> Self {
         self.to_date = Some(date);
+        self.day = None;
         self
     }
 
+    /// Filters results to the last `hours` hours.
+    ///
+    /// Like [`last_days`](Self::last_days), [`today`](Self::today) and
+    /// [`yesterday`](Self::yesterday), the range is measured on the server clock when the
+    /// client knows its offset, see [`NightscoutClient::sync_clock`].
+    pub fn last_hours(self, hours: i64) -> Self {
+        let delta = Duration::try_hours(hours).unwrap_or(Duration::MAX);
+        let now = self.client.server_now();
+        self.since(saturating_sub(now, delta))
+    }
+
+    /// Filters results to the last `days` days.
+    pub fn last_days(self, days: i64) -> Self {
+        let now = self.client.server_now();
+        self.since(days_ago(now, days))
+    }
+
+    /// Filters results to the current day, from midnight in the query's timezone.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// # use cinnamon::client::NightscoutClient;
+    /// # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+    /// let client = NightscoutClient::new("https://ns.example.com")?;
+    /// let overnight = client.sgv()
+    ///     .get()
+    ///     .today()
+    ///     .timezone(chrono_tz::Europe::Paris)
+    ///     .limit(300)
+    ///     .send()
+    ///     .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn today(mut self) -> Self {
+        self.day = Some(0);
+        self.resolve_day();
+        self
+    }
+
+    /// Filters results to the previous day, midnight to midnight in the query's timezone.
+    pub fn yesterday(mut self) -> Self {
+        self.day = Some(1);
+        self.resolve_day();
+        self
+    }
+
+    /// Sets the timezone whose midnight bounds [`today`](Self::today) and
+    /// [`yesterday`](Self::yesterday). Defaults to the client's timezone, or UTC.
+    pub fn timezone(mut self, tz: Tz) -> Self {
+        self.tz = tz;
+        self.resolve_day();
+        self
+    }
+
+    fn since(mut self, from: DateTime<Utc>) -> Self {
+        self.from_date = Some(from);
+        self.to_date = None;
+        self.day = None;
+        self
+    }
+
+    fn resolve_day(&mut self) {
+        let Some(days) = self.day else {
+            return;
+        };
+
+        let today = self
+            .client
+            .server_now()
+            .with_timezone(&self.tz)
+            .date_naive();
+        let date = today
+            .checked_sub_days(chrono::Days::new(days as u64))
+            .unwrap_or(today);
+
+        self.from_date = Some(local_midnight(&self.tz, date));
+        self.to_date = match date.succ_opt() {
+            // `to` is inclusive, stop right before the next midnight.
+            Some(next) if days > 0 => {
+                Some(local_midnight(&self.tz, next) - Duration::milliseconds(1))
+            }
+            _ => None,
+        };
+    }
+
     /// Limits the number of results returned. Default is 10.
     pub fn limit(mut self, count: usize) -> Self {
         self.count = count;
@@ -84,6 +357,100 @@ impl<T> QueryBuilder<T> {
         self
     }
 
+    /// Confirms a delete matching the date range and filters instead of a single
+    /// [`id`](Self::id).
+    ///
+    /// Such a delete removes every matching document on the server, not only the first
+    /// `limit` ones, so it is refused unless confirmed. A delete without a date range, a
+    /// filter or a device would empty the collection, and is still refused, see
+    /// [`confirm_all`](Self::confirm_all).
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// # use cinnamon::client::NightscoutClient;
+    /// # use cinnamon::query_builder::FilterOp;
+    /// # use chrono::{Duration, Utc};
+    /// # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+    /// let client = NightscoutClient::new("https://ns.example.com")?.with_secret("secret");
+    /// client.treatments()
+    ///     .delete()
+    ///     .from(Utc::now() - Duration::days(1))
+    ///     .filter("enteredBy", FilterOp::Eq, "test")
+    ///     .confirm()
+    ///     .send()
+    ///     .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn confirm(mut self) -> Self {
+        self.confirmed = true;
+        self
+    }
+
+    /// Confirms a delete by query even without a date range, filter or device, which
+    /// removes every document of the collection.
+    pub fn confirm_all(mut self) -> Self {
+        self.confirmed = true;
+        self.confirmed_all = true;
+        self
+    }
+
+    /// Skips items that fail to deserialize instead of failing the whole query.
+    ///
+    /// Useful on instances where a single uploader writes malformed documents. Use
+    /// [`send_partial`](Self::send_partial) to also get the errors.
+    pub fn lenient(mut self) -> Self {
+        self.lenient = true;
+        self
+    }
+
+    /// Fails the request with [`NightscoutError::Timeout`] if it takes longer than
+    /// `timeout`.
+    ///
+    /// This is an upper bound on top of the client's own
+    /// [`timeout`](crate::client::ClientBuilder::timeout), which still applies to
+    /// each attempt: it can shorten a query, but not let a single request run longer than
+    /// the client allows.
+    ///
+    /// The limit covers the whole call, retries included; with
+    /// [`paginate`](Self::paginate) it applies to each page. The request is aborted when
+    /// the limit is reached, as it is when the future is dropped, so an application can
+    /// also cancel a query by dropping it.
+    pub fn timeout(mut self, timeout: std::time::Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Authenticates this query with other credentials than the client's.
+    ///
+    /// Lets one client read with a restricted token and write with the API secret. The
+    /// JWTs of access tokens are still shared with the client, so they are only
+    /// exchanged once, and so are its response cache and server capabilities.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// # use cinnamon::client::NightscoutClient;
+    /// # use cinnamon::models::auth::AuthMode;
+    /// # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+    /// let client = NightscoutClient::new("https://ns.example.com")?.with_token("readable-1a2b3c");
+    /// let entries = client.sgv().get().limit(10).send().await?;
+    ///
+    /// client.treatments()
+    ///     .delete()
+    ///     .id("65a0c0ffee")
+    ///     .with_auth(AuthMode::secret("api-secret"))
+    ///     .send()
+    ///     .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn with_auth(mut self, mode: AuthMode) -> Self {
+        self.client = self.client.with_query_auth(mode);
+        self
+    }
+
     /// Some nightscout entries use different date filter names
     ///
     /// This function allows to override the default dateString date field query
@@ -125,43 +492,196 @@ impl<T> QueryBuilder<T> {
         self.device = device;
         self
     }
+
+    /// Adds an arbitrary `find` condition on any field of the collection.
+    ///
+    /// Conditions are combined with the date range and device filters. Note that
+    /// Nightscout stores numbers and strings differently, the server decides how the
+    /// value is compared.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// # use cinnamon::client::NightscoutClient;
+    /// # use cinnamon::query_builder::FilterOp;
+    /// # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+    /// let client = NightscoutClient::new("https://ns.example.com")?;
+    ///
+    /// let highs = client.sgv().get()
+    ///     .filter("sgv", FilterOp::Gte, 250)
+    ///     .send()
+    ///     .await?;
+    ///
+    /// let site_changes = client.treatments().get()
+    ///     .filter("eventType", FilterOp::Eq, "Site Change")
+    ///     .send()
+    ///     .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn filter(mut self, field: impl Into<String>, op: FilterOp, value: impl ToString) -> Self {
+        self.filters.push(Filter {
+            field: field.into(),
+            op,
+            value: value.to_string(),
+        });
+        self
+    }
+
+    /// Matches documents whose `field` equals any of `values`.
+    pub fn filter_in<V: ToString>(mut self, field: impl Into<String>, values: &[V]) -> Self {
+        let field = field.into();
+        for value in values {
+            self = self.filter(field.clone(), FilterOp::In, value.to_string());
+        }
+        self
+    }
+
+    /// Restricts the returned documents to the given fields.
+    ///
+    /// This reduces the payload size. The fields required by the model (e.g. `sgv`,
+    /// `date`, `direction` and `type` for SGV entries) must be included for the
+    /// response to deserialize.
+    pub fn fields(mut self, fields: &[&str]) -> Self {
+        self.fields.extend(fields.iter().map(|f| f.to_string()));
+        self
+    }
+
+    /// Sorts the results by `field`.
+    ///
+    /// Without it, Nightscout returns the newest documents first. Pagination relies on
+    /// that order, so avoid sorting ascending when using
+    /// [`paginate`](Self::paginate).
+    pub fn sort(mut self, field: impl Into<String>, order: Order) -> Self {
+        self.sort = Some((field.into(), order));
+        self
+    }
+
+    /// Whether this query goes through the v3 API, see [`ApiVersion::V3`].
+    fn uses_v3(&self) -> bool {
+        self.method == Method::GET
+            && self.client.api_version == ApiVersion::V3
+            && self.endpoint.v3_collection().is_some()
+    }
+
+    /// The endpoint path for the client's API version.
+    fn path(&self) -> Cow<'static, str> {
+        if self.uses_v3() {
+            self.endpoint.path_for(ApiVersion::V3)
+        } else {
+            self.endpoint.path_for(self.client.api_version.legacy())
+        }
+    }
+
+    /// The query parameter limiting the number of results.
+    fn count_key(&self) -> &'static str {
+        if self.uses_v3() {
+            "limit"
+        } else {
+            "count"
+        }
+    }
+
+    /// Appends the date range and custom filters to a query.
+    fn append_filters(
+        &self,
+        query: &mut url::form_urlencoded::Serializer<'_, url::UrlQuery<'_>>,
+    ) -> Result<(), NightscoutError> {
+        if self.uses_v3() {
+            return self.append_v3_filters(query);
+        }
+
+        if let Some(from) = self.from_date {
+            let key = format!("find[{}][$gte]", self.date_field);
+            query.append_pair(&key, &self.format_bound(from));
+        }
+
+        if let Some(to) = self.to_date {
+            let key = format!("find[{}][$lte]", self.date_field);
+            query.append_pair(&key, &self.format_bound(to));
+        }
+
+        for filter in &self.filters {
+            query.append_pair(&filter.op.key(&filter.field), &filter.value);
+        }
+
+        Ok(())
+    }
+
+    /// Appends the filters in the v3 `field$op=value` syntax, where the values of `$in`
+    /// and `$nin` are joined with `|`.
+    fn append_v3_filters(
+        &self,
+        query: &mut url::form_urlencoded::Serializer<'_, url::UrlQuery<'_>>,
+    ) -> Result<(), NightscoutError> {
+        if let Some((_, Some(entry_type))) = self.endpoint.v3_collection() {
+            query.append_pair("type$eq", entry_type);
+        }
+
+        if let Some(from) = self.from_date {
+            let key = format!("{}$gte", self.date_field);
+            query.append_pair(&key, &self.format_bound(from));
+        }
+
+        if let Some(to) = self.to_date {
+            let key = format!("{}$lte", self.date_field);
+            query.append_pair(&key, &self.format_bound(to));
+        }
+
+        let mut lists: Vec<(String, Vec<&str>)> = Vec::new();
+        for filter in &self.filters {
+            let op = filter.op.v3_operator().ok_or_else(|| {
+                NightscoutError::InvalidInput(format!(
+                    "{:?} filters are not supported by the v3 API",
+                    filter.op
+                ))
+            })?;
+            let key = format!("{}${}", filter.field, op);
+
+            if matches!(filter.op, FilterOp::In | FilterOp::Nin) {
+                match lists.iter_mut().find(|(k, _)| *k == key) {
+                    Some((_, values)) => values.push(&filter.value),
+                    None => lists.push((key, vec![&filter.value])),
+                }
+            } else {
+                query.append_pair(&key, &filter.value);
+            }
+        }
+
+        for (key, values) in lists {
+            query.append_pair(&key, &values.join("|"));
+        }
+
+        Ok(())
+    }
 }
 
 impl<T> QueryBuilder<T>
 where
     T: DeserializeOwned + Send + Sync + 'static + HasDevice,
 {
-    /// Executes the built query.
-    ///
-    /// This method sends the HTTP request to Nightscout constructed by the builder methods.
-    pub async fn send(self) -> Result<Vec<T>, NightscoutError> {
+    /// Resolves the device name to filter on, if any.
+    async fn resolve_device(&self) -> Option<String> {
         // For Device::Auto, it is needed to do a pre-flight to determine which device to use.
         // While it has performance impact, it's a good tradeoff if you do not know the device
         // names on the server and only want data from one device.
-        let resolved_device_name: Option<String> = match &self.device {
+        match &self.device {
             Device::Custom(name) => Some(name.clone()),
             Device::Auto => {
-                let mut probe_url = self.client.base_url.join(self.endpoint.as_path())?;
+                let mut probe_url = self.client.base_url.join(&self.path()).ok()?;
                 {
                     let mut query = probe_url.query_pairs_mut();
-                    query.append_pair("count", "1");
+                    query.append_pair(self.count_key(), "1");
 
                     // We still need to access the data at the interval which the user wants us to get data
                     // if we didn't the device name could be (and probably will be) total wrong.
-                    if let Some(from) = self.from_date {
-                        let key = format!("find[{}][$gte]", self.date_field);
-                        query.append_pair(&key, &self.format_bound(from));
-                    }
-
-                    if let Some(to) = self.to_date {
-                        let key = format!("find[{}][$lte]", self.date_field);
-                        query.append_pair(&key, &self.format_bound(to));
-                    }
+                    self.append_filters(&mut query).ok()?;
                 }
-                let probe_result: Result<Vec<T>, _> = self.client.fetch(probe_url).await;
+                let probe_result = self.fetch_items(probe_url).await;
 
                 match probe_result {
-                    Ok(items) => items
+                    Ok(page) => page
+                        .items
                         .first()
                         .and_then(|item| item.device())
                         .map(|s| s.to_string()),
@@ -169,12 +689,15 @@ where
                 }
             }
             Device::All => None,
-        };
+        }
+    }
 
+    /// Builds the request URL from the current builder state.
+    fn build_url(&self, device: Option<&str>) -> Result<reqwest::Url, NightscoutError> {
         let path = if let Some(id) = &self.id {
-            format!("{}/{}", self.endpoint.as_path(), id)
+            format!("{}/{}", self.path(), id)
         } else {
-            self.endpoint.as_path().to_string()
+            self.path().into_owned()
         };
 
         let mut url = self.client.base_url.join(&path)?;
@@ -183,69 +706,568 @@ where
             let mut query = url.query_pairs_mut();
 
             if self.id.is_none() {
-                query.append_pair("count", &self.count.to_string());
+                query.append_pair(self.count_key(), &self.count.to_string());
+                self.append_filters(&mut query)?;
 
-                if let Some(from) = self.from_date {
-                    let key = format!("find[{}][$gte]", self.date_field);
-                    query.append_pair(&key, &self.format_bound(from));
+                if !self.fields.is_empty() {
+                    query.append_pair("fields", &self.fields.join(","));
                 }
 
-                if let Some(to) = self.to_date {
-                    let key = format!("find[{}][$lte]", self.date_field);
-                    query.append_pair(&key, &self.format_bound(to));
+                match &self.sort {
+                    Some((field, Order::Asc)) => {
+                        query.append_pair("sort", field);
+                    }
+                    Some((field, Order::Desc)) => {
+                        query.append_pair("sort$desc", field);
+                    }
+                    None => {}
                 }
 
-                if let Some(name) = &resolved_device_name {
-                    query.append_pair("find[device]", name);
+                if let Some(name) = device {
+                    let key = if self.uses_v3() {
+                        format!("{}$eq", T::DEVICE_FIELD)
+                    } else {
+                        format!("find[{}]", T::DEVICE_FIELD)
+                    };
+                    query.append_pair(&key, name);
                 }
             }
         }
 
-        match self.method {
-            Method::GET => {
-                let items: Vec<T> = self.client.fetch(url).await?;
-                Ok(items)
+        Ok(url)
+    }
+
+    /// Executes the built query.
+    ///
+    /// This method sends the HTTP request to Nightscout constructed by the builder methods.
+    pub async fn send(self) -> Result<Vec<T>, NightscoutError> {
+        Ok(self.execute().await?.items)
+    }
+
+    /// Executes the built query, returning the status, headers, URL and timing of the
+    /// response along with the items.
+    ///
+    /// The request always reaches the server, bypassing the response cache and
+    /// conditional requests. Only available for queries built with `get()`.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// # use cinnamon::client::NightscoutClient;
+    /// # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+    /// let client = NightscoutClient::new("https://ns.example.com")?;
+    /// let response = client.sgv().get().limit(12).send_with_meta().await?;
+    ///
+    /// println!("{} readings in {:?}", response.data.len(), response.meta.elapsed);
+    /// if let Some(remaining) = response.meta.header("x-ratelimit-remaining") {
+    ///     println!("{remaining} requests left");
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn send_with_meta(self) -> Result<WithMeta<Vec<T>>, NightscoutError> {
+        if self.method != Method::GET {
+            return Err(NightscoutError::InvalidInput(
+                "send_with_meta() only supports queries built with get()".to_string(),
+            ));
+        }
+
+        runtime::timeout(self.timeout, async {
+            let device = self.resolve_device().await;
+            let url = self.build_url(device.as_deref())?;
+            let (body, meta) = self.client.fetch_with_meta(url).await?;
+            let items = self.items_from(serde_json::from_slice(&body)?)?.items;
+
+            Ok(WithMeta { data: items, meta })
+        })
+        .await
+    }
+
+    /// Executes the query asking for tab-separated values instead of JSON.
+    ///
+    /// The path is requested without its `.json` suffix and with an
+    /// `Accept: text/tab-separated-values` header, the lightweight format old instances
+    /// and some uploaders use for entries. Rows are read with [`export::from_tsv`]; a
+    /// server answering with JSON anyway is handled too. Only available for entries
+    /// queries built with `get()`, on the v1 and v2 APIs.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// # use cinnamon::client::NightscoutClient;
+    /// # use cinnamon::endpoints::ApiVersion;
+    /// # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+    /// let client = NightscoutClient::new("https://ns.example.com")?.with_api_version(ApiVersion::V1);
+    /// let readings = client.sgv().get().limit(288).send_tsv().await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg(feature = "csv")]
+    pub async fn send_tsv(self) -> Result<Vec<T>, NightscoutError>
+    where
+        T: CsvRecord,
+    {
+        let entries = matches!(
+            self.endpoint,
+            Endpoint::Entries | Endpoint::Sgv | Endpoint::Mbg | Endpoint::Cal
+        );
+        if self.method != Method::GET || !entries || self.uses_v3() {
+            return Err(NightscoutError::InvalidInput(
+                "send_tsv() only supports v1/v2 entries queries built with get()".to_string(),
+            ));
+        }
+
+        runtime::timeout(self.timeout, async {
+            let device = self.resolve_device().await;
+            let mut url = self.build_url(device.as_deref())?;
+            let path = url.path().trim_end_matches(".json").to_string();
+            url.set_path(&path);
+
+            let (body, content_type) = self
+                .client
+                .fetch_text(url, "text/tab-separated-values")
+                .await?;
+            if content_type.is_some_and(|media| media.contains("json")) {
+                return Ok(self.items_from(serde_json::from_str(&body)?)?.items);
+            }
+            export::from_tsv(&body)
+        })
+        .await
+    }
+
+    /// Executes the query for the most recent matching document, `None` if there is none.
+    ///
+    /// Overrides the limit to 1 and the sort to the date field, newest first.
+    pub async fn first(mut self) -> Result<Option<T>, NightscoutError> {
+        self.count = 1;
+        self.sort = Some((self.date_field.clone(), Order::Desc));
+        Ok(self.send().await?.into_iter().next())
+    }
+
+    /// Executes the built query, decoding each item independently.
+    ///
+    /// Items that fail to deserialize are returned in [`PartialResult::errors`] with their
+    /// position in the response instead of failing the whole query.
+    pub async fn send_partial(mut self) -> Result<PartialResult<T>, NightscoutError> {
+        self.lenient = true;
+        self.execute().await
+    }
+
+    /// Executes the query as a conditional request.
+    ///
+    /// Returns [`Conditional::NotModified`] when the server reports that the result did not
+    /// change since the previous identical query, see [`crate::conditional`].
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// # use cinnamon::client::NightscoutClient;
+    /// # use cinnamon::conditional::Conditional;
+    /// # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+    /// let client = NightscoutClient::new("https://ns.example.com")?;
+    ///
+    /// if let Conditional::Modified(entries) = client.sgv().get().limit(1).send_if_modified().await? {
+    ///     println!("New reading: {:?}", entries.first().map(|e| e.sgv));
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn send_if_modified(self) -> Result<Conditional<Vec<T>>, NightscoutError> {
+        if self.method != Method::GET {
+            return Err(NightscoutError::InvalidInput(
+                "only queries can be sent conditionally".to_string(),
+            ));
+        }
+
+        runtime::timeout(self.timeout, async {
+            let resolved_device_name = self.resolve_device().await;
+            let url = self.build_url(resolved_device_name.as_deref())?;
+
+            match self.client.fetch_conditional(url).await? {
+                Conditional::Modified(body) => {
+                    Ok(Conditional::Modified(self.items_from(body)?.items))
+                }
+                Conditional::NotModified => Ok(Conditional::NotModified),
             }
+        })
+        .await
+    }
+
+    async fn execute(self) -> Result<PartialResult<T>, NightscoutError> {
+        runtime::timeout(self.timeout, self.execute_untimed()).await
+    }
+
+    async fn execute_untimed(&self) -> Result<PartialResult<T>, NightscoutError> {
+        let resolved_device_name = self.resolve_device().await;
+        let url = self.build_url(resolved_device_name.as_deref())?;
+
+        match self.method {
+            Method::GET => self.fetch_items(url).await,
             Method::DELETE => {
+                // Fail before the items to delete are fetched.
+                self.client.ensure_writable("DELETE queries")?;
+
                 if self.id.is_some() {
-                    let item: Vec<T> = self.client.fetch(url.clone()).await?;
+                    let items = self.fetch_items(url.clone()).await?;
 
                     let mut del_req = self.client.http.delete(url);
-                    del_req = self.client.auth(del_req);
+                    del_req = self.client.authorize(del_req).await?;
                     self.client.send_checked(del_req).await?;
 
-                    Ok(item)
+                    Ok(items)
                 } else {
-                    let items: Vec<serde_json::Value> = self.client.fetch(url.clone()).await?;
-
-                    let delete_urls: Vec<reqwest::Url> = items
-                        .iter()
-                        .filter_map(|item| {
-                            let id = item.get("_id")?.as_str()?;
-                            let delete_path = format!("{}/{}", self.endpoint.as_path(), id);
-                            self.client.base_url.join(&delete_path).ok()
-                        })
-                        .collect();
-
-                    let delete_tasks = delete_urls.into_iter().map(|url| {
-                        let client = self.client.clone();
-                        async move {
-                            let mut req = client.http.delete(url);
-                            req = client.auth(req);
-                            client.send_checked(req).await
-                        }
-                    });
-
-                    stream::iter(delete_tasks)
-                        .buffer_unordered(10)
-                        .collect::<Vec<_>>()
-                        .await;
-
-                    let t_items: Vec<T> = serde_json::from_value(serde_json::Value::Array(items))?;
-                    Ok(t_items)
+                    if !self.confirmed {
+                        return Err(NightscoutError::InvalidInput(
+                            "deleting by query removes every matching document, call confirm() first"
+                                .to_string(),
+                        ));
+                    }
+
+                    let bounded = self.from_date.is_some()
+                        || self.to_date.is_some()
+                        || !self.filters.is_empty()
+                        || resolved_device_name.is_some();
+                    if !bounded && !self.confirmed_all {
+                        return Err(NightscoutError::InvalidInput(
+                            "deleting by query without a date range, filter or device empties the collection, call confirm_all() to do so"
+                                .to_string(),
+                        ));
+                    }
+
+                    // Report what is about to be removed, then delete it in one request.
+                    let items = self.fetch_items(url.clone()).await?;
+
+                    let mut del_req = self.client.http.delete(url);
+                    del_req = self.client.authorize(del_req).await?;
+                    self.client.send_checked(del_req).await?;
+
+                    Ok(items)
                 }
             }
             _ => Err(NightscoutError::Unknown),
         }
     }
 }
+
+impl<T: DeserializeOwned> QueryBuilder<T> {
+    /// Fetches a page of items, decoding them one by one in lenient mode.
+    async fn fetch_items(&self, url: reqwest::Url) -> Result<PartialResult<T>, NightscoutError> {
+        if self.uses_v3() {
+            let body = self.client.fetch(url).await?;
+            return self.items_from(body);
+        }
+
+        if self.lenient {
+            let values: Vec<serde_json::Value> = self.client.fetch(url).await?;
+            self.decode(values)
+        } else {
+            Ok(PartialResult {
+                items: self.client.fetch(url).await?,
+                errors: Vec::new(),
+            })
+        }
+    }
+
+    /// Decodes a response body holding an array of items.
+    fn items_from(&self, body: serde_json::Value) -> Result<PartialResult<T>, NightscoutError> {
+        // The v3 API wraps results in `{"status": 200, "result": [...]}`.
+        let result = match body {
+            serde_json::Value::Object(mut object) if object.contains_key("result") => {
+                object.remove("result").unwrap_or_default()
+            }
+            other => other,
+        };
+        let values = match result {
+            serde_json::Value::Array(items) => items,
+            serde_json::Value::Null => Vec::new(),
+            item => vec![item],
+        };
+        self.decode(values)
+    }
+
+    /// Decodes raw items, failing on the first invalid one unless in lenient mode.
+    fn decode(&self, values: Vec<serde_json::Value>) -> Result<PartialResult<T>, NightscoutError> {
+        let mut result = PartialResult {
+            items: Vec::with_capacity(values.len()),
+            errors: Vec::new(),
+        };
+
+        for (index, value) in values.into_iter().enumerate() {
+            match serde_json::from_value(value) {
+                Ok(item) => result.items.push(item),
+                Err(e) if self.lenient => {
+                    #[cfg(feature = "tracing")]
+                    tracing::debug!(index, error = %e, "skipping malformed item");
+
+                    result.errors.push((index, e));
+                }
+                Err(e) => return Err(e.into()),
+            }
+        }
+
+        Ok(result)
+    }
+}
+
+impl<T> QueryBuilder<T>
+where
+    T: DeserializeOwned + Send + Sync + 'static + HasDevice + HasDate,
+{
+    /// Streams every result matching the query, fetching `page_size` items per request.
+    ///
+    /// Nightscout returns the newest items first. After each page, the upper date bound
+    /// is moved to the oldest item received, until a short page is returned or the `from`
+    /// bound is reached. Items at that millisecond are fetched again and dropped, so
+    /// documents sharing it are not lost at the page boundary. This allows pulling long
+    /// date ranges without manual chunking.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// # use cinnamon::client::NightscoutClient;
+    /// # use chrono::{Duration, Utc};
+    /// # use futures::StreamExt;
+    /// # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+    /// let client = NightscoutClient::new("https://ns.example.com")?;
+    /// let mut entries = Box::pin(
+    ///     client.sgv()
+    ///         .get()
+    ///         .from(Utc::now() - Duration::days(90))
+    ///         .paginate(1000),
+    /// );
+    ///
+    /// while let Some(entry) = entries.next().await {
+    ///     println!("{}", entry?.sgv);
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn paginate(mut self, page_size: usize) -> impl Stream<Item = Result<T, NightscoutError>>
+    where
+        T: Serialize,
+    {
+        self.count = page_size.max(1);
+
+        let state = PageState {
+            builder: self,
+            device: None,
+            seen: HashSet::new(),
+            done: false,
+        };
+
+        stream::unfold(state, |mut state| async move {
+            if state.done {
+                return None;
+            }
+
+            let device = match &state.device {
+                Some(device) => device.clone(),
+                None => {
+                    let device = state.builder.resolve_device().await;
+                    state.device = Some(device.clone());
+                    device
+                }
+            };
+
+            let page = match state.builder.build_url(device.as_deref()) {
+                Ok(url) => {
+                    let timeout = state.builder.timeout;
+                    runtime::timeout(timeout, state.builder.fetch_items(url)).await
+                }
+                Err(e) => Err(e),
+            };
+
+            match page {
+                Ok(page) => {
+                    let fetched = page.items.len() + page.errors.len();
+                    let items = state.advance(page.items, fetched);
+                    Some((items.into_iter().map(Ok).collect::<Vec<_>>(), state))
+                }
+                Err(e) => {
+                    state.done = true;
+                    Some((vec![Err(e)], state))
+                }
+            }
+        })
+        .flat_map(stream::iter)
+    }
+
+    /// Fetches every result of the date range by splitting it into chunks of `chunk`,
+    /// fetched `concurrency` at a time.
+    ///
+    /// Over high-latency links this is much faster than [`paginate`](Self::paginate),
+    /// which has to wait for each page before requesting the next one. Each chunk is
+    /// paginated on its own, the results are merged newest first and documents returned
+    /// twice are dropped. The range needs a start, set with [`from`](Self::from) or one
+    /// of the relative ranges; it ends now unless [`to`](Self::to) is set.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// # use cinnamon::client::NightscoutClient;
+    /// # use std::time::Duration;
+    /// # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+    /// let client = NightscoutClient::new("https://ns.example.com")?;
+    /// let year = client.sgv()
+    ///     .get()
+    ///     .last_days(365)
+    ///     .send_chunked(Duration::from_secs(7 * 24 * 3600), 4)
+    ///     .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn send_chunked(
+        mut self,
+        chunk: std::time::Duration,
+        concurrency: usize,
+    ) -> Result<Vec<T>, NightscoutError>
+    where
+        T: Serialize,
+    {
+        let from = self.from_date.ok_or_else(|| {
+            NightscoutError::InvalidInput("send_chunked() needs a start date".to_string())
+        })?;
+        let to = self.to_date.unwrap_or_else(|| self.client.server_now());
+        let step = Duration::from_std(chunk)
+            .ok()
+            .filter(|step| *step > Duration::zero())
+            .ok_or_else(|| {
+                NightscoutError::InvalidInput("the chunk duration must be positive".to_string())
+            })?;
+
+        // Resolve an automatic device once instead of once per chunk.
+        if self.device == Device::Auto {
+            self.device = match self.resolve_device().await {
+                Some(name) => Device::Custom(name),
+                None => Device::All,
+            };
+        }
+
+        let mut ranges = Vec::new();
+        let mut start = from;
+        while start <= to {
+            let next = start
+                .checked_add_signed(step)
+                .unwrap_or(DateTime::<Utc>::MAX_UTC);
+            // Bounds are inclusive, stop right before the next chunk.
+            let end = (next - Duration::milliseconds(1)).min(to);
+            ranges.push((start, end));
+            if next > to {
+                break;
+            }
+            start = next;
+        }
+
+        let chunks = ranges.into_iter().map(|(from, to)| {
+            let mut query = self.clone();
+            query.from_date = Some(from);
+            query.to_date = Some(to);
+            query.paginate(CHUNK_PAGE_SIZE).try_collect::<Vec<T>>()
+        });
+        let pages: Vec<Vec<T>> = stream::iter(chunks)
+            .buffer_unordered(concurrency.max(1))
+            .try_collect()
+            .await?;
+
+        let mut items: Vec<T> = pages.into_iter().flatten().collect();
+        items.sort_by_key(|item| std::cmp::Reverse(item.timestamp()));
+
+        let mut seen = HashSet::new();
+        let mut unique = Vec::with_capacity(items.len());
+        for item in items {
+            if seen.insert(document_key(&item)) {
+                unique.push(item);
+            }
+        }
+
+        Ok(unique)
+    }
+
+    /// Streams every result matching the query, using the configured `limit` as page size.
+    ///
+    /// See [`paginate`](Self::paginate).
+    pub fn into_stream(self) -> impl Stream<Item = Result<T, NightscoutError>>
+    where
+        T: Serialize,
+    {
+        let page_size = self.count;
+        self.paginate(page_size)
+    }
+}
+
+/// Internal cursor state of [`QueryBuilder::paginate`].
+struct PageState<T> {
+    builder: QueryBuilder<T>,
+    /// The resolved device filter, `None` until the first page is requested.
+    device: Option<Option<String>>,
+    /// Keys of the items already returned at the upper date bound, which the next page
+    /// includes again.
+    seen: HashSet<String>,
+    done: bool,
+}
+
+impl<T: HasDate + Serialize> PageState<T> {
+    /// Moves the upper date bound to the oldest item of the page, and returns the items
+    /// not returned by an earlier page.
+    ///
+    /// The bound is inclusive, so documents sharing the oldest millisecond that did not fit
+    /// in the page are fetched by the next one. `fetched` counts every item of the page,
+    /// including those skipped in lenient mode.
+    fn advance(&mut self, items: Vec<T>, fetched: usize) -> Vec<T> {
+        let bound = self.builder.to_date;
+        let oldest = items.iter().filter_map(|item| item.timestamp()).min();
+        // Guard against servers ignoring the date filter, which would loop forever.
+        let ignored = bound.is_some_and(|to| {
+            items
+                .iter()
+                .any(|item| item.timestamp().is_some_and(|date| date > to))
+        });
+
+        let previous = std::mem::take(&mut self.seen);
+        let mut seen = if oldest.is_some() && oldest == bound {
+            previous.clone()
+        } else {
+            HashSet::new()
+        };
+        let mut fresh = Vec::with_capacity(items.len());
+        for item in items {
+            let key = document_key(&item);
+            if oldest.is_some() && item.timestamp() == oldest {
+                seen.insert(key.clone());
+            }
+            if !previous.contains(&key) {
+                fresh.push(item);
+            }
+        }
+        self.seen = seen;
+
+        let Some(oldest) = oldest else {
+            self.done = true;
+            return fresh;
+        };
+
+        // A page without anything new cannot move the bound, which happens when more
+        // documents than the page size share a millisecond.
+        let stalled = ignored || fresh.is_empty();
+        let exhausted = self.builder.from_date.is_some_and(|from| oldest < from);
+
+        if fetched < self.builder.count || stalled || exhausted {
+            self.done = true;
+        }
+
+        self.builder.to_date = Some(oldest);
+        fresh
+    }
+}
+
+/// The `_id` of a document, or its whole JSON when it has none, to recognize documents
+/// returned twice.
+fn document_key<T: Serialize>(item: &T) -> String {
+    match serde_json::to_value(item) {
+        Ok(value) => match value.get("_id").and_then(|id| id.as_str()) {
+            Some(id) => id.to_string(),
+            None => value.to_string(),
+        },
+        Err(_) => String::new(),
+    }
+}
@@ -0,0 +1,371 @@
+//! An offline upload queue for uploaders running on unreliable networks.
+//!
+//! Writes are buffered locally and uploaded when [`UploadQueue::flush`] succeeds. Each item
+//! carries a client-generated UUID: pushing the same item twice, or replaying a queue that
+//! was partially uploaded, is rejected locally.
+//!
+//! Uploads go through the v1/v2 API, which does not deduplicate on the UUID. An upload that
+//! reached the server but whose response was lost, e.g. in a crash, is sent again as is,
+//! and the server's upsert keeps a single document: entries are matched on their date and
+//! `type`, treatments on their `created_at` and `eventType`. The UUID is still sent as the
+//! document's `identifier`, for v3 clients reading the data.
+//!
+//! With the `persistence` feature, [`UploadQueue::persistent`] stores the queue in a JSON
+//! file so pending writes survive restarts.
+
+use crate::client::NightscoutClient;
+use crate::error::NightscoutError;
+use crate::models::entries::{MbgEntry, SgvEntry};
+use crate::models::treatments::Treatment;
+use crate::retry::RetryPolicy;
+use crate::runtime;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha1::{Digest, Sha1};
+use std::collections::VecDeque;
+use std::time::Duration;
+use tokio::sync::Mutex;
+
+#[cfg(feature = "persistence")]
+use std::path::{Path, PathBuf};
+
+/// Number of uploaded UUIDs remembered to reject replays.
+const UPLOADED_CAPACITY: usize = 10_000;
+
+/// Failed uploads of an item before it is set aside, see [`UploadQueue::max_attempts`].
+const DEFAULT_MAX_ATTEMPTS: u32 = 10;
+
+/// A document waiting to be uploaded.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(tag = "kind", content = "document", rename_all = "lowercase")]
+#[non_exhaustive]
+pub enum QueuedItem {
+    Sgv(SgvEntry),
+    Mbg(MbgEntry),
+    Treatment(Box<Treatment>),
+}
+
+/// A queued document with its deduplication UUID.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct QueuedUpload {
+    pub uuid: String,
+    pub item: QueuedItem,
+    /// Number of failed upload attempts so far.
+    pub attempts: u32,
+}
+
+#[derive(Debug, Serialize, Deserialize, Default)]
+struct QueueState {
+    pending: VecDeque<QueuedUpload>,
+    uploaded: VecDeque<String>,
+    #[serde(default)]
+    dead_letters: Vec<QueuedUpload>,
+}
+
+impl QueueState {
+    fn knows(&self, uuid: &str) -> bool {
+        self.pending.iter().any(|p| p.uuid == uuid)
+            || self.uploaded.iter().any(|u| u == uuid)
+            || self.dead_letters.iter().any(|p| p.uuid == uuid)
+    }
+}
+
+pub struct UploadQueue {
+    client: NightscoutClient,
+    state: Mutex<QueueState>,
+    /// Held for the whole of [`UploadQueue::flush`], so concurrent flushes do not upload
+    /// the same items while `state` is unlocked.
+    flushing: Mutex<()>,
+    max_attempts: u32,
+    #[cfg(feature = "persistence")]
+    path: Option<PathBuf>,
+}
+
+impl UploadQueue {
+    /// Creates an in-memory queue.
+    pub fn new(client: NightscoutClient) -> Self {
+        Self {
+            client,
+            state: Mutex::new(QueueState::default()),
+            flushing: Mutex::new(()),
+            max_attempts: DEFAULT_MAX_ATTEMPTS,
+            #[cfg(feature = "persistence")]
+            path: None,
+        }
+    }
+
+    /// Creates a queue persisted to the JSON file at `path`, loading any pending
+    /// uploads left from a previous run.
+    #[cfg(feature = "persistence")]
+    pub fn persistent(
+        client: NightscoutClient,
+        path: impl AsRef<Path>,
+    ) -> Result<Self, NightscoutError> {
+        let path = path.as_ref().to_path_buf();
+        let state = match std::fs::read(&path) {
+            Ok(bytes) => serde_json::from_slice(&bytes)?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => QueueState::default(),
+            Err(e) => return Err(e.into()),
+        };
+
+        Ok(Self {
+            client,
+            state: Mutex::new(state),
+            flushing: Mutex::new(()),
+            max_attempts: DEFAULT_MAX_ATTEMPTS,
+            path: Some(path),
+        })
+    }
+
+    /// Failed uploads after which an item is moved to the
+    /// [dead letters](Self::take_dead_letters) instead of blocking the items behind it.
+    /// Defaults to 10.
+    pub fn max_attempts(mut self, attempts: u32) -> Self {
+        self.max_attempts = attempts.max(1);
+        self
+    }
+
+    /// Generates the UUID of an item from its content, so identical pushes deduplicate.
+    pub fn uuid_for(item: &QueuedItem) -> Result<String, NightscoutError> {
+        let bytes = serde_json::to_vec(item)?;
+        let hash = Sha1::digest(&bytes);
+        let hex = format!("{:x}", hash);
+
+        Ok(format!(
+            "{}-{}-5{}-{}-{}",
+            &hex[0..8],
+            &hex[8..12],
+            &hex[13..16],
+            &hex[16..20],
+            &hex[20..32]
+        ))
+    }
+
+    /// Queues an item under a content-derived UUID, returning it.
+    pub async fn push(&self, item: QueuedItem) -> Result<String, NightscoutError> {
+        let uuid = Self::uuid_for(&item)?;
+        self.push_with_uuid(uuid.clone(), item).await?;
+        Ok(uuid)
+    }
+
+    /// Queues an item under a caller-provided UUID.
+    ///
+    /// Returns `false` if an item with this UUID is already queued or was uploaded.
+    pub async fn push_with_uuid(
+        &self,
+        uuid: impl Into<String>,
+        item: QueuedItem,
+    ) -> Result<bool, NightscoutError> {
+        let uuid = uuid.into();
+        let mut state = self.state.lock().await;
+
+        if state.knows(&uuid) {
+            return Ok(false);
+        }
+
+        state.pending.push_back(QueuedUpload {
+            uuid,
+            item,
+            attempts: 0,
+        });
+        self.save(&state)?;
+
+        Ok(true)
+    }
+
+    /// Queues an SGV entry.
+    pub async fn push_sgv(&self, entry: SgvEntry) -> Result<String, NightscoutError> {
+        self.push(QueuedItem::Sgv(entry)).await
+    }
+
+    /// Queues an MBG entry.
+    pub async fn push_mbg(&self, entry: MbgEntry) -> Result<String, NightscoutError> {
+        self.push(QueuedItem::Mbg(entry)).await
+    }
+
+    /// Queues a treatment.
+    pub async fn push_treatment(&self, treatment: Treatment) -> Result<String, NightscoutError> {
+        self.push(QueuedItem::Treatment(Box::new(treatment))).await
+    }
+
+    /// Number of pending uploads.
+    pub async fn len(&self) -> usize {
+        self.state.lock().await.pending.len()
+    }
+
+    pub async fn is_empty(&self) -> bool {
+        self.len().await == 0
+    }
+
+    /// A snapshot of the pending uploads, oldest first.
+    pub async fn pending(&self) -> Vec<QueuedUpload> {
+        self.state.lock().await.pending.iter().cloned().collect()
+    }
+
+    /// Removes and returns the items that failed [`max_attempts`](Self::max_attempts)
+    /// times, oldest first. Push them again with [`push_with_uuid`](Self::push_with_uuid)
+    /// once the cause is fixed.
+    pub async fn take_dead_letters(&self) -> Result<Vec<QueuedUpload>, NightscoutError> {
+        let mut state = self.state.lock().await;
+        let dead_letters = std::mem::take(&mut state.dead_letters);
+        self.save(&state)?;
+        Ok(dead_letters)
+    }
+
+    /// Uploads pending items, oldest first, and returns how many were uploaded.
+    ///
+    /// Consecutive items of the same kind are sent in a single request. On the first
+    /// failure, the remaining items stay queued and the error is returned; call `flush`
+    /// again once the connection is back. Items of the failed request that reached
+    /// [`max_attempts`](Self::max_attempts) are moved to the dead letters. The queue is not
+    /// locked during uploads, items can be pushed meanwhile.
+    pub async fn flush(&self) -> Result<usize, NightscoutError> {
+        let _flushing = self.flushing.lock().await;
+        let mut uploaded = 0;
+
+        loop {
+            let batch: Vec<QueuedUpload> = {
+                let state = self.state.lock().await;
+                let Some(first) = state.pending.front() else {
+                    return Ok(uploaded);
+                };
+                state
+                    .pending
+                    .iter()
+                    .take_while(|p| {
+                        std::mem::discriminant(&p.item) == std::mem::discriminant(&first.item)
+                    })
+                    .cloned()
+                    .collect()
+            };
+            let batch_len = batch.len();
+
+            // Only this flush removes items, and pushes append, so the batch is still at
+            // the front of the queue.
+            let result = self.upload(&batch).await;
+            let mut state = self.state.lock().await;
+
+            if let Err(e) = result {
+                let failed: Vec<QueuedUpload> = state.pending.drain(..batch_len).collect();
+                let (dead, retry): (Vec<_>, Vec<_>) = failed
+                    .into_iter()
+                    .map(|mut pending| {
+                        pending.attempts = pending.attempts.saturating_add(1);
+                        pending
+                    })
+                    .partition(|pending| pending.attempts >= self.max_attempts);
+                for pending in retry.into_iter().rev() {
+                    state.pending.push_front(pending);
+                }
+                state.dead_letters.extend(dead);
+                self.save(&state)?;
+                return Err(e);
+            }
+
+            for done in state.pending.drain(..batch_len).collect::<Vec<_>>() {
+                state.uploaded.push_back(done.uuid);
+            }
+            while state.uploaded.len() > UPLOADED_CAPACITY {
+                state.uploaded.pop_front();
+            }
+            uploaded += batch_len;
+            self.save(&state)?;
+        }
+    }
+
+    /// Flushes the queue, retrying every `interval` until it is empty.
+    ///
+    /// Only transient failures (connection errors, `5xx` and `429` responses) are retried;
+    /// any other error, such as a document the server rejects, is returned.
+    pub async fn drain(&self, interval: Duration) -> Result<(), NightscoutError> {
+        let transient = RetryPolicy::default();
+        loop {
+            match self.flush().await {
+                Ok(_) => return Ok(()),
+                Err(e) if transient.should_retry(&e) => runtime::sleep(interval).await,
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    async fn upload(&self, batch: &[QueuedUpload]) -> Result<(), NightscoutError> {
+        let Some(first) = batch.first() else {
+            return Ok(());
+        };
+
+        match first.item {
+            QueuedItem::Sgv(_) => {
+                let entries = batch
+                    .iter()
+                    .filter_map(|p| match &p.item {
+                        QueuedItem::Sgv(e) => {
+                            let mut e = e.clone();
+                            set_identifier(&mut e.extra, &p.uuid);
+                            Some(e)
+                        }
+                        _ => None,
+                    })
+                    .collect();
+                self.client.sgv().create(entries).await?;
+            }
+            QueuedItem::Mbg(_) => {
+                let entries = batch
+                    .iter()
+                    .filter_map(|p| match &p.item {
+                        QueuedItem::Mbg(e) => {
+                            let mut e = e.clone();
+                            set_identifier(&mut e.extra, &p.uuid);
+                            Some(e)
+                        }
+                        _ => None,
+                    })
+                    .collect();
+                self.client.mbg().create(entries).await?;
+            }
+            QueuedItem::Treatment(_) => {
+                let treatments = batch
+                    .iter()
+                    .filter_map(|p| match &p.item {
+                        QueuedItem::Treatment(t) => {
+                            let mut t = (**t).clone();
+                            set_identifier(&mut t.extra, &p.uuid);
+                            Some(t)
+                        }
+                        _ => None,
+                    })
+                    .collect();
+                self.client.treatments().create(treatments).await?;
+            }
+        }
+
+        Ok(())
+    }
+
+    #[cfg(feature = "persistence")]
+    fn save(&self, state: &QueueState) -> Result<(), NightscoutError> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+
+        // Write then rename, so a crash mid-write never corrupts the queue.
+        let tmp = path.with_extension("tmp");
+        std::fs::write(&tmp, serde_json::to_vec(state)?)?;
+        std::fs::rename(&tmp, path)?;
+        Ok(())
+    }
+
+    #[cfg(not(feature = "persistence"))]
+    fn save(&self, _state: &QueueState) -> Result<(), NightscoutError> {
+        Ok(())
+    }
+}
+
+/// Sets the `identifier` of a document through its flattened extra fields. The v1/v2 API
+/// stores it without deduplicating on it, see the module documentation.
+fn set_identifier(extra: &mut Value, uuid: &str) {
+    if !extra.is_object() {
+        *extra = Value::Object(Default::default());
+    }
+    extra["identifier"] = Value::String(uuid.to_string());
+}
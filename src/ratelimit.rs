@@ -0,0 +1,144 @@
+//! Client-side throttling, so a polling loop gone wrong cannot overload a small server.
+
+use crate::error::NightscoutError;
+use crate::runtime;
+
+use chrono::{DateTime, Utc};
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// Maximum request rate of a client, see [`NightscoutClient::with_rate_limit`].
+///
+/// Requests are let through as long as tokens are available in a bucket of `burst`
+/// tokens, refilled at `requests_per_second`. Once it is empty, requests wait for their
+/// turn instead of failing.
+///
+/// [`NightscoutClient::with_rate_limit`]: crate::client::NightscoutClient::with_rate_limit
+///
+/// # Example
+///
+/// ```rust
+/// # use cinnamon::client::NightscoutClient;
+/// # use cinnamon::ratelimit::RateLimit;
+/// let client = NightscoutClient::new("https://example.com").unwrap()
+///     .with_rate_limit(RateLimit::per_second(2.0).unwrap().burst(5));
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct RateLimit {
+    /// Sustained number of requests per second.
+    requests_per_second: f64,
+    /// Number of requests that can be sent at once after a quiet period.
+    burst: u32,
+}
+
+impl RateLimit {
+    /// Allows `requests` requests per second, without bursts.
+    ///
+    /// Fails with [`NightscoutError::InvalidInput`] unless `requests` is positive and
+    /// finite.
+    pub fn per_second(requests: f64) -> Result<Self, NightscoutError> {
+        if !(requests > 0.0 && requests.is_finite()) {
+            return Err(NightscoutError::InvalidInput(format!(
+                "the request rate must be positive and finite, got {requests}"
+            )));
+        }
+
+        Ok(Self {
+            requests_per_second: requests,
+            burst: 1,
+        })
+    }
+
+    /// Allows `requests` requests per minute, without bursts.
+    ///
+    /// Fails with [`NightscoutError::InvalidInput`] unless `requests` is positive and
+    /// finite.
+    pub fn per_minute(requests: f64) -> Result<Self, NightscoutError> {
+        Self::per_second(requests / 60.0)
+    }
+
+    /// Sets the number of requests that can be sent at once. Defaults to 1.
+    pub fn burst(mut self, burst: u32) -> Self {
+        self.burst = burst.max(1);
+        self
+    }
+}
+
+/// The token bucket enforcing a [`RateLimit`], shared by the clones of a client.
+pub(crate) struct RateLimiter {
+    limit: RateLimit,
+    bucket: Mutex<Bucket>,
+}
+
+struct Bucket {
+    /// Available tokens, negative when requests are queued.
+    tokens: f64,
+    updated: DateTime<Utc>,
+}
+
+impl RateLimiter {
+    /// Creates a limiter with a full bucket.
+    pub(crate) fn new(limit: RateLimit) -> Self {
+        Self {
+            bucket: Mutex::new(Bucket {
+                tokens: f64::from(limit.burst.max(1)),
+                updated: Utc::now(),
+            }),
+            limit,
+        }
+    }
+
+    pub(crate) fn limit(&self) -> &RateLimit {
+        &self.limit
+    }
+
+    /// Waits until a request may be sent.
+    ///
+    /// The token is reserved right away, so concurrent callers are served in order
+    /// instead of competing for each refill. It is given back if the wait is cancelled.
+    pub(crate) async fn acquire(&self) {
+        let wait = {
+            let mut bucket = self.bucket.lock().unwrap_or_else(|e| e.into_inner());
+            let now = Utc::now();
+            let elapsed = (now - bucket.updated).to_std().unwrap_or_default();
+
+            bucket.tokens = (bucket.tokens
+                + elapsed.as_secs_f64() * self.limit.requests_per_second)
+                .min(self.burst())
+                - 1.0;
+            bucket.updated = now;
+
+            (bucket.tokens < 0.0).then(|| {
+                Duration::try_from_secs_f64(-bucket.tokens / self.limit.requests_per_second)
+                    .unwrap_or(Duration::MAX)
+            })
+        };
+
+        if let Some(wait) = wait {
+            let reservation = Reservation { limiter: self };
+            runtime::sleep(wait).await;
+            std::mem::forget(reservation);
+        }
+    }
+
+    fn burst(&self) -> f64 {
+        f64::from(self.limit.burst.max(1))
+    }
+}
+
+/// A token taken by a waiting [`RateLimiter::acquire`], returned to the bucket when the
+/// wait is dropped before it ends.
+struct Reservation<'a> {
+    limiter: &'a RateLimiter,
+}
+
+impl Drop for Reservation<'_> {
+    fn drop(&mut self) {
+        let mut bucket = self
+            .limiter
+            .bucket
+            .lock()
+            .unwrap_or_else(|e| e.into_inner());
+        bucket.tokens = (bucket.tokens + 1.0).min(self.limiter.burst());
+    }
+}
@@ -0,0 +1,137 @@
+//! Aggregated reports over a date range.
+
+use crate::analysis::basal::{self, TEMP_BASAL_EVENT};
+use crate::client::NightscoutClient;
+use crate::error::NightscoutError;
+use crate::models::treatments::Treatment;
+use crate::query_builder::{local_midnight, saturating_sub, HasDate};
+
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+use chrono_tz::Tz;
+use futures::TryStreamExt;
+use serde::{Deserialize, Serialize};
+
+/// How far back treatments are fetched, to catch a temp basal already running at the start.
+const LOOKBACK_HOURS: i64 = 24;
+
+/// Number of treatments fetched per request.
+const PAGE_SIZE: usize = 500;
+
+/// Insulin and carb totals for one local day, see [`ReportsService::daily_totals`].
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct DailyTotals {
+    pub date: NaiveDate,
+    /// Bolus insulin (U).
+    pub bolus_insulin: f64,
+    /// Basal insulin from the effective basal timeline (U).
+    pub basal_insulin: f64,
+    /// Carbs (g).
+    pub carbs: f64,
+    /// Number of treatments with insulin.
+    pub bolus_count: usize,
+}
+
+impl DailyTotals {
+    /// Total daily dose (U).
+    pub fn total_insulin(&self) -> f64 {
+        self.bolus_insulin + self.basal_insulin
+    }
+
+    /// Share of the total daily dose given as basal, in percent.
+    pub fn basal_percent(&self) -> Option<f64> {
+        let total = self.total_insulin();
+        (total > 0.0).then(|| self.basal_insulin / total * 100.0)
+    }
+}
+
+pub struct ReportsService {
+    pub client: NightscoutClient,
+}
+
+impl ReportsService {
+    /// Computes insulin and carb totals for each day in `[from, to)`.
+    ///
+    /// Days are split at midnight in the client's timezone, or else the timezone of the
+    /// profile active at `from`. The first and last days only cover the part that falls
+    /// within the range.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// # use cinnamon::client::NightscoutClient;
+    /// # use chrono::{Duration, Utc};
+    /// # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+    /// let client = NightscoutClient::new("https://ns.example.com")?;
+    /// let to = Utc::now();
+    /// let days = client.reports().daily_totals(to - Duration::days(7), to).await?;
+    ///
+    /// for day in days {
+    ///     println!("{}: {:.1} U, {:.0} g", day.date, day.total_insulin(), day.carbs);
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn daily_totals(
+        &self,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> Result<Vec<DailyTotals>, NightscoutError> {
+        let profile = self.client.analysis().active_profile(from).await?;
+        let treatments: Vec<Treatment> = self
+            .client
+            .treatments()
+            .get()
+            .from(saturating_sub(from, Duration::hours(LOOKBACK_HOURS)))
+            .to(to)
+            .paginate(PAGE_SIZE)
+            .try_collect()
+            .await?;
+
+        let timeline = basal::basal_timeline(&profile, &treatments, from, to);
+        let tz = self
+            .client
+            .timezone
+            .or_else(|| profile.tz())
+            .unwrap_or(Tz::UTC);
+
+        let mut totals = Vec::new();
+        let mut date = from.with_timezone(&tz).date_naive();
+        loop {
+            let start = local_midnight(&tz, date).max(from);
+            let Some(next) = date.succ_opt() else {
+                break;
+            };
+            let end = local_midnight(&tz, next).min(to);
+            if start >= to {
+                break;
+            }
+
+            let mut day = DailyTotals {
+                date,
+                bolus_insulin: 0.0,
+                basal_insulin: timeline.insulin_between(start, end),
+                carbs: 0.0,
+                bolus_count: 0,
+            };
+
+            for treatment in &treatments {
+                if treatment.timestamp().is_none_or(|t| t < start || t >= end) {
+                    continue;
+                }
+                if let Some(insulin) = treatment
+                    .insulin
+                    .filter(|_| treatment.event_type != TEMP_BASAL_EVENT)
+                {
+                    day.bolus_insulin += insulin;
+                    day.bolus_count += 1;
+                }
+                day.carbs += treatment.carbs.unwrap_or(0.0);
+            }
+
+            totals.push(day);
+            date = next;
+        }
+
+        Ok(totals)
+    }
+}
@@ -0,0 +1,32 @@
+//! HTTP details of a response, for callers handling caching, debugging or rate limits
+//! themselves.
+
+use reqwest::header::HeaderMap;
+use reqwest::StatusCode;
+use std::time::Duration;
+use url::Url;
+
+/// What the server answered besides the body.
+#[derive(Debug, Clone)]
+pub struct ResponseMeta {
+    pub status: StatusCode,
+    pub headers: HeaderMap,
+    /// The final URL of the request, after redirects.
+    pub url: Url,
+    /// Time from sending the request to receiving the whole body, retries included.
+    pub elapsed: Duration,
+}
+
+impl ResponseMeta {
+    /// The value of a header, if present and valid UTF-8.
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers.get(name)?.to_str().ok()
+    }
+}
+
+/// A parsed body with the [`ResponseMeta`] of its response.
+#[derive(Debug, Clone)]
+pub struct WithMeta<T> {
+    pub data: T,
+    pub meta: ResponseMeta,
+}
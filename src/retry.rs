@@ -0,0 +1,194 @@
+use crate::error::NightscoutError;
+
+use std::time::Duration;
+
+/// Controls how failed requests are retried.
+///
+/// Retries apply to every request sent by the client. Only transient failures are retried:
+/// timeouts and connection errors, `5xx` responses and `429 Too Many Requests`, each of which
+/// can be toggled individually.
+///
+/// `POST` and `PATCH` requests are not idempotent: after a timeout or a `5xx` the server may
+/// have stored the document already, and sending it again would duplicate it. They are only
+/// retried when the failure shows the request was not processed (a refused connection or a
+/// `429`), unless [`RetryPolicy::retry_non_idempotent`] is set.
+///
+/// # Example
+///
+/// ```rust
+/// # use cinnamon::client::NightscoutClient;
+/// # use cinnamon::retry::RetryPolicy;
+/// # use std::time::Duration;
+/// let client = NightscoutClient::new("https://example.com").unwrap()
+///     .with_retry_policy(
+///         RetryPolicy::default()
+///             .max_attempts(5)
+///             .initial_backoff(Duration::from_millis(500)),
+///     );
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct RetryPolicy {
+    /// Total number of attempts, including the first one.
+    pub max_attempts: u32,
+    /// Delay before the first retry.
+    pub initial_backoff: Duration,
+    /// Upper bound for the delay between two attempts.
+    pub max_backoff: Duration,
+    /// Factor the delay is multiplied by after each attempt.
+    pub multiplier: f64,
+    /// Randomizes each delay between half and the full computed value.
+    pub jitter: bool,
+    /// Retry on `5xx` responses.
+    pub retry_server_errors: bool,
+    /// Retry on timeouts and connection failures.
+    pub retry_timeouts: bool,
+    /// Retry on `429 Too Many Requests`.
+    pub retry_rate_limited: bool,
+    /// Retry `POST` and `PATCH` requests on every transient failure, at the risk of
+    /// creating duplicates.
+    pub retry_non_idempotent: bool,
+}
+
+impl Default for RetryPolicy {
+    /// Three attempts with exponential backoff starting at 200ms, retrying on every
+    /// transient failure.
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            initial_backoff: Duration::from_millis(200),
+            max_backoff: Duration::from_secs(10),
+            multiplier: 2.0,
+            jitter: true,
+            retry_server_errors: true,
+            retry_timeouts: true,
+            retry_rate_limited: true,
+            retry_non_idempotent: false,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// A policy that never retries. This is the client default.
+    pub fn none() -> Self {
+        Self {
+            max_attempts: 1,
+            ..Self::default()
+        }
+    }
+
+    pub fn max_attempts(mut self, attempts: u32) -> Self {
+        self.max_attempts = attempts.max(1);
+        self
+    }
+
+    pub fn initial_backoff(mut self, backoff: Duration) -> Self {
+        self.initial_backoff = backoff;
+        self
+    }
+
+    pub fn max_backoff(mut self, backoff: Duration) -> Self {
+        self.max_backoff = backoff;
+        self
+    }
+
+    pub fn multiplier(mut self, multiplier: f64) -> Self {
+        self.multiplier = multiplier;
+        self
+    }
+
+    pub fn jitter(mut self, jitter: bool) -> Self {
+        self.jitter = jitter;
+        self
+    }
+
+    pub fn retry_server_errors(mut self, retry: bool) -> Self {
+        self.retry_server_errors = retry;
+        self
+    }
+
+    pub fn retry_timeouts(mut self, retry: bool) -> Self {
+        self.retry_timeouts = retry;
+        self
+    }
+
+    pub fn retry_rate_limited(mut self, retry: bool) -> Self {
+        self.retry_rate_limited = retry;
+        self
+    }
+
+    pub fn retry_non_idempotent(mut self, retry: bool) -> Self {
+        self.retry_non_idempotent = retry;
+        self
+    }
+
+    /// Whether a request sent with `method` that failed with `error` should be sent again.
+    pub fn should_retry_request(&self, method: &reqwest::Method, error: &NightscoutError) -> bool {
+        let idempotent = !matches!(*method, reqwest::Method::POST | reqwest::Method::PATCH);
+        self.should_retry(error)
+            && (idempotent || self.retry_non_idempotent || not_processed(error))
+    }
+
+    /// Whether the error is transient according to this policy.
+    pub fn should_retry(&self, error: &NightscoutError) -> bool {
+        match error {
+            NightscoutError::RequestError(e) => self.retry_timeouts && is_transient(e),
+            NightscoutError::ApiError { status, .. } => {
+                (self.retry_server_errors && status.is_server_error())
+                    || (self.retry_rate_limited
+                        && *status == reqwest::StatusCode::TOO_MANY_REQUESTS)
+            }
+            _ => false,
+        }
+    }
+
+    /// The delay to wait after `error` on the given (1-based) attempt.
+    ///
+    /// A `Retry-After` delay requested by the server is honored when it is longer than the
+    /// backoff, up to `max_backoff`.
+    pub fn delay_for(&self, error: &NightscoutError, attempt: u32) -> Duration {
+        let backoff = self.backoff(attempt);
+        match error.retry_after() {
+            Some(after) => after.min(self.max_backoff).max(backoff),
+            None => backoff,
+        }
+    }
+
+    /// The delay to wait after the given (1-based) failed attempt.
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        let exponent = attempt.saturating_sub(1) as i32;
+        let delay = self.initial_backoff.as_secs_f64() * self.multiplier.powi(exponent);
+        let delay = delay.min(self.max_backoff.as_secs_f64()).max(0.0);
+
+        let delay = if self.jitter {
+            // Cheap jitter source, good enough to spread out concurrent retries.
+            let nanos = chrono::Utc::now().timestamp_subsec_nanos();
+            let factor = 0.5 + (nanos % 1000) as f64 / 2000.0;
+            delay * factor
+        } else {
+            delay
+        };
+
+        Duration::from_secs_f64(delay)
+    }
+}
+
+/// Whether the failure shows the server did not process the request.
+fn not_processed(error: &NightscoutError) -> bool {
+    match error {
+        // Connection errors are not reported separately by the wasm backend.
+        #[cfg(not(target_arch = "wasm32"))]
+        NightscoutError::RequestError(e) => e.is_connect(),
+        error => error.is_rate_limited(),
+    }
+}
+
+/// Whether a transport error is a timeout or a connection failure.
+fn is_transient(error: &reqwest::Error) -> bool {
+    // Connection errors are not reported separately by the wasm backend.
+    #[cfg(not(target_arch = "wasm32"))]
+    if error.is_connect() {
+        return true;
+    }
+
+    error.is_timeout()
+}
@@ -0,0 +1,36 @@
+//! Runtime specific primitives, so the client builds both natively and for
+//! `wasm32-unknown-unknown` where tokio's timer is unavailable.
+
+use crate::error::NightscoutError;
+
+use futures::future::{self, Either};
+use std::future::Future;
+use std::time::Duration;
+
+/// Waits for `duration` without blocking the executor.
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) async fn sleep(duration: Duration) {
+    tokio::time::sleep(duration).await;
+}
+
+/// Waits for `duration` without blocking the executor.
+#[cfg(target_arch = "wasm32")]
+pub(crate) async fn sleep(duration: Duration) {
+    gloo_timers::future::sleep(duration).await;
+}
+
+/// Runs `task`, failing with [`NightscoutError::Timeout`] if it does not complete within
+/// `limit`. The task is dropped on timeout, which aborts its requests.
+pub(crate) async fn timeout<T>(
+    limit: Option<Duration>,
+    task: impl Future<Output = Result<T, NightscoutError>>,
+) -> Result<T, NightscoutError> {
+    let Some(limit) = limit else {
+        return task.await;
+    };
+
+    match future::select(std::pin::pin!(task), std::pin::pin!(sleep(limit))).await {
+        Either::Left((result, _)) => result,
+        Either::Right(_) => Err(NightscoutError::Timeout(limit)),
+    }
+}
@@ -0,0 +1,84 @@
+//! The current state of a Nightscout site in a single call.
+
+use crate::analysis::series::SgvSeries;
+use crate::client::NightscoutClient;
+use crate::error::NightscoutError;
+use crate::models::devicestatus::DeviceStatus;
+use crate::models::entries::SgvEntry;
+use crate::models::profile::ProfileConfig;
+use crate::models::trends::Trend;
+
+use chrono::{DateTime, Utc};
+
+/// Number of recent device statuses searched for pump and uploader batteries.
+const DEVICE_STATUS_COUNT: usize = 10;
+
+/// What a watch face or status bar shows, see [`NightscoutClient::snapshot`].
+#[derive(Debug, Clone)]
+pub struct Snapshot {
+    /// The latest reading.
+    pub sgv: SgvEntry,
+    /// Change since the previous reading (mg/dL), `None` without a recent one.
+    pub delta: Option<f64>,
+    pub direction: Trend,
+    /// Insulin on board (U).
+    pub iob: Option<f64>,
+    /// Carbs on board (g).
+    pub cob: Option<f64>,
+    /// Pump battery (%).
+    pub pump_battery: Option<f64>,
+    /// Uploader phone or bridge battery (%).
+    pub uploader_battery: Option<f64>,
+    /// The profile in effect, including Profile Switches.
+    pub profile: Option<ProfileConfig>,
+    pub fetched_at: DateTime<Utc>,
+}
+
+impl Snapshot {
+    /// Minutes since the latest reading, to flag stale data.
+    pub fn minutes_ago(&self) -> Option<i64> {
+        self.sgv
+            .datetime()
+            .map(|date| (self.fetched_at - date).num_minutes())
+    }
+}
+
+pub(crate) async fn fetch(client: &NightscoutClient) -> Result<Snapshot, NightscoutError> {
+    let fetched_at = Utc::now();
+    let properties = client.properties();
+    let analysis = client.analysis();
+
+    let (readings, iob, cob, statuses, profile) = futures::join!(
+        client.sgv().get().limit(2).send(),
+        properties.iob(),
+        properties.cob(),
+        client
+            .devicestatus()
+            .get()
+            .limit(DEVICE_STATUS_COUNT)
+            .send(),
+        analysis.effective_profile(fetched_at),
+    );
+
+    let readings = readings?;
+    let delta = SgvSeries::from_entries(&readings).delta();
+    let sgv = readings
+        .into_iter()
+        .next()
+        .ok_or(NightscoutError::NotFound)?;
+    let statuses = statuses.unwrap_or_default();
+
+    Ok(Snapshot {
+        direction: sgv.direction,
+        sgv,
+        delta,
+        iob: iob.ok(),
+        cob: cob.ok(),
+        pump_battery: statuses.iter().find_map(DeviceStatus::pump_battery),
+        uploader_battery: statuses
+            .iter()
+            .find_map(|status| status.uploader_status()?.battery),
+        profile: profile.ok(),
+        fetched_at,
+    })
+}
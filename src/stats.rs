@@ -0,0 +1,287 @@
+//! Glucose statistics computed from SGV history.
+//!
+//! Percentages are computed over the number of readings, which assumes readings are
+//! evenly spaced (as produced by a CGM every 5 minutes).
+
+use crate::client::NightscoutClient;
+use crate::error::NightscoutError;
+use crate::models::entries::SgvEntry;
+use crate::query_builder::days_ago;
+
+use chrono::{DateTime, NaiveDate, Utc};
+use chrono_tz::Tz;
+use futures::TryStreamExt;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Thresholds (mg/dL) used to bucket readings for time-in-range.
+///
+/// Defaults follow the international consensus ranges: very low < 54, low < 70,
+/// in range 70-180, high > 180, very high > 250.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub struct TargetRanges {
+    pub very_low: f64,
+    pub low: f64,
+    pub high: f64,
+    pub very_high: f64,
+}
+
+impl Default for TargetRanges {
+    fn default() -> Self {
+        Self {
+            very_low: 54.0,
+            low: 70.0,
+            high: 180.0,
+            very_high: 250.0,
+        }
+    }
+}
+
+impl TargetRanges {
+    /// Custom low/high target with the default very low/very high limits.
+    pub fn target(low: f64, high: f64) -> Self {
+        Self {
+            low,
+            high,
+            ..Self::default()
+        }
+    }
+}
+
+/// Share of readings (in percent) falling into each range.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Default)]
+pub struct TimeInRange {
+    pub very_low: f64,
+    pub low: f64,
+    pub in_range: f64,
+    pub high: f64,
+    pub very_high: f64,
+}
+
+impl TimeInRange {
+    /// Percent of readings below the low threshold (including very low).
+    pub fn below(&self) -> f64 {
+        self.very_low + self.low
+    }
+
+    /// Percent of readings above the high threshold (including very high).
+    pub fn above(&self) -> f64 {
+        self.high + self.very_high
+    }
+}
+
+/// Summary statistics of a set of glucose readings, in mg/dL.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct GlucoseStats {
+    pub count: usize,
+    pub mean: f64,
+    pub median: f64,
+    pub min: f64,
+    pub max: f64,
+    /// Population standard deviation.
+    pub std_dev: f64,
+    /// Coefficient of variation in percent (`std_dev / mean * 100`).
+    pub cv: f64,
+    /// Glucose Management Indicator in percent (`3.31 + 0.02392 * mean`).
+    pub gmi: f64,
+    /// Estimated A1c in percent, using the ADAG formula (`(mean + 46.7) / 28.7`).
+    pub estimated_a1c: f64,
+    pub time_in_range: TimeInRange,
+}
+
+impl GlucoseStats {
+    /// Computes statistics from raw mg/dL values.
+    ///
+    /// Returns `None` if `values` is empty.
+    pub fn from_values(values: &[f64], ranges: &TargetRanges) -> Option<Self> {
+        if values.is_empty() {
+            return None;
+        }
+
+        let count = values.len();
+        let n = count as f64;
+        let mean = values.iter().sum::<f64>() / n;
+        let variance = values.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / n;
+        let std_dev = variance.sqrt();
+
+        let mut sorted = values.to_vec();
+        sorted.sort_by(|a, b| a.total_cmp(b));
+        let median = if count.is_multiple_of(2) {
+            (sorted[count / 2 - 1] + sorted[count / 2]) / 2.0
+        } else {
+            sorted[count / 2]
+        };
+
+        let mut tir = TimeInRange::default();
+        for &v in values {
+            let bucket = if v < ranges.very_low {
+                &mut tir.very_low
+            } else if v < ranges.low {
+                &mut tir.low
+            } else if v <= ranges.high {
+                &mut tir.in_range
+            } else if v <= ranges.very_high {
+                &mut tir.high
+            } else {
+                &mut tir.very_high
+            };
+            *bucket += 1.0;
+        }
+        for bucket in [
+            &mut tir.very_low,
+            &mut tir.low,
+            &mut tir.in_range,
+            &mut tir.high,
+            &mut tir.very_high,
+        ] {
+            *bucket = *bucket / n * 100.0;
+        }
+
+        Some(Self {
+            count,
+            mean,
+            median,
+            min: sorted[0],
+            max: sorted[count - 1],
+            std_dev,
+            cv: if mean > 0.0 {
+                std_dev / mean * 100.0
+            } else {
+                0.0
+            },
+            gmi: 3.31 + 0.02392 * mean,
+            estimated_a1c: (mean + 46.7) / 28.7,
+            time_in_range: tir,
+        })
+    }
+
+    /// Computes statistics from SGV entries.
+    ///
+    /// Returns `None` if `entries` is empty.
+    pub fn from_entries(entries: &[SgvEntry], ranges: &TargetRanges) -> Option<Self> {
+        let values: Vec<f64> = entries.iter().map(|e| e.sgv as f64).collect();
+        Self::from_values(&values, ranges)
+    }
+}
+
+/// Statistics of the readings of one local day, see [`StatsRequest::send_daily`].
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct DailyStats {
+    pub date: NaiveDate,
+    pub stats: GlucoseStats,
+}
+
+/// A builder computing statistics over SGV history fetched from Nightscout.
+///
+/// Created by [`SgvService::stats`](crate::models::entries::SgvService::stats).
+pub struct StatsRequest {
+    client: NightscoutClient,
+    from: DateTime<Utc>,
+    to: Option<DateTime<Utc>>,
+    ranges: TargetRanges,
+    tz: Tz,
+}
+
+/// Number of entries fetched per request while collecting history.
+const STATS_PAGE_SIZE: usize = 1000;
+
+impl StatsRequest {
+    pub fn new(client: NightscoutClient) -> Self {
+        let tz = client.timezone.unwrap_or(Tz::UTC);
+        Self {
+            from: days_ago(client.server_now(), 14),
+            client,
+            to: None,
+            ranges: TargetRanges::default(),
+            tz,
+        }
+    }
+
+    /// Uses the readings of the last `days` days. Default is 14.
+    pub fn last_days(mut self, days: i64) -> Self {
+        self.from = days_ago(self.client.server_now(), days);
+        self.to = None;
+        self
+    }
+
+    /// Uses readings on or after this date.
+    pub fn from(mut self, date: DateTime<Utc>) -> Self {
+        self.from = date;
+        self
+    }
+
+    /// Uses readings on or before this date.
+    pub fn to(mut self, date: DateTime<Utc>) -> Self {
+        self.to = Some(date);
+        self
+    }
+
+    /// Overrides the time-in-range thresholds.
+    pub fn ranges(mut self, ranges: TargetRanges) -> Self {
+        self.ranges = ranges;
+        self
+    }
+
+    /// Sets the timezone splitting days in [`send_daily`](Self::send_daily). Defaults to the
+    /// client's timezone, or UTC.
+    pub fn timezone(mut self, tz: Tz) -> Self {
+        self.tz = tz;
+        self
+    }
+
+    /// Fetches the readings and computes the statistics.
+    ///
+    /// Returns `NightscoutError::NotFound` if there is no data in the requested range.
+    pub async fn send(self) -> Result<GlucoseStats, NightscoutError> {
+        let entries = self.entries().await?;
+
+        GlucoseStats::from_entries(&entries, &self.ranges).ok_or(NightscoutError::NotFound)
+    }
+
+    /// Fetches the readings and computes the statistics of each local day, oldest first.
+    ///
+    /// Days without readings are left out.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// # use cinnamon::client::NightscoutClient;
+    /// # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+    /// let client = NightscoutClient::new("https://ns.example.com")?
+    ///     .with_timezone(chrono_tz::Europe::Berlin);
+    ///
+    /// for day in client.sgv().stats().last_days(7).send_daily().await? {
+    ///     println!("{}: {:.0}% in range", day.date, day.stats.time_in_range.in_range);
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn send_daily(self) -> Result<Vec<DailyStats>, NightscoutError> {
+        let entries = self.entries().await?;
+
+        let mut days: BTreeMap<NaiveDate, Vec<f64>> = BTreeMap::new();
+        for entry in &entries {
+            if let Some(at) = entry.datetime() {
+                let date = at.with_timezone(&self.tz).date_naive();
+                days.entry(date).or_default().push(entry.sgv as f64);
+            }
+        }
+
+        Ok(days
+            .into_iter()
+            .filter_map(|(date, values)| {
+                let stats = GlucoseStats::from_values(&values, &self.ranges)?;
+                Some(DailyStats { date, stats })
+            })
+            .collect())
+    }
+
+    async fn entries(&self) -> Result<Vec<SgvEntry>, NightscoutError> {
+        let mut query = self.client.sgv().get().from(self.from);
+        if let Some(to) = self.to {
+            query = query.to(to);
+        }
+
+        query.paginate(STATS_PAGE_SIZE).try_collect().await
+    }
+}
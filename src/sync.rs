@@ -0,0 +1,317 @@
+//! Incremental synchronization through the v3 API.
+//!
+//! [`SyncManager`] remembers, per collection, the `srvModified` time of the last document it
+//! received. Each [`SyncManager::sync`] asks the server which collections changed since
+//! (`/api/v3/lastModified`) and pulls only the documents created, updated or deleted after
+//! that point from their `history` endpoint. This is the building block for apps keeping a
+//! local copy of the data.
+
+use crate::client::NightscoutClient;
+use crate::endpoints::Endpoint;
+use crate::error::NightscoutError;
+use crate::models::devicestatus::DeviceStatus;
+use crate::models::entries::Entry;
+use crate::models::profile::ProfileSet;
+use crate::models::treatments::Treatment;
+
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use serde_json::Value;
+use std::collections::{HashMap, HashSet};
+
+/// Number of documents requested per history page, the v3 API default maximum.
+const PAGE_SIZE: usize = 1000;
+
+/// A collection that can be synchronized.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum Collection {
+    Entries,
+    Treatments,
+    DeviceStatus,
+    Profile,
+}
+
+impl Collection {
+    pub const ALL: [Collection; 4] = [
+        Collection::Entries,
+        Collection::Treatments,
+        Collection::DeviceStatus,
+        Collection::Profile,
+    ];
+
+    /// The collection name used by the v3 API.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Collection::Entries => "entries",
+            Collection::Treatments => "treatments",
+            Collection::DeviceStatus => "devicestatus",
+            Collection::Profile => "profile",
+        }
+    }
+}
+
+/// A change to a single document.
+#[derive(Debug, Clone)]
+pub enum Change<T> {
+    /// The document was created or updated.
+    Upserted { id: String, document: T },
+    /// The document was deleted.
+    Deleted { id: String },
+}
+
+impl<T> Change<T> {
+    /// The v3 `identifier` of the document.
+    pub fn id(&self) -> &str {
+        match self {
+            Change::Upserted { id, .. } | Change::Deleted { id } => id,
+        }
+    }
+}
+
+/// A typed change received by [`SyncManager::sync`].
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub enum SyncEvent {
+    Entry(Change<Entry>),
+    Treatment(Change<Box<Treatment>>),
+    DeviceStatus(Change<Box<DeviceStatus>>),
+    Profile(Change<ProfileSet>),
+}
+
+/// The result of one [`SyncManager::sync`] run.
+#[derive(Debug, Default)]
+pub struct SyncReport {
+    /// Changes in the order the server reported them, per collection.
+    pub events: Vec<SyncEvent>,
+    /// Documents that could not be decoded into their model and were skipped.
+    pub skipped: usize,
+}
+
+#[derive(Debug, Deserialize)]
+struct V3Response<T> {
+    result: T,
+}
+
+#[derive(Debug, Deserialize)]
+struct LastModified {
+    #[serde(default)]
+    collections: HashMap<String, i64>,
+}
+
+/// Pulls changes incrementally from a Nightscout v3 server.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// # use cinnamon::client::NightscoutClient;
+/// # use cinnamon::sync::{Change, SyncEvent};
+/// # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+/// let client = NightscoutClient::new("https://ns.example.com")?.with_token("sync-0123456789abcdef");
+/// let mut sync = client.sync();
+///
+/// loop {
+///     for event in sync.sync().await?.events {
+///         if let SyncEvent::Treatment(Change::Upserted { document, .. }) = event {
+///             println!("{}", document.event_type);
+///         }
+///     }
+///     tokio::time::sleep(std::time::Duration::from_secs(60)).await;
+/// }
+/// # }
+/// ```
+pub struct SyncManager {
+    pub client: NightscoutClient,
+    collections: Vec<Collection>,
+    cursors: HashMap<Collection, i64>,
+    /// The ids of the documents received at the cursor's millisecond, per collection.
+    seen: HashMap<Collection, HashSet<String>>,
+    page_size: usize,
+}
+
+impl SyncManager {
+    /// Synchronizes every collection, starting from the beginning.
+    pub fn new(client: NightscoutClient) -> Self {
+        Self {
+            client,
+            collections: Collection::ALL.to_vec(),
+            cursors: HashMap::new(),
+            seen: HashMap::new(),
+            page_size: PAGE_SIZE,
+        }
+    }
+
+    /// Restricts synchronization to the given collections.
+    pub fn collections(mut self, collections: &[Collection]) -> Self {
+        self.collections = collections.to_vec();
+        self
+    }
+
+    /// Resumes from cursors saved with [`SyncManager::cursors`].
+    ///
+    /// The documents modified in the same millisecond as a cursor are received again on
+    /// the first run, as the ids already received are not part of the cursor.
+    pub fn with_cursors(mut self, cursors: HashMap<Collection, i64>) -> Self {
+        self.cursors = cursors;
+        self.seen.clear();
+        self
+    }
+
+    /// Number of documents requested per page. Defaults to 1000.
+    pub fn page_size(mut self, page_size: usize) -> Self {
+        self.page_size = page_size.max(1);
+        self
+    }
+
+    /// The `srvModified` time (ms) of the last change received, per collection.
+    ///
+    /// Persist these to resume synchronization after a restart.
+    pub fn cursors(&self) -> &HashMap<Collection, i64> {
+        &self.cursors
+    }
+
+    /// Fetches every change since the previous run and advances the cursors.
+    ///
+    /// The cursors only advance when every collection was synchronized. On error they are
+    /// left as they were, so the next run fetches the changes of this one again instead of
+    /// losing them.
+    pub async fn sync(&mut self) -> Result<SyncReport, NightscoutError> {
+        let url = self
+            .client
+            .base_url
+            .join(Endpoint::LastModified.as_path())?;
+        let last_modified = self
+            .client
+            .fetch::<V3Response<LastModified>>(url)
+            .await?
+            .result;
+
+        let mut cursors = self.cursors.clone();
+        let mut seen = self.seen.clone();
+        let mut report = SyncReport::default();
+        for collection in self.collections.clone() {
+            let cursor = cursors.get(&collection).copied().unwrap_or(0);
+            let changed = last_modified
+                .collections
+                .get(collection.as_str())
+                .is_none_or(|modified| *modified > cursor);
+
+            if changed {
+                let (cursor, ids) = self
+                    .sync_collection(collection, cursor, seen.remove(&collection), &mut report)
+                    .await?;
+                cursors.insert(collection, cursor);
+                seen.insert(collection, ids);
+            }
+        }
+
+        self.cursors = cursors;
+        self.seen = seen;
+        Ok(report)
+    }
+
+    /// Pulls the history of a collection from `cursor`, returning the new cursor and the
+    /// ids of the documents modified at it.
+    async fn sync_collection(
+        &self,
+        collection: Collection,
+        mut cursor: i64,
+        seen: Option<HashSet<String>>,
+        report: &mut SyncReport,
+    ) -> Result<(i64, HashSet<String>), NightscoutError> {
+        let mut seen = seen.unwrap_or_default();
+
+        loop {
+            // From the millisecond before the cursor, so the documents sharing the
+            // cursor's millisecond are returned again whether the server compares with
+            // `>` or `>=`. The ones already received are recognised by their id.
+            let from = cursor.saturating_sub(1).max(0);
+            let path = format!("api/v3/{}/history/{}", collection.as_str(), from);
+            let mut url = self.client.base_url.join(&path)?;
+            url.query_pairs_mut()
+                .append_pair("limit", &self.page_size.to_string());
+
+            let page = self
+                .client
+                .fetch::<V3Response<Vec<Value>>>(url)
+                .await?
+                .result;
+            let fetched = page.len();
+            let mut received = 0;
+
+            for document in page {
+                let modified = document.get("srvModified").and_then(Value::as_i64);
+                let id = document_id(&document);
+
+                if let Some(modified) = modified {
+                    let already_received = modified < cursor
+                        || (modified == cursor && id.is_some_and(|id| seen.contains(id)));
+                    if already_received {
+                        continue;
+                    }
+                    if modified > cursor {
+                        cursor = modified;
+                        seen.clear();
+                    }
+                    if let Some(id) = id {
+                        seen.insert(id.to_string());
+                    }
+                }
+                received += 1;
+
+                match decode(collection, document) {
+                    Some(event) => report.events.push(event),
+                    None => report.skipped += 1,
+                }
+            }
+
+            // A short page is the last one, and a page without new documents would be
+            // requested again forever.
+            if fetched < self.page_size || received == 0 {
+                return Ok((cursor, seen));
+            }
+        }
+    }
+}
+
+/// The v3 `identifier` of a history document, or its `_id` on older servers.
+fn document_id(document: &Value) -> Option<&str> {
+    document
+        .get("identifier")
+        .or_else(|| document.get("_id"))
+        .and_then(Value::as_str)
+}
+
+/// Decodes a history document into a typed event, `None` if it does not fit the model.
+fn decode(collection: Collection, document: Value) -> Option<SyncEvent> {
+    let id = document_id(&document)?.to_string();
+
+    let deleted = document.get("isValid").and_then(Value::as_bool) == Some(false);
+    if deleted {
+        return Some(match collection {
+            Collection::Entries => SyncEvent::Entry(Change::Deleted { id }),
+            Collection::Treatments => SyncEvent::Treatment(Change::Deleted { id }),
+            Collection::DeviceStatus => SyncEvent::DeviceStatus(Change::Deleted { id }),
+            Collection::Profile => SyncEvent::Profile(Change::Deleted { id }),
+        });
+    }
+
+    match collection {
+        Collection::Entries => upserted(id, document).map(SyncEvent::Entry),
+        Collection::Treatments => upserted(id, document).map(SyncEvent::Treatment),
+        Collection::DeviceStatus => upserted(id, document).map(SyncEvent::DeviceStatus),
+        Collection::Profile => upserted(id, document).map(SyncEvent::Profile),
+    }
+}
+
+fn upserted<T: DeserializeOwned>(id: String, document: Value) -> Option<Change<T>> {
+    match serde_json::from_value(document) {
+        Ok(document) => Some(Change::Upserted { id, document }),
+        Err(_e) => {
+            #[cfg(feature = "tracing")]
+            tracing::debug!(error = %_e, "skipping malformed document");
+
+            None
+        }
+    }
+}
@@ -0,0 +1,129 @@
+//! Canned responses of a Nightscout site, as the server sends them.
+//!
+//! Every fixture is dated around [`FIXTURE_TIME`], so assertions on them do not depend on
+//! when the test runs.
+
+use serde_json::{json, Value};
+
+/// Epoch milliseconds of the latest fixture document (2024-01-01T00:00:00Z).
+pub const FIXTURE_TIME: i64 = 1_704_067_200_000;
+
+/// Response of `/status.json`, with the IOB and COB plugins enabled.
+pub fn status() -> Value {
+    json!({
+        "status": "ok",
+        "name": "nightscout",
+        "version": "15.0.2",
+        "serverTime": "2024-01-01T00:00:00.000Z",
+        "serverTimeEpoch": FIXTURE_TIME,
+        "apiEnabled": true,
+        "careportalEnabled": true,
+        "boluscalcEnabled": false,
+        "settings": {
+            "units": "mg/dl",
+            "enable": ["careportal", "iob", "cob", "basal"],
+            "thresholds": {
+                "bgHigh": 260,
+                "bgTargetTop": 180,
+                "bgTargetBottom": 80,
+                "bgLow": 55
+            }
+        },
+        "authorized": true
+    })
+}
+
+/// Response of `/entries/sgv.json`: three readings five minutes apart, newest first.
+pub fn sgv_entries() -> Value {
+    json!([
+        { "_id": "sgv3", "type": "sgv", "sgv": 128, "date": FIXTURE_TIME, "direction": "FortyFiveUp", "device": "xDrip" },
+        { "_id": "sgv2", "type": "sgv", "sgv": 120, "date": FIXTURE_TIME - 300_000, "direction": "Flat", "device": "xDrip" },
+        { "_id": "sgv1", "type": "sgv", "sgv": 118, "date": FIXTURE_TIME - 600_000, "direction": "Flat", "device": "xDrip" }
+    ])
+}
+
+/// Response of `/treatments.json`: a meal bolus and a temporary basal, newest first.
+pub fn treatments() -> Value {
+    json!([
+        {
+            "_id": "treatment2",
+            "eventType": "Meal Bolus",
+            "created_at": "2023-12-31T23:30:00.000Z",
+            "insulin": 4.5,
+            "carbs": 45,
+            "enteredBy": "cinnamon"
+        },
+        {
+            "_id": "treatment1",
+            "eventType": "Temp Basal",
+            "created_at": "2023-12-31T22:00:00.000Z",
+            "absolute": 0.5,
+            "duration": 30,
+            "enteredBy": "cinnamon"
+        }
+    ])
+}
+
+/// Response of `/devicestatus.json`: a loop status with pump and uploader batteries.
+pub fn device_statuses() -> Value {
+    json!([{
+        "_id": "status1",
+        "device": "loop://iPhone",
+        "created_at": "2024-01-01T00:00:00.000Z",
+        "pump": {
+            "clock": "2024-01-01T00:00:00.000Z",
+            "reservoir": 120.5,
+            "battery": { "percent": 75 }
+        },
+        "uploader": { "battery": 80 }
+    }])
+}
+
+/// Response of `/profile.json`: one profile set with a single `Default` profile.
+pub fn profiles() -> Value {
+    json!([{
+        "_id": "profile1",
+        "defaultProfile": "Default",
+        "startDate": "2023-01-01T00:00:00.000Z",
+        "created_at": "2023-01-01T00:00:00.000Z",
+        "store": {
+            "Default": {
+                "dia": 3.0,
+                "timezone": "UTC",
+                "units": "mg/dl",
+                "carbratio": [{ "time": "00:00", "value": 10.0 }],
+                "sens": [{ "time": "00:00", "value": 30.0 }],
+                "basal": [{ "time": "00:00", "value": 1.0 }],
+                "target_low": [{ "time": "00:00", "value": 80.0 }],
+                "target_high": [{ "time": "00:00", "value": 120.0 }]
+            }
+        }
+    }])
+}
+
+/// Response of `/properties/iob`.
+pub fn iob() -> Value {
+    json!({
+        "iob": {
+            "iob": 2.25,
+            "activity": 0.01,
+            "source": "Loop",
+            "display": "2.25",
+            "displayLine": "IOB: 2.25U"
+        }
+    })
+}
+
+/// Response of `/properties/cob`.
+pub fn cob() -> Value {
+    json!({
+        "cob": {
+            "cob": 30.0,
+            "isDecaying": 0,
+            "decayedBy": "2024-01-01T01:30:00.000Z",
+            "source": "Loop",
+            "display": 30,
+            "displayLine": "COB: 30g"
+        }
+    })
+}
@@ -0,0 +1,180 @@
+//! Generators of realistic, reproducible data series.
+
+use crate::analysis::ages::SITE_CHANGE_EVENT;
+use crate::models::entries::SgvEntry;
+use crate::models::treatments::{Treatment, TreatmentBuilder};
+use crate::models::trends::Trend;
+
+use chrono::{DateTime, Duration, NaiveTime, Utc};
+use std::f64::consts::TAU;
+
+/// Glucose readings following a smooth wave with sensor noise.
+///
+/// The same settings always produce the same values, only the timestamps follow
+/// [`ending_at`](Self::ending_at), which defaults to now.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// # use cinnamon::testing::generate::SgvGenerator;
+/// // A day of readings, newest first.
+/// let entries = SgvGenerator::new().amplitude(60.0).generate(288);
+/// assert_eq!(entries.len(), 288);
+/// ```
+#[derive(Debug, Clone)]
+pub struct SgvGenerator {
+    end: DateTime<Utc>,
+    interval: Duration,
+    baseline: f64,
+    amplitude: f64,
+    period: Duration,
+    noise: f64,
+    seed: u64,
+}
+
+impl Default for SgvGenerator {
+    fn default() -> Self {
+        Self {
+            end: Utc::now(),
+            interval: Duration::minutes(5),
+            baseline: 130.0,
+            amplitude: 40.0,
+            period: Duration::hours(6),
+            noise: 4.0,
+            seed: 1,
+        }
+    }
+}
+
+impl SgvGenerator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Time of the newest reading.
+    pub fn ending_at(mut self, end: DateTime<Utc>) -> Self {
+        self.end = end;
+        self
+    }
+
+    /// Time between readings. Defaults to 5 minutes.
+    pub fn interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    /// Mean glucose (mg/dL). Defaults to 130.
+    pub fn baseline(mut self, mgdl: f64) -> Self {
+        self.baseline = mgdl;
+        self
+    }
+
+    /// Height of the wave above and below the baseline (mg/dL). Defaults to 40.
+    pub fn amplitude(mut self, mgdl: f64) -> Self {
+        self.amplitude = mgdl;
+        self
+    }
+
+    /// Length of one wave. Defaults to 6 hours.
+    pub fn period(mut self, period: Duration) -> Self {
+        self.period = period;
+        self
+    }
+
+    /// Largest random deviation of a reading (mg/dL). Defaults to 4.
+    pub fn noise(mut self, mgdl: f64) -> Self {
+        self.noise = mgdl;
+        self
+    }
+
+    /// Seed of the noise, to get a different but still reproducible series.
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    /// Generates `count` readings, newest first, with directions matching their slope.
+    pub fn generate(&self, count: usize) -> Vec<SgvEntry> {
+        let mut noise = Lcg(self.seed);
+        let period_ms = self.period.num_milliseconds().max(1) as f64;
+        let interval_minutes = self.interval.num_seconds() as f64 / 60.0;
+
+        let mut entries: Vec<SgvEntry> = Vec::with_capacity(count);
+        let mut previous: Option<f64> = None;
+        for step in (0..count).rev() {
+            let date = self.end - self.interval * step as i32;
+            let phase = date.timestamp_millis() as f64 / period_ms;
+            let value = (self.baseline
+                + self.amplitude * (TAU * phase).sin()
+                + self.noise * noise.next_signed())
+            .clamp(40.0, 400.0)
+            .round();
+
+            let direction = match previous {
+                Some(previous) if interval_minutes > 0.0 => {
+                    Trend::from_slope((value - previous) / interval_minutes)
+                }
+                _ => Trend::Flat,
+            };
+            previous = Some(value);
+            entries.push(SgvEntry::new(value as i32, direction, date));
+        }
+
+        entries.reverse();
+        entries
+    }
+}
+
+/// A routine of three meal boluses a day, and a site change every third day, over the
+/// `days` days ending at `end`. Returned newest first.
+pub fn daily_treatments(end: DateTime<Utc>, days: u32) -> Vec<Treatment> {
+    let meals = [(7, 30, 45.0), (12, 30, 60.0), (19, 0, 75.0)];
+    let first_day = end.date_naive() - Duration::days(i64::from(days.saturating_sub(1)));
+
+    let mut treatments = Vec::new();
+    for day in 0..days {
+        let date = first_day + Duration::days(i64::from(day));
+        let at = |hour, minute| {
+            let time = NaiveTime::from_hms_opt(hour, minute, 0).unwrap_or_default();
+            date.and_time(time).and_utc()
+        };
+
+        if day % 3 == 0 {
+            treatments.push(build(TreatmentBuilder::new(SITE_CHANGE_EVENT).at(at(9, 0))));
+        }
+        for (hour, minute, carbs) in meals {
+            // Vary the meals a little from one day to the next.
+            let carbs = carbs + f64::from(day % 3) * 5.0;
+            treatments.push(build(
+                Treatment::carbs(carbs)
+                    .insulin(carbs / 10.0)
+                    .at(at(hour, minute)),
+            ));
+        }
+    }
+
+    treatments.retain(|treatment| treatment.created_at <= end);
+    treatments.sort_by_key(|treatment| std::cmp::Reverse(treatment.created_at));
+    treatments
+}
+
+fn build(builder: TreatmentBuilder) -> Treatment {
+    builder
+        .entered_by("cinnamon")
+        .build()
+        .expect("generated treatments are valid")
+}
+
+/// Linear congruential generator, enough for reproducible sensor noise.
+struct Lcg(u64);
+
+impl Lcg {
+    /// A value between -1 and 1.
+    fn next_signed(&mut self) -> f64 {
+        self.0 = self
+            .0
+            .wrapping_mul(6_364_136_223_846_793_005)
+            .wrapping_add(1_442_695_040_888_963_407);
+        (self.0 >> 11) as f64 / (1u64 << 53) as f64 * 2.0 - 1.0
+    }
+}
@@ -0,0 +1,153 @@
+//! Helpers for testing code built on cinnamon, behind the `testing` feature.
+//!
+//! [`MockNightscout`] runs a local server with the standard endpoints already mounted,
+//! [`fixtures`] holds the canned responses it serves, and [`generate`] builds longer
+//! series of readings and treatments.
+
+pub mod fixtures;
+pub mod generate;
+
+use crate::client::NightscoutClient;
+use crate::models::devicestatus::DeviceStatus;
+use crate::models::entries::SgvEntry;
+use crate::models::treatments::Treatment;
+
+use serde::Serialize;
+use serde_json::Value;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, Request, ResponseTemplate};
+
+/// API secret accepted by [`MockNightscout::client`].
+pub const MOCK_SECRET: &str = "test-secret-123";
+
+/// Priority of the standard endpoints; mocks mounted later with the default priority of
+/// wiremock take precedence over them.
+const FIXTURE_PRIORITY: u8 = 10;
+
+/// A local mock server answering like a Nightscout site.
+///
+/// [`start`](Self::start) mounts the [`fixtures`] on the v2 endpoints of the status,
+/// entries, treatments, device status, profile and IOB/COB properties, and echoes uploads
+/// back. Responses can be replaced per test with the `mount_*` methods, or with plain
+/// wiremock mocks on [`server`](Self::server).
+///
+/// # Example
+///
+/// ```rust,no_run
+/// # use cinnamon::testing::MockNightscout;
+/// # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+/// let mock = MockNightscout::start().await;
+/// let client = mock.client();
+///
+/// let latest = client.sgv().latest().await?;
+/// assert_eq!(latest.map(|entry| entry.sgv), Some(128));
+/// # Ok(())
+/// # }
+/// ```
+pub struct MockNightscout {
+    server: MockServer,
+}
+
+impl MockNightscout {
+    /// Starts a server with the standard endpoints mounted.
+    pub async fn start() -> Self {
+        let mock = Self {
+            server: MockServer::start().await,
+        };
+
+        let fixtures = [
+            ("/api/v2/status.json", fixtures::status()),
+            ("/api/v2/entries/sgv.json", fixtures::sgv_entries()),
+            ("/api/v2/treatments.json", fixtures::treatments()),
+            ("/api/v2/devicestatus.json", fixtures::device_statuses()),
+            ("/api/v2/profile.json", fixtures::profiles()),
+            ("/api/v2/properties/iob", fixtures::iob()),
+            ("/api/v2/properties/cob", fixtures::cob()),
+        ];
+        for (endpoint, body) in fixtures {
+            Mock::given(method("GET"))
+                .and(path(endpoint))
+                .respond_with(ResponseTemplate::new(200).set_body_json(body))
+                .with_priority(FIXTURE_PRIORITY)
+                .mount(&mock.server)
+                .await;
+        }
+
+        for endpoint in [
+            "/api/v2/entries.json",
+            "/api/v2/treatments.json",
+            "/api/v2/devicestatus.json",
+        ] {
+            Mock::given(method("POST"))
+                .and(path(endpoint))
+                .respond_with(echo)
+                .with_priority(FIXTURE_PRIORITY)
+                .mount(&mock.server)
+                .await;
+        }
+
+        mock
+    }
+
+    /// The underlying wiremock server, to mount custom mocks or inspect requests.
+    pub fn server(&self) -> &MockServer {
+        &self.server
+    }
+
+    /// Base URL of the server.
+    pub fn uri(&self) -> String {
+        self.server.uri()
+    }
+
+    /// A client pointed at the server, authenticated with [`MOCK_SECRET`].
+    pub fn client(&self) -> NightscoutClient {
+        NightscoutClient::new(&self.server.uri())
+            .expect("mock server URL is valid")
+            .with_secret(MOCK_SECRET)
+    }
+
+    /// Answers GET requests to `endpoint` (e.g. `/api/v2/status.json`) with `body`.
+    pub async fn mount_json(&self, endpoint: &str, body: Value) {
+        Mock::given(method("GET"))
+            .and(path(endpoint))
+            .respond_with(ResponseTemplate::new(200).set_body_json(body))
+            .mount(&self.server)
+            .await;
+    }
+
+    /// Serves `entries` as the glucose readings.
+    pub async fn mount_sgv(&self, entries: &[SgvEntry]) {
+        self.mount_documents("/api/v2/entries/sgv.json", entries)
+            .await;
+    }
+
+    /// Serves `treatments` as the treatments.
+    pub async fn mount_treatments(&self, treatments: &[Treatment]) {
+        self.mount_documents("/api/v2/treatments.json", treatments)
+            .await;
+    }
+
+    /// Serves `statuses` as the device statuses.
+    pub async fn mount_device_statuses(&self, statuses: &[DeviceStatus]) {
+        self.mount_documents("/api/v2/devicestatus.json", statuses)
+            .await;
+    }
+
+    /// Every request received so far.
+    pub async fn received_requests(&self) -> Vec<Request> {
+        self.server.received_requests().await.unwrap_or_default()
+    }
+
+    async fn mount_documents<T: Serialize>(&self, endpoint: &str, documents: &[T]) {
+        let body = serde_json::to_value(documents).expect("documents serialize to JSON");
+        self.mount_json(endpoint, body).await;
+    }
+}
+
+/// Answers an upload with the uploaded documents, as Nightscout does.
+fn echo(request: &Request) -> ResponseTemplate {
+    match serde_json::from_slice::<Value>(&request.body) {
+        Ok(body) => ResponseTemplate::new(200).set_body_json(body),
+        Err(_) => ResponseTemplate::new(400),
+    }
+}
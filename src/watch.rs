@@ -0,0 +1,100 @@
+//! Polling watchers emitting newly arrived documents.
+
+use crate::error::NightscoutError;
+use crate::query_builder::HasDate;
+use crate::runtime;
+
+use chrono::{DateTime, Utc};
+use futures::stream::{self, Stream, StreamExt};
+use std::collections::{HashSet, VecDeque};
+use std::future::Future;
+use std::time::Duration;
+
+/// Number of recently emitted keys remembered for deduplication.
+const SEEN_CAPACITY: usize = 1024;
+
+/// Polls `fetch` every `interval` and yields documents not seen before, oldest first.
+///
+/// `fetch` receives the timestamp of the newest document emitted so far (`None` on the
+/// first poll) and should return documents at or after it. Documents are deduplicated by
+/// the key returned by `key`, falling back to their timestamp. Errors are yielded without
+/// ending the stream, so a transient outage does not stop the watcher.
+pub(crate) fn poll<T, F, Fut>(
+    interval: Duration,
+    fetch: F,
+    key: fn(&T) -> Option<String>,
+) -> impl Stream<Item = Result<T, NightscoutError>>
+where
+    T: HasDate,
+    F: Fn(Option<DateTime<Utc>>) -> Fut,
+    Fut: Future<Output = Result<Vec<T>, NightscoutError>>,
+{
+    let state = WatchState {
+        fetch,
+        key,
+        interval,
+        newest: None,
+        seen: HashSet::new(),
+        order: VecDeque::new(),
+        first: true,
+    };
+
+    stream::unfold(state, |mut state| async move {
+        if !state.first {
+            runtime::sleep(state.interval).await;
+        }
+        state.first = false;
+
+        let batch = match (state.fetch)(state.newest).await {
+            Ok(items) => state.accept(items).into_iter().map(Ok).collect(),
+            Err(e) => vec![Err(e)],
+        };
+
+        Some((batch, state))
+    })
+    .flat_map(stream::iter)
+}
+
+struct WatchState<T, F> {
+    fetch: F,
+    key: fn(&T) -> Option<String>,
+    interval: Duration,
+    newest: Option<DateTime<Utc>>,
+    seen: HashSet<String>,
+    order: VecDeque<String>,
+    first: bool,
+}
+
+impl<T: HasDate, F> WatchState<T, F> {
+    /// Filters out already emitted documents and records the new ones.
+    fn accept(&mut self, mut items: Vec<T>) -> Vec<T> {
+        items.sort_by_key(|item| item.timestamp());
+
+        let mut fresh = Vec::new();
+        for item in items {
+            let ts = item.timestamp();
+            let key = (self.key)(&item)
+                .or_else(|| ts.map(|t| t.timestamp_millis().to_string()))
+                .unwrap_or_default();
+
+            if self.seen.contains(&key) || (ts.is_some() && ts < self.newest) {
+                continue;
+            }
+
+            self.seen.insert(key.clone());
+            self.order.push_back(key);
+            if self.order.len() > SEEN_CAPACITY {
+                if let Some(oldest) = self.order.pop_front() {
+                    self.seen.remove(&oldest);
+                }
+            }
+
+            if ts > self.newest {
+                self.newest = ts;
+            }
+            fresh.push(item);
+        }
+
+        fresh
+    }
+}